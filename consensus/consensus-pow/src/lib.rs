@@ -78,6 +78,8 @@ pub enum Error<B: BlockT> {
 	HeaderUnsealed(B::Hash),
 	#[display(fmt = "PoW validation error: invalid seal")]
 	InvalidSeal,
+	#[display(fmt = "PoW validation error: {}", _0)]
+	SealVerification(Box<dyn std::error::Error + Send + Sync>),
 	#[display(fmt = "PoW validation error: preliminary verification failed")]
 	FailedPreliminaryVerify,
 	#[display(fmt = "Rejecting block too far in future")]
@@ -127,6 +129,16 @@ fn aux_key<T: AsRef<[u8]>>(hash: &T) -> Vec<u8> {
 	POW_AUX_PREFIX.iter().chain(hash.as_ref()).copied().collect()
 }
 
+/// How far ahead of the local clock the timestamp a seal commits to may be, in milliseconds.
+///
+/// This bounds the seal timestamp, not the block timestamp. The block timestamp is checked by
+/// the timestamp inherent of the runtime, handed the seal timestamp in place of the local clock:
+/// it may be ahead of the seal timestamp by the drift the runtime tolerates, and behind it down
+/// to the minimum the runtime allows after the parent. The importer can't require the two to be
+/// equal, the block timestamp being an extrinsic only the runtime decodes, and the sealing node
+/// commits the seal to the very timestamp of its block anyway.
+const MAX_TIMESTAMP_DRIFT_MILLIS: u64 = 60_000;
+
 /// Intermediate value passed to block importer.
 #[derive(Encode, Decode, Clone, Debug, Default)]
 pub struct PowIntermediate<Difficulty> {
@@ -174,6 +186,16 @@ pub trait PowAlgorithm<B: BlockT> {
 	/// Get the next block's difficulty.
 	fn calc_difficulty(&self, parent: B::Hash, cur: B::Hash) -> Result<Self::Difficulty, Error<B>>;
 
+	/// The timestamp inherent, in milliseconds, of the block carrying `seal`, when the seal
+	/// commits to it.
+	///
+	/// The importer then checks the timestamp of the block against the one of the seal, and
+	/// the seal timestamp against the local clock. `None` checks the block timestamp against the
+	/// local clock directly.
+	fn seal_timestamp(&self, _seal: &Seal) -> Option<u64> {
+		None
+	}
+
 	/// Verify that the seal is valid against given pre hash when parent block is not yet imported.
 	///
 	/// None means that preliminary verify is not available for this algorithm.
@@ -350,10 +372,23 @@ impl<B, I, C, S, Algorithm, CAW> BlockImport<B> for PowBlockImport<B, I, C, S, A
 		let best_aux = PowAux::read::<_, B>(self.client.as_ref(), &best_hash)?;
 		let mut aux = PowAux::read::<_, B>(self.client.as_ref(), &parent_hash)?;
 
+		let inner_seal = fetch_seal::<B>(block.post_digests.last(), block.header.hash())?;
+
 		if let Some(inner_body) = block.body.take() {
-			let inherent_data = self.inherent_data_providers
+			let mut inherent_data = self.inherent_data_providers
 				.create_inherent_data().map_err(|e| e.into_string())?;
-			let timestamp_now = inherent_data.timestamp_inherent_data().map_err(|e| e.into_string())?;
+			let mut timestamp_now = inherent_data.timestamp_inherent_data().map_err(|e| e.into_string())?;
+
+			// The block timestamp is checked against the one the seal commits to, so every node
+			// agrees on it, within the tolerance of the runtime, and the seal timestamp against
+			// the local clock.
+			if let Some(sealed) = self.algorithm.seal_timestamp(&inner_seal) {
+				if sealed > timestamp_now + MAX_TIMESTAMP_DRIFT_MILLIS {
+					return Err(Error::<B>::TooFarInFuture.into())
+				}
+				inherent_data.replace_data(sp_timestamp::INHERENT_IDENTIFIER, &sealed);
+				timestamp_now = sealed;
+			}
 
 			let check_block = B::new(block.header.clone(), inner_body);
 
@@ -367,8 +402,6 @@ impl<B, I, C, S, Algorithm, CAW> BlockImport<B> for PowBlockImport<B, I, C, S, A
 			block.body = Some(check_block.deconstruct().1);
 		}
 
-		let inner_seal = fetch_seal::<B>(block.post_digests.last(), block.header.hash())?;
-
 		let intermediate = block.take_intermediate::<PowIntermediate::<Algorithm::Difficulty>>(
 			INTERMEDIATE_KEY
 		)?;
@@ -576,8 +609,10 @@ pub fn start_mining_worker<Block, C, S, Algorithm, E, SO, CAW>(
 	let timer = UntilImportedOrTimeout::new(client.import_notification_stream(), timeout);
 	let worker = Arc::new(Mutex::new(MiningWorker::<Block, Algorithm, C> {
 		build: None,
+		recent_builds: Default::default(),
 		algorithm: algorithm.clone(),
 		block_import,
+		metadata_sinks: Vec::new(),
 	}));
 	let worker_ret = worker.clone();
 
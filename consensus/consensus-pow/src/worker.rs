@@ -16,17 +16,20 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use std::{pin::Pin, time::Duration, collections::HashMap, any::Any, borrow::Cow};
+use std::{pin::Pin, time::Duration, collections::{HashMap, VecDeque}, any::Any, borrow::Cow};
 use sc_client_api::ImportNotifications;
 use sp_runtime::{DigestItem, traits::Block as BlockT, generic::BlockId, traits::NumberFor};
 use sp_consensus::{Proposal, BlockOrigin, BlockImportParams, import_queue::BoxBlockImport};
-use futures::{prelude::*, task::{Context, Poll}};
+use futures::{prelude::*, channel::mpsc, task::{Context, Poll}};
 use futures_timer::Delay;
 use sp_core::U256;
 use log::*;
 
 use crate::{INTERMEDIATE_KEY, POW_ENGINE_ID, Seal, PowAlgorithm, PowIntermediate};
 
+/// Number of replaced builds on the current best hash kept to seal late solutions.
+const RECENT_BUILDS: usize = 8;
+
 /// Mining metadata. This is the information needed to start an actual mining loop.
 #[derive(Clone, Eq, PartialEq)]
 pub struct MiningMetadata<H, D, B: BlockT> {
@@ -55,8 +58,11 @@ pub struct MiningBuild<Block: BlockT, Algorithm: PowAlgorithm<Block>, C: sp_api:
 /// Mining worker that exposes structs to query the current mining build and submit mined blocks.
 pub struct MiningWorker<Block: BlockT, Algorithm: PowAlgorithm<Block>, C: sp_api::ProvideRuntimeApi<Block>> {
 	pub(crate) build: Option<MiningBuild<Block, Algorithm, C>>,
+	/// Builds replaced by a newer one on the same best hash, oldest first.
+	pub(crate) recent_builds: VecDeque<MiningBuild<Block, Algorithm, C>>,
 	pub(crate) algorithm: Algorithm,
 	pub(crate) block_import: BoxBlockImport<Block, sp_api::TransactionFor<C, Block>>,
+	pub(crate) metadata_sinks: Vec<mpsc::UnboundedSender<Option<MiningMetadata<Block::Hash, Algorithm::Difficulty, Block>>>>,
 }
 
 impl<Block, Algorithm, C> MiningWorker<Block, Algorithm, C> where
@@ -72,14 +78,56 @@ impl<Block, Algorithm, C> MiningWorker<Block, Algorithm, C> where
 	}
 
 	pub(crate) fn on_major_syncing(&mut self) {
+		self.recent_builds.clear();
+		if self.build.take().is_some() {
+			self.notify_metadata();
+		}
+	}
+
+	/// Drop the current build unless it is built on `best_hash`, the new best block: the block
+	/// it seals can no longer extend the best chain. The next build is started by the import of
+	/// the new best block. Returns whether a build was dropped.
+	pub fn on_new_best(&mut self, best_hash: &Block::Hash) -> bool {
+		if self.best_hash().map_or(true, |hash| hash == *best_hash) {
+			return false;
+		}
+		self.recent_builds.clear();
 		self.build = None;
+		self.notify_metadata();
+		true
 	}
 
 	pub(crate) fn on_build(
 		&mut self,
 		build: MiningBuild<Block, Algorithm, C>,
 	) {
+		match self.build.take() {
+			// Miners may still be working on the replaced build, it seals the same block.
+			Some(replaced) if replaced.metadata.best_hash == build.metadata.best_hash => {
+				if self.recent_builds.len() == RECENT_BUILDS {
+					self.recent_builds.pop_front();
+				}
+				self.recent_builds.push_back(replaced);
+			}
+			_ => self.recent_builds.clear(),
+		}
 		self.build = Some(build);
+		self.notify_metadata();
+	}
+
+	/// Stream of the mining metadata, yielding the new metadata every time a build is started and
+	/// `None` every time the current build is dropped.
+	pub fn metadata_stream(
+		&mut self,
+	) -> mpsc::UnboundedReceiver<Option<MiningMetadata<Block::Hash, Algorithm::Difficulty, Block>>> {
+		let (sink, stream) = mpsc::unbounded();
+		self.metadata_sinks.push(sink);
+		stream
+	}
+
+	fn notify_metadata(&mut self) {
+		let metadata = self.metadata();
+		self.metadata_sinks.retain(|sink| sink.unbounded_send(metadata.clone()).is_ok());
 	}
 
 	/// Get a copy of the current mining metadata, if available.
@@ -87,70 +135,23 @@ impl<Block, Algorithm, C> MiningWorker<Block, Algorithm, C> where
 		self.build.as_ref().map(|b| b.metadata.clone())
 	}
 
+	/// Get a copy of the mining metadata of the current or a recent build by its pre-hash.
+	pub fn metadata_for(
+		&self,
+		pre_hash: &Block::Hash,
+	) -> Option<MiningMetadata<Block::Hash, Algorithm::Difficulty, Block>> {
+		self.build.iter()
+			.chain(self.recent_builds.iter())
+			.find(|b| b.metadata.pre_hash == *pre_hash)
+			.map(|b| b.metadata.clone())
+	}
+
 	/// Submit a mined seal. The seal will be validated again. Returns true if the submission is
 	/// successful.
 	pub fn submit(&mut self, seal: Seal) -> bool {
 		if let Some(build) = self.build.take() {
-			match self.algorithm.verify(
-				&BlockId::Hash(build.metadata.best_hash),
-				&build.metadata.pre_hash,
-				build.metadata.pre_runtime.as_ref().map(|v| &v[..]),
-				&seal,
-				build.metadata.difficulty,
-			) {
-				Ok(true) => (),
-				Ok(false) => {
-					warn!(
-						target: "pow",
-						"Unable to import mined block: seal is invalid",
-					);
-					return false
-				},
-				Err(err) => {
-					warn!(
-						target: "pow",
-						"Unable to import mined block: {:?}",
-						err,
-					);
-					return false
-				},
-			}
-
-			let seal = DigestItem::Seal(POW_ENGINE_ID, seal);
-			let (header, body) = build.proposal.block.deconstruct();
-
-			let mut import_block = BlockImportParams::new(BlockOrigin::Own, header);
-			import_block.post_digests.push(seal);
-			import_block.body = Some(body);
-			import_block.storage_changes = Some(build.proposal.storage_changes);
-
-			let intermediate = PowIntermediate::<Algorithm::Difficulty> {
-				difficulty: Some(build.metadata.difficulty),
-			};
-
-			import_block.intermediates.insert(
-				Cow::from(INTERMEDIATE_KEY),
-				Box::new(intermediate) as Box<dyn Any>
-			);
-
-			match self.block_import.import_block(import_block, HashMap::default()) {
-				Ok(_) => {
-					info!(
-						target: "pow",
-						"✅ Successfully mined block on top of: {}",
-						build.metadata.best_hash
-					);
-					true
-				},
-				Err(err) => {
-					warn!(
-						target: "pow",
-						"Unable to import mined block: {:?}",
-						err,
-					);
-					false
-				},
-			}
+			self.notify_metadata();
+			self.import_build(build, seal).is_some()
 		} else {
 			warn!(
 				target: "pow",
@@ -159,6 +160,105 @@ impl<Block, Algorithm, C> MiningWorker<Block, Algorithm, C> where
 			false
 		}
 	}
+
+	/// Submit a mined seal for the current or a recent build, by its pre-hash. The seal will be
+	/// validated again. Returns true if the submission is successful.
+	pub fn submit_for(&mut self, pre_hash: &Block::Hash, seal: Seal) -> bool {
+		self.submit_for_block(pre_hash, seal).is_some()
+	}
+
+	/// Submit a mined seal for the current or a recent build, by its pre-hash. The seal will be
+	/// validated again. Returns the hash of the sealed block if it was imported.
+	pub fn submit_for_block(&mut self, pre_hash: &Block::Hash, seal: Seal) -> Option<Block::Hash> {
+		if self.build.as_ref().map_or(false, |b| b.metadata.pre_hash == *pre_hash) {
+			let build = self.build.take().expect("build was just checked; qed");
+			self.notify_metadata();
+			return self.import_build(build, seal)
+		}
+		match self.recent_builds.iter().position(|b| b.metadata.pre_hash == *pre_hash) {
+			Some(index) => {
+				let build = self.recent_builds.remove(index).expect("index was just found; qed");
+				self.import_build(build, seal)
+			},
+			None => {
+				warn!(
+					target: "pow",
+					"Unable to import mined block: build {} does not exist",
+					pre_hash,
+				);
+				None
+			},
+		}
+	}
+
+	/// Import the block of `build` sealed with `seal`, returning its hash if it was imported.
+	fn import_build(
+		&mut self,
+		build: MiningBuild<Block, Algorithm, C>,
+		seal: Seal,
+	) -> Option<Block::Hash> {
+		match self.algorithm.verify(
+			&BlockId::Hash(build.metadata.best_hash),
+			&build.metadata.pre_hash,
+			build.metadata.pre_runtime.as_ref().map(|v| &v[..]),
+			&seal,
+			build.metadata.difficulty,
+		) {
+			Ok(true) => (),
+			Ok(false) => {
+				warn!(
+					target: "pow",
+					"Unable to import mined block: seal is invalid",
+				);
+				return None
+			},
+			Err(err) => {
+				warn!(
+					target: "pow",
+					"Unable to import mined block: {:?}",
+					err,
+				);
+				return None
+			},
+		}
+
+		let seal = DigestItem::Seal(POW_ENGINE_ID, seal);
+		let (header, body) = build.proposal.block.deconstruct();
+
+		let mut import_block = BlockImportParams::new(BlockOrigin::Own, header);
+		import_block.post_digests.push(seal);
+		import_block.body = Some(body);
+		import_block.storage_changes = Some(build.proposal.storage_changes);
+
+		let intermediate = PowIntermediate::<Algorithm::Difficulty> {
+			difficulty: Some(build.metadata.difficulty),
+		};
+
+		import_block.intermediates.insert(
+			Cow::from(INTERMEDIATE_KEY),
+			Box::new(intermediate) as Box<dyn Any>
+		);
+		let hash = import_block.post_hash();
+
+		match self.block_import.import_block(import_block, HashMap::default()) {
+			Ok(_) => {
+				info!(
+					target: "pow",
+					"✅ Successfully mined block on top of: {}",
+					build.metadata.best_hash
+				);
+				Some(hash)
+			},
+			Err(err) => {
+				warn!(
+					target: "pow",
+					"Unable to import mined block: {:?}",
+					err,
+				);
+				None
+			},
+		}
+	}
 }
 
 /// A stream that waits for a block import or timeout.
@@ -17,7 +17,10 @@
 use compute::Light;
 use either::Either;
 use keccak::{keccak_512, H256};
+#[cfg(feature = "mmap")]
 use memmap::MmapMut;
+#[cfg(not(feature = "mmap"))]
+use self::no_mmap::MmapMut;
 use parking_lot::Mutex;
 use seed_compute::SeedHashCompute;
 
@@ -34,6 +37,40 @@ use std::{
 
 type Cache = Either<Vec<Node>, MmapMut>;
 
+/// Stand-in for `memmap` when the crate is built without the `mmap` feature. Mapping always
+/// fails, so `OptimizeFor::Memory` falls back to heap allocated caches.
+#[cfg(not(feature = "mmap"))]
+mod no_mmap {
+    use std::{fs::File, io};
+
+    pub enum MmapMut {}
+
+    impl MmapMut {
+        pub unsafe fn map_mut(_file: &File) -> io::Result<MmapMut> {
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "memory mapped caches are not supported by this build",
+            ))
+        }
+
+        pub fn len(&self) -> usize {
+            match *self {}
+        }
+
+        pub fn flush(&self) -> io::Result<()> {
+            match *self {}
+        }
+
+        pub fn as_ptr(&self) -> *const u8 {
+            match *self {}
+        }
+
+        pub fn as_mut_ptr(&mut self) -> *mut u8 {
+            match *self {}
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum OptimizeFor {
     Cpu,
@@ -214,7 +251,7 @@ fn make_memory_cache(num_nodes: usize, ident: &H256) -> Vec<Node> {
     nodes
 }
 
-fn cache_path<'a, P: Into<Cow<'a, Path>>>(path: P, ident: &H256) -> PathBuf {
+pub(crate) fn cache_path<'a, P: Into<Cow<'a, Path>>>(path: P, ident: &H256) -> PathBuf {
     let mut buf = path.into().into_owned();
     buf.push(to_hex(ident));
     buf
@@ -24,9 +24,16 @@ use keccak::{keccak_256, keccak_512, H256};
 use progpow::{generate_cdag, keccak_f800_long, keccak_f800_short, progpow, CDag};
 use seed_compute::SeedHashCompute;
 use shared::*;
-use std::io;
+use std::io::{self, BufReader, BufWriter, Read, Write};
 
-use std::{mem, path::Path};
+use std::{
+    cell::RefCell,
+    collections::BTreeSet,
+    fs, mem,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+};
 
 const MIX_WORDS: usize = ETHASH_MIX_BYTES / 4;
 const MIX_NODES: usize = MIX_WORDS / NODE_WORDS;
@@ -119,6 +126,142 @@ impl Light {
         self.cache.flush()?;
         Ok(self.cache.cache_path())
     }
+
+    /// The nodes of the light cache.
+    pub fn cache_nodes(&self) -> &[NodeBytes] {
+        let cache: &[Node] = self.cache.as_ref();
+        // `Node` is a `repr(C)` union of `NodeBytes` and arrays of the same size.
+        unsafe { std::slice::from_raw_parts(cache.as_ptr() as *const NodeBytes, cache.len()) }
+    }
+
+    /// The indices of the cache nodes read computing the Ethash proof of work of `nonce` for
+    /// `header_hash`, sorted, to prove the computation to a verifier without the cache. ProgPoW
+    /// reads the cache differently.
+    pub fn cache_reads(&self, header_hash: &H256, nonce: u64) -> Vec<u32> {
+        let full_size = get_data_size(self.block_number);
+        let cache: &[Node] = self.cache.as_ref();
+        let reads = RefCell::new(BTreeSet::new());
+        hash_compute(
+            |index| {
+                calculate_dag_item_with(index, cache, |parent| {
+                    reads.borrow_mut().insert(parent);
+                })
+            },
+            full_size,
+            header_hash,
+            nonce,
+        );
+        reads.into_inner().into_iter().collect()
+    }
+}
+
+/// Full dataset of an epoch, the DAG miners hash against.
+///
+/// Hashing against the dataset is much faster than computing its items from the light cache on
+/// the fly, at the cost of generating it first, which takes minutes and over a gigabyte of memory.
+pub struct Full {
+    light: Arc<Light>,
+    dataset: Vec<Node>,
+}
+
+impl Full {
+    /// Generate the dataset of the epoch of `light`, split across `threads` threads.
+    ///
+    /// ProgPoW epochs have no dataset, their hashes are computed from the light cache.
+    pub fn generate(light: Arc<Light>, threads: usize) -> Self {
+        if let Algorithm::Progpow(_) = light.algorithm {
+            return Full { light, dataset: Vec::new() };
+        }
+
+        let items = get_data_size(light.block_number) / NODE_BYTES;
+        let threads = threads.max(1);
+        let chunk = (items + threads - 1) / threads;
+        let workers: Vec<_> = (0..threads)
+            .map(|worker| {
+                let light = light.clone();
+                let start = (worker * chunk).min(items);
+                let end = ((worker + 1) * chunk).min(items);
+                thread::spawn(move || {
+                    let cache: &[Node] = light.cache.as_ref();
+                    (start..end)
+                        .map(|index| calculate_dag_item(index as u32, cache))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let mut dataset = Vec::with_capacity(items);
+        for worker in workers {
+            dataset.extend(worker.join().expect("dataset generation doesn't panic; qed"));
+        }
+        Full { light, dataset }
+    }
+
+    /// Load the dataset of the epoch of `light` written to `path` by `to_file`. Only the size of
+    /// the file is checked: a damaged dataset yields solutions the node rejects.
+    pub fn from_file(light: Arc<Light>, path: &Path) -> io::Result<Self> {
+        if let Algorithm::Progpow(_) = light.algorithm {
+            return Ok(Full { light, dataset: Vec::new() });
+        }
+
+        let file = fs::File::open(path)?;
+        let size = get_data_size(light.block_number);
+        let found = file.metadata()?.len();
+        if found != size as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} bytes instead of {}", found, size),
+            ));
+        }
+
+        let mut file = BufReader::new(file);
+        let items = size / NODE_BYTES;
+        let mut dataset = Vec::with_capacity(items);
+        for _ in 0..items {
+            let mut node = Node { bytes: [0u8; NODE_BYTES] };
+            file.read_exact(node.as_bytes_mut())?;
+            dataset.push(node);
+        }
+        Ok(Full { light, dataset })
+    }
+
+    /// Write the dataset to `path`, through a temporary file so that a partially written dataset
+    /// is never found there.
+    pub fn to_file(&self, path: &Path) -> io::Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+
+        let mut file = BufWriter::new(fs::File::create(&tmp)?);
+        for node in &self.dataset {
+            file.write_all(node.as_bytes())?;
+        }
+        file.flush()?;
+        drop(file);
+        fs::rename(&tmp, path)
+    }
+
+    /// Block number the dataset was generated for.
+    pub fn block_number(&self) -> u64 {
+        self.light.block_number
+    }
+
+    /// Calculate the boundary data of `header_hash` and `nonce` for block `block_number` of the
+    /// epoch of the dataset.
+    pub fn compute(&self, header_hash: &H256, nonce: u64, block_number: u64) -> ProofOfWork {
+        match self.light.algorithm {
+            Algorithm::Progpow(_) => self.light.compute(header_hash, nonce, block_number),
+            Algorithm::Hashimoto => {
+                let full_size = self.dataset.len() * NODE_BYTES;
+                hash_compute(
+                    |index| self.dataset[index as usize].clone(),
+                    full_size,
+                    header_hash,
+                    nonce,
+                )
+            }
+        }
+    }
 }
 
 pub fn slow_hash_block_number(block_number: u64) -> H256 {
@@ -168,10 +311,21 @@ pub fn quick_get_difficulty(
 /// `nonce` - The nonce to pack into the mix
 pub fn light_compute(light: &Light, header_hash: &H256, nonce: u64) -> ProofOfWork {
     let full_size = get_data_size(light.block_number);
-    hash_compute(light, full_size, header_hash, nonce)
+    // deref once for better performance
+    let cache: &[Node] = light.cache.as_ref();
+    hash_compute(
+        |index| calculate_dag_item(index, cache),
+        full_size,
+        header_hash,
+        nonce,
+    )
 }
 
-fn hash_compute(light: &Light, full_size: usize, header_hash: &H256, nonce: u64) -> ProofOfWork {
+/// Hashimoto over a dataset of `full_size` bytes, whose items are given by `dag_item`.
+fn hash_compute<F>(dag_item: F, full_size: usize, header_hash: &H256, nonce: u64) -> ProofOfWork
+where
+    F: Fn(u32) -> Node,
+{
     macro_rules! make_const_array {
         ($n:expr, $value:expr) => {{
             // We use explicit lifetimes to ensure that val's borrow is invalidated until the
@@ -228,8 +382,6 @@ fn hash_compute(light: &Light, full_size: usize, header_hash: &H256, nonce: u64)
 
     let page_size = 4 * MIX_WORDS;
     let num_full_pages = (full_size / page_size) as u32;
-    // deref once for better performance
-    let cache: &[Node] = light.cache.as_ref();
     let first_val = buf.half_mix.as_words()[0];
 
     debug_assert_eq!(MIX_NODES, 2);
@@ -247,7 +399,7 @@ fn hash_compute(light: &Light, full_size: usize, header_hash: &H256, nonce: u64)
 
         // MIX_NODES
         for n in 0..2 {
-            let tmp_node = calculate_dag_item(index * MIX_NODES as u32 + n as u32, cache);
+            let tmp_node = dag_item(index * MIX_NODES as u32 + n as u32);
 
             // NODE_WORDS
             for (a, b) in mix[n].as_words_mut().iter_mut().zip(tmp_node.as_words()) {
@@ -306,7 +458,14 @@ fn hash_compute(light: &Light, full_size: usize, header_hash: &H256, nonce: u64)
 
 // TODO: Use the `simd` crate
 pub fn calculate_dag_item(node_index: u32, cache: &[Node]) -> Node {
+    calculate_dag_item_with(node_index, cache, |_| ())
+}
+
+/// `calculate_dag_item`, calling `read` with the index of every cache node read.
+#[inline(always)]
+fn calculate_dag_item_with<R: FnMut(u32)>(node_index: u32, cache: &[Node], mut read: R) -> Node {
     let num_parent_nodes = cache.len();
+    read(node_index % num_parent_nodes as u32);
     let mut ret = cache[node_index as usize % num_parent_nodes].clone();
     ret.as_words_mut()[0] ^= node_index;
 
@@ -316,6 +475,7 @@ pub fn calculate_dag_item(node_index: u32, cache: &[Node]) -> Node {
     for i in 0..ETHASH_DATASET_PARENTS as u32 {
         let parent_index = fnv_hash(node_index ^ i, ret.as_words()[i as usize % NODE_WORDS])
             % num_parent_nodes as u32;
+        read(parent_index);
         let parent = &cache[parent_index as usize];
 
         unroll! {
@@ -421,6 +581,49 @@ mod test {
         assert_eq!(result.value[..], boundary[..]);
     }
 
+    #[test]
+    fn test_dataset_compute_matches_light() {
+        let hash = [0x42; 32];
+        let tempdir = TempDir::new("").unwrap();
+        let light = NodeCacheBuilder::new(None, u64::max_value()).light(tempdir.path(), 0);
+        let cache: &[Node] = light.cache.as_ref();
+
+        // A dataset small enough to generate here exercises the same code as a full one.
+        let full_size = 1024 * 4 * MIX_WORDS;
+        let dataset: Vec<_> = (0..(full_size / NODE_BYTES) as u32)
+            .map(|index| calculate_dag_item(index, cache))
+            .collect();
+
+        for nonce in 0..4 {
+            let light_result = hash_compute(|index| calculate_dag_item(index, cache), full_size, &hash, nonce);
+            let full_result = hash_compute(|index| dataset[index as usize].clone(), full_size, &hash, nonce);
+            assert_eq!(full_result.mix_hash, light_result.mix_hash);
+            assert_eq!(full_result.value, light_result.value);
+        }
+    }
+
+    #[test]
+    fn test_light_cache_reads_suffice() {
+        let hash = [0x42; 32];
+        let nonce = 7;
+        let tempdir = TempDir::new("").unwrap();
+        let light = NodeCacheBuilder::new(None, u64::max_value()).light(tempdir.path(), 0);
+        let reads = light.cache_reads(&hash, nonce);
+        assert!(reads.len() <= ETHASH_ACCESSES * MIX_NODES * (ETHASH_DATASET_PARENTS as usize + 1));
+
+        // Zeroing every node not read doesn't change the result.
+        let cache: &[Node] = light.cache.as_ref();
+        let mut sparse = vec![Node { bytes: [0; NODE_BYTES] }; cache.len()];
+        for &index in &reads {
+            sparse[index as usize] = cache[index as usize].clone();
+        }
+        let full_size = get_data_size(0);
+        let expected = light_compute(&light, &hash, nonce);
+        let result = hash_compute(|index| calculate_dag_item(index, &sparse), full_size, &hash, nonce);
+        assert_eq!(result.mix_hash, expected.mix_hash);
+        assert_eq!(result.value, expected.value);
+    }
+
     #[test]
     fn test_drop_old_data() {
         let tempdir = TempDir::new("").unwrap();
@@ -0,0 +1,243 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of OpenEthereum.
+
+// OpenEthereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// OpenEthereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Import of light caches generated by other ethash implementations, so a farm can generate an
+//! epoch's cache once and distribute it to all of its nodes.
+
+use cache::{cache_path, NodeCacheBuilder, OptimizeFor};
+use seed_compute::SeedHashCompute;
+use shared::{get_cache_size, to_hex, ETHASH_EPOCH_LENGTH, NODE_BYTES};
+
+use std::{
+    fs,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Magic words go-ethereum writes in front of its cache and dataset dumps.
+const GETH_DUMP_MAGIC: [u32; 2] = [0xbaddcafe, 0xfee1dead];
+
+/// On-disk layout of a light cache file.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum CacheFileFormat {
+    /// Raw cache nodes, as written by this crate and OpenEthereum.
+    Parity,
+    /// Raw cache nodes preceded by go-ethereum's dump magic.
+    Geth,
+}
+
+impl CacheFileFormat {
+    /// Guess the format of a cache file from its contents.
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.len() >= 8 && bytes[..8] == geth_magic()[..] {
+            CacheFileFormat::Geth
+        } else {
+            CacheFileFormat::Parity
+        }
+    }
+}
+
+fn geth_magic() -> [u8; 8] {
+    let mut magic = [0u8; 8];
+    magic[..4].copy_from_slice(&GETH_DUMP_MAGIC[0].to_le_bytes());
+    magic[4..].copy_from_slice(&GETH_DUMP_MAGIC[1].to_le_bytes());
+    magic
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Check that a go-ethereum file name (`cache-R23-<seed prefix>`) matches the expected seed.
+/// Names not following that scheme are accepted as is.
+fn check_geth_file_name(source: &Path, seed_hash: &[u8; 32]) -> io::Result<()> {
+    let name = match source.file_name().and_then(|n| n.to_str()) {
+        Some(name) => name,
+        None => return Ok(()),
+    };
+
+    if name.starts_with("full-R") {
+        return Err(invalid_data(format!(
+            "{} is a full dataset; only light caches are used for verification",
+            name
+        )));
+    }
+
+    if name.starts_with("cache-R") {
+        let expected = to_hex(&seed_hash[..8]);
+        match name.rsplit('-').next() {
+            Some(prefix) if prefix == expected => {}
+            _ => {
+                return Err(invalid_data(format!(
+                    "{} does not belong to the epoch with seed hash prefix {}",
+                    name, expected
+                )))
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Import the light cache of `epoch` from `source` into `cache_dir`.
+///
+/// The cache is checked against the epoch's seed hash: go-ethereum file names must carry the
+/// right seed prefix and the size must match the epoch. With `verify` the cache is also
+/// regenerated from the seed and compared byte by byte, which is as slow as generating it.
+///
+/// Returns the path the cache was written to.
+pub fn import_cache(
+    cache_dir: &Path,
+    epoch: u64,
+    source: &Path,
+    format: Option<CacheFileFormat>,
+    verify: bool,
+) -> io::Result<PathBuf> {
+    let seed_hash = SeedHashCompute::default().hash_epoch(epoch);
+
+    let bytes = fs::read(source)?;
+    let format = format.unwrap_or_else(|| CacheFileFormat::detect(&bytes));
+    let nodes = match format {
+        CacheFileFormat::Parity => &bytes[..],
+        CacheFileFormat::Geth => {
+            check_geth_file_name(source, &seed_hash)?;
+            if bytes.len() < 8 || bytes[..8] != geth_magic()[..] {
+                return Err(invalid_data("Missing go-ethereum dump magic".into()));
+            }
+            &bytes[8..]
+        }
+    };
+
+    import_cache_nodes(cache_dir, epoch, nodes, verify)
+}
+
+/// Import the raw nodes of the light cache of `epoch`, e.g. fetched from a peer, into
+/// `cache_dir`.
+///
+/// The size must match the epoch. With `verify` the cache is also regenerated from the seed and
+/// compared byte by byte, which is as slow as generating it.
+///
+/// Returns the path the cache was written to.
+pub fn import_cache_nodes(
+    cache_dir: &Path,
+    epoch: u64,
+    nodes: &[u8],
+    verify: bool,
+) -> io::Result<PathBuf> {
+    let block_number = epoch * ETHASH_EPOCH_LENGTH;
+    let seed_hash = SeedHashCompute::default().hash_epoch(epoch);
+
+    let expected_size = get_cache_size(block_number);
+    if nodes.len() != expected_size {
+        return Err(invalid_data(format!(
+            "Cache of epoch {} must be {} bytes, found {}",
+            epoch,
+            expected_size,
+            nodes.len()
+        )));
+    }
+
+    if verify {
+        let builder = NodeCacheBuilder::new(OptimizeFor::Cpu, u64::max_value());
+        let generated = builder.new_cache(cache_dir.to_path_buf(), block_number);
+        let matches = generated
+            .as_ref()
+            .iter()
+            .zip(nodes.chunks(NODE_BYTES))
+            .all(|(node, imported)| &node.as_bytes()[..] == imported);
+        if !matches {
+            return Err(invalid_data(format!(
+                "Cache content does not match the seed hash 0x{}",
+                to_hex(&seed_hash)
+            )));
+        }
+    }
+
+    fs::create_dir_all(cache_dir)?;
+    let path = cache_path(cache_dir, &seed_hash);
+    fs::write(&path, nodes)?;
+
+    Ok(path)
+}
+
+/// Path of the light cache file of `epoch` in `cache_dir`.
+pub fn cache_file(cache_dir: &Path, epoch: u64) -> PathBuf {
+    cache_path(cache_dir, &SeedHashCompute::default().hash_epoch(epoch))
+}
+
+/// Path of the full dataset file of `epoch` in `cache_dir`.
+pub fn dataset_file(cache_dir: &Path, epoch: u64) -> PathBuf {
+    let seed_hash = SeedHashCompute::default().hash_epoch(epoch);
+    cache_dir.join(format!("full-{}", to_hex(&seed_hash)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn detects_geth_magic() {
+        let mut bytes = geth_magic().to_vec();
+        bytes.extend_from_slice(&[0u8; NODE_BYTES]);
+
+        assert_eq!(CacheFileFormat::detect(&bytes), CacheFileFormat::Geth);
+        assert_eq!(CacheFileFormat::detect(&bytes[8..]), CacheFileFormat::Parity);
+    }
+
+    #[test]
+    fn rejects_wrong_size() {
+        let tempdir = TempDir::new("").unwrap();
+        let source = tempdir.path().join("cache");
+        fs::write(&source, vec![0u8; NODE_BYTES]).unwrap();
+
+        let err = import_cache(tempdir.path(), 0, &source, None, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_geth_cache_of_other_epoch() {
+        let tempdir = TempDir::new("").unwrap();
+        let source = tempdir.path().join("cache-R23-0000000000000000");
+        fs::write(&source, geth_magic()).unwrap();
+
+        let err = import_cache(tempdir.path(), 1, &source, None, false).unwrap_err();
+        assert!(err.to_string().contains("does not belong"));
+    }
+
+    #[test]
+    fn imports_and_verifies_generated_cache() {
+        let source_dir = TempDir::new("").unwrap();
+        let target_dir = TempDir::new("").unwrap();
+
+        let builder = NodeCacheBuilder::new(OptimizeFor::Cpu, u64::max_value());
+        let mut cache = builder.new_cache(source_dir.path().to_path_buf(), 0);
+        cache.flush().unwrap();
+        let source = cache.cache_path().to_path_buf();
+
+        let path = import_cache(target_dir.path(), 0, &source, None, true).unwrap();
+        assert_eq!(fs::read(path).unwrap(), fs::read(source).unwrap());
+    }
+
+    #[test]
+    fn cache_file_is_where_caches_are_written() {
+        let tempdir = TempDir::new("").unwrap();
+
+        let builder = NodeCacheBuilder::new(OptimizeFor::Cpu, u64::max_value());
+        let cache = builder.new_cache(tempdir.path().to_path_buf(), ETHASH_EPOCH_LENGTH);
+        assert_eq!(cache.cache_path(), cache_file(tempdir.path(), 1).as_path());
+    }
+}
@@ -0,0 +1,154 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of OpenEthereum.
+
+// OpenEthereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// OpenEthereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Integrity check of the light cache files kept in a cache directory.
+//!
+//! A cache file is only checked for its size when loaded, so a file of the right size whose
+//! content was damaged on disk would verify every seal of its epoch wrongly. The check
+//! regenerates the cache of every file found from the seed hash naming it and compares both,
+//! which is as slow as generating them.
+
+use cache::{NodeCacheBuilder, OptimizeFor};
+use seed_compute::SeedHashCompute;
+use shared::{to_hex, ETHASH_EPOCH_LENGTH};
+
+use std::{
+    collections::HashMap,
+    fs,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// A light cache file found corrupt, and replaced by a regenerated one.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct RepairedCache {
+    /// The epoch of the cache.
+    pub epoch: u64,
+    /// The cache file.
+    pub path: PathBuf,
+    /// What was wrong with the file.
+    pub problem: String,
+}
+
+/// Epochs up to `max_epoch` whose light cache file is in `cache_dir`, in ascending order. Files
+/// are matched to their epoch through the seed hash naming them, other files are ignored.
+pub fn cached_epochs(cache_dir: &Path, max_epoch: u64) -> io::Result<Vec<u64>> {
+    let entries = match fs::read_dir(cache_dir) {
+        Ok(entries) => entries,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+
+    let mut names = HashMap::new();
+    let mut seed_hash = [0u8; 32];
+    for epoch in 0..=max_epoch {
+        names.insert(to_hex(&seed_hash), epoch);
+        seed_hash = SeedHashCompute::resume_compute_seedhash(seed_hash, epoch, epoch + 1);
+    }
+
+    let mut epochs = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if let Some(epoch) = entry.file_name().to_str().and_then(|name| names.get(name)) {
+            epochs.push(*epoch);
+        }
+    }
+    epochs.sort();
+
+    Ok(epochs)
+}
+
+/// The light cache of `epoch`, regenerated from its seed hash.
+pub(crate) fn generate_cache_bytes(cache_dir: &Path, epoch: u64) -> Vec<u8> {
+    // Optimized for CPU, the cache stays on the heap and never touches the file.
+    let builder = NodeCacheBuilder::new(OptimizeFor::Cpu, u64::max_value());
+    let cache = builder.new_cache(cache_dir.to_path_buf(), epoch * ETHASH_EPOCH_LENGTH);
+    cache
+        .as_ref()
+        .iter()
+        .flat_map(|node| node.as_bytes().iter().cloned())
+        .collect()
+}
+
+/// What is wrong with the cache file `found`, compared to the `expected` cache.
+pub(crate) fn cache_problem(found: &[u8], expected: &[u8]) -> Option<String> {
+    if found.len() != expected.len() {
+        Some(format!("{} bytes instead of {}", found.len(), expected.len()))
+    } else if found != expected {
+        Some("content does not match the seed hash".into())
+    } else {
+        None
+    }
+}
+
+/// Replace the file at `path` by `bytes`, without ever leaving a partially written file there.
+pub(crate) fn replace_file(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    fs::write(&tmp, bytes)?;
+    fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use import::cache_file;
+    use tempdir::TempDir;
+
+    #[test]
+    fn finds_the_epochs_of_cache_files() {
+        let tempdir = TempDir::new("").unwrap();
+        fs::write(cache_file(tempdir.path(), 0), b"").unwrap();
+        fs::write(cache_file(tempdir.path(), 2), b"").unwrap();
+        fs::write(tempdir.path().join("unrelated"), b"").unwrap();
+
+        assert_eq!(cached_epochs(tempdir.path(), 3).unwrap(), vec![0, 2]);
+        assert_eq!(cached_epochs(tempdir.path(), 1).unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn missing_cache_dir_has_no_epochs() {
+        let tempdir = TempDir::new("").unwrap();
+        let missing = tempdir.path().join("missing");
+
+        assert_eq!(cached_epochs(&missing, 3).unwrap(), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn reports_size_and_content_problems() {
+        let expected = vec![1u8, 2, 3, 4];
+
+        assert_eq!(cache_problem(&expected, &expected), None);
+        assert_eq!(cache_problem(&[1, 2], &expected), Some("2 bytes instead of 4".into()));
+        assert_eq!(
+            cache_problem(&[1, 2, 3, 5], &expected),
+            Some("content does not match the seed hash".into())
+        );
+    }
+
+    #[test]
+    fn generated_cache_matches_written_cache() {
+        let tempdir = TempDir::new("").unwrap();
+
+        let builder = NodeCacheBuilder::new(OptimizeFor::Cpu, u64::max_value());
+        let mut cache = builder.new_cache(tempdir.path().to_path_buf(), 0);
+        cache.flush().unwrap();
+
+        let written = fs::read(cache.cache_path()).unwrap();
+        assert_eq!(cache_problem(&written, &generate_cache_bytes(tempdir.path(), 0)), None);
+    }
+}
@@ -16,6 +16,7 @@
 
 extern crate either;
 extern crate ethereum_types;
+#[cfg(feature = "mmap")]
 extern crate memmap;
 extern crate parking_lot;
 extern crate primal;
@@ -41,6 +42,8 @@ pub mod compute;
 mod compute;
 
 mod cache;
+mod import;
+mod integrity;
 mod keccak;
 mod seed_compute;
 mod shared;
@@ -51,17 +54,20 @@ pub mod progpow;
 mod progpow;
 
 pub use cache::{NodeCacheBuilder, OptimizeFor};
-use compute::Light;
-pub use compute::{quick_get_difficulty, slow_hash_block_number, ProofOfWork};
+pub use compute::{quick_get_difficulty, slow_hash_block_number, Full, Light, ProofOfWork};
+pub use import::{cache_file, dataset_file, import_cache, import_cache_nodes, CacheFileFormat};
+pub use integrity::{cached_epochs, RepairedCache};
 use ethereum_types::{BigEndianHash, U256, U512};
 use keccak::H256;
 use parking_lot::Mutex;
 pub use seed_compute::SeedHashCompute;
-pub use shared::ETHASH_EPOCH_LENGTH;
+pub use shared::{get_cache_size, get_data_size, ETHASH_EPOCH_LENGTH};
 use std::{
+    collections::HashMap,
     convert::TryFrom,
-    mem,
+    fs, io, mem,
     path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
 use std::sync::Arc;
@@ -79,6 +85,7 @@ pub struct EthashManager {
     cache: Mutex<LightCache>,
     cache_dir: PathBuf,
     progpow_transition: u64,
+    generation_times: Mutex<HashMap<u64, Duration>>,
 }
 
 impl EthashManager {
@@ -101,70 +108,184 @@ impl EthashManager {
                 prev_epoch: None,
                 prev: None,
             }),
+            generation_times: Mutex::new(HashMap::new()),
         }
     }
 
+    /// Time taken to generate the light cache of `epoch`, if it was generated rather than
+    /// loaded from the cache directory.
+    pub fn cache_generation_time(&self, epoch: u64) -> Option<Duration> {
+        self.generation_times.lock().get(&epoch).cloned()
+    }
+
+    /// Check the light cache files in the cache directory, up to the one of `max_epoch`,
+    /// against caches regenerated from their seed hashes, and replace the corrupt ones. A corrupt
+    /// cache already loaded is dropped, so the next verification of its epoch loads the repaired
+    /// file. As slow as generating every cache found: run it in the background.
+    ///
+    /// Returns the caches repaired.
+    pub fn repair_caches(&self, max_epoch: u64) -> io::Result<Vec<RepairedCache>> {
+        let mut repaired = Vec::new();
+        for epoch in cached_epochs(&self.cache_dir, max_epoch)? {
+            let path = cache_file(&self.cache_dir, epoch);
+            let expected = integrity::generate_cache_bytes(&self.cache_dir, epoch);
+            let found = match fs::read(&path) {
+                Ok(found) => found,
+                // Removed since it was listed, by `NodeCache::flush`.
+                Err(ref err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+            let problem = match integrity::cache_problem(&found, &expected) {
+                Some(problem) => problem,
+                None => continue,
+            };
+
+            // Loading a cache takes the lock, so it never reads the file while it's replaced.
+            let mut lights = self.cache.lock();
+            integrity::replace_file(&path, &expected)?;
+            if lights.recent_epoch == Some(epoch) {
+                lights.recent_epoch = None;
+                lights.recent = None;
+            }
+            if lights.prev_epoch == Some(epoch) {
+                lights.prev_epoch = None;
+                lights.prev = None;
+            }
+            repaired.push(RepairedCache { epoch, path, problem });
+        }
+
+        Ok(repaired)
+    }
+
+    /// Generate the light cache of `epoch` into the cache directory ahead of time, unless it's
+    /// already there, so that the first verification in the epoch loads it instead of stalling
+    /// on its generation. Verifications carry on meanwhile, and the file only appears once
+    /// complete.
+    ///
+    /// Returns whether the cache was generated.
+    pub fn prepare_cache(&self, epoch: u64) -> io::Result<bool> {
+        let path = cache_file(&self.cache_dir, epoch);
+        if path.exists() {
+            return Ok(false);
+        }
+        fs::create_dir_all(&self.cache_dir)?;
+        let bytes = integrity::generate_cache_bytes(&self.cache_dir, epoch);
+        integrity::replace_file(&path, &bytes)?;
+        Ok(true)
+    }
+
+    /// Generate the full dataset of `epoch` on `threads` threads into the cache directory ahead
+    /// of time, unless it's already there, for `full` to load instead of generating it. The light
+    /// cache of the epoch is prepared first, and the dataset of two epochs before removed.
+    ///
+    /// Returns whether the dataset was generated.
+    pub fn prepare_dataset(&self, epoch: u64, threads: usize) -> io::Result<bool> {
+        let path = dataset_file(&self.cache_dir, epoch);
+        if path.exists() {
+            return Ok(false);
+        }
+        self.prepare_cache(epoch)?;
+        let light = self
+            .nodecache_builder
+            .light_from_file(&self.cache_dir, epoch * ETHASH_EPOCH_LENGTH)?;
+        Full::generate(Arc::new(light), threads).to_file(&path)?;
+
+        if let Some(stale) = epoch.checked_sub(2) {
+            fs::remove_file(dataset_file(&self.cache_dir, stale)).or_else(|err| match err.kind() {
+                io::ErrorKind::NotFound => Ok(()),
+                _ => Err(err),
+            })?;
+        }
+        Ok(true)
+    }
+
     /// Calculate the light client data
     /// `block_number` - Block number to check
     /// `light` - The light client handler
     /// `header_hash` - The header hash to pack into the mix
     /// `nonce` - The nonce to pack into the mix
     pub fn compute_light(&self, block_number: u64, header_hash: &H256, nonce: u64) -> ProofOfWork {
-        let epoch = block_number / ETHASH_EPOCH_LENGTH;
-        let light = {
-            let mut lights = self.cache.lock();
-            let light = if block_number == self.progpow_transition {
-                // we need to regenerate the cache to trigger algorithm change to progpow inside `Light`
-                None
-            } else {
-                match lights.recent_epoch.clone() {
-                    Some(ref e) if *e == epoch => lights.recent.clone(),
-                    _ => match lights.prev_epoch.clone() {
-                        Some(e) if e == epoch => {
-                            // don't swap if recent is newer.
-                            if lights.recent_epoch > lights.prev_epoch {
-                                None
-                            } else {
-                                // swap
-                                let t = lights.prev_epoch;
-                                lights.prev_epoch = lights.recent_epoch;
-                                lights.recent_epoch = t;
-                                let t = lights.prev.clone();
-                                lights.prev = lights.recent.clone();
-                                lights.recent = t;
-                                lights.recent.clone()
-                            }
-                        }
-                        _ => None,
-                    },
+        self.light(block_number).compute(header_hash, nonce, block_number)
+    }
+
+    /// The light cache of the epoch of `block_number`, to read its nodes, see
+    /// `Light::cache_reads`.
+    pub fn light_cache(&self, block_number: u64) -> Arc<Light> {
+        self.light(block_number)
+    }
+
+    /// The full dataset of the epoch of `block_number`, loaded from the cache directory when
+    /// prepared there by `prepare_dataset`, otherwise generated on `threads` threads from its
+    /// light cache.
+    pub fn full(&self, block_number: u64, threads: usize) -> Full {
+        let light = self.light(block_number);
+        let path = dataset_file(&self.cache_dir, block_number / ETHASH_EPOCH_LENGTH);
+        match Full::from_file(light.clone(), &path) {
+            Ok(full) => full,
+            Err(e) => {
+                if e.kind() != io::ErrorKind::NotFound {
+                    warn!("Dataset file {} not loaded: {}", path.display(), e);
                 }
-            };
+                Full::generate(light, threads)
+            }
+        }
+    }
 
-            match light {
-                None => {
-                    let light = match self
-                        .nodecache_builder
-                        .light_from_file(&self.cache_dir, block_number)
-                    {
-                        Ok(light) => Arc::new(light),
-                        Err(e) => {
-                            debug!("Light cache file not found for {}:{}", block_number, e);
-                            let mut light =
-                                self.nodecache_builder.light(&self.cache_dir, block_number);
-                            if let Err(e) = light.to_file() {
-                                warn!("Light cache file write error: {}", e);
-                            }
-                            Arc::new(light)
+    fn light(&self, block_number: u64) -> Arc<Light> {
+        let epoch = block_number / ETHASH_EPOCH_LENGTH;
+        let mut lights = self.cache.lock();
+        let light = if block_number == self.progpow_transition {
+            // we need to regenerate the cache to trigger algorithm change to progpow inside `Light`
+            None
+        } else {
+            match lights.recent_epoch.clone() {
+                Some(ref e) if *e == epoch => lights.recent.clone(),
+                _ => match lights.prev_epoch.clone() {
+                    Some(e) if e == epoch => {
+                        // don't swap if recent is newer.
+                        if lights.recent_epoch > lights.prev_epoch {
+                            None
+                        } else {
+                            // swap
+                            let t = lights.prev_epoch;
+                            lights.prev_epoch = lights.recent_epoch;
+                            lights.recent_epoch = t;
+                            let t = lights.prev.clone();
+                            lights.prev = lights.recent.clone();
+                            lights.recent = t;
+                            lights.recent.clone()
                         }
-                    };
-                    lights.prev_epoch = mem::replace(&mut lights.recent_epoch, Some(epoch));
-                    lights.prev = mem::replace(&mut lights.recent, Some(light.clone()));
-                    light
-                }
-                Some(light) => light,
+                    }
+                    _ => None,
+                },
             }
         };
-        light.compute(header_hash, nonce, block_number)
+
+        match light {
+            None => {
+                let light = match self
+                    .nodecache_builder
+                    .light_from_file(&self.cache_dir, block_number)
+                {
+                    Ok(light) => Arc::new(light),
+                    Err(e) => {
+                        debug!("Light cache file not found for {}:{}", block_number, e);
+                        let started = Instant::now();
+                        let mut light =
+                            self.nodecache_builder.light(&self.cache_dir, block_number);
+                        self.generation_times.lock().insert(epoch, started.elapsed());
+                        if let Err(e) = light.to_file() {
+                            warn!("Light cache file write error: {}", e);
+                        }
+                        Arc::new(light)
+                    }
+                };
+                lights.prev_epoch = mem::replace(&mut lights.recent_epoch, Some(epoch));
+                lights.prev = mem::replace(&mut lights.recent, Some(light.clone()));
+                light
+            }
+            Some(light) => light,
+        }
     }
 }
 
@@ -210,6 +331,49 @@ fn test_lru() {
     assert_eq!(ethash.cache.lock().prev_epoch.unwrap(), 0);
 }
 
+#[test]
+fn test_repair_caches() {
+    use tempdir::TempDir;
+
+    let tempdir = TempDir::new("").unwrap();
+    let ethash = EthashManager::new(tempdir.path(), None, u64::max_value());
+    let hash = [0u8; 32];
+    let valid = ethash.compute_light(1, &hash, 1);
+    assert_eq!(ethash.repair_caches(1).unwrap(), vec![]);
+
+    let path = cache_file(tempdir.path(), 0);
+    let mut bytes = fs::read(&path).unwrap();
+    bytes[100] ^= 0xff;
+    fs::write(&path, &bytes).unwrap();
+
+    let repaired = ethash.repair_caches(1).unwrap();
+    assert_eq!(repaired.len(), 1);
+    assert_eq!(repaired[0].epoch, 0);
+    assert_eq!(repaired[0].path, path);
+    assert_eq!(ethash.cache.lock().recent_epoch, None);
+    assert_eq!(ethash.repair_caches(1).unwrap(), vec![]);
+    assert_eq!(ethash.compute_light(1, &hash, 1).mix_hash, valid.mix_hash);
+}
+
+#[test]
+fn test_prepare_cache() {
+    use tempdir::TempDir;
+
+    let tempdir = TempDir::new("").unwrap();
+    let ethash = EthashManager::new(tempdir.path(), None, u64::max_value());
+    assert!(ethash.prepare_cache(1).unwrap());
+    assert!(!ethash.prepare_cache(1).unwrap());
+    assert_eq!(cached_epochs(tempdir.path(), 1).unwrap(), vec![1]);
+
+    let hash = [0u8; 32];
+    let prepared = ethash.compute_light(ETHASH_EPOCH_LENGTH, &hash, 1);
+    let other = TempDir::new("").unwrap();
+    let generated = EthashManager::new(other.path(), None, u64::max_value())
+        .compute_light(ETHASH_EPOCH_LENGTH, &hash, 1);
+    assert_eq!(prepared.mix_hash, generated.mix_hash);
+    assert_eq!(ethash.cache_generation_time(1), None);
+}
+
 #[test]
 fn test_difficulty_to_boundary() {
     use ethereum_types::{BigEndianHash, H256};
@@ -21,7 +21,11 @@ pub const DATASET_BYTES_GROWTH: u64 = 1 << 23;
 pub const CACHE_BYTES_INIT: u64 = 1 << 24;
 pub const CACHE_BYTES_GROWTH: u64 = 1 << 17;
 
+#[cfg(not(feature = "short-epochs"))]
 pub const ETHASH_EPOCH_LENGTH: u64 = 30000;
+/// Epochs of a few blocks, for tests crossing epoch boundaries. Incompatible with Ethereum.
+#[cfg(feature = "short-epochs")]
+pub const ETHASH_EPOCH_LENGTH: u64 = 32;
 pub const ETHASH_CACHE_ROUNDS: usize = 3;
 pub const ETHASH_MIX_BYTES: usize = 128;
 pub const ETHASH_ACCESSES: usize = 64;
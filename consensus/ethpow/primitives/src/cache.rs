@@ -0,0 +1,105 @@
+//! Commitments to the ethash light caches and proofs of the cache nodes read by a seal.
+//!
+//! A light cache is committed to by the root of a binary Merkle tree over its nodes: leaf `i` is
+//! the keccak-256 hash of node `i`, the leaves are padded with zero hashes up to a power of two,
+//! and every parent is the keccak-256 hash of its children concatenated. Verifying a seal reads a
+//! few thousand nodes of the cache, which a `CacheProof` carries along with the siblings needed
+//! to recompute the root from them, so a verifier holding only the root, such as the runtime,
+//! can check the seal without the cache.
+
+use parity_scale_codec::{Decode, Encode};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_core::{H256, H512};
+use sp_std::vec::Vec;
+
+/// Commitment to the light cache of an epoch.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Debug, Default)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct EpochCache {
+	/// Root of the Merkle tree over the cache nodes.
+	pub root: H256,
+	/// Number of 64 byte nodes in the cache.
+	pub cache_nodes: u32,
+	/// Size of the full dataset of the epoch, in bytes.
+	pub dataset_size: u64,
+}
+
+impl EpochCache {
+	/// Depth of the Merkle tree over the cache nodes.
+	pub fn depth(&self) -> u32 {
+		tree_depth(self.cache_nodes)
+	}
+}
+
+/// Depth of the Merkle tree over `leaves` leaves.
+pub fn tree_depth(leaves: u32) -> u32 {
+	leaves.max(1).next_power_of_two().trailing_zeros()
+}
+
+/// Cache nodes read by a seal and the Merkle multiproof of their inclusion in the cache.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Debug, Default)]
+pub struct CacheProof {
+	/// The nodes read, by ascending index.
+	pub nodes: Vec<(u32, H512)>,
+	/// Siblings missing to recompute the root from the nodes, level by level from the leaves,
+	/// each level by ascending index.
+	pub siblings: Vec<H256>,
+}
+
+impl CacheProof {
+	/// The root of a tree of `depth` levels recomputed from the proof with `hash`, the keccak-256
+	/// function of the verifier. `None` when the proof is malformed: nodes out of order or out of
+	/// the tree, siblings missing or left over.
+	pub fn root(&self, depth: u32, hash: impl Fn(&[u8]) -> [u8; 32]) -> Option<H256> {
+		if self.nodes.is_empty() || self.nodes.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+			return None;
+		}
+		if self.nodes.iter().any(|(index, _)| u64::from(*index) >> depth != 0) {
+			return None;
+		}
+		let mut level: Vec<(u32, H256)> =
+			self.nodes.iter().map(|(index, node)| (*index, H256(hash(node.as_bytes())))).collect();
+		let mut siblings = self.siblings.iter();
+		for _ in 0..depth {
+			let mut parents = Vec::with_capacity(level.len());
+			let mut known = level.into_iter().peekable();
+			while let Some((index, node)) = known.next() {
+				let (left, right) = if index % 2 == 0 {
+					let right = match known.peek() {
+						Some((next, _)) if *next == index + 1 => known.next().map(|(_, right)| right),
+						_ => siblings.next().copied(),
+					}?;
+					(node, right)
+				} else {
+					(*siblings.next()?, node)
+				};
+				parents.push((index / 2, H256(hash(&[left.as_bytes(), right.as_bytes()].concat()))));
+			}
+			level = parents;
+		}
+		if siblings.next().is_some() {
+			return None;
+		}
+		match level.as_slice() {
+			[(0, root)] => Some(*root),
+			_ => None,
+		}
+	}
+}
+
+/// Why the runtime rejected a seal.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Debug)]
+pub enum SealFailure {
+	/// The runtime holds no commitment to the light cache of the epoch of the seal.
+	UnknownEpoch(u64),
+	/// The proof doesn't recompute the root committed to.
+	CacheMismatch,
+	/// A cache node read by the seal isn't in the proof.
+	MissingNode(u32),
+	/// The mix digest of the seal isn't the one of its nonce.
+	MixMismatch { expected: H256, found: H256 },
+	/// The seal doesn't meet its own difficulty.
+	UnderTarget,
+}
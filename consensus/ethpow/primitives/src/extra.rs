@@ -0,0 +1,10 @@
+//! Extra data attached to a block by its miner, like the tag of a pool.
+//!
+//! The data is carried raw in a pre-runtime digest of its own, so it is covered by the pre-hash
+//! and the proof of work like the rest of the header.
+
+/// Engine id of the pre-runtime digest carrying the extra data of a block.
+pub const EXTRA_DATA_ENGINE_ID: [u8; 4] = *b"xtra";
+
+/// Maximum length of the extra data of a block, in bytes.
+pub const MAX_EXTRA_DATA_LEN: usize = 32;
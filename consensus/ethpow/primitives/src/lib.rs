@@ -0,0 +1,18 @@
+//! Types describing ethash work packages, seals and commitments to the light caches.
+//!
+//! The crate builds without `std` so the runtime can decode seals with exactly the same types
+//! the node encodes them with. Serde support, only needed by the RPC, requires `std`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+mod cache;
+mod extra;
+mod params;
+mod seal;
+mod work;
+
+pub use cache::{tree_depth, CacheProof, EpochCache, SealFailure};
+pub use extra::{EXTRA_DATA_ENGINE_ID, MAX_EXTRA_DATA_LEN};
+pub use params::PowParams;
+pub use seal::{decode_seal, decode_versioned_seal, VersionedWorkSeal, WorkSeal, LEGACY_SEAL_LEN};
+pub use work::Work;
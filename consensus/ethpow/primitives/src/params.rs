@@ -0,0 +1,19 @@
+//! Proof of work parameters the runtime sets for the node.
+//!
+//! The node reads them at the state of the parent of every block it verifies, so a runtime
+//! upgrade changing them takes effect on every node at the same block: the first one built on
+//! the state of the upgraded runtime.
+
+use parity_scale_codec::{Decode, Encode};
+use sp_core::U256;
+
+/// Seal requirements of the blocks built on a state, set by its runtime.
+///
+/// The default accepts every seal, as the runtimes predating the parameters did.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Debug, Default)]
+pub struct PowParams {
+	/// Seals must carry at least this difficulty, whatever the difficulty retargeted.
+	pub minimum_difficulty: U256,
+	/// Oldest seal layout accepted, see `VersionedWorkSeal::version`.
+	pub min_seal_version: u8,
+}
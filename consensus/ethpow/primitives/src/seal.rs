@@ -0,0 +1,174 @@
+//! The ethash seal and its on-chain encodings.
+//!
+//! The first seals of this chain were the plain SCALE encoding of `WorkSeal`, without any
+//! version information. Every layout introduced since is wrapped in `VersionedWorkSeal`, whose
+//! leading tag tells the layouts apart. `decode_seal` accepts all of them, so blocks sealed by
+//! older nodes keep verifying after the format is extended.
+//!
+//! Since `V2` the timestamp of a seal is the one of the timestamp inherent of its block, in
+//! milliseconds, so importers check the block timestamp against it. The timestamps of the older
+//! layouts were the clock of the sealing node, in seconds, and aren't checked.
+
+use parity_scale_codec::{Decode, DecodeAll, Encode, Error as CodecError};
+use sp_core::{H256, U256};
+use sp_std::vec::Vec;
+
+/// Length of an untagged seal in the original layout.
+pub const LEGACY_SEAL_LEN: usize = 120;
+
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Debug)]
+pub struct WorkSeal {
+	/// The found nonce
+	pub nonce: u64,
+	/// The proof-of-work hash of header.
+	pub pow_hash: H256,
+	/// The seed hash.
+	pub mix_digest: H256,
+	/// The difficulty
+	pub difficulty: U256,
+	/// The block number
+	pub header_nr: u64,
+	/// The timestamp of the block, see `VersionedWorkSeal::block_timestamp`.
+	pub timestamp: u64,
+}
+
+impl WorkSeal {
+	/// Encode the seal in the current, version tagged, layout.
+	pub fn encode_versioned(&self) -> Vec<u8> {
+		VersionedWorkSeal::V2(self.clone()).encode()
+	}
+}
+
+/// Version tagged seal layouts.
+///
+/// Index 0 is never used so a tagged seal can't be mistaken for the untagged legacy layout
+/// starting with a zero byte. New layouts get a new variant and a conversion into `WorkSeal`.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, Debug)]
+pub enum VersionedWorkSeal {
+	/// Same fields as the legacy layout, preceded by the version tag.
+	#[codec(index = 1)]
+	V1(WorkSeal),
+	/// Same fields, the timestamp being the one of the timestamp inherent of the block.
+	#[codec(index = 2)]
+	V2(WorkSeal),
+}
+
+impl VersionedWorkSeal {
+	/// The version tag of the layout, `1` for the untagged legacy layout as well.
+	pub fn version(&self) -> u8 {
+		match self {
+			VersionedWorkSeal::V1(_) => 1,
+			VersionedWorkSeal::V2(_) => 2,
+		}
+	}
+
+	/// Unix time of the timestamp inherent of the sealed block, in milliseconds, `None` for the
+	/// layouts not carrying it.
+	pub fn block_timestamp(&self) -> Option<u64> {
+		match self {
+			VersionedWorkSeal::V1(_) => None,
+			VersionedWorkSeal::V2(seal) => Some(seal.timestamp),
+		}
+	}
+}
+
+impl From<VersionedWorkSeal> for WorkSeal {
+	fn from(seal: VersionedWorkSeal) -> Self {
+		match seal {
+			VersionedWorkSeal::V1(seal) | VersionedWorkSeal::V2(seal) => seal,
+		}
+	}
+}
+
+/// Decode a raw seal in any layout ever used by this chain, keeping its layout. Untagged
+/// legacy seals come out as `V1`, which has the same fields.
+///
+/// The whole input must be consumed, trailing bytes are rejected.
+pub fn decode_versioned_seal(raw: &[u8]) -> Result<VersionedWorkSeal, CodecError> {
+	if raw.len() == LEGACY_SEAL_LEN {
+		return WorkSeal::decode_all(raw).map(VersionedWorkSeal::V1);
+	}
+
+	VersionedWorkSeal::decode_all(raw)
+}
+
+/// Decode a raw seal in any layout ever used by this chain.
+///
+/// The whole input must be consumed, trailing bytes are rejected.
+pub fn decode_seal(raw: &[u8]) -> Result<WorkSeal, CodecError> {
+	decode_versioned_seal(raw).map(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use hex_literal::hex;
+
+	/// A seal encoded by the original, untagged layout.
+	const LEGACY_SEAL: [u8; LEGACY_SEAL_LEN] = hex!("08070605040302011111111111111111111111111111111111111111111111111111111111111111222222222222222222222222222222222222222222222222222222222222222240420f00000000000000000000000000000000000000000000000000000000002a0000000000000000105e5f00000000");
+
+	fn expected_seal() -> WorkSeal {
+		WorkSeal {
+			nonce: 0x0102030405060708,
+			pow_hash: H256::repeat_byte(0x11),
+			mix_digest: H256::repeat_byte(0x22),
+			difficulty: U256::from(1_000_000),
+			header_nr: 42,
+			timestamp: 1_600_000_000,
+		}
+	}
+
+	#[test]
+	fn legacy_layout_is_stable() {
+		assert_eq!(expected_seal().encode(), LEGACY_SEAL.to_vec());
+	}
+
+	#[test]
+	fn decodes_legacy_seal() {
+		assert_eq!(decode_seal(&LEGACY_SEAL).unwrap(), expected_seal());
+	}
+
+	#[test]
+	fn decodes_versioned_seal() {
+		let raw = VersionedWorkSeal::V1(expected_seal()).encode();
+
+		assert_eq!(raw[0], 1);
+		assert_eq!(&raw[1..], &LEGACY_SEAL[..]);
+		assert_eq!(decode_seal(&raw).unwrap(), expected_seal());
+	}
+
+	#[test]
+	fn only_current_layout_carries_block_timestamp() {
+		let raw = expected_seal().encode_versioned();
+
+		assert_eq!(raw[0], 2);
+		assert_eq!(decode_seal(&raw).unwrap(), expected_seal());
+		assert_eq!(decode_versioned_seal(&raw).unwrap().block_timestamp(), Some(1_600_000_000));
+		assert_eq!(decode_versioned_seal(&LEGACY_SEAL).unwrap().block_timestamp(), None);
+		let v1 = VersionedWorkSeal::V1(expected_seal()).encode();
+		assert_eq!(decode_versioned_seal(&v1).unwrap().block_timestamp(), None);
+	}
+
+	#[test]
+	fn legacy_seal_has_first_version() {
+		assert_eq!(decode_versioned_seal(&LEGACY_SEAL).unwrap().version(), 1);
+		assert_eq!(decode_versioned_seal(&expected_seal().encode_versioned()).unwrap().version(), 2);
+	}
+
+	#[test]
+	fn rejects_unknown_version() {
+		let mut raw = expected_seal().encode_versioned();
+		raw[0] = 0xff;
+
+		assert!(decode_seal(&raw).is_err());
+	}
+
+	#[test]
+	fn rejects_trailing_bytes() {
+		let mut raw = expected_seal().encode_versioned();
+		raw.push(0);
+
+		assert!(decode_seal(&raw).is_err());
+		assert!(decode_seal(&LEGACY_SEAL[..LEGACY_SEAL_LEN - 1]).is_err());
+	}
+}
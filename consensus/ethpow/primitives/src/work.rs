@@ -0,0 +1,160 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of OpenEthereum.
+
+// OpenEthereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// OpenEthereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
+
+use parity_scale_codec::{Decode, Encode};
+use sp_core::H256;
+#[cfg(feature = "std")]
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+#[cfg(feature = "std")]
+use std::fmt;
+
+/// The result of an `eth_getWork` call: it differs based on an option
+/// whether to send the block number. The share target, when set, comes fifth.
+///
+/// Serialized as the array of 0x-prefixed hex strings stock ethash miners expect:
+/// `[pow_hash, seed_hash, target]`, followed by the block number when known and the share
+/// target when set. A share target without a block number leaves a `null` in between.
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+pub struct Work {
+    /// The proof-of-work hash.
+    pub pow_hash: H256,
+    /// The seed hash.
+    pub seed_hash: H256,
+    /// The target.
+    pub target: H256,
+    /// The block number: this isn't always stored.
+    pub number: Option<u64>,
+    /// The share target, looser than `target`, set when the node serves a pool.
+    pub share_target: Option<H256>,
+}
+
+#[cfg(feature = "std")]
+impl Serialize for Work {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let number = self.number.map(sp_core::U256::from);
+        match (number, self.share_target.as_ref()) {
+            (number, Some(share_target)) => (
+                &self.pow_hash,
+                &self.seed_hash,
+                &self.target,
+                number,
+                share_target,
+            )
+                .serialize(s),
+            (Some(num), None) => (&self.pow_hash, &self.seed_hash, &self.target, num).serialize(s),
+            (None, None) => (&self.pow_hash, &self.seed_hash, &self.target).serialize(s),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de> Deserialize<'de> for Work {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        d.deserialize_seq(WorkVisitor)
+    }
+}
+
+#[cfg(feature = "std")]
+struct WorkVisitor;
+
+#[cfg(feature = "std")]
+impl<'de> Visitor<'de> for WorkVisitor {
+    type Value = Work;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of a pow hash, a seed hash, a target, and optionally a block number and a share target")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Work, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let pow_hash = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let seed_hash = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        let target = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(2, &self))?;
+        let number = match seq.next_element::<Option<sp_core::U256>>()?.flatten() {
+            Some(number) if number > sp_core::U256::from(u64::max_value()) => {
+                return Err(de::Error::custom(format!("block number {} out of range", number)));
+            }
+            number => number.map(|number| number.low_u64()),
+        };
+        let share_target = seq.next_element::<Option<H256>>()?.flatten();
+        Ok(Work { pow_hash, seed_hash, target, number, share_target })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn work(number: Option<u64>, share_target: Option<H256>) -> Work {
+        Work {
+            pow_hash: H256::repeat_byte(0x11),
+            seed_hash: H256::repeat_byte(0x22),
+            target: H256::repeat_byte(0x33),
+            number,
+            share_target,
+        }
+    }
+
+    fn hash(byte: &str) -> String {
+        format!("0x{}", byte.repeat(32))
+    }
+
+    #[test]
+    fn serializes_as_ethereum_array() {
+        assert_eq!(
+            serde_json::to_value(work(None, None)).unwrap(),
+            json!([hash("11"), hash("22"), hash("33")]),
+        );
+        assert_eq!(
+            serde_json::to_value(work(Some(0x1b4), None)).unwrap(),
+            json!([hash("11"), hash("22"), hash("33"), "0x1b4"]),
+        );
+        assert_eq!(
+            serde_json::to_value(work(Some(0), Some(H256::repeat_byte(0x44)))).unwrap(),
+            json!([hash("11"), hash("22"), hash("33"), "0x0", hash("44")]),
+        );
+    }
+
+    #[test]
+    fn deserializes_every_form() {
+        for expected in vec![
+            work(None, None),
+            work(Some(0x1b4), None),
+            work(Some(0x1b4), Some(H256::repeat_byte(0x44))),
+            work(None, Some(H256::repeat_byte(0x44))),
+        ] {
+            let json = serde_json::to_string(&expected).unwrap();
+            assert_eq!(serde_json::from_str::<Work>(&json).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn rejects_short_array() {
+        assert!(serde_json::from_value::<Work>(json!([hash("11"), hash("22")])).is_err());
+    }
+}
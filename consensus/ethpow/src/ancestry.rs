@@ -0,0 +1,47 @@
+//! Seals proven to belong to the ancestors of the checkpoint.
+//!
+//! A block below the checkpoint is only imported without its proof of work once it is known to
+//! be an ancestor of the checkpoint: the node downloads the headers from the checkpoint down,
+//! checking that every header hashes to the parent hash of the one above, and records the seal
+//! of each. A seal commits to the pre hash of its block, which the import checks, so a recorded
+//! seal only fits the very block it was downloaded with. The blocks of a side chain, or imported
+//! before the headers reached them, are fully verified.
+
+use parking_lot::RwLock;
+use sp_core::hashing::blake2_256;
+use std::{collections::HashSet, sync::Arc};
+
+/// The digests of the seals of the ancestors of the checkpoint, shared by the clones.
+#[derive(Clone, Default)]
+pub struct CheckpointAncestry {
+	seals: Arc<RwLock<HashSet<[u8; 32]>>>,
+}
+
+impl CheckpointAncestry {
+	/// Record `seal`, the raw seal of a header proven to be an ancestor of the checkpoint.
+	pub fn insert(&self, seal: &[u8]) {
+		self.seals.write().insert(blake2_256(seal));
+	}
+
+	/// Whether `seal` is the seal of an ancestor of the checkpoint.
+	pub fn contains(&self, seal: &[u8]) -> bool {
+		self.seals.read().contains(&blake2_256(seal))
+	}
+
+	/// Forget the seals, once the checkpoint is imported.
+	pub fn clear(&self) {
+		let mut seals = self.seals.write();
+		seals.clear();
+		seals.shrink_to_fit();
+	}
+
+	/// Number of seals recorded.
+	pub fn len(&self) -> usize {
+		self.seals.read().len()
+	}
+
+	/// Whether no seal is recorded.
+	pub fn is_empty(&self) -> bool {
+		self.seals.read().is_empty()
+	}
+}
@@ -0,0 +1,162 @@
+//! Proofs of the light cache nodes read by seals, for the runtime to verify them.
+//!
+//! The runtime holds the Merkle root of the light cache of an epoch, see
+//! `ethpow_primitives::EpochCache`. The tree over the cache of the latest epoch proven is kept,
+//! as building it hashes every node of the cache.
+
+use ethash::{get_data_size, EthashManager, ETHASH_EPOCH_LENGTH};
+use ethpow_primitives::{tree_depth, CacheProof, EpochCache, WorkSeal};
+use parking_lot::Mutex;
+use sp_core::{hashing::keccak_256, H256, H512};
+use std::sync::Arc;
+
+/// The Merkle tree over the nodes of a light cache.
+struct CacheTree {
+	/// The levels of the tree, from the leaves padded to a power of two up to the root.
+	levels: Vec<Vec<H256>>,
+}
+
+impl CacheTree {
+	fn new(nodes: &[[u8; 64]]) -> Self {
+		let depth = tree_depth(nodes.len() as u32);
+		let mut leaves: Vec<H256> = nodes.iter().map(|node| H256(keccak_256(node))).collect();
+		leaves.resize(1 << depth, H256::zero());
+		let mut levels = vec![leaves];
+		while levels[levels.len() - 1].len() > 1 {
+			let parents = levels[levels.len() - 1]
+				.chunks(2)
+				.map(|pair| H256(keccak_256(&[pair[0].0, pair[1].0].concat())))
+				.collect();
+			levels.push(parents);
+		}
+		Self { levels }
+	}
+
+	fn root(&self) -> H256 {
+		self.levels.last().map(|root| root[0]).unwrap_or_default()
+	}
+
+	/// The proof of the nodes `reads`, sorted, of `nodes`: at every level, the siblings of the
+	/// nodes known which aren't known themselves, in the order `CacheProof::root` takes them.
+	fn prove(&self, nodes: &[[u8; 64]], reads: &[u32]) -> CacheProof {
+		let mut siblings = Vec::new();
+		let mut known = reads.to_vec();
+		for level in &self.levels[..self.levels.len() - 1] {
+			let mut parents = Vec::with_capacity(known.len());
+			let mut indices = known.iter().peekable();
+			while let Some(&index) = indices.next() {
+				if index % 2 == 0 && indices.peek() == Some(&&(index + 1)) {
+					indices.next();
+				} else {
+					siblings.push(level[(index ^ 1) as usize]);
+				}
+				parents.push(index / 2);
+			}
+			known = parents;
+		}
+		CacheProof {
+			nodes: reads.iter().map(|&index| (index, H512(nodes[index as usize]))).collect(),
+			siblings,
+		}
+	}
+}
+
+/// The root of the Merkle tree over `cache`, the raw nodes of a light cache, as committed to by
+/// `EpochCache::root`.
+pub fn cache_root(cache: &[u8]) -> H256 {
+	let nodes: Vec<[u8; 64]> = cache
+		.chunks(64)
+		.map(|chunk| {
+			let mut node = [0u8; 64];
+			node[..chunk.len()].copy_from_slice(chunk);
+			node
+		})
+		.collect();
+	CacheTree::new(&nodes).root()
+}
+
+/// Proves the light cache nodes read by seals, sharing the caches of the algorithm.
+#[derive(Clone)]
+pub(crate) struct CacheProver {
+	pow: Arc<EthashManager>,
+	/// The tree over the cache of the latest epoch proven, with its epoch.
+	tree: Arc<Mutex<Option<(u64, Arc<CacheTree>)>>>,
+}
+
+impl CacheProver {
+	pub(crate) fn new(pow: Arc<EthashManager>) -> Self {
+		Self { pow, tree: Default::default() }
+	}
+
+	fn tree(&self, epoch: u64, nodes: &[[u8; 64]]) -> Arc<CacheTree> {
+		let mut tree = self.tree.lock();
+		match &*tree {
+			Some((cached, built)) if *cached == epoch => built.clone(),
+			_ => {
+				let built = Arc::new(CacheTree::new(nodes));
+				*tree = Some((epoch, built.clone()));
+				built
+			}
+		}
+	}
+
+	/// The commitment to the light cache of `epoch`, for the runtime to verify its seals.
+	pub(crate) fn epoch_cache(&self, epoch: u64) -> EpochCache {
+		let block_number = epoch * ETHASH_EPOCH_LENGTH;
+		let light = self.pow.light_cache(block_number);
+		let nodes = light.cache_nodes();
+		EpochCache {
+			root: self.tree(epoch, nodes).root(),
+			cache_nodes: nodes.len() as u32,
+			dataset_size: get_data_size(block_number) as u64,
+		}
+	}
+
+	/// The proof of the light cache nodes read computing the proof of work of `seal`.
+	pub(crate) fn prove(&self, seal: &WorkSeal) -> CacheProof {
+		let light = self.pow.light_cache(seal.header_nr);
+		let reads = light.cache_reads(&seal.pow_hash.0, seal.nonce);
+		let nodes = light.cache_nodes();
+		self.tree(seal.header_nr / ETHASH_EPOCH_LENGTH, nodes).prove(nodes, &reads)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn proof_recomputes_root_of_any_reads() {
+		let nodes: Vec<[u8; 64]> = (0..11u8).map(|index| [index; 64]).collect();
+		let tree = CacheTree::new(&nodes);
+		for reads in [vec![0], vec![10], vec![0, 1, 2, 3], vec![1, 4, 5, 9, 10], (0..11).collect()].iter() {
+			let proof = tree.prove(&nodes, reads);
+			assert_eq!(proof.root(4, keccak_256), Some(tree.root()), "reads {:?}", reads);
+		}
+	}
+
+	#[test]
+	fn runtime_verifies_proven_seal() {
+		let dir = tempdir::TempDir::new("ethpow-cache-proof").unwrap();
+		let pow = Arc::new(EthashManager::new(dir.path(), None, u64::max_value()));
+		let prover = CacheProver::new(pow.clone());
+		let seal = WorkSeal {
+			nonce: 7,
+			pow_hash: H256::repeat_byte(0x42),
+			mix_digest: H256::zero(),
+			difficulty: 1.into(),
+			header_nr: 1,
+			timestamp: 0,
+		};
+		let expected = pow.compute_light(seal.header_nr, &seal.pow_hash.0, seal.nonce);
+
+		let cache = prover.epoch_cache(0);
+		assert_eq!(cache_root(&pow.light_cache(seal.header_nr).cache_nodes().concat()), cache.root);
+		let proof = prover.prove(&seal);
+		assert_eq!(proof.root(cache.depth(), keccak_256), Some(cache.root));
+		let nodes = ethash_seal::SparseCache::new(&proof.nodes, cache.cache_nodes);
+		let (mix, value) = ethash_seal::hashimoto(&nodes, cache.dataset_size, &seal.pow_hash, seal.nonce).unwrap();
+		assert_eq!(mix, H256(expected.mix_hash));
+		assert_eq!(value, H256(expected.value));
+	}
+}
@@ -0,0 +1,91 @@
+//! Seed hashes of the ethash epochs, shared between the algorithm and the mining service.
+//!
+//! The seed hash of an epoch is the seed hash of the epoch before it hashed once more, so
+//! computing it from scratch hashes once per epoch since genesis. `EpochSeeds` remembers the
+//! seed hashes of the epochs last asked for, and computes a new one from the closest epoch it
+//! remembers below it.
+
+use ethash::{SeedHashCompute, ETHASH_EPOCH_LENGTH};
+use parking_lot::Mutex;
+use sp_core::H256;
+use std::{collections::VecDeque, sync::Arc};
+
+/// Number of epochs remembered, the least recently used are forgotten first.
+const CAPACITY: usize = 16;
+
+/// The epoch of block `number`.
+pub fn epoch(number: u64) -> u64 {
+	number / ETHASH_EPOCH_LENGTH
+}
+
+/// The seed hashes of the epochs last asked for, shared by the clones.
+#[derive(Clone, Default)]
+pub struct EpochSeeds {
+	/// By epoch, the most recently used last.
+	seeds: Arc<Mutex<VecDeque<(u64, [u8; 32])>>>,
+}
+
+impl EpochSeeds {
+	/// The seed hash of the epoch of block `number`.
+	pub fn hash_block_number(&self, number: u64) -> H256 {
+		self.hash_epoch(epoch(number))
+	}
+
+	/// The seed hash of `epoch`.
+	pub fn hash_epoch(&self, epoch: u64) -> H256 {
+		let mut seeds = self.seeds.lock();
+		if let Some(index) = seeds.iter().position(|(known, _)| *known == epoch) {
+			let entry = seeds.remove(index).expect("the index was just found; qed");
+			seeds.push_back(entry);
+			return H256(entry.1);
+		}
+
+		let (start, seed) = seeds
+			.iter()
+			.filter(|(known, _)| *known < epoch)
+			.max_by_key(|(known, _)| *known)
+			.copied()
+			.unwrap_or((0, [0u8; 32]));
+		let seed = SeedHashCompute::resume_compute_seedhash(seed, start, epoch);
+		if seeds.len() == CAPACITY {
+			seeds.pop_front();
+		}
+		seeds.push_back((epoch, seed));
+		H256(seed)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ethash::slow_hash_block_number;
+
+	#[test]
+	fn matches_seed_hashes_computed_from_genesis() {
+		let seeds = EpochSeeds::default();
+		// Out of order, so some are resumed from a remembered epoch and some from genesis.
+		for epoch in &[3u64, 0, 7, 5, 7, 1] {
+			let number = epoch * ETHASH_EPOCH_LENGTH;
+			assert_eq!(seeds.hash_block_number(number).0, slow_hash_block_number(number));
+		}
+	}
+
+	#[test]
+	fn forgets_least_recently_used_epochs_beyond_capacity() {
+		let seeds = EpochSeeds::default();
+		for epoch in 0..=CAPACITY as u64 {
+			seeds.hash_epoch(epoch);
+		}
+
+		let remembered: Vec<_> = seeds.seeds.lock().iter().map(|(epoch, _)| *epoch).collect();
+		assert_eq!(remembered, (1..=CAPACITY as u64).collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn clones_share_the_seed_hashes() {
+		let seeds = EpochSeeds::default();
+		seeds.clone().hash_epoch(2);
+
+		assert_eq!(seeds.seeds.lock().len(), 1);
+	}
+}
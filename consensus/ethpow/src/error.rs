@@ -16,51 +16,55 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-//! A manual sealing engine: the engine listens for rpc calls to seal blocks and create forks.
-//! This is suitable for a testing environment.
+//! Errors of the ethash proof of work.
 
+use ethpow_primitives::SealFailure;
+use sp_core::{H256, U256};
 
-/// Error code for rpc
-mod codes {
-	pub const MISMATCHED_H256_SEAL: i64 = 10_000;
-	pub const INVALID_POW: i64 = 11_000;
-	pub const EMPTY_TRANSACTION_POOL: i64 = 12_000;
-	pub const BLOCK_NOT_FOUND: i64 = 13_000;
-	pub const CONSENSUS_ERROR: i64 = 14_000;
-	pub const INHERENTS_ERROR: i64 = 15_000;
-	pub const BLOCKCHAIN_ERROR: i64 = 16_000;
-	pub const UNKNOWN_ERROR: i64 = 20_000;
+/// Why a seal failed verification.
+#[derive(Debug, derive_more::Display)]
+pub enum SealError {
+	/// The seal doesn't decode
+	#[display(fmt = "Undecodable seal: {}", _0)]
+	Decode(parity_scale_codec::Error),
+	/// The seal was computed for another header
+	#[display(fmt = "Seal pow hash {} doesn't match the header pre-hash {}", found, expected)]
+	PreHashMismatch { expected: H256, found: H256 },
+	/// The seal was computed with the dataset of another epoch than the one of the block
+	#[display(fmt = "Seal of epoch {}, the block is in epoch {}", found, expected)]
+	WrongEpoch { expected: u64, found: u64 },
+	/// The seal was computed for another block number than the one of the block, in its epoch
+	#[display(fmt = "Seal of block #{}, the block is #{}", found, expected)]
+	WrongNumber { expected: u64, found: u64 },
+	/// The mix digest of the seal isn't the one of its nonce
+	#[display(fmt = "Seal mix digest {} doesn't match the computed mix {}", found, expected)]
+	MixMismatch { expected: H256, found: H256 },
+	/// The seal doesn't meet the difficulty required for the block
+	#[display(fmt = "Seal difficulty {} is below the required difficulty {}", found, expected)]
+	UnderTarget { expected: U256, found: U256 },
+	/// The seal layout is older than the runtime accepts
+	#[display(fmt = "Seal layout version {} is older than the required version {}", found, expected)]
+	OutdatedLayout { expected: u8, found: u8 },
+	/// The runtime rejected the proof of work of the seal
+	#[display(fmt = "Seal rejected by the runtime: {:?}", _0)]
+	Runtime(SealFailure),
 }
 
-/// errors encountered by background block authorship task
-#[derive(Debug, derive_more::Display, derive_more::From)]
-pub enum Error {
-	/// Transaction pool is empty, cannot create a block
-	#[display(fmt = "Transaction pool is empty, set create_empty to true,\
-	if you want to create empty blocks")]
-	EmptyTransactionPool,
-	#[display(fmt = "Mismatched H256 Seal Element")]
-	MismatchedH256SealElement,
-	//#[display(fmt = "Invalid ProofOfWork: expected: {}, found: {}", _0, _1)]
-	#[display(fmt = "Invalid ProofOfWork, Invalid Difficulty")]
-	InvalidProofOfWork,
-	/// Some other error.
-	Other(String),
-}
+impl std::error::Error for SealError {}
 
-impl Error {
-	fn to_code(&self) -> i64 {
-		use Error::*;
+impl SealError {
+	/// Label of the failure kind in logs and metrics.
+	pub fn kind(&self) -> &'static str {
+		use SealError::*;
 		match self {
-			MismatchedH256SealElement => codes::MISMATCHED_H256_SEAL,
-			InvalidProofOfWork => codes::BLOCK_NOT_FOUND,
-			EmptyTransactionPool => codes::EMPTY_TRANSACTION_POOL,
-			// ConsensusError(_) => codes::CONSENSUS_ERROR,
-			// InherentError(_) => codes::INHERENTS_ERROR,
-			// BlockchainError(_) => codes::BLOCKCHAIN_ERROR,
-			// SendError(_) | Canceled(_) => codes::SERVER_SHUTTING_DOWN,
-			_ => codes::UNKNOWN_ERROR
+			Decode(_) => "decode",
+			PreHashMismatch { .. } => "pre_hash_mismatch",
+			WrongEpoch { .. } => "wrong_epoch",
+			WrongNumber { .. } => "wrong_number",
+			MixMismatch { .. } => "mix_mismatch",
+			UnderTarget { .. } => "under_target",
+			OutdatedLayout { .. } => "outdated_layout",
+			Runtime(_) => "runtime",
 		}
 	}
 }
-
@@ -1,84 +1,83 @@
-use parity_scale_codec::{Decode, Encode};
 use sc_consensus_pow::{Error, PowAlgorithm};
 
-use sp_api::ProvideRuntimeApi;
+use sp_api::{ApiExt, ProvideRuntimeApi};
+use difficulty_runtime_api::PowParamsApi;
+use ethash_seal_runtime_api::EthashSealApi;
 use sp_consensus_pow::{DifficultyApi, Seal as RawSeal};
 use sp_blockchain::HeaderBackend;
 use ethereum_types::{self, U256 as EU256, H256 as EH256};
 use sp_core::{U256, H256};
 use sp_runtime::generic::BlockId;
-use sp_runtime::traits::{Block as BlockT, Header as HeaderT, UniqueSaturatedInto};
-use std::{cmp, sync::Arc, time::{SystemTime, UNIX_EPOCH}};
-use ethash::{self, quick_get_difficulty, slow_hash_block_number, EthashManager};
+use sp_runtime::traits::{Block as BlockT, UniqueSaturatedInto};
+use serde_derive::Deserialize;
+use std::{io, path::Path, sync::Arc, thread, time::Instant};
+use ethash::{
+	self, quick_get_difficulty, EthashManager, ProofOfWork, RepairedCache,
+	ETHASH_EPOCH_LENGTH,
+};
 use log::{error, info, debug, trace, warn};
+use substrate_prometheus_endpoint::Registry;
 
+mod ancestry;
+mod cache_proof;
+mod epoch;
 mod error;
-use error::{Error as EthError};
-
-
-#[derive(Clone, PartialEq, Eq, Encode, Decode, Debug)]
-pub struct WorkSeal {
-    /// The found nonce
-    pub nonce : u64,
-    /// The proof-of-work hash of header.
-    pub pow_hash: H256,
-    /// The seed hash.
-    pub mix_digest: H256,
-    /// The difficulty
-    pub difficulty: U256,
-    /// The block number
-    pub header_nr: u64,
-    /// The timestamp
-    pub timestamp: u64,
-}
+mod metrics;
+mod pool;
+mod stats;
+mod verified;
+pub use ancestry::CheckpointAncestry;
+pub use epoch::EpochSeeds;
+pub use error::SealError;
+pub use stats::{EpochStat, EpochStats};
+use verified::VerifiedSeals;
+use metrics::SealMetrics;
+use pool::VerificationPool;
+use cache_proof::CacheProver;
+pub use cache_proof::cache_root;
+
+pub use ethpow_primitives::{
+	decode_seal, decode_versioned_seal, EpochCache, PowParams, VersionedWorkSeal, Work, WorkSeal,
+	EXTRA_DATA_ENGINE_ID, LEGACY_SEAL_LEN, MAX_EXTRA_DATA_LEN,
+};
 
 /// A minimal PoW algorithm that uses Sha3 hashing.
 /// Difficulty is fixed at 1_000_000
 #[derive(Clone)]
 pub struct MinimalEthashAlgorithm {
 	pow: Arc<EthashManager>,
+	seeds: EpochSeeds,
 }
 
 impl MinimalEthashAlgorithm {
-	pub fn new() -> Self {
-		use tempdir::TempDir;
-
-		let tempdir = TempDir::new("").unwrap();
-		Self { pow: Arc::new(EthashManager::new(tempdir.path(), None, u64::max_value())), }
-	}
-
-	fn verify_seal(&self, seal: &WorkSeal) -> Result<(), EthError> {
-		let mut tmp:[u8; 32] = seal.pow_hash.into();
-		let pre_hash = EH256::from(tmp);
-		tmp = seal.mix_digest.into();
-		let mix_digest = EH256::from(tmp);
-
-        let result = self.pow.compute_light(
-            seal.header_nr,
-            &pre_hash.0,
-            seal.nonce,
-        );
-        let mix = EH256(result.mix_hash);
-		tmp = ethash::boundary_to_difficulty(&EH256(result.value)).into();
-		let difficulty = U256::from(tmp);
-        trace!(target:"pow", "num: {num}, seed: {seed}, h: {h}, non: {non}, mix: {mix}, res: {res}",
-			   num = seal.header_nr,
-			   seed = EH256(slow_hash_block_number(seal.header_nr)),
-			   h = pre_hash,
-			   non = seal.nonce,
-			   mix = EH256(result.mix_hash),
-			   res = EH256(result.value));
-
-		if mix != mix_digest {
-            return Err(EthError::MismatchedH256SealElement);
-        }
-        if difficulty < seal.difficulty {
-            return Err(EthError::InvalidProofOfWork);
-        }
-
-		trace!(target:"pow", "miner verified ok");
-        Ok(())
-    }
+	/// Create the algorithm, keeping the ethash caches in `cache_dir` across restarts.
+	pub fn new(cache_dir: &Path) -> Self {
+		Self { pow: Arc::new(EthashManager::new(cache_dir, None, u64::max_value())), seeds: EpochSeeds::default() }
+	}
+
+	/// The seed hashes of the epochs, shared with the algorithm this one was made from.
+	pub fn seeds(&self) -> &EpochSeeds {
+		&self.seeds
+	}
+
+	/// Check the nonce and mix digest of `seal`, the seal of block `number`, against the light
+	/// cache of the epoch of the block. The block number of the seal comes from the miner, a seal
+	/// carrying another one is refused rather than verified against the cache it points to.
+	pub fn verify_seal(&self, seal: &WorkSeal, number: u64) -> Result<(), SealError> {
+		check_number(seal, number)?;
+		let result = self.pow.compute_light(
+			number,
+			&seal.pow_hash.0,
+			seal.nonce,
+		);
+		check_proof(seal, &result, &self.seeds)
+	}
+
+	/// The commitment to the light cache of `epoch`, for root to set in the runtimes verifying
+	/// the seals of the epoch.
+	pub fn epoch_cache(&self, epoch: u64) -> EpochCache {
+		CacheProver::new(self.pow.clone()).epoch_cache(epoch)
+	}
 }
 
 // Here we implement the general PowAlgorithm trait for our concrete EthashAlgorithm
@@ -97,96 +96,395 @@ impl<B: BlockT<Hash = H256>> PowAlgorithm<B> for MinimalEthashAlgorithm {
 
 	fn verify(
 		&self,
-		_parent: &BlockId<B>,
+		parent: &BlockId<B>,
 		pre_hash: &H256,
 		_pre_digest: Option<&[u8]>,
 		seal: &RawSeal,
 		difficulty: Self::Difficulty,
 	) -> Result<bool, Error<B>> {
-		// Try to construct a seal object by decoding the raw seal given
-		let seal = match WorkSeal::decode(&mut &seal[..]) {
-			Ok(seal) => seal,
-			Err(_) => return Ok(false),
-		};
-
-		match self.verify_seal(&seal) {
-			Ok(_) => {},
-			Err(_) => return Ok(false),
+		// Without a client, the number of the block is only known from a parent given by number:
+		// the seal would be checked against the number it carries itself. `EthashAlgorithm` reads
+		// it from the chain.
+		let number = match parent {
+			BlockId::Number(number) => UniqueSaturatedInto::<u64>::unique_saturated_into(*number) + 1,
+			BlockId::Hash(hash) => {
+				return Err(Error::Other(format!("Parent {} given by hash, its number is unknown", hash)));
+			}
 		};
+		decode_seal(&seal)
+			.map_err(SealError::Decode)
+			.and_then(|seal| check_target(&seal, pre_hash, difficulty).map(|()| seal))
+			.and_then(|seal| self.verify_seal(&seal, number))
+			.map_err(|err| {
+				warn!(target:"pow", "Invalid seal for {}: {}", pre_hash, err);
+				Error::SealVerification(Box::new(err))
+			})?;
 
 		Ok(true)
 	}
 }
 
+/// Check that `seal` was computed for the header hashing to `pre_hash`, at no less than the
+/// `difficulty` required for the block.
+fn check_target(seal: &WorkSeal, pre_hash: &H256, difficulty: U256) -> Result<(), SealError> {
+	if seal.pow_hash != *pre_hash {
+		return Err(SealError::PreHashMismatch { expected: *pre_hash, found: seal.pow_hash });
+	}
+	if seal.difficulty < difficulty {
+		return Err(SealError::UnderTarget { expected: difficulty, found: seal.difficulty });
+	}
+	Ok(())
+}
+
+/// Check that `seal` was computed for block `number`, with the dataset of its epoch: a seal
+/// carrying another block number may be verified against the wrong cache.
+fn check_number(seal: &WorkSeal, number: u64) -> Result<(), SealError> {
+	let expected = number / ETHASH_EPOCH_LENGTH;
+	let found = seal.header_nr / ETHASH_EPOCH_LENGTH;
+	if found != expected {
+		return Err(SealError::WrongEpoch { expected, found });
+	}
+	if seal.header_nr != number {
+		return Err(SealError::WrongNumber { expected: number, found: seal.header_nr });
+	}
+	Ok(())
+}
+
+/// Check that `seal`, in the layout `version`, meets the requirements `params` of the runtime of
+/// the parent of its block.
+fn check_params(seal: &WorkSeal, version: u8, params: &PowParams) -> Result<(), SealError> {
+	if version < params.min_seal_version {
+		return Err(SealError::OutdatedLayout { expected: params.min_seal_version, found: version });
+	}
+	if seal.difficulty < params.minimum_difficulty {
+		return Err(SealError::UnderTarget { expected: params.minimum_difficulty, found: seal.difficulty });
+	}
+	Ok(())
+}
+
+/// Check the proof of work `result` computed for `seal` against its mix digest and difficulty.
+fn check_proof(seal: &WorkSeal, result: &ProofOfWork, seeds: &EpochSeeds) -> Result<(), SealError> {
+	let mix = H256(result.mix_hash);
+	let tmp: [u8; 32] = ethash::boundary_to_difficulty(&EH256(result.value)).into();
+	let difficulty = U256::from(tmp);
+	trace!(target:"pow", "num: {num}, seed: {seed}, h: {h}, non: {non}, mix: {mix}, res: {res}",
+		   num = seal.header_nr,
+		   seed = seeds.hash_block_number(seal.header_nr),
+		   h = seal.pow_hash,
+		   non = seal.nonce,
+		   mix = mix,
+		   res = EH256(result.value));
+
+	if mix != seal.mix_digest {
+		return Err(SealError::MixMismatch { expected: mix, found: seal.mix_digest });
+	}
+	if difficulty < seal.difficulty {
+		return Err(SealError::UnderTarget { expected: seal.difficulty, found: difficulty });
+	}
+
+	trace!(target:"pow", "miner verified ok");
+	Ok(())
+}
+
+/// Consensus parameters of the chain.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct EthashParams {
+	/// Whether the chain uses ProgPoW instead of Ethash.
+	pub progpow: bool,
+	/// Difficulty required for every block instead of the one retargeted by the runtime, for
+	/// single node private chains. Nodes without the same override reject the blocks.
+	pub fixed_difficulty: Option<U256>,
+	/// Number of threads computing the proofs of work of the seals queued for import ahead of
+	/// their verification, one per CPU when unset. 0 computes them in the verification only.
+	pub verification_threads: Option<usize>,
+	/// Block whose ancestors are imported without computing the proof of work of their seals,
+	/// for a fast initial sync, once proven to be its ancestors, see `CheckpointAncestry`. The
+	/// other checks of the seals still apply.
+	pub checkpoint: Option<Checkpoint>,
+	/// Whether the proofs of work are verified by the runtime, for the epochs whose light cache
+	/// it holds a commitment to, rather than by the native code of the node.
+	pub runtime_verification: bool,
+}
+
+/// A block trusted to be part of the chain, as are all its ancestors.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+pub struct Checkpoint {
+	/// Number of the block.
+	pub number: u64,
+	/// Hash of the block, seal included.
+	pub hash: H256,
+}
+
 /// A complete PoW Algorithm that uses Sha3 hashing.
 /// Needs a reference to the client so it can grab the difficulty from the runtime.
 pub struct EthashAlgorithm<C> {
 	client: Arc<C>,
 	pow: Arc<EthashManager>,
-	minimum_difficulty: U256,
-	difficulty_bound_divisor: U256,
-	difficulty_increment_divisor: u64,
-	duration_limit: u64,
-	progpow: bool,
+	params: EthashParams,
+	metrics: Option<SealMetrics>,
+	stats: EpochStats,
+	verified: VerifiedSeals,
+	pool: VerificationPool,
+	prover: CacheProver,
+	seeds: EpochSeeds,
+	ancestry: CheckpointAncestry,
 }
 
 impl<C> EthashAlgorithm<C> {
-	pub fn new(client: Arc<C>) -> Self {
-		use tempdir::TempDir;
+	/// Create the algorithm with the default parameters, keeping the ethash caches in `cache_dir`
+	/// across restarts.
+	pub fn new(client: Arc<C>, cache_dir: &Path) -> Self {
+		Self::with_params(client, EthashParams::default(), cache_dir)
+	}
 
-		let tempdir = TempDir::new("").unwrap();
+	/// Create the algorithm, keeping the ethash caches in `cache_dir` across restarts.
+	pub fn with_params(client: Arc<C>, params: EthashParams, cache_dir: &Path) -> Self {
+		let pow = Arc::new(EthashManager::new(cache_dir, None, u64::max_value()));
+		let threads = params.verification_threads.unwrap_or_else(|| {
+			thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1)
+		});
 		Self { 
 			client, 
-			pow: Arc::new(EthashManager::new(tempdir.path(), None, u64::max_value())), 
-			minimum_difficulty: U256::from(1_000_000),
-			difficulty_bound_divisor: U256::from(2048),
-            difficulty_increment_divisor: 10,
-			duration_limit: 13,
-			progpow: false,
+			pow: pow.clone(), 
+			params,
+			metrics: None,
+			stats: EpochStats::new(pow.clone()),
+			verified: VerifiedSeals::default(),
+			pool: VerificationPool::new(pow.clone(), threads),
+			prover: CacheProver::new(pow),
+			seeds: EpochSeeds::default(),
+			ancestry: CheckpointAncestry::default(),
 		}
 	}
 
-	fn verify_seal(&self, seal: &WorkSeal) -> Result<(), EthError> {
-		let mut tmp:[u8; 32] = seal.pow_hash.into();
-		let pre_hash = EH256::from(tmp);
-		tmp = seal.mix_digest.into();
-		let mix_digest = EH256::from(tmp);
-
-        let result = self.pow.compute_light(
-            seal.header_nr,
-            &pre_hash.0,
-            seal.nonce,
-        );
-        let mix = EH256(result.mix_hash);
-		tmp = ethash::boundary_to_difficulty(&EH256(result.value)).into();
-		let difficulty = U256::from(tmp);
-        trace!(target:"pow", "num: {num}, seed: {seed}, h: {h}, non: {non}, mix: {mix}, res: {res}",
-			   num = seal.header_nr,
-			   seed = EH256(slow_hash_block_number(seal.header_nr)),
-			   h = pre_hash,
-			   non = seal.nonce,
-			   mix = EH256(result.mix_hash),
-			   res = EH256(result.value));
-
-		if mix != mix_digest {
-			debug!(target:"pow", "verify_seal EthError::MismatchedH256SealElement");
-            return Err(EthError::MismatchedH256SealElement);
-        }
-        if difficulty < seal.difficulty {
-			debug!(target:"pow", "verify_seal EthError::InvalidProofOfWork");
-            return Err(EthError::InvalidProofOfWork);
-        }
-
-		trace!(target:"pow", "miner verified ok");
-        Ok(())
-    }
+	/// Count the seals failing verification by kind of failure in `registry`.
+	pub fn with_prometheus(mut self, registry: Option<&Registry>) -> Self {
+		self.metrics = registry.and_then(|registry| {
+			SealMetrics::register(registry)
+				.map_err(|err| warn!(target:"pow", "Failed to register the seal metrics: {:?}", err))
+				.ok()
+		});
+		self
+	}
+
+	/// The consensus parameters used by this algorithm.
+	pub fn params(&self) -> &EthashParams {
+		&self.params
+	}
+
+	/// The seals of the ancestors of the checkpoint, for the node to record them as it downloads
+	/// the headers below the checkpoint.
+	pub fn checkpoint_ancestry(&self) -> CheckpointAncestry {
+		self.ancestry.clone()
+	}
+
+	/// Verification statistics of the epochs seen by this algorithm and its clones.
+	pub fn epoch_stats(&self) -> EpochStats {
+		self.stats.clone()
+	}
+
+	/// A `MinimalEthashAlgorithm` sharing the ethash caches of this algorithm, to check seals
+	/// without going through the client.
+	pub fn minimal(&self) -> MinimalEthashAlgorithm {
+		MinimalEthashAlgorithm { pow: self.pow.clone(), seeds: self.seeds.clone() }
+	}
+
+	/// Check the light cache files up to the one of `max_epoch` against caches regenerated from
+	/// their seed hashes, and replace the corrupt ones. As slow as generating every cache found.
+	pub fn repair_caches(&self, max_epoch: u64) -> io::Result<Vec<RepairedCache>> {
+		self.pow.repair_caches(max_epoch)
+	}
+
+	/// Generate the light cache of `epoch` ahead of time, see `EthashManager::prepare_cache`.
+	pub fn prepare_cache(&self, epoch: u64) -> io::Result<bool> {
+		self.pow.prepare_cache(epoch)
+	}
+
+	/// Generate the full dataset of `epoch` ahead of time for the miners sharing the cache
+	/// directory, see `EthashManager::prepare_dataset`.
+	pub fn prepare_dataset(&self, epoch: u64, threads: usize) -> io::Result<bool> {
+		self.pow.prepare_dataset(epoch, threads)
+	}
+
+	/// Start computing the proof of work of `seal`, the raw seal of a block queued for import, on
+	/// the verification threads. Its verification then only waits for the result. Seals failing
+	/// to decode are left to the verification to reject.
+	pub fn prefetch_seal(&self, seal: &RawSeal) {
+		if let Ok(decoded) = decode_seal(seal) {
+			// The proven ancestors of the checkpoint are imported without their proof of work.
+			if self.trusts_ancestor(decoded.header_nr, seal) {
+				return;
+			}
+			self.pool.submit(&decoded);
+		}
+	}
+
+	/// Whether `seal`, the raw seal of block `number`, is trusted without its proof of work: the
+	/// seal of a block proven to be an ancestor of the checkpoint.
+	fn trusts_ancestor(&self, number: u64, seal: &RawSeal) -> bool {
+		match self.params.checkpoint {
+			Some(checkpoint) => number <= checkpoint.number && self.ancestry.contains(seal),
+			None => false,
+		}
+	}
+
+	/// Check `seal`, the raw seal of block `number` hashing to `pre_hash`, against the
+	/// `difficulty` required for the block and the requirements `params` of the runtime. The
+	/// proof of work of a `trusted` seal isn't computed.
+	fn check_seal(
+		&self,
+		number: u64,
+		pre_hash: &H256,
+		seal: &RawSeal,
+		difficulty: U256,
+		params: &PowParams,
+		trusted: bool,
+	) -> Result<(), SealError> {
+		let seal = decode_versioned_seal(seal).map_err(SealError::Decode)?;
+		let version = seal.version();
+		let seal = WorkSeal::from(seal);
+		check_params(&seal, version, params)?;
+		// The proof of work is checked against the difficulty of the seal, which must be the
+		// one required for the block.
+		check_target(&seal, pre_hash, difficulty)?;
+		check_number(&seal, number)?;
+		if trusted || self.verified.take(&seal) {
+			return Ok(());
+		}
+		self.check_proof_of_work(&seal)
+	}
+
+	/// Check the seal of the announced block `number` hashing to `pre_hash`, before the block is
+	/// downloaded. The difficulty required for the block takes the state of its parent, so the
+	/// proof of work is only checked against the difficulty of the seal, and the import checks
+	/// the rest. A valid proof of work isn't computed again at import.
+	pub fn check_announced_seal(&self, number: u64, pre_hash: &H256, seal: &RawSeal) -> Result<(), SealError> {
+		let seal = decode_seal(seal).map_err(SealError::Decode)?;
+		if seal.pow_hash != *pre_hash {
+			return Err(SealError::PreHashMismatch { expected: *pre_hash, found: seal.pow_hash });
+		}
+		check_number(&seal, number)?;
+		self.check_proof_of_work(&seal)?;
+		self.verified.insert(seal);
+		Ok(())
+	}
+
+	/// Compute the proof of work of `seal` against the light cache of its epoch, unless the
+	/// verification threads already did, and check it.
+	fn check_proof_of_work(&self, seal: &WorkSeal) -> Result<(), SealError> {
+		let started = Instant::now();
+		let result = self.pool.proof_of_work(seal);
+		check_proof(seal, &result, &self.seeds)?;
+		self.stats.record(seal.header_nr / ETHASH_EPOCH_LENGTH, started.elapsed());
+		Ok(())
+	}
 }
 
 // Manually implement clone. Deriving doesn't work because
 // it'll derive impl<C: Clone> Clone for EthashAlgorithm<C>. But C in practice isn't Clone.
+// Clones share the ethash caches.
 impl<C> Clone for EthashAlgorithm<C> {
 	fn clone(&self) -> Self {
-		Self::new(self.client.clone())
+		Self {
+			client: self.client.clone(),
+			pow: self.pow.clone(),
+			params: self.params.clone(),
+			metrics: self.metrics.clone(),
+			stats: self.stats.clone(),
+			verified: self.verified.clone(),
+			pool: self.pool.clone(),
+			prover: self.prover.clone(),
+			seeds: self.seeds.clone(),
+			ancestry: self.ancestry.clone(),
+		}
+	}
+}
+
+impl<C> EthashAlgorithm<C> {
+	/// The seal requirements of the runtime at `at`, the parent of the block verified. The
+	/// runtimes predating `PowParamsApi` accept every seal.
+	fn pow_params<B>(&self, at: &BlockId<B>) -> Result<PowParams, Error<B>>
+	where
+		B: BlockT<Hash = H256>,
+		C: ProvideRuntimeApi<B>,
+		C::Api: PowParamsApi<B>,
+	{
+		let api = self.client.runtime_api();
+		let fetch_failed = |err| Error::Other(format!("Fetching PoW parameters from runtime failed: {:?}", err));
+		if !api.has_api::<dyn PowParamsApi<B>>(at).map_err(fetch_failed)? {
+			return Ok(PowParams::default());
+		}
+		api.pow_params(at).map_err(fetch_failed)
+	}
+
+	/// Whether block `number`, a child of `parent` sealed with `seal`, is trusted without its
+	/// proof of work: a proven ancestor of the checkpoint. The child of the checkpoint must be
+	/// built on it, and once it is imported the seals of the ancestors are forgotten.
+	fn below_checkpoint<B>(&self, parent: &BlockId<B>, number: u64, seal: &RawSeal) -> Result<bool, Error<B>>
+	where
+		B: BlockT<Hash = H256>,
+		C: HeaderBackend<B>,
+	{
+		let checkpoint = match self.params.checkpoint {
+			Some(checkpoint) => checkpoint,
+			None => return Ok(false),
+		};
+		if number == checkpoint.number + 1 {
+			let parent_hash = self.client.block_hash_from_id(parent).map_err(Error::Client)?;
+			if parent_hash != Some(checkpoint.hash) {
+				let err = format!("Block #{} isn't built on the checkpoint {}", number, checkpoint.hash);
+				warn!(target:"pow", "{}", err);
+				return Err(Error::Other(err));
+			}
+			if !self.ancestry.is_empty() {
+				self.ancestry.clear();
+			}
+		}
+		Ok(self.trusts_ancestor(number, seal))
+	}
+
+	/// Whether the runtime at `at`, the parent of the block verified, verifies the proofs of work
+	/// of `epoch`: asked to, it does when it holds a commitment to the light cache of the epoch.
+	fn runtime_verifies<B>(&self, at: &BlockId<B>, epoch: u64) -> Result<bool, Error<B>>
+	where
+		B: BlockT<Hash = H256>,
+		C: ProvideRuntimeApi<B>,
+		C::Api: EthashSealApi<B>,
+	{
+		if !self.params.runtime_verification {
+			return Ok(false);
+		}
+		Ok(self.committed_cache(at, epoch)?.is_some())
+	}
+
+	/// The commitment to the light cache of `epoch` held by the runtime at `at`, if any.
+	pub fn committed_cache<B>(&self, at: &BlockId<B>, epoch: u64) -> Result<Option<EpochCache>, Error<B>>
+	where
+		B: BlockT<Hash = H256>,
+		C: ProvideRuntimeApi<B>,
+		C::Api: EthashSealApi<B>,
+	{
+		let api = self.client.runtime_api();
+		let fetch_failed = |err| Error::Other(format!("Fetching light cache commitment from runtime failed: {:?}", err));
+		if !api.has_api::<dyn EthashSealApi<B>>(at).map_err(fetch_failed)? {
+			return Ok(None);
+		}
+		api.epoch_cache(at, epoch).map_err(fetch_failed)
+	}
+
+	/// Have the runtime at `at` verify the proof of work of `seal`, proving it the light cache
+	/// nodes read.
+	fn check_in_runtime<B>(&self, at: &BlockId<B>, seal: WorkSeal) -> Result<Result<(), SealError>, Error<B>>
+	where
+		B: BlockT<Hash = H256>,
+		C: ProvideRuntimeApi<B>,
+		C::Api: EthashSealApi<B>,
+	{
+		let proof = self.prover.prove(&seal);
+		let verdict = self.client.runtime_api().verify_seal(at, seal, proof).map_err(|err| {
+			Error::Other(format!("Verifying seal in runtime failed: {:?}", err))
+		})?;
+		Ok(verdict.map_err(SealError::Runtime))
 	}
 }
 
@@ -194,117 +492,232 @@ impl<C> Clone for EthashAlgorithm<C> {
 impl<B: BlockT<Hash = H256>, C> PowAlgorithm<B> for EthashAlgorithm<C>
 where
 	C: HeaderBackend<B> + ProvideRuntimeApi<B>,
+	C::Api: DifficultyApi<B, U256> + PowParamsApi<B> + EthashSealApi<B>,
 {
 	type Difficulty = U256;
 
-	fn difficulty(&self, hash: B::Hash) -> Result<Self::Difficulty, Error<B>> {
-		let header = self.client.header(BlockId::<B>::hash(hash)).map_err(|err| {
-				sc_consensus_pow::Error::Other(format!("{:?}", err))
-			})?.ok_or_else(|| {
-				sc_consensus_pow::Error::Other(format!("there should be header"))
-			})?;
-		let raw_seal = match sc_consensus_pow::fetch_seal::<B>(
-				header.digest().logs.last(),
-				hash,
-			) {
-			Ok(seal) => seal,
-			Err(err) => {
-				let nr :u64 = UniqueSaturatedInto::<u64>::unique_saturated_into(*header.number());
-				if nr == 0 { //:NOTICE: This should be the genesis header, use minimum_difficulty
-					return Ok(self.minimum_difficulty);
-				} else {
-					return Err(sc_consensus_pow::Error::Other(format!("{:?}", err)));
-				}
-			},
-		};
-		let seal = WorkSeal::decode(&mut &raw_seal[..]).map_err(|err| {
-				sc_consensus_pow::Error::Other(format!("{:?}", err))
-			})?;
+	fn difficulty(&self, parent: B::Hash) -> Result<Self::Difficulty, Error<B>> {
+		if let Some(difficulty) = self.params.fixed_difficulty {
+			return Ok(difficulty);
+		}
+		// The runtime retargets the difficulty of the next block when importing `parent`.
+		self.client.runtime_api().difficulty(&BlockId::<B>::hash(parent)).map_err(|err| {
+				sc_consensus_pow::Error::Other(format!("Fetching difficulty from runtime failed: {:?}", err))
+			})
+	}
 
-		// header difficulty
-		Ok(seal.difficulty)
-	}
-
-	fn calc_difficulty(&self, parent: B::Hash, cur: B::Hash) -> Result<Self::Difficulty, Error<B>> {
-		let parent_header = match self.client.header(BlockId::<B>::hash(parent)).map_err(|err| {
-				sc_consensus_pow::Error::Other(format!("{:?}", err))
-			})? {
-				Some(header) => header,
-				None => {
-					//:NOTICE: This should be the genesis header, use minimum_difficulty
-					return Ok(self.minimum_difficulty);
-				},
-			};
-		let raw_seal = match sc_consensus_pow::fetch_seal::<B>(
-				parent_header.digest().logs.last(),
-				parent,
-			) {
-			Ok(seal) => seal,
-			Err(err) => {
-				let nr :u64 = UniqueSaturatedInto::<u64>::unique_saturated_into(*parent_header.number());
-				if nr == 0 { //:NOTICE: This should be the genesis header, use minimum_difficulty
-					return Ok(self.minimum_difficulty);
-				} else {
-					return Err(sc_consensus_pow::Error::Other(format!("{:?}", err)));
-				}
-			},
-		};
-		let parent_seal = WorkSeal::decode(&mut &raw_seal[..]).map_err(|err| {
-				sc_consensus_pow::Error::Other(format!("{:?}", err))
-			})?;
+	fn calc_difficulty(&self, _parent: B::Hash, cur: B::Hash) -> Result<Self::Difficulty, Error<B>> {
+		// The block being mined is a child of `cur`.
+		let difficulty = self.difficulty(cur)?;
+		debug!(target:"pow", "difficulty of the child of {}: {}", cur, difficulty);
+		Ok(difficulty)
+	}
 
-		let header = self.client.header(BlockId::<B>::hash(cur)).map_err(|err| {
-				sc_consensus_pow::Error::Other(format!("{:?}", err))
-			})?.ok_or_else(|| {
-				sc_consensus_pow::Error::Other(format!("there should be header"))
-			})?;
-		let raw_seal = sc_consensus_pow::fetch_seal::<B>(
-				header.digest().logs.last(),
-				cur,
-			).map_err(|err| {
-				sc_consensus_pow::Error::Other(format!("{:?}", err))
-			})?;
-		let seal = WorkSeal::decode(&mut &raw_seal[..]).map_err(|err| {
-				sc_consensus_pow::Error::Other(format!("{:?}", err))
-			})?;
-		
-		let min_difficulty = self.minimum_difficulty;
-		let difficulty_bound_divisor = self.difficulty_bound_divisor;
-		let duration_limit = self.duration_limit;
-
-        let mut target = if seal.timestamp >= parent_seal.timestamp + duration_limit {
-			seal.difficulty - (seal.difficulty / difficulty_bound_divisor)
-		} else {
-			seal.difficulty + (seal.difficulty / difficulty_bound_divisor)
-		};
-		target = cmp::max(min_difficulty, target);
-		// debug!(target:"pow", "duration: {}, pTime: {}, cTime: {}, old_dif: {}, new_dif: {}", 
-		// 	seal.timestamp-parent_seal.timestamp, parent_seal.timestamp, seal.timestamp, seal.difficulty, target);
-		println!("******duration: {}, pTime: {}, cTime: {}, old_dif: {}, new_dif: {}", 
-			seal.timestamp-parent_seal.timestamp, parent_seal.timestamp, seal.timestamp, seal.difficulty, target);
-			
-		// parent header difficulty
-		Ok(target)
+	fn seal_timestamp(&self, seal: &RawSeal) -> Option<u64> {
+		decode_versioned_seal(seal).ok().and_then(|seal| seal.block_timestamp())
 	}
 
 	fn verify(
 		&self,
-		_parent: &BlockId<B>,
+		parent: &BlockId<B>,
 		pre_hash: &H256,
 		_pre_digest: Option<&[u8]>,
 		seal: &RawSeal,
 		difficulty: Self::Difficulty,
 	) -> Result<bool, Error<B>> {
-		// Try to construct a seal object by decoding the raw seal given
-		let seal = match WorkSeal::decode(&mut &seal[..]) {
-			Ok(seal) => seal,
-			Err(_) => return Ok(false),
+		let parent_number = self.client.block_number_from_id(parent)
+			.map_err(Error::Client)?
+			.ok_or_else(|| Error::Other(format!("Unknown parent block {}", parent)))?;
+		let number = UniqueSaturatedInto::<u64>::unique_saturated_into(parent_number) + 1;
+		// Read at the parent, the parameters change at the first block built on the state of
+		// the runtime upgrade changing them, on every node alike.
+		let params = self.pow_params(parent)?;
+		let trusted = self.below_checkpoint(parent, number, seal)?;
+		let in_runtime = !trusted && self.runtime_verifies(parent, number / ETHASH_EPOCH_LENGTH)?;
+		let report = |err: SealError| -> Error<B> {
+			warn!(target:"pow", "Invalid seal for block #{} ({}): {}", number, pre_hash, err);
+			if let Some(metrics) = &self.metrics {
+				metrics.report(&err);
+			}
+			Error::SealVerification(Box::new(err))
 		};
-		
-		self.verify_seal(&seal).map_err(|err| {
-				sc_consensus_pow::Error::Other(format!("{:?}", err))
-			})?;
+
+		self.check_seal(number, pre_hash, seal, difficulty, &params, trusted || in_runtime).map_err(report)?;
+		if in_runtime {
+			// Decoded by `check_seal` already.
+			let seal = decode_seal(seal).map_err(SealError::Decode).map_err(report)?;
+			self.check_in_runtime(parent, seal)?.map_err(report)?;
+		}
 
 		Ok(true)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn seal(difficulty: u64) -> WorkSeal {
+		WorkSeal {
+			nonce: 42,
+			pow_hash: H256::repeat_byte(0x11),
+			mix_digest: H256::repeat_byte(0x22),
+			difficulty: difficulty.into(),
+			header_nr: 1,
+			timestamp: 0,
+		}
+	}
+
+	#[test]
+	fn check_target_accepts_required_difficulty() {
+		let pre_hash = H256::repeat_byte(0x11);
+		assert!(check_target(&seal(1_000), &pre_hash, U256::from(1_000u64)).is_ok());
+		assert!(check_target(&seal(2_000), &pre_hash, U256::from(1_000u64)).is_ok());
+	}
+
+	#[test]
+	fn check_target_rejects_easier_seal() {
+		let pre_hash = H256::repeat_byte(0x11);
+		match check_target(&seal(999), &pre_hash, U256::from(1_000u64)) {
+			Err(SealError::UnderTarget { expected, found }) => {
+				assert_eq!(expected, U256::from(1_000u64));
+				assert_eq!(found, U256::from(999u64));
+			}
+			other => panic!("unexpected {:?}", other),
+		}
+	}
+
+	#[test]
+	fn check_target_rejects_other_header() {
+		let pre_hash = H256::repeat_byte(0x33);
+		assert!(matches!(
+			check_target(&seal(1_000), &pre_hash, U256::from(1_000u64)),
+			Err(SealError::PreHashMismatch { .. })
+		));
+	}
+
+	#[test]
+	fn check_params_follows_runtime_upgrade() {
+		let before = PowParams { minimum_difficulty: U256::from(1_000u64), min_seal_version: 1 };
+		let after = PowParams { minimum_difficulty: U256::from(2_000u64), min_seal_version: 2 };
+
+		// The blocks built on the state before the upgrade accept the legacy layout.
+		assert!(check_params(&seal(2_000), 1, &before).is_ok());
+		assert!(matches!(
+			check_params(&seal(2_000), 1, &after),
+			Err(SealError::OutdatedLayout { expected: 2, found: 1 })
+		));
+		assert!(check_params(&seal(2_000), 2, &after).is_ok());
+		assert!(matches!(check_params(&seal(1_500), 2, &after), Err(SealError::UnderTarget { .. })));
+		// Runtimes without the parameters accept every seal.
+		assert!(check_params(&seal(1), 1, &PowParams::default()).is_ok());
+	}
+
+	#[test]
+	fn check_number_rejects_seal_of_another_epoch() {
+		let mut seal = seal(1_000);
+		seal.header_nr = ETHASH_EPOCH_LENGTH;
+		assert!(check_number(&seal, ETHASH_EPOCH_LENGTH).is_ok());
+		match check_number(&seal, ETHASH_EPOCH_LENGTH - 1) {
+			Err(SealError::WrongEpoch { expected, found }) => {
+				assert_eq!(expected, 0);
+				assert_eq!(found, 1);
+			}
+			other => panic!("unexpected {:?}", other),
+		}
+		// A seal pointing to an earlier epoch, whose cache the miner may have at hand.
+		seal.header_nr = 1;
+		match check_number(&seal, 3 * ETHASH_EPOCH_LENGTH + 1) {
+			Err(SealError::WrongEpoch { expected, found }) => {
+				assert_eq!(expected, 3);
+				assert_eq!(found, 0);
+			}
+			other => panic!("unexpected {:?}", other),
+		}
+	}
+
+	#[test]
+	fn check_number_rejects_other_block_of_the_epoch() {
+		let mut seal = seal(1_000);
+		seal.header_nr = ETHASH_EPOCH_LENGTH;
+		match check_number(&seal, ETHASH_EPOCH_LENGTH + 1) {
+			Err(SealError::WrongNumber { expected, found }) => {
+				assert_eq!(expected, ETHASH_EPOCH_LENGTH + 1);
+				assert_eq!(found, ETHASH_EPOCH_LENGTH);
+			}
+			other => panic!("unexpected {:?}", other),
+		}
+	}
+
+	#[test]
+	fn verify_seal_refuses_seal_of_another_epoch() {
+		let dir = tempdir::TempDir::new("ethpow-verify-seal-epoch").unwrap();
+		let algorithm = MinimalEthashAlgorithm::new(dir.path());
+		let mut seal = seal(1);
+		seal.header_nr = ETHASH_EPOCH_LENGTH + 1;
+		// Refused before the light cache of either epoch is computed.
+		assert!(matches!(
+			algorithm.verify_seal(&seal, 1),
+			Err(SealError::WrongEpoch { expected: 0, found: 1 })
+		));
+		assert!(matches!(
+			algorithm.verify_seal(&seal, ETHASH_EPOCH_LENGTH + 2),
+			Err(SealError::WrongNumber { .. })
+		));
+	}
+
+	#[test]
+	fn minimal_verify_refuses_parent_given_by_hash() {
+		use sp_runtime::testing::{Block, ExtrinsicWrapper};
+		type TestBlock = Block<ExtrinsicWrapper<u64>>;
+
+		let dir = tempdir::TempDir::new("ethpow-verify-parent-hash").unwrap();
+		let algorithm = MinimalEthashAlgorithm::new(dir.path());
+		let seal = seal(1);
+		let raw = seal.encode_versioned();
+		let parent = BlockId::<TestBlock>::Hash(H256::repeat_byte(0x11));
+		assert!(matches!(
+			algorithm.verify(&parent, &seal.pow_hash, None, &raw, 1.into()),
+			Err(Error::Other(_))
+		));
+	}
+
+	#[test]
+	fn zero_work_side_chain_below_checkpoint_is_verified() {
+		let dir = tempdir::TempDir::new("ethpow-checkpoint").unwrap();
+		let params = EthashParams {
+			checkpoint: Some(Checkpoint { number: 10, hash: H256::repeat_byte(0x44) }),
+			verification_threads: Some(0),
+			..Default::default()
+		};
+		let algorithm = EthashAlgorithm::with_params(Arc::new(()), params, dir.path());
+		let pow_params = PowParams::default();
+
+		// The seal of a header proven to be an ancestor of the checkpoint, whose mix digest
+		// isn't checked.
+		let ancestor = seal(1);
+		let raw = ancestor.encode_versioned();
+		algorithm.checkpoint_ancestry().insert(&raw);
+		let trusted = algorithm.trusts_ancestor(1, &raw);
+		assert!(trusted);
+		assert!(algorithm.check_seal(1, &ancestor.pow_hash, &raw, 1.into(), &pow_params, trusted).is_ok());
+
+		// A block of a side chain at the same height, sealed without any work.
+		let mut forged = seal(1);
+		forged.pow_hash = H256::repeat_byte(0x55);
+		let raw = forged.encode_versioned();
+		let trusted = algorithm.trusts_ancestor(1, &raw);
+		assert!(!trusted);
+		assert!(matches!(
+			algorithm.check_seal(1, &forged.pow_hash, &raw, 1.into(), &pow_params, trusted),
+			Err(SealError::MixMismatch { .. })
+		));
+
+		// Nor is the seal of the ancestor trusted above the checkpoint.
+		let mut above = seal(1);
+		above.header_nr = 11;
+		let raw = above.encode_versioned();
+		algorithm.checkpoint_ancestry().insert(&raw);
+		assert!(!algorithm.trusts_ancestor(11, &raw));
+	}
+}
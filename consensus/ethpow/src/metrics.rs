@@ -0,0 +1,32 @@
+//! Prometheus metrics of the seal verification.
+
+use substrate_prometheus_endpoint::{register, CounterVec, Opts, PrometheusError, Registry, U64};
+use crate::error::SealError;
+
+/// Counters of the seals failing verification, by kind of failure.
+#[derive(Clone)]
+pub struct SealMetrics {
+	failures: CounterVec<U64>,
+}
+
+impl SealMetrics {
+	pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			failures: register(
+				CounterVec::new(
+					Opts::new(
+						"ethash_seal_verification_failures",
+						"Number of seals failing verification, by kind of failure",
+					),
+					&["kind"],
+				)?,
+				registry,
+			)?,
+		})
+	}
+
+	/// Count a seal failing verification with `err`.
+	pub fn report(&self, err: &SealError) {
+		self.failures.with_label_values(&[err.kind()]).inc();
+	}
+}
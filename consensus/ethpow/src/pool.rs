@@ -0,0 +1,255 @@
+//! Threads computing the proof of work of seals ahead of their verification.
+//!
+//! The import queue verifies the blocks of a sync range one after the other, and the proof of
+//! work computed against the light cache is most of the time taken. When a range is queued, the
+//! seals of its blocks are handed to the pool, whose threads compute the proofs of work in
+//! parallel while the blocks before them are imported: the verification of a block then only
+//! waits for its result. Seals the pool never saw, or dropped because its queue was full, are
+//! computed by the verification itself.
+
+use ethash::{EthashManager, ProofOfWork};
+use ethpow_primitives::WorkSeal;
+use log::{trace, warn};
+use parking_lot::{Condvar, Mutex, MutexGuard};
+use sp_core::H256;
+use std::{
+	collections::{HashMap, VecDeque},
+	sync::{
+		mpsc::{self, Receiver, SyncSender},
+		Arc,
+	},
+	thread,
+	time::Duration,
+};
+
+/// Seals waiting for a thread, the ones submitted beyond are left to their verification.
+const QUEUE_SIZE: usize = 1024;
+
+/// Number of proofs of work remembered, pending or computed. The oldest are forgotten first.
+const CAPACITY: usize = 2048;
+
+/// How long a verification waits for a pending proof of work before computing it itself.
+const WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A seal is keyed by its pow hash and nonce.
+type Key = (H256, u64);
+
+/// A proof of work to compute: the block number of the seal, its pow hash and nonce.
+type Job = (u64, H256, u64);
+
+/// The proofs of work submitted to the threads, by seal.
+#[derive(Default)]
+struct Results {
+	/// The block number the proof of work is computed for, and the proof once computed.
+	entries: HashMap<Key, (u64, Option<ProofOfWork>)>,
+	/// The keys of `entries`, oldest first.
+	order: VecDeque<Key>,
+}
+
+impl Results {
+	/// Expect the proof of work of `key` for block `number`. Returns `false` if already expected.
+	fn insert(&mut self, key: Key, number: u64) -> bool {
+		if self.entries.contains_key(&key) {
+			return false;
+		}
+		if self.order.len() == CAPACITY {
+			if let Some(oldest) = self.order.pop_front() {
+				self.entries.remove(&oldest);
+			}
+		}
+		self.entries.insert(key, (number, None));
+		self.order.push_back(key);
+		true
+	}
+
+	/// Record the proof of work computed for `key` at block `number`, unless forgotten since.
+	fn complete(&mut self, key: Key, number: u64, result: ProofOfWork) {
+		if let Some(entry) = self.entries.get_mut(&key) {
+			if entry.0 == number {
+				entry.1 = Some(result);
+			}
+		}
+	}
+
+	fn remove(&mut self, key: &Key) -> Option<(u64, Option<ProofOfWork>)> {
+		let entry = self.entries.remove(key)?;
+		if let Some(index) = self.order.iter().position(|queued| queued == key) {
+			self.order.remove(index);
+		}
+		Some(entry)
+	}
+}
+
+struct Shared {
+	results: Mutex<Results>,
+	/// Notified whenever a proof of work is computed.
+	computed: Condvar,
+}
+
+impl Shared {
+	fn results(&self) -> MutexGuard<'_, Results> {
+		self.results.lock()
+	}
+}
+
+/// The verification threads, shared by the clones of the algorithm. The threads stop once every
+/// clone is dropped.
+#[derive(Clone)]
+pub struct VerificationPool {
+	pow: Arc<EthashManager>,
+	shared: Arc<Shared>,
+	/// `None` without threads, every proof of work is then computed by its verification.
+	jobs: Option<SyncSender<Job>>,
+}
+
+impl VerificationPool {
+	/// Compute the proofs of work of the seals submitted on `threads` threads, none to compute
+	/// them in the verification only.
+	pub(crate) fn new(pow: Arc<EthashManager>, threads: usize) -> Self {
+		let shared = Arc::new(Shared { results: Mutex::new(Results::default()), computed: Condvar::new() });
+		let mut pool = Self { pow, shared, jobs: None };
+		if threads == 0 {
+			return pool;
+		}
+
+		let (sender, receiver) = mpsc::sync_channel(QUEUE_SIZE);
+		let receiver = Arc::new(Mutex::new(receiver));
+		for index in 0..threads {
+			let (pow, shared, receiver) = (pool.pow.clone(), pool.shared.clone(), receiver.clone());
+			let spawned = thread::Builder::new()
+				.name(format!("seal-verification-{}", index))
+				.spawn(move || run_worker(pow, shared, receiver));
+			if let Err(err) = spawned {
+				warn!(target: "pow", "Failed to start a seal verification thread: {}", err);
+				break;
+			}
+			pool.jobs = Some(sender.clone());
+		}
+		pool
+	}
+
+	/// Start computing the proof of work of `seal`. Does nothing without threads, or when they
+	/// are too far behind.
+	pub(crate) fn submit(&self, seal: &WorkSeal) {
+		let jobs = match &self.jobs {
+			Some(jobs) => jobs,
+			None => return,
+		};
+		let key = (seal.pow_hash, seal.nonce);
+		if !self.shared.results().insert(key, seal.header_nr) {
+			return;
+		}
+		if jobs.try_send((seal.header_nr, seal.pow_hash, seal.nonce)).is_err() {
+			trace!(target: "pow", "Seal verification queue full, {} left to the import", seal.pow_hash);
+			self.shared.results().remove(&key);
+		}
+	}
+
+	/// The proof of work of `seal`: the one computed by the threads if submitted, waiting for it
+	/// if still pending, else computed on the spot.
+	pub(crate) fn proof_of_work(&self, seal: &WorkSeal) -> ProofOfWork {
+		let key = (seal.pow_hash, seal.nonce);
+		let mut results = self.shared.results();
+		loop {
+			let state = results
+				.entries
+				.get(&key)
+				.map(|(number, result)| (*number == seal.header_nr, result.is_some()));
+			match state {
+				Some((true, true)) => {
+					if let Some((_, Some(result))) = results.remove(&key) {
+						return result;
+					}
+				}
+				Some((true, false)) => {
+					if self.shared.computed.wait_for(&mut results, WAIT_TIMEOUT).timed_out() {
+						break;
+					}
+				}
+				// Not submitted, forgotten, or submitted for another block number.
+				_ => break,
+			}
+		}
+		drop(results);
+		self.pow.compute_light(seal.header_nr, &seal.pow_hash.0, seal.nonce)
+	}
+}
+
+fn run_worker(pow: Arc<EthashManager>, shared: Arc<Shared>, jobs: Arc<Mutex<Receiver<Job>>>) {
+	loop {
+		let job = jobs.lock().recv();
+		let (number, pow_hash, nonce) = match job {
+			Ok(job) => job,
+			// Every clone of the pool was dropped.
+			Err(_) => return,
+		};
+		let result = pow.compute_light(number, &pow_hash.0, nonce);
+		shared.results().complete((pow_hash, nonce), number, result);
+		shared.computed.notify_all();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tempdir::TempDir;
+
+	fn seal(nonce: u64) -> WorkSeal {
+		WorkSeal {
+			nonce,
+			pow_hash: H256::repeat_byte(0x11),
+			mix_digest: H256::repeat_byte(0x22),
+			difficulty: 1_000.into(),
+			header_nr: 1,
+			timestamp: 0,
+		}
+	}
+
+	/// A manager generating its caches in a temporary directory, removed once dropped.
+	fn manager() -> (TempDir, Arc<EthashManager>) {
+		let dir = TempDir::new("ethpow-pool").unwrap();
+		let pow = Arc::new(EthashManager::new(dir.path(), None, u64::max_value()));
+		(dir, pow)
+	}
+
+	#[test]
+	fn forgets_oldest_results_beyond_capacity() {
+		let mut results = Results::default();
+		for nonce in 0..=CAPACITY as u64 {
+			assert!(results.insert((H256::zero(), nonce), 1));
+		}
+		assert!(!results.insert((H256::zero(), 1), 1));
+
+		assert!(results.remove(&(H256::zero(), 0)).is_none());
+		assert!(results.remove(&(H256::zero(), 1)).is_some());
+		assert_eq!(results.entries.len(), results.order.len());
+	}
+
+	#[test]
+	fn pooled_proof_of_work_matches_inline_one() {
+		let (_dir, pow) = manager();
+		let pool = VerificationPool::new(pow.clone(), 2);
+		for nonce in 0..4 {
+			pool.submit(&seal(nonce));
+		}
+
+		for nonce in 0..4 {
+			let expected = pow.compute_light(1, &seal(nonce).pow_hash.0, nonce);
+			let result = pool.proof_of_work(&seal(nonce));
+			assert_eq!(result.value, expected.value);
+			assert_eq!(result.mix_hash, expected.mix_hash);
+		}
+		assert!(pool.shared.results().entries.is_empty());
+	}
+
+	#[test]
+	fn computes_unsubmitted_seals_without_threads() {
+		let (_dir, pow) = manager();
+		let pool = VerificationPool::new(pow.clone(), 0);
+		pool.submit(&seal(7));
+		assert!(pool.shared.results().entries.is_empty());
+
+		let expected = pow.compute_light(1, &seal(7).pow_hash.0, 7);
+		assert_eq!(pool.proof_of_work(&seal(7)).mix_hash, expected.mix_hash);
+	}
+}
@@ -0,0 +1,95 @@
+//! Per-epoch statistics of the seal verification.
+//!
+//! Every epoch grows the ethash dataset, and with it the time taken to generate the light cache
+//! and to verify a seal. Comparing the epochs a node went through shows when that starts to hurt.
+
+use ethash::EthashManager;
+use parking_lot::Mutex;
+use serde_derive::Serialize;
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+/// Verification statistics of an epoch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpochStat {
+	/// The ethash epoch.
+	pub epoch: u64,
+	/// Blocks of the epoch whose seal was verified since the node started.
+	pub blocks_verified: u64,
+	/// Average time taken to compute the proof of work of a seal, in microseconds.
+	pub average_verification_micros: u64,
+	/// Time taken to generate the light cache of the epoch, in milliseconds. `None` if the cache
+	/// was loaded from the cache directory instead.
+	pub cache_generation_millis: Option<u64>,
+}
+
+#[derive(Default)]
+struct Counters {
+	blocks_verified: u64,
+	verification_time: Duration,
+}
+
+/// Verification statistics of the epochs seen since the node started, shared by the clones of
+/// the algorithm.
+#[derive(Clone)]
+pub struct EpochStats {
+	counters: Arc<Mutex<BTreeMap<u64, Counters>>>,
+	pow: Arc<EthashManager>,
+}
+
+impl EpochStats {
+	pub(crate) fn new(pow: Arc<EthashManager>) -> Self {
+		Self { counters: Default::default(), pow }
+	}
+
+	/// Count a seal of `epoch` verified in `elapsed`.
+	pub(crate) fn record(&self, epoch: u64, elapsed: Duration) {
+		let mut counters = self.counters.lock();
+		let counters = counters.entry(epoch).or_default();
+		counters.blocks_verified += 1;
+		counters.verification_time += elapsed;
+	}
+
+	/// Time taken to generate the light cache of `epoch`, `None` if it was loaded from the cache
+	/// directory or not needed yet.
+	pub fn cache_generation_time(&self, epoch: u64) -> Option<Duration> {
+		self.pow.cache_generation_time(epoch)
+	}
+
+	/// The statistics of every epoch, oldest first.
+	pub fn get(&self) -> Vec<EpochStat> {
+		let counters = self.counters.lock();
+		counters
+			.iter()
+			.map(|(epoch, counters)| EpochStat {
+				epoch: *epoch,
+				blocks_verified: counters.blocks_verified,
+				average_verification_micros: (counters.verification_time.as_micros()
+					/ u128::from(counters.blocks_verified.max(1))) as u64,
+				cache_generation_millis: self
+					.pow
+					.cache_generation_time(*epoch)
+					.map(|elapsed| elapsed.as_millis() as u64),
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn averages_verification_time_per_epoch() {
+		let dir = tempdir::TempDir::new("ethpow-epoch-stats").unwrap();
+		let stats = EpochStats::new(Arc::new(EthashManager::new(dir.path(), None, u64::max_value())));
+		stats.record(1, Duration::from_micros(100));
+		stats.record(1, Duration::from_micros(300));
+		stats.record(0, Duration::from_micros(50));
+
+		assert_eq!(stats.get(), vec![
+			EpochStat { epoch: 0, blocks_verified: 1, average_verification_micros: 50, cache_generation_millis: None },
+			EpochStat { epoch: 1, blocks_verified: 2, average_verification_micros: 200, cache_generation_millis: None },
+		]);
+	}
+}
@@ -0,0 +1,84 @@
+//! Seals whose proof of work was already computed, shared between the checks of a block.
+//!
+//! The seal of a block announced by a peer is checked before the block is downloaded, then again
+//! when the block is imported. Computing the proof of work is the expensive part of both, so the
+//! announce check records the seals it found valid, and the import skips their computation.
+
+use ethpow_primitives::WorkSeal;
+use parking_lot::Mutex;
+use std::{collections::VecDeque, sync::Arc};
+
+/// Number of seals remembered, the oldest are forgotten first.
+const CAPACITY: usize = 256;
+
+/// The seals last found to carry a valid proof of work, shared by the clones of the algorithm.
+#[derive(Clone, Default)]
+pub struct VerifiedSeals {
+	seals: Arc<Mutex<VecDeque<WorkSeal>>>,
+}
+
+impl VerifiedSeals {
+	/// Remember that the proof of work of `seal` is valid.
+	pub(crate) fn insert(&self, seal: WorkSeal) {
+		let mut seals = self.seals.lock();
+		if seals.contains(&seal) {
+			return;
+		}
+		if seals.len() == CAPACITY {
+			seals.pop_front();
+		}
+		seals.push_back(seal);
+	}
+
+	/// Whether the proof of work of `seal` was found valid, forgetting it: a block is imported
+	/// once.
+	pub(crate) fn take(&self, seal: &WorkSeal) -> bool {
+		let mut seals = self.seals.lock();
+		match seals.iter().position(|verified| verified == seal) {
+			Some(index) => {
+				seals.remove(index);
+				true
+			}
+			None => false,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::H256;
+
+	fn seal(nonce: u64) -> WorkSeal {
+		WorkSeal {
+			nonce,
+			pow_hash: H256::repeat_byte(0x11),
+			mix_digest: H256::repeat_byte(0x22),
+			difficulty: 1_000.into(),
+			header_nr: 1,
+			timestamp: 0,
+		}
+	}
+
+	#[test]
+	fn takes_inserted_seals_once() {
+		let verified = VerifiedSeals::default();
+		verified.insert(seal(1));
+
+		assert!(!verified.take(&seal(2)));
+		assert!(verified.take(&seal(1)));
+		assert!(!verified.take(&seal(1)));
+	}
+
+	#[test]
+	fn forgets_oldest_seals_beyond_capacity() {
+		let verified = VerifiedSeals::default();
+		for nonce in 0..=CAPACITY as u64 {
+			verified.insert(seal(nonce));
+		}
+
+		assert!(!verified.take(&seal(0)));
+		assert!(verified.take(&seal(1)));
+		assert!(verified.take(&seal(CAPACITY as u64)));
+	}
+}
@@ -34,9 +34,12 @@ extern crate tokio_io;
 
 mod traits;
 
-pub use traits::{Error, JobDispatcher, PushWorkHandler, ServiceConfiguration};
+pub use traits::{Error, JobDispatcher, PushWorkHandler, ServiceConfiguration, SubmitFuture};
 
-use jsonrpc_core::{to_value, Compatibility, IoDelegate, MetaIoHandler, Metadata, Params, Value};
+use jsonrpc_core::{
+    futures::{future, Future},
+    to_value, BoxFuture, Compatibility, IoDelegate, MetaIoHandler, Metadata, Params, Value,
+};
 use jsonrpc_tcp_server::{
     Dispatcher, MetaExtractor, PushMessageError, RequestContext, Server as JsonRpcServer,
     ServerBuilder as JsonRpcServerBuilder,
@@ -89,9 +92,13 @@ impl Stratum {
         let mut delegate = IoDelegate::<StratumImpl, SocketMetadata>::new(implementation.clone());
         delegate.add_method_with_meta("mining.subscribe", StratumImpl::subscribe);
         delegate.add_method_with_meta("mining.authorize", StratumImpl::authorize);
-        delegate.add_method_with_meta("mining.submit", StratumImpl::submit);
         let mut handler = MetaIoHandler::<SocketMetadata>::with_compatibility(Compatibility::Both);
         handler.extend_with(delegate);
+        // The answer outlives the call, it holds on to the implementation.
+        let submitting = implementation.clone();
+        handler.add_method_with_meta("mining.submit", move |params: Params, meta: SocketMetadata| {
+            StratumImpl::submit(&submitting, params, meta)
+        });
 
         let server_builder = JsonRpcServerBuilder::new(handler);
         let tcp_dispatcher = server_builder.dispatcher();
@@ -114,6 +121,10 @@ impl PushWorkHandler for Stratum {
         self.implementation
             .push_work_all(payload, &self.tcp_dispatcher)
     }
+
+    fn push_work_each(&self) {
+        self.implementation.push_work_each(&self.tcp_dispatcher)
+    }
 }
 
 impl Drop for Stratum {
@@ -166,11 +177,15 @@ impl StratumImpl {
             .parse::<(String, String)>()
             .map(|(worker_id, secret)| {
                 if let Some(valid_secret) = self.secret {
-                    let hash = keccak(secret);
+                    let hash = keccak(&secret);
                     if hash != valid_secret {
                         return to_value(&false);
                     }
                 }
+                if !self.dispatcher.authorize(&worker_id, &secret, meta.addr()) {
+                    trace!(target: "stratum", "Worker #{} refused by the dispatcher", worker_id);
+                    return to_value(&false);
+                }
                 trace!(target: "stratum", "New worker #{} registered", worker_id);
                 self.workers.write().insert(meta.addr().clone(), worker_id);
                 to_value(true)
@@ -178,50 +193,74 @@ impl StratumImpl {
             .map(|v| v.expect("Only true/false is returned and it's always serializable; qed"))
     }
 
-    /// rpc method `mining.submit`
-    fn submit(&self, params: Params, meta: SocketMetadata) -> RpcResult {
-        Ok(match params {
-            Params::Array(vals) => {
-                // first two elements are service messages (worker_id & job_id)
-                match self.dispatcher.submit(
-                    vals.iter()
-                        .skip(2)
-                        .filter_map(|val| match *val {
-                            Value::String(ref s) => Some(s.to_owned()),
-                            _ => None,
-                        })
-                        .collect::<Vec<String>>(),
-                ) {
-                    Ok(()) => {
-                        self.update_peers(
-                            &meta
-                                .tcp_dispatcher
-                                .expect("tcp_dispatcher is always initialized; qed"),
-                        );
-                        to_value(true)
-                    }
-                    Err(submit_err) => {
-                        warn!("Error while submitting share: {:?}", submit_err);
-                        to_value(false)
-                    }
-                }
-            }
+    /// rpc method `mining.submit`, answered once the dispatcher checked the submission
+    fn submit(this: &Arc<Self>, params: Params, meta: SocketMetadata) -> BoxFuture<Value> {
+        let vals = match params {
+            Params::Array(vals) => vals,
             _ => {
                 trace!(target: "stratum", "Invalid submit work format {:?}", params);
-                to_value(false)
+                return Box::new(future::ok(
+                    to_value(false).expect("false is always serializable; qed"),
+                ));
             }
-        }
-        .expect("Only true/false is returned and it's always serializable; qed"))
+        };
+        // first two elements are service messages (worker_id & job_id)
+        let worker_id = match vals.first() {
+            Some(Value::String(ref s)) => s.as_str(),
+            _ => "",
+        };
+        let submission = this.dispatcher.submit_async(
+            worker_id,
+            meta.addr(),
+            vals.iter()
+                .skip(2)
+                .filter_map(|val| match *val {
+                    Value::String(ref s) => Some(s.to_owned()),
+                    _ => None,
+                })
+                .collect::<Vec<String>>(),
+        );
+        let this = this.clone();
+        Box::new(submission.then(move |result| {
+            Ok::<_, jsonrpc_core::Error>(match result {
+                Ok(()) => {
+                    this.update_peers(
+                        meta.tcp_dispatcher
+                            .as_ref()
+                            .expect("tcp_dispatcher is always initialized; qed"),
+                    );
+                    to_value(true)
+                }
+                Err(submit_err) => {
+                    warn!("Error while submitting share: {:?}", submit_err);
+                    to_value(false)
+                }
+            }
+            .expect("Only true/false is returned and it's always serializable; qed"))
+        }))
     }
 
     /// Helper method
     fn update_peers(&self, tcp_dispatcher: &Dispatcher) {
-        if let Some(job) = self.dispatcher.job() {
-            self.push_work_all(job, tcp_dispatcher)
-        }
+        self.push_work_each(tcp_dispatcher)
     }
 
     fn push_work_all(&self, payload: String, tcp_dispatcher: &Dispatcher) {
+        self.push_work(|_, _| Some(payload.clone()), tcp_dispatcher)
+    }
+
+    fn push_work_each(&self, tcp_dispatcher: &Dispatcher) {
+        self.push_work(
+            |addr, worker_id| self.dispatcher.job_for(worker_id, addr),
+            tcp_dispatcher,
+        )
+    }
+
+    /// Push to every worker the payload `payload_for` its address and worker_id, if any
+    fn push_work<F>(&self, payload_for: F, tcp_dispatcher: &Dispatcher)
+    where
+        F: Fn(&SocketAddr, &str) -> Option<String>,
+    {
         let hup_peers = {
             let workers = self.workers.read();
             let next_request_id = {
@@ -235,14 +274,18 @@ impl StratumImpl {
             };
 
             let mut hup_peers = HashSet::new();
-            let workers_msg = format!(
-                "{{ \"id\": {}, \"method\": \"mining.notify\", \"params\": {} }}",
-                next_request_id, payload
-            );
-            trace!(target: "stratum", "pushing work for {} workers (payload: '{}')", workers.len(), &workers_msg);
-            for (addr, _) in workers.iter() {
-                trace!(target: "stratum", "pusing work to {}", addr);
-                match tcp_dispatcher.push_message(addr, workers_msg.clone()) {
+            trace!(target: "stratum", "pushing work for {} workers", workers.len());
+            for (addr, worker_id) in workers.iter() {
+                let payload = match payload_for(addr, worker_id) {
+                    Some(payload) => payload,
+                    None => continue,
+                };
+                let workers_msg = format!(
+                    "{{ \"id\": {}, \"method\": \"mining.notify\", \"params\": {} }}",
+                    next_request_id, payload
+                );
+                trace!(target: "stratum", "pusing work to {} (payload: '{}')", addr, &workers_msg);
+                match tcp_dispatcher.push_message(addr, workers_msg) {
                     Err(PushMessageError::NoSuchPeer) => {
                         trace!(target: "stratum", "Worker no longer connected: {}", addr);
                         hup_peers.insert(addr.clone());
@@ -260,6 +303,7 @@ impl StratumImpl {
             let mut workers = self.workers.write();
             for hup_peer in hup_peers {
                 workers.remove(&hup_peer);
+                self.dispatcher.disconnected(&hup_peer);
             }
         }
     }
@@ -448,6 +492,43 @@ mod tests {
         assert_eq!(1, stratum.implementation.workers.read().len());
     }
 
+    struct RefusingManager;
+
+    impl JobDispatcher for RefusingManager {
+        fn authorize(&self, _worker_id: &str, secret: &str, _peer: &SocketAddr) -> bool {
+            secret == "token"
+        }
+
+        fn submit(&self, _payload: Vec<String>) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn dispatcher_can_refuse_authorization() {
+        let addr = "127.0.0.1:19965".parse().unwrap();
+        let stratum = Stratum::start(&addr, Arc::new(RefusingManager), None)
+            .expect("There should be no error starting stratum");
+
+        let request = r#"{"jsonrpc": "2.0", "method": "mining.authorize", "params": ["miner1", "wrong"], "id": 1}"#;
+        let response = String::from_utf8(dummy_request(&addr, request)).unwrap();
+
+        assert_eq!(
+            terminated_str(r#"{"jsonrpc":"2.0","result":false,"id":1}"#),
+            response
+        );
+        assert_eq!(0, stratum.implementation.workers.read().len());
+
+        let request = r#"{"jsonrpc": "2.0", "method": "mining.authorize", "params": ["miner1", "token"], "id": 2}"#;
+        let response = String::from_utf8(dummy_request(&addr, request)).unwrap();
+
+        assert_eq!(
+            terminated_str(r#"{"jsonrpc":"2.0","result":true,"id":2}"#),
+            response
+        );
+        assert_eq!(1, stratum.implementation.workers.read().len());
+    }
+
     #[test]
     fn can_push_work() {
         let _ = ::env_logger::try_init();
@@ -15,8 +15,10 @@
 // along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
 
 use ethereum_types::H256;
+use jsonrpc_core::futures::{future, Future};
 use jsonrpc_tcp_server::PushMessageError;
 use std;
+use std::net::SocketAddr;
 
 #[derive(Debug, Clone)]
 pub enum Error {
@@ -39,6 +41,9 @@ impl From<PushMessageError> for Error {
     }
 }
 
+/// Result of a submission, resolved once the dispatcher checked it
+pub type SubmitFuture = Box<dyn Future<Item = (), Error = Error> + Send>;
+
 /// Interface that can provide pow/blockchain-specific responses for the clients
 pub trait JobDispatcher: Send + Sync {
     // json for initial client handshake
@@ -53,14 +58,45 @@ pub trait JobDispatcher: Send + Sync {
     fn job(&self) -> Option<String> {
         None
     }
+    // json for job update of the peer authorized as worker_id
+    fn job_for(&self, _worker_id: &str, _peer: &SocketAddr) -> Option<String> {
+        self.job()
+    }
     // miner job result
     fn submit(&self, payload: Vec<String>) -> Result<(), Error>;
+    // whether the peer may work as worker_id, given the secret it authorized with
+    fn authorize(&self, _worker_id: &str, _secret: &str, _peer: &SocketAddr) -> bool {
+        true
+    }
+    // miner job result, with the worker_id it was submitted under and the peer submitting it
+    fn submit_from(
+        &self,
+        _worker_id: &str,
+        _peer: &SocketAddr,
+        payload: Vec<String>,
+    ) -> Result<(), Error> {
+        self.submit(payload)
+    }
+    // miner job result, answered once the future resolves rather than on the server thread
+    fn submit_async(
+        &self,
+        worker_id: &str,
+        peer: &SocketAddr,
+        payload: Vec<String>,
+    ) -> SubmitFuture {
+        Box::new(future::result(self.submit_from(worker_id, peer, payload)))
+    }
+    // the peer disconnected
+    fn disconnected(&self, _peer: &SocketAddr) {}
 }
 
 /// Interface that can handle requests to push job for workers
 pub trait PushWorkHandler: Send + Sync {
     /// push the same work package for all workers (`payload`: json of pow-specific set of work specification)
     fn push_work_all(&self, payload: String);
+
+    /// push to every worker the work package the dispatcher has for it (`JobDispatcher::job_for`)
+    fn push_work_each(&self);
 }
 
 pub struct ServiceConfiguration {
@@ -143,11 +143,12 @@ where
 			})
 	}
 
-	fn calc_difficulty(&self, parent: B::Hash, _cur: B::Hash) -> Result<Self::Difficulty, Error<B>> {
-		let parent_id = BlockId::<B>::hash(parent);
+	fn calc_difficulty(&self, _parent: B::Hash, cur: B::Hash) -> Result<Self::Difficulty, Error<B>> {
+		// The block mined on top of `cur` is verified with `difficulty(cur)`: read at the same state.
+		let cur_id = BlockId::<B>::hash(cur);
 		self.client
 			.runtime_api()
-			.difficulty(&parent_id)
+			.difficulty(&cur_id)
 			.map_err(|err| {
 				sc_consensus_pow::Error::Environment(format!(
 					"Fetching difficulty from runtime failed: {:?}",
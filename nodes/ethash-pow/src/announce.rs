@@ -0,0 +1,79 @@
+//! Check of the seals of the blocks announced by peers.
+//!
+//! A block announced with a bogus seal is dropped before it is downloaded, and the peer
+//! announcing it disconnected. The proofs of work found valid are remembered by the algorithm,
+//! so the import of the block doesn't compute them again. Only the blocks of the epoch of the
+//! best block and of the next one are checked: any other epoch would have its light cache loaded
+//! or generated at the whim of the announcer.
+
+use ethash::ETHASH_EPOCH_LENGTH;
+use ethpow::EthashAlgorithm;
+use futures::{future, prelude::*};
+use log::debug;
+use sp_blockchain::HeaderBackend;
+use sp_consensus::block_validation::{BlockAnnounceValidator, Validation};
+use sp_consensus_pow::POW_ENGINE_ID;
+use sp_core::H256;
+use sp_runtime::{
+	traits::{Block as BlockT, Header as HeaderT},
+	DigestItem, SaturatedConversion,
+};
+use std::{error::Error, pin::Pin, sync::Arc};
+
+/// Validates the announced blocks by their seal.
+pub struct SealAnnounceValidator<C, AC> {
+	client: Arc<C>,
+	algorithm: EthashAlgorithm<AC>,
+}
+
+impl<C, AC> SealAnnounceValidator<C, AC> {
+	/// Check the announced seals with `algorithm`, the one of the block import, against the best
+	/// block of `client`.
+	pub fn new(client: Arc<C>, algorithm: EthashAlgorithm<AC>) -> Self {
+		Self { client, algorithm }
+	}
+
+	fn check<B>(&self, header: &B::Header) -> Validation
+	where
+		B: BlockT<Hash = H256>,
+		C: HeaderBackend<B>,
+	{
+		let number = (*header.number()).saturated_into::<u64>();
+		let best_epoch = self.client.info().best_number.saturated_into::<u64>() / ETHASH_EPOCH_LENGTH;
+		let epoch = number / ETHASH_EPOCH_LENGTH;
+		if epoch < best_epoch || epoch > best_epoch + 1 {
+			return Validation::Success { is_new_best: false };
+		}
+
+		let mut header = header.clone();
+		let seal = match header.digest_mut().pop() {
+			Some(DigestItem::Seal(id, seal)) if id == POW_ENGINE_ID => seal,
+			_ => {
+				debug!(target: "pow", "Announced block #{} is not sealed", number);
+				return Validation::Failure { disconnect: true };
+			}
+		};
+
+		match self.algorithm.check_announced_seal(number, &header.hash(), &seal) {
+			Ok(()) => Validation::Success { is_new_best: false },
+			Err(err) => {
+				debug!(target: "pow", "Announced block #{} has an invalid seal: {}", number, err);
+				Validation::Failure { disconnect: true }
+			}
+		}
+	}
+}
+
+impl<B, C, AC> BlockAnnounceValidator<B> for SealAnnounceValidator<C, AC>
+where
+	B: BlockT<Hash = H256>,
+	C: HeaderBackend<B>,
+{
+	fn validate(
+		&mut self,
+		header: &B::Header,
+		_data: &[u8],
+	) -> Pin<Box<dyn Future<Output = Result<Validation, Box<dyn Error + Send>>> + Send>> {
+		future::ready(Ok(self.check::<B>(header))).boxed()
+	}
+}
@@ -0,0 +1,206 @@
+//! The `benchmark-pow` subcommand: measure how fast this machine runs ethash, to size the seal
+//! verification threads of the import queue and decide whether local CPU mining is worth it.
+//!
+//! For every epoch benchmarked, the light cache is generated in a scratch directory, so nothing
+//! is loaded from the caches of a node, then seals are verified against it with `compute_light`
+//! as the import does. Unless `--skip-dataset`, the full dataset is generated from the cache and
+//! hashed on every thread as `mine` and `--mining-threads` do.
+
+use ethash::{EthashManager, Full, ETHASH_EPOCH_LENGTH};
+use serde_derive::Serialize;
+use sp_core::H256;
+use std::{
+	sync::{atomic::{AtomicBool, AtomicU64, Ordering}, Arc},
+	thread,
+	time::{Duration, Instant},
+};
+use structopt::StructOpt;
+use tempdir::TempDir;
+
+/// Nonces a thread hashes between two checks of the deadline.
+const NONCES_PER_ROUND: u64 = 100;
+
+/// Benchmark the generation of the ethash caches and datasets, seal verification and hashing.
+#[derive(Debug, StructOpt)]
+pub struct BenchmarkPowCmd {
+	/// Epochs to benchmark, comma separated. Later epochs have larger caches and datasets.
+	#[structopt(long, value_name = "EPOCHS", use_delimiter = true, default_value = "0")]
+	pub epochs: Vec<u64>,
+
+	/// Number of seals verified against the light cache of every epoch.
+	#[structopt(long, default_value = "200")]
+	pub verifications: u64,
+
+	/// Number of threads generating the dataset and hashing against it.
+	#[structopt(long, default_value = "1")]
+	pub threads: usize,
+
+	/// Seconds spent hashing against the dataset of every epoch.
+	#[structopt(long, value_name = "SECONDS", default_value = "10")]
+	pub hash_duration: u64,
+
+	/// Skip the generation of the datasets and the hashing, which take minutes and over a
+	/// gigabyte of memory per epoch.
+	#[structopt(long)]
+	pub skip_dataset: bool,
+
+	/// Print the report as JSON rather than as a table.
+	#[structopt(long)]
+	pub json: bool,
+}
+
+/// The measurements of an epoch.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EpochReport {
+	epoch: u64,
+	/// Time to generate the light cache, in milliseconds.
+	cache_generation_ms: u64,
+	/// Seals verified per second with `compute_light`, on one thread.
+	verifications_per_second: f64,
+	/// Time to generate the full dataset, in milliseconds.
+	dataset_generation_ms: Option<u64>,
+	/// Hashes per second against the full dataset, on every thread.
+	hashrate: Option<f64>,
+	/// Hashes per second against the full dataset, per thread.
+	hashrate_per_thread: Option<f64>,
+}
+
+/// The report of the benchmark.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Report {
+	threads: usize,
+	epochs: Vec<EpochReport>,
+}
+
+impl BenchmarkPowCmd {
+	/// Run the benchmark and print the report.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		if self.epochs.is_empty() {
+			return Err("--epochs must list at least one epoch".into());
+		}
+		if self.threads == 0 || self.verifications == 0 || self.hash_duration == 0 {
+			return Err("--threads, --verifications and --hash-duration must be greater than zero".into());
+		}
+
+		let mut epochs = Vec::with_capacity(self.epochs.len());
+		for &epoch in &self.epochs {
+			if !self.json {
+				eprintln!("Benchmarking epoch {}", epoch);
+			}
+			epochs.push(self.benchmark_epoch(epoch)?);
+		}
+		let report = Report { threads: self.threads, epochs };
+
+		if self.json {
+			let json = serde_json::to_string_pretty(&report).map_err(|err| err.to_string())?;
+			println!("{}", json);
+		} else {
+			print_table(&report);
+		}
+
+		Ok(())
+	}
+
+	fn benchmark_epoch(&self, epoch: u64) -> sc_cli::Result<EpochReport> {
+		// A scratch directory, for the cache to be generated rather than loaded.
+		let cache_dir = TempDir::new("ethash-benchmark")?;
+		let ethash = EthashManager::new(cache_dir.path(), None, u64::max_value());
+		let number = epoch * ETHASH_EPOCH_LENGTH;
+
+		let started = Instant::now();
+		let light = ethash.light_cache(number);
+		let cache_generation = started.elapsed();
+
+		let started = Instant::now();
+		for nonce in 0..self.verifications {
+			light.compute(&pow_hash(epoch), nonce, number);
+		}
+		let verifications_per_second = per_second(self.verifications, started.elapsed());
+
+		let mut report = EpochReport {
+			epoch,
+			cache_generation_ms: cache_generation.as_millis() as u64,
+			verifications_per_second,
+			dataset_generation_ms: None,
+			hashrate: None,
+			hashrate_per_thread: None,
+		};
+		if self.skip_dataset {
+			return Ok(report);
+		}
+
+		let started = Instant::now();
+		let dataset = Arc::new(Full::generate(light, self.threads));
+		report.dataset_generation_ms = Some(started.elapsed().as_millis() as u64);
+
+		let hashrate = hash(&dataset, epoch, self.threads, Duration::from_secs(self.hash_duration));
+		report.hashrate = Some(hashrate);
+		report.hashrate_per_thread = Some(hashrate / self.threads as f64);
+		Ok(report)
+	}
+}
+
+/// Hash against `dataset` on `threads` threads for `duration`. Returns the hashes per second.
+fn hash(dataset: &Arc<Full>, epoch: u64, threads: usize, duration: Duration) -> f64 {
+	let number = epoch * ETHASH_EPOCH_LENGTH;
+	let hashes = Arc::new(AtomicU64::new(0));
+	let stop = Arc::new(AtomicBool::new(false));
+	let started = Instant::now();
+	let workers: Vec<_> = (0..threads as u64)
+		.map(|worker| {
+			let dataset = dataset.clone();
+			let hashes = hashes.clone();
+			let stop = stop.clone();
+			thread::spawn(move || {
+				// Every thread searches its own nonces, as miners do.
+				let mut nonce = worker << 48;
+				while !stop.load(Ordering::Relaxed) {
+					for _ in 0..NONCES_PER_ROUND {
+						dataset.compute(&pow_hash(epoch), nonce, number);
+						nonce += 1;
+					}
+					hashes.fetch_add(NONCES_PER_ROUND, Ordering::Relaxed);
+				}
+			})
+		})
+		.collect();
+
+	thread::sleep(duration);
+	stop.store(true, Ordering::Relaxed);
+	for worker in workers {
+		worker.join().expect("hashing doesn't panic; qed");
+	}
+	per_second(hashes.load(Ordering::Relaxed), started.elapsed())
+}
+
+/// A header hash to hash in `epoch`, its value doesn't matter to the speed.
+fn pow_hash(epoch: u64) -> [u8; 32] {
+	H256::from_low_u64_be(epoch).0
+}
+
+fn per_second(count: u64, elapsed: Duration) -> f64 {
+	count as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
+}
+
+fn print_table(report: &Report) {
+	println!(
+		"{:>8} {:>12} {:>16} {:>14} {:>14} {:>14}",
+		"epoch", "cache (ms)", "verifications/s", "dataset (ms)", "H/s", "H/s/thread",
+	);
+	let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".into());
+	for epoch in &report.epochs {
+		println!(
+			"{:>8} {:>12} {:>16.1} {:>14} {:>14} {:>14}",
+			epoch.epoch,
+			epoch.cache_generation_ms,
+			epoch.verifications_per_second,
+			or_dash(epoch.dataset_generation_ms.map(|ms| ms.to_string())),
+			or_dash(epoch.hashrate.map(|rate| format!("{:.1}", rate))),
+			or_dash(epoch.hashrate_per_thread.map(|rate| format!("{:.1}", rate))),
+		);
+	}
+	println!();
+	println!("Hashing on {} thread(s).", report.threads);
+}
@@ -0,0 +1,32 @@
+//! Integrity check of the ethash light caches kept in the cache directory, run at startup.
+//!
+//! A light cache damaged on disk still has the right size, so it is loaded as is and every seal
+//! of its epoch is verified wrongly: valid blocks get rejected, or worse. The check regenerates
+//! the cache of every file found and replaces the corrupt ones, in the background since it takes
+//! as long as generating them.
+
+use ethash::ETHASH_EPOCH_LENGTH;
+use ethpow::EthashAlgorithm;
+use log::{info, warn};
+
+/// Check the caches of the epochs up to the one following `best_number`, logging what was
+/// repaired.
+pub async fn run_cache_check<C>(algorithm: EthashAlgorithm<C>, best_number: u64) {
+	// The cache of the next epoch may already have been generated.
+	let max_epoch = best_number / ETHASH_EPOCH_LENGTH + 1;
+	match algorithm.repair_caches(max_epoch) {
+		Ok(repaired) if repaired.is_empty() => info!(target: "pow", "Ethash light caches checked"),
+		Ok(repaired) => {
+			for cache in repaired {
+				warn!(
+					target: "pow",
+					"Regenerated the corrupt ethash light cache of epoch {} in {}: {}",
+					cache.epoch,
+					cache.path.display(),
+					cache.problem,
+				);
+			}
+		}
+		Err(err) => warn!(target: "pow", "Failed to check the ethash light caches: {}", err),
+	}
+}
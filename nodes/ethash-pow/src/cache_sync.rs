@@ -0,0 +1,258 @@
+//! Request/response protocol serving ethash light caches to peers.
+//!
+//! Generating the light cache of a late epoch takes a while, and a freshly started node can't
+//! verify a single block of that epoch before it is done. Every full node serves the caches kept
+//! in its cache directory, a cache at most every `CACHE_INTERVAL` to a peer. A node started with
+//! `--ethash-cache-from-peers` asks its first peers for the digest of the cache of the epoch of
+//! its best block, and downloads it once enough of them agree on it.
+//!
+//! Peers agreeing is no proof: the download is only written to the cache directory, where the
+//! seal verification trusts it, once its Merkle root matches the commitment to the cache the
+//! runtime holds, see `ethpow::EpochCache`. Without a commitment for the epoch nothing is
+//! downloaded. The download is only a head start: a node that doesn't get it generates the cache
+//! itself as before.
+//!
+//! Requests are SCALE encoded `CacheRequest`s. A `Digest` request is answered with the encoded
+//! `Option` of the blake2_256 of the cache of the epoch, a `Cache` request with the raw cache
+//! nodes.
+
+use ethash::{cache_file, import_cache_nodes, ETHASH_EPOCH_LENGTH};
+use ethpow::{cache_root, EpochCache};
+use futures::{channel::mpsc, future, prelude::*};
+use futures_timer::Delay;
+use log::{debug, info, warn};
+use parity_scale_codec::{Decode, Encode};
+use runtime::opaque::Block;
+use sc_network::{
+	config::{IncomingRequest, OutgoingResponse, RequestResponseConfig},
+	Event, NetworkService, PeerId,
+};
+use sp_blockchain::HeaderBackend;
+use sp_core::{hashing::blake2_256, H256};
+use sp_runtime::traits::{Block as BlockT, UniqueSaturatedInto};
+use std::{
+	collections::{HashMap, HashSet},
+	fs,
+	path::PathBuf,
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+/// Name of the protocol.
+pub const PROTOCOL_NAME: &str = "/ethash/cache/2";
+
+/// Least time between two caches sent to a peer.
+const CACHE_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Largest cache served, about the size of the cache of epoch 1900.
+const MAX_RESPONSE_SIZE: u64 = 256 * 1024 * 1024;
+
+/// How long to wait for enough peers before giving up on fetching the cache.
+const PEERS_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Request of the protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub enum CacheRequest {
+	/// The digest of the cache of an epoch.
+	Digest(u64),
+	/// The cache of an epoch.
+	Cache(u64),
+}
+
+/// Configuration of the protocol, handing the incoming requests to `inbound_queue`.
+pub fn request_response_config(inbound_queue: mpsc::Sender<IncomingRequest>) -> RequestResponseConfig {
+	RequestResponseConfig {
+		name: PROTOCOL_NAME.into(),
+		max_request_size: 32,
+		max_response_size: MAX_RESPONSE_SIZE,
+		request_timeout: Duration::from_secs(60),
+		inbound_queue: Some(inbound_queue),
+	}
+}
+
+/// Answer the cache requests of the peers with the caches of `cache_dir`.
+///
+/// Reads cache files synchronously, spawn it as a blocking task.
+pub async fn run_cache_server<C>(
+	client: Arc<C>,
+	cache_dir: PathBuf,
+	mut requests: mpsc::Receiver<IncomingRequest>,
+)
+where
+	C: HeaderBackend<Block>,
+{
+	// Hashing a cache takes about as long as reading it, do it once per epoch.
+	let mut digests = HashMap::new();
+	// When each peer was last sent a cache.
+	let mut served: HashMap<PeerId, Instant> = HashMap::new();
+
+	while let Some(IncomingRequest { peer, payload, pending_response }) = requests.next().await {
+		let result = match CacheRequest::decode(&mut &payload[..]) {
+			Ok(CacheRequest::Digest(epoch)) => {
+				let digest = match digests.get(&epoch) {
+					// Only the caches of the epochs the chain reached are served.
+					_ if epoch > best_epoch(&*client) => None,
+					Some(digest) => Some(*digest),
+					None => fs::read(cache_file(&cache_dir, epoch)).ok().map(|cache| {
+						let digest = H256(blake2_256(&cache));
+						digests.insert(epoch, digest);
+						digest
+					}),
+				};
+				Ok(digest.encode())
+			}
+			Ok(CacheRequest::Cache(epoch)) => {
+				let now = Instant::now();
+				served.retain(|_, sent| now.duration_since(*sent) < CACHE_INTERVAL);
+				if served.contains_key(&peer) {
+					debug!(target: "ethash-cache", "{} asked for another cache within {:?}", peer, CACHE_INTERVAL);
+					Err(())
+				} else if epoch > best_epoch(&*client) {
+					Err(())
+				} else {
+					let cache = fs::read(cache_file(&cache_dir, epoch)).map_err(|_| ());
+					if cache.is_ok() {
+						served.insert(peer, now);
+					}
+					cache
+				}
+			}
+			Err(err) => {
+				debug!(target: "ethash-cache", "Invalid cache request from {}: {:?}", peer, err);
+				Err(())
+			}
+		};
+
+		let response = OutgoingResponse { result, reputation_changes: Vec::new() };
+		if pending_response.send(response).is_err() {
+			debug!(target: "ethash-cache", "{} dropped its cache request", peer);
+		}
+	}
+}
+
+/// Fetch the cache of the epoch of the best block of `client` into `cache_dir`, once `min_peers`
+/// peers agree on its digest and it matches the commitment to the cache of the epoch returned by
+/// `commitment`, the one the runtime holds.
+pub async fn fetch_cache<C>(
+	network: Arc<NetworkService<Block, <Block as BlockT>::Hash>>,
+	client: Arc<C>,
+	commitment: impl Fn(u64) -> Result<Option<EpochCache>, String>,
+	cache_dir: PathBuf,
+	min_peers: usize,
+)
+where
+	C: HeaderBackend<Block>,
+{
+	let epoch = best_epoch(&*client);
+	if cache_file(&cache_dir, epoch).exists() {
+		return;
+	}
+	let commitment = match commitment(epoch) {
+		Ok(Some(commitment)) => commitment,
+		Ok(None) => {
+			info!(target: "ethash-cache", "No commitment to the ethash cache of epoch {} to check a download against, generating it", epoch);
+			return;
+		}
+		Err(err) => {
+			warn!(target: "ethash-cache", "Failed to read the commitment to the ethash cache of epoch {}: {}", epoch, err);
+			return;
+		}
+	};
+
+	let peers = match connected_peers(&network, min_peers).await {
+		Some(peers) => peers,
+		None => {
+			info!(target: "ethash-cache", "Not enough peers to fetch the ethash cache from, generating it");
+			return;
+		}
+	};
+
+	let digests = future::join_all(peers.iter().map(|peer| {
+		network
+			.request(peer.clone(), PROTOCOL_NAME, CacheRequest::Digest(epoch).encode())
+			.map(move |response| {
+				let digest = response
+					.ok()
+					.and_then(|response| Option::<H256>::decode(&mut &response[..]).ok())
+					.flatten();
+				(peer.clone(), digest)
+			})
+	})).await;
+
+	let mut votes: HashMap<H256, Vec<PeerId>> = HashMap::new();
+	for (peer, digest) in digests {
+		if let Some(digest) = digest {
+			votes.entry(digest).or_default().push(peer);
+		}
+	}
+	let (digest, sources) = match votes.into_iter().max_by_key(|(_, sources)| sources.len()) {
+		Some(vote) if vote.1.len() >= min_peers => vote,
+		_ => {
+			info!(target: "ethash-cache", "Peers don't agree on the ethash cache, generating it");
+			return;
+		}
+	};
+
+	for peer in sources {
+		let cache = match network.request(peer.clone(), PROTOCOL_NAME, CacheRequest::Cache(epoch).encode()).await {
+			Ok(cache) => cache,
+			Err(err) => {
+				debug!(target: "ethash-cache", "{} failed to send the cache of epoch {}: {:?}", peer, epoch, err);
+				continue;
+			}
+		};
+		if H256(blake2_256(&cache)) != digest {
+			warn!(target: "ethash-cache", "{} sent a cache of epoch {} not matching its digest", peer, epoch);
+			continue;
+		}
+		if !is_committed(&cache, &commitment) {
+			// Every source agreed on this digest, the others would send the same cache.
+			warn!(target: "ethash-cache", "Peers agree on a cache of epoch {} not matching the commitment of the runtime", epoch);
+			break;
+		}
+
+		match import_cache_nodes(&cache_dir, epoch, &cache, false) {
+			Ok(path) => info!(target: "ethash-cache", "Fetched the ethash cache of epoch {} to {}", epoch, path.display()),
+			Err(err) => warn!(target: "ethash-cache", "Failed to import the cache of epoch {}: {}", epoch, err),
+		}
+		return;
+	}
+
+	info!(target: "ethash-cache", "Failed to fetch the ethash cache of epoch {}, generating it", epoch);
+}
+
+/// Whether `cache` is the light cache `commitment` commits to.
+fn is_committed(cache: &[u8], commitment: &EpochCache) -> bool {
+	cache.len() == commitment.cache_nodes as usize * 64 && cache_root(cache) == commitment.root
+}
+
+/// The epoch of the best block of `client`.
+fn best_epoch<C: HeaderBackend<Block>>(client: &C) -> u64 {
+	let best: u64 = client.info().best_number.unique_saturated_into();
+	best / ETHASH_EPOCH_LENGTH
+}
+
+/// The peers we sync with once there are at least `min_peers` of them, `None` on timeout.
+async fn connected_peers(
+	network: &NetworkService<Block, <Block as BlockT>::Hash>,
+	min_peers: usize,
+) -> Option<HashSet<PeerId>> {
+	let mut events = network.event_stream("ethash-cache");
+	let mut timeout = Delay::new(PEERS_TIMEOUT);
+	let mut peers = HashSet::new();
+
+	while peers.len() < min_peers {
+		match future::select(events.next(), &mut timeout).await {
+			future::Either::Left((Some(Event::SyncConnected { remote }), _)) => {
+				peers.insert(remote);
+			}
+			future::Either::Left((Some(Event::SyncDisconnected { remote }), _)) => {
+				peers.remove(&remote);
+			}
+			future::Either::Left((Some(_), _)) => {}
+			future::Either::Left((None, _)) | future::Either::Right(_) => return None,
+		}
+	}
+
+	Some(peers)
+}
@@ -1,8 +1,32 @@
+//! The chains the node knows by name, and custom chain specs.
+//!
+//! `--chain dev` and `--chain local` build their genesis from the presets below. Any other value
+//! is the path of a chain spec JSON file, written by `new-chain-spec` for a chain of its own, or
+//! by `build-spec --chain dev` and edited: the genesis difficulty is
+//! `genesis.runtime.difficulty.difficulty`, and the floor the retarget never goes below
+//! `genesis.runtime.difficulty.minimumDifficulty`, both hex encoded. A test network can start
+//! with a target low enough to mine on a CPU by lowering both. The retarget rule is
+//! `genesis.runtime.difficulty.strategy`: `"parent-delta"`, as in the presets, `"eip100"`,
+//! `"lwma"` or `"fixed"`. The block reward follows the halving schedule of the runtime, unless
+//! `genesis.runtime.blockReward.schedule` sets another curve from block 0.
+//!
+//! A chain spec may name a block trusted by `--pow-fast-sync` in its properties, as
+//! `"powCheckpoint": { "number": 100000, "hash": "0x..." }`.
+//!
+//! The mined blocks are finalized by the GRANDPA voters of `genesis.runtime.palletGrandpa`: Alice
+//! alone in `dev`, Alice and Bob in `local`, whose keys `--alice` and `--bob` put in the keystore.
+
+use ethpow::Checkpoint;
 use runtime::{
-	genesis::{account_id_from_seed, dev_genesis, testnet_genesis},
+	genesis::{account_id_from_seed, authority_keys_from_seed, pow_genesis},
 	GenesisConfig, WASM_BINARY,
 };
+use sc_service::ChainType;
 use sp_core::sr25519;
+use std::path::PathBuf;
+
+/// Property of the chain spec holding the checkpoint of `--pow-fast-sync`.
+const CHECKPOINT_PROPERTY: &str = "powCheckpoint";
 
 // Note this is the URL for the telemetry server
 //const STAGING_TELEMETRY_URL: &str = "wss://telemetry.polkadot.io/submit/";
@@ -10,53 +34,114 @@ use sp_core::sr25519;
 /// Specialized `ChainSpec`. This is a specialization of the general Substrate `ChainSpec` type.
 pub type ChainSpec = sc_service::GenericChainSpec<GenesisConfig>;
 
+/// Genesis of a built-in chain.
+struct Preset {
+	name: &'static str,
+	id: &'static str,
+	chain_type: ChainType,
+	/// Seeds of the GRANDPA voters.
+	authorities: &'static [&'static str],
+	/// Seed of the sudo key.
+	root: &'static str,
+	/// Seeds of the accounts endowed at genesis.
+	endowed: &'static [&'static str],
+	/// Difficulty of the first block.
+	difficulty: u64,
+	/// The retargeted difficulty never drops below this value.
+	minimum_difficulty: u64,
+}
+
+impl Preset {
+	fn build(self) -> Result<ChainSpec, String> {
+		let wasm_binary = WASM_BINARY.ok_or_else(|| "Development wasm not available".to_string())?;
+		let Preset { name, id, chain_type, authorities, root, endowed, difficulty, minimum_difficulty } = self;
+
+		Ok(ChainSpec::from_genesis(
+			name,
+			id,
+			chain_type,
+			move || {
+				pow_genesis(
+					wasm_binary,
+					authorities.iter().map(|seed| authority_keys_from_seed(seed)).collect(),
+					account_id_from_seed::<sr25519::Pair>(root),
+					endowed.iter().map(|seed| account_id_from_seed::<sr25519::Pair>(seed)).collect(),
+					difficulty.into(),
+					minimum_difficulty.into(),
+				)
+			},
+			vec![],
+			None,
+			None,
+			None,
+			None,
+		))
+	}
+}
+
+/// A single node chain, mined in a few seconds with `mine` on one CPU thread.
 pub fn dev_config() -> Result<ChainSpec, String> {
-	let wasm_binary = WASM_BINARY.ok_or_else(|| "Development wasm not available".to_string())?;
-
-	Ok(ChainSpec::from_genesis(
-		"Development",
-		"dev",
-		sc_service::ChainType::Development,
-		move || dev_genesis(wasm_binary),
-		vec![],
-		None,
-		None,
-		None,
-		None,
-	))
+	Preset {
+		name: "Development",
+		id: "dev",
+		chain_type: ChainType::Development,
+		authorities: &["Alice"],
+		root: "Alice",
+		endowed: &["Alice", "Bob", "Alice//stash", "Bob//stash"],
+		difficulty: 100_000,
+		minimum_difficulty: 10_000,
+	}
+	.build()
 }
 
+/// A network of a few nodes, mined with GPUs or several CPU miners.
 pub fn local_testnet_config() -> Result<ChainSpec, String> {
-	let wasm_binary = WASM_BINARY.ok_or_else(|| "Development wasm not available".to_string())?;
-
-	Ok(ChainSpec::from_genesis(
-		"Local Testnet",
-		"local_testnet",
-		sc_service::ChainType::Local,
-		move || {
-			testnet_genesis(
-				wasm_binary,
-				account_id_from_seed::<sr25519::Pair>("Alice"),
-				vec![
-					account_id_from_seed::<sr25519::Pair>("Alice"),
-					account_id_from_seed::<sr25519::Pair>("Bob"),
-					account_id_from_seed::<sr25519::Pair>("Charlie"),
-					account_id_from_seed::<sr25519::Pair>("Dave"),
-					account_id_from_seed::<sr25519::Pair>("Eve"),
-					account_id_from_seed::<sr25519::Pair>("Ferdie"),
-					account_id_from_seed::<sr25519::Pair>("Alice//stash"),
-					account_id_from_seed::<sr25519::Pair>("Bob//stash"),
-					account_id_from_seed::<sr25519::Pair>("Charlie//stash"),
-					account_id_from_seed::<sr25519::Pair>("Dave//stash"),
-					account_id_from_seed::<sr25519::Pair>("Eve//stash"),
-					account_id_from_seed::<sr25519::Pair>("Ferdie//stash"),
-				],
-			)
-		},
-		vec![],
-		None,
-		None,
-		None,
-		None,
-	))
+	Preset {
+		name: "Local Testnet",
+		id: "local_testnet",
+		chain_type: ChainType::Local,
+		authorities: &["Alice", "Bob"],
+		root: "Alice",
+		endowed: &[
+			"Alice",
+			"Bob",
+			"Charlie",
+			"Dave",
+			"Eve",
+			"Ferdie",
+			"Alice//stash",
+			"Bob//stash",
+			"Charlie//stash",
+			"Dave//stash",
+			"Eve//stash",
+			"Ferdie//stash",
+		],
+		difficulty: 1_000_000,
+		minimum_difficulty: 1_000_000,
+	}
+	.build()
+}
+
+/// The chain spec of the JSON file at `path`.
+pub fn custom_config(path: &str) -> Result<ChainSpec, String> {
+	let path = PathBuf::from(path);
+	if !path.is_file() {
+		return Err(format!(
+			"Unknown chain {}: use dev, local or the path of a chain spec JSON file",
+			path.display(),
+		));
+	}
+	ChainSpec::from_json_file(path)
+}
+
+/// The block of `spec` whose ancestors `--pow-fast-sync` imports without their proof of work,
+/// if any.
+pub fn pow_checkpoint(spec: &dyn sc_service::ChainSpec) -> Result<Option<Checkpoint>, String> {
+	spec.properties()
+		.get(CHECKPOINT_PROPERTY)
+		.map(|checkpoint| {
+			serde_json::from_value(checkpoint.clone())
+				.map_err(|err| format!("Invalid {} in the chain spec properties: {}", CHECKPOINT_PROPERTY, err))
+		})
+		.transpose()
 }
@@ -0,0 +1,216 @@
+//! Request/response protocol proving the ancestry of the checkpoint of `--pow-fast-sync`.
+//!
+//! The blocks below the checkpoint are imported without their proof of work only once proven to
+//! be its ancestors. The node downloads the headers from the checkpoint down, from any peer: the
+//! first must hash to the checkpoint hash, and every other to the parent hash of the one above,
+//! so a peer can't slip in a header of another chain. The seal of every header checked is
+//! recorded in the `CheckpointAncestry` of the algorithm. Headers are small and need no proof of
+//! work, so the download runs far ahead of the import of the blocks, which are fully verified
+//! until it reaches them.
+//!
+//! Requests are SCALE encoded `AncestryRequest`s, answered with the encoded `Vec` of the headers
+//! from `from` down, at most `MAX_HEADERS` and fewer if the peer doesn't have them all.
+
+use ethpow::{Checkpoint, CheckpointAncestry};
+use futures::{channel::mpsc, prelude::*};
+use futures_timer::Delay;
+use log::{debug, info, warn};
+use parity_scale_codec::{Decode, Encode};
+use runtime::opaque::Block;
+use sc_network::{
+	config::{IncomingRequest, OutgoingResponse, RequestResponseConfig},
+	Event, NetworkService, PeerId,
+};
+use sp_blockchain::HeaderBackend;
+use sp_consensus_pow::POW_ENGINE_ID;
+use sp_core::H256;
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block as BlockT, Header as HeaderT, UniqueSaturatedInto},
+	DigestItem,
+};
+use std::{sync::Arc, time::Duration};
+
+/// Name of the protocol.
+pub const PROTOCOL_NAME: &str = "/ethash/checkpoint/1";
+
+/// Most headers sent in a response.
+const MAX_HEADERS: u32 = 2048;
+
+/// How long the peers which failed to prove the ancestry wait before they are asked again, once
+/// every peer failed.
+const RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Request of the protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub struct AncestryRequest {
+	/// Hash of the highest header wanted.
+	pub from: H256,
+	/// Number of headers wanted, going down from `from`.
+	pub count: u32,
+}
+
+/// Configuration of the protocol, handing the incoming requests to `inbound_queue`.
+pub fn request_response_config(inbound_queue: mpsc::Sender<IncomingRequest>) -> RequestResponseConfig {
+	RequestResponseConfig {
+		name: PROTOCOL_NAME.into(),
+		max_request_size: 64,
+		max_response_size: 2 * 1024 * 1024,
+		request_timeout: Duration::from_secs(30),
+		inbound_queue: Some(inbound_queue),
+	}
+}
+
+/// Answer the ancestry requests of the peers with the headers of `client`.
+///
+/// Reads headers synchronously, spawn it as a blocking task.
+pub async fn run_ancestry_server<C>(client: Arc<C>, mut requests: mpsc::Receiver<IncomingRequest>)
+where
+	C: HeaderBackend<Block>,
+{
+	while let Some(IncomingRequest { peer, payload, pending_response }) = requests.next().await {
+		let result = match AncestryRequest::decode(&mut &payload[..]) {
+			Ok(AncestryRequest { from, count }) => {
+				let mut headers = Vec::new();
+				let mut hash = from;
+				while headers.len() < count.min(MAX_HEADERS) as usize {
+					match client.header(BlockId::Hash(hash)) {
+						Ok(Some(header)) => {
+							hash = *header.parent_hash();
+							let genesis = header.number() == &0;
+							headers.push(header);
+							if genesis {
+								break;
+							}
+						}
+						_ => break,
+					}
+				}
+				Ok(headers.encode())
+			}
+			Err(err) => {
+				debug!(target: "ethash-checkpoint", "Invalid ancestry request from {}: {:?}", peer, err);
+				Err(())
+			}
+		};
+
+		let response = OutgoingResponse { result, reputation_changes: Vec::new() };
+		if pending_response.send(response).is_err() {
+			debug!(target: "ethash-checkpoint", "{} dropped its ancestry request", peer);
+		}
+	}
+}
+
+/// Download the headers from `checkpoint` down to the blocks `client` imported already, recording
+/// their seals in `ancestry`.
+pub async fn fetch_ancestry<C>(
+	network: Arc<NetworkService<Block, <Block as BlockT>::Hash>>,
+	client: Arc<C>,
+	ancestry: CheckpointAncestry,
+	checkpoint: Checkpoint,
+)
+where
+	C: HeaderBackend<Block>,
+{
+	let mut events = network.event_stream("ethash-checkpoint");
+	let mut peers: Vec<PeerId> = Vec::new();
+	// The peers which failed to prove the ancestry, asked again once every other peer failed.
+	let mut failed: Vec<PeerId> = Vec::new();
+	// The next header to download, and its number.
+	let mut cursor = (checkpoint.hash, checkpoint.number);
+
+	loop {
+		if matches!(client.number(cursor.0), Ok(Some(_))) {
+			break;
+		}
+		while let Some(Some(event)) = events.next().now_or_never() {
+			note_peer(&mut peers, &mut failed, event);
+		}
+		let peer = match peers.first() {
+			Some(peer) => peer.clone(),
+			None if !failed.is_empty() => {
+				warn!(
+					target: "ethash-checkpoint",
+					"The ancestry download stalled at #{}: every peer failed to prove it, asking {} of \
+					them again in {}s",
+					cursor.1,
+					failed.len(),
+					RETRY_DELAY.as_secs(),
+				);
+				Delay::new(RETRY_DELAY).await;
+				peers.append(&mut failed);
+				continue;
+			}
+			None => match events.next().await {
+				Some(event) => {
+					note_peer(&mut peers, &mut failed, event);
+					continue;
+				}
+				None => return,
+			},
+		};
+
+		let request = AncestryRequest { from: cursor.0, count: MAX_HEADERS };
+		let checked = network
+			.request(peer.clone(), PROTOCOL_NAME, request.encode())
+			.await
+			.map_err(|err| format!("{:?}", err))
+			.and_then(|response| check_headers(&response, cursor, &ancestry));
+		match checked {
+			Ok(next) => cursor = next,
+			Err(err) => {
+				debug!(target: "ethash-checkpoint", "{} failed to prove the ancestry from #{}: {}", peer, cursor.1, err);
+				peers.retain(|known| *known != peer);
+				failed.push(peer);
+			}
+		}
+	}
+
+	info!(
+		target: "ethash-checkpoint",
+		"Proved the ancestry of the checkpoint #{}: {} blocks imported without their proof of work",
+		checkpoint.number,
+		ancestry.len(),
+	);
+}
+
+/// Check the encoded `headers` of a response against `cursor`, the hash and number expected of
+/// the first, recording their seals in `ancestry`. Returns the hash and number of the next header
+/// to download.
+fn check_headers(
+	headers: &[u8],
+	mut cursor: (H256, u64),
+	ancestry: &CheckpointAncestry,
+) -> Result<(H256, u64), String> {
+	let headers = Vec::<<Block as BlockT>::Header>::decode(&mut &headers[..]).map_err(|err| err.to_string())?;
+	if headers.is_empty() {
+		return Err("no header".into());
+	}
+	for header in headers {
+		let number: u64 = (*header.number()).unique_saturated_into();
+		if header.hash() != cursor.0 || number != cursor.1 {
+			return Err(format!("header #{} {} isn't the expected #{} {}", number, header.hash(), cursor.1, cursor.0));
+		}
+		if number == 0 {
+			break;
+		}
+		match header.digest().logs().last() {
+			Some(DigestItem::Seal(id, seal)) if *id == POW_ENGINE_ID => ancestry.insert(seal),
+			_ => return Err(format!("header #{} has no seal", number)),
+		}
+		cursor = (*header.parent_hash(), number - 1);
+	}
+	Ok(cursor)
+}
+
+/// Track the peers we sync with, forgetting those disconnecting whether they `failed` or not.
+fn note_peer(peers: &mut Vec<PeerId>, failed: &mut Vec<PeerId>, event: Event) {
+	match event {
+		Event::SyncConnected { remote } => peers.push(remote),
+		Event::SyncDisconnected { remote } => {
+			peers.retain(|peer| *peer != remote);
+			failed.retain(|peer| *peer != remote);
+		}
+		_ => {}
+	}
+}
@@ -1,4 +1,15 @@
+use crate::fee_priority::FeePriority;
+use crate::mining::{
+	reward_authors, work_tracker::TRACKED_WORK, MinerToken, MiningConfig, NoncePartition, RewardAuthors,
+	RotationPolicy, WorkRpcConfig,
+};
+use ethpow::MAX_EXTRA_DATA_LEN;
+use hyper::Uri;
+use runtime::AccountId;
 use sc_cli::RunCmd;
+use sc_service::config::TransactionPoolOptions;
+use sp_core::crypto::Ss58Codec;
+use std::{net::{Ipv4Addr, SocketAddr}, path::PathBuf, time::Duration};
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
@@ -8,6 +19,370 @@ pub struct Cli {
 
 	#[structopt(flatten)]
 	pub run: RunCmd,
+
+	#[structopt(flatten)]
+	pub mining: MiningParams,
+
+	#[structopt(flatten)]
+	pub pool: PoolParams,
+}
+
+/// Transaction pool options completing `--pool-limit` and `--pool-kbytes`, which only size the
+/// ready queue generously enough for RPC nodes. Mining nodes often want to hold on to more
+/// transactions waiting for their predecessors as well.
+#[derive(Debug, Clone, StructOpt)]
+pub struct PoolParams {
+	/// Maximum number of transactions in the future queue, waiting for earlier nonces.
+	#[structopt(long)]
+	pub pool_future_limit: Option<usize>,
+
+	/// Maximum number of kilobytes of all transactions in the future queue.
+	#[structopt(long)]
+	pub pool_future_kbytes: Option<usize>,
+
+	/// Reject transactions that can't be included right away instead of keeping them in the
+	/// future queue.
+	#[structopt(long)]
+	pub pool_reject_future: bool,
+}
+
+impl PoolParams {
+	/// Apply these options on top of the pool options of the node configuration.
+	pub fn apply(&self, options: &mut TransactionPoolOptions) {
+		if let Some(count) = self.pool_future_limit {
+			options.future.count = count;
+		}
+		if let Some(kbytes) = self.pool_future_kbytes {
+			options.future.total_bytes = kbytes * 1024;
+		}
+		options.reject_future_transactions |= self.pool_reject_future;
+	}
+}
+
+/// Options of the mining service.
+#[derive(Debug, Clone, StructOpt)]
+pub struct MiningParams {
+	/// First nonce of the partition searched by the miners of this node. Solutions of nonces
+	/// outside the partition are rejected, pass the same to the `mine` subcommand.
+	#[structopt(long, default_value = "0")]
+	pub miner_nonce_start: u64,
+
+	/// Distance between two nonces searched by the miners of this node. Give every node of a
+	/// farm the same stride and a distinct start below it to avoid overlapping searches.
+	#[structopt(long, default_value = "1")]
+	pub miner_nonce_stride: u64,
+
+	/// Accounts rewarded for the blocks mined by this node, comma separated in SS58 format. With
+	/// several accounts the reward rotates across them following `--author-rotation`.
+	/// `eth_getWork` hands out no work until one is set.
+	#[structopt(
+		long,
+		alias = "author",
+		value_name = "ACCOUNTS",
+		use_delimiter = true,
+		parse(try_from_str = parse_account),
+	)]
+	pub mining_author: Vec<AccountId>,
+
+	/// File listing more accounts rewarded for the blocks mined by this node, one SS58 address
+	/// per line, after those of `--mining-author`. Lines starting with `#` are skipped.
+	#[structopt(long, value_name = "PATH", parse(from_os_str))]
+	pub mining_authors_file: Option<PathBuf>,
+
+	/// How the reward rotates across the mining authors: `block` to reward the next account with
+	/// every block, `era:<BLOCKS>` to reward the same account for eras of this many blocks.
+	#[structopt(long, value_name = "ROTATION", default_value = "block")]
+	pub author_rotation: RotationPolicy,
+
+	/// Extra data attached to the blocks mined by this node, like a pool tag, of at most 32
+	/// bytes. `pow_setExtra` replaces it at runtime.
+	#[structopt(long, value_name = "TEXT")]
+	pub mining_extra_data: Option<String>,
+
+	/// Serve a pool: hand out a share target of this difficulty alongside the block target and
+	/// acknowledge solutions meeting it.
+	#[structopt(long)]
+	pub pool_share_difficulty: Option<u64>,
+
+	/// Acknowledge the shares of the last this many work packages handed out, even once they
+	/// are replaced: miners keep hashing the old work until they get the new one. 0 only
+	/// acknowledges shares of the current work.
+	#[structopt(long, value_name = "PACKAGES", default_value = "4")]
+	pub stale_share_window: usize,
+
+	/// Maximum number of seconds spent building a block proposal, executing the transactions of
+	/// the pool. Longer includes more transactions, shorter hands out work sooner.
+	#[structopt(long, value_name = "SECONDS", default_value = "10")]
+	pub max_block_proposal_time: u64,
+
+	/// Build a new proposal, with the transactions received since, after this many seconds
+	/// without a new block imported.
+	#[structopt(long, value_name = "SECONDS", default_value = "10")]
+	pub work_refresh_interval: u64,
+
+	/// Only include the transactions paying at least this fee, tip included, per microsecond of
+	/// weight (1_000_000 units) in the blocks mined by this node. `miner_setMinFee` changes it at
+	/// runtime.
+	#[structopt(long, value_name = "FEE", default_value = "0")]
+	pub min_fee: u64,
+
+	/// Include the transactions paying the most fee, tip included, per weight first in the blocks
+	/// mined by this node, rather than in the order of the pool. A transaction still comes after
+	/// those it depends on.
+	#[structopt(long)]
+	pub order_by_fee: bool,
+
+	/// Stop including transactions in the blocks mined by this node once they weigh this much,
+	/// below the block weight limit of the runtime, like the gas target of geth miners. Blocks
+	/// are filled up to the limit when unset or 0.
+	#[structopt(long, value_name = "WEIGHT")]
+	pub block_weight_target: Option<u64>,
+
+	/// Also hash the work on this many CPU threads of the node itself, generating the full
+	/// dataset of every epoch in the ethash cache directory. `pow_setThreads` changes it at
+	/// runtime. 0 leaves the hashing to external miners.
+	#[structopt(long, value_name = "THREADS", default_value = "0")]
+	pub mining_threads: usize,
+
+	/// Log an error and raise the `ethash_mining_stalled` metric when no new best block is
+	/// produced or imported for this many seconds. 0 disables the watchdog.
+	#[structopt(long, default_value = "600")]
+	pub stall_timeout: u64,
+
+	/// Stop handing out work, and log a warning, once the work has been built on the same parent
+	/// block for this many seconds: the node most likely stopped importing blocks, and the
+	/// blocks mined on such work would be orphaned. Work is handed out again from the next block
+	/// imported. `pow_workAge` tells the current age.
+	#[structopt(long, value_name = "SECONDS")]
+	pub max_work_age: Option<u64>,
+
+	/// Directory the ethash caches are kept in across restarts. Defaults to `ethash` in the
+	/// chain directory.
+	#[structopt(long, parse(from_os_str))]
+	pub ethash_cache_dir: Option<PathBuf>,
+
+	/// Fetch the ethash cache of the epoch of the best block from peers at startup instead of
+	/// generating it, once this many peers agree on its digest. The cache is only used if it
+	/// matches the commitment to it held by the runtime.
+	#[structopt(long, value_name = "PEERS")]
+	pub ethash_cache_from_peers: Option<usize>,
+
+	/// Also generate the full dataset of the next epoch ahead of time, on this many threads, into
+	/// the ethash cache directory. A `mine` pointed at the same `--ethash-cache-dir` loads it at
+	/// the epoch change instead of generating it. Takes over a gigabyte of disk per epoch.
+	#[structopt(long, value_name = "THREADS")]
+	pub ethash_prepare_dag: Option<usize>,
+
+	/// Only accept solutions submitted with `ethash_submitSignedWork` and signed with the key
+	/// registered for the worker through `ethash_setWorkerKey`.
+	#[structopt(long)]
+	pub require_signed_shares: bool,
+
+	/// Serve stratum to external miners on this TCP port.
+	#[structopt(long)]
+	pub stratum_port: Option<u16>,
+
+	/// Listen for stratum connections on all interfaces instead of localhost only.
+	#[structopt(long)]
+	pub stratum_external: bool,
+
+	/// Serve the mining RPCs, the `eth_`, `ethash_` and `miner_` methods, over HTTP on this port
+	/// instead of the general RPC, so the two can be firewalled independently. The work
+	/// subscriptions stay on the general WebSocket RPC.
+	#[structopt(long)]
+	pub mining_rpc_port: Option<u16>,
+
+	/// Listen for mining RPC connections on all interfaces instead of localhost only. Unsafe
+	/// mining RPCs are denied on all interfaces.
+	#[structopt(long)]
+	pub mining_rpc_external: bool,
+
+	/// Send the current work again to the `newWork` subscribers after this many seconds without
+	/// new work.
+	#[structopt(long, default_value = "30")]
+	pub work_keep_alive: u64,
+
+	/// Answer an `ethash_pollWork` long-poll with the current work after this many seconds
+	/// without new work.
+	#[structopt(long, default_value = "60")]
+	pub work_idle_timeout: u64,
+
+	/// Maximum number of `ethash_pollWork` long-polls in flight on one WebSocket connection.
+	#[structopt(long, default_value = "4")]
+	pub max_long_polls: usize,
+
+	/// POST the work package as JSON to these comma separated http:// URLs whenever new work is
+	/// available.
+	#[structopt(
+		long,
+		value_name = "URLS",
+		use_delimiter = true,
+		parse(try_from_str = crate::notify_work::parse_url),
+	)]
+	pub notify_work: Vec<Uri>,
+
+	/// Stop issuing work while fewer than this many peers are connected, so a miner cut off from
+	/// the network doesn't build a private fork that will be orphaned.
+	#[structopt(long, value_name = "PEERS")]
+	pub min_mining_peers: Option<usize>,
+
+	/// Enable the `miner_shutdown` and `miner_restartWorker` RPCs, which must be called with this
+	/// secret. They are unsafe RPCs as well.
+	#[structopt(long, value_name = "SECRET")]
+	pub miner_control_secret: Option<String>,
+
+	/// Only accept the solutions and hashrates submitted with one of these comma separated
+	/// tokens, given as the last parameter of the submit RPCs and as the password of stratum
+	/// workers. Each is `NAME:TOKEN`, the name identifying the miner for `--miner-rate-limit`.
+	#[structopt(
+		long,
+		value_name = "NAME:TOKEN",
+		use_delimiter = true,
+		parse(try_from_str = crate::mining::miner_auth::parse_miner_token),
+	)]
+	pub miner_token: Vec<MinerToken>,
+
+	/// Reject the submissions of a miner beyond this many per second, miners being told apart by
+	/// their `--miner-token`, which this requires. 0 doesn't limit them.
+	#[structopt(long, value_name = "REQUESTS")]
+	pub miner_rate_limit: Option<u32>,
+
+	/// Compute the proofs of work of the blocks queued for import on this many threads, ahead of
+	/// their verification. Defaults to one per CPU, 0 computes them one block at a time.
+	#[structopt(long, value_name = "THREADS")]
+	pub seal_verification_threads: Option<usize>,
+
+	/// Import the ancestors of the `powCheckpoint` of the chain spec without their proof of work,
+	/// for a fast initial sync. Their headers are downloaded from the checkpoint down first, the
+	/// blocks not yet proven ancestors and the blocks after it are fully verified.
+	#[structopt(long)]
+	pub pow_fast_sync: bool,
+
+	/// Have the runtime verify the proofs of work of the epochs it holds a light cache
+	/// commitment for, rather than the native code of the node. Much slower: every seal proves
+	/// the runtime the few thousand light cache nodes it reads.
+	#[structopt(long)]
+	pub runtime_seal_verification: bool,
+
+	/// Developer option: require this difficulty for every block instead of the one retargeted
+	/// from the chain history, to produce blocks at a predictable rate on a single node private
+	/// chain. Nodes without the same option reject the blocks of this node.
+	#[structopt(long, value_name = "DIFFICULTY")]
+	pub fixed_difficulty: Option<u64>,
+
+	/// Developer option: seal a block as soon as a transaction enters the pool, and whenever
+	/// `engine_createBlock` is called, instead of mining. The mining RPCs and options are
+	/// disabled, and nodes without the same option reject the blocks of this node.
+	#[structopt(long)]
+	pub dev_instant_seal: bool,
+
+	/// File the mining statistics are dumped to as JSON when the node receives SIGUSR1 or
+	/// `miner_dumpStats` is called. Defaults to `mining-stats.json` in the chain directory.
+	#[structopt(long, value_name = "PATH", parse(from_os_str))]
+	pub mining_stats_file: Option<PathBuf>,
+
+	/// Append every work package handed out and every solution submitted, with its outcome, to
+	/// this file as JSON lines, to settle disputes about rejected shares.
+	#[structopt(long, value_name = "PATH", parse(from_os_str))]
+	pub mining_audit_log: Option<PathBuf>,
+}
+
+impl MiningParams {
+	/// The mining service settings configured by these options.
+	pub fn mining_config(&self) -> Result<MiningConfig, String> {
+		if self.pool_share_difficulty == Some(0) {
+			return Err("--pool-share-difficulty must be greater than zero".into());
+		}
+		if self.stale_share_window > TRACKED_WORK {
+			return Err(format!("--stale-share-window must be at most {}", TRACKED_WORK));
+		}
+		if self.miner_rate_limit.map_or(false, |limit| limit > 0) && self.miner_token.is_empty() {
+			return Err("--miner-rate-limit requires --miner-token, to tell the miners apart".into());
+		}
+		if self.ethash_cache_from_peers == Some(0) {
+			return Err("--ethash-cache-from-peers must be greater than zero".into());
+		}
+		if self.ethash_prepare_dag == Some(0) {
+			return Err("--ethash-prepare-dag must be greater than zero".into());
+		}
+		if self.fixed_difficulty == Some(0) {
+			return Err("--fixed-difficulty must be greater than zero".into());
+		}
+		if self.max_block_proposal_time == 0 || self.work_refresh_interval == 0 {
+			return Err("--max-block-proposal-time and --work-refresh-interval must be greater than zero".into());
+		}
+		if self.work_keep_alive == 0 || self.work_idle_timeout == 0 {
+			return Err("--work-keep-alive and --work-idle-timeout must be greater than zero".into());
+		}
+
+		let extra_data = self.mining_extra_data.clone().unwrap_or_default().into_bytes();
+		if extra_data.len() > MAX_EXTRA_DATA_LEN {
+			return Err(format!("--mining-extra-data must fit in {} bytes", MAX_EXTRA_DATA_LEN));
+		}
+		let mut authors = self.mining_author.clone();
+		if let Some(path) = &self.mining_authors_file {
+			authors.extend(reward_authors::read_authors(path)?);
+		}
+		let author = if authors.is_empty() {
+			None
+		} else {
+			Some(RewardAuthors::new(authors, self.author_rotation)?)
+		};
+
+		Ok(MiningConfig {
+			nonce_partition: NoncePartition::new(self.miner_nonce_start, self.miner_nonce_stride)?,
+			share_difficulty: self.pool_share_difficulty.map(Into::into),
+			stale_share_window: self.stale_share_window,
+			author,
+			extra_data,
+			proposal_time: Duration::from_secs(self.max_block_proposal_time),
+			refresh_interval: Duration::from_secs(self.work_refresh_interval),
+			min_fee: self.min_fee,
+			fee_priority: FeePriority {
+				order_by_fee: self.order_by_fee,
+				weight_target: self.block_weight_target.filter(|weight| *weight > 0),
+			},
+			threads: self.mining_threads,
+			stall_timeout: Some(self.stall_timeout)
+				.filter(|secs| *secs > 0)
+				.map(Duration::from_secs),
+			max_work_age: self.max_work_age.filter(|secs| *secs > 0).map(Duration::from_secs),
+			ethash_cache_dir: self.ethash_cache_dir.clone(),
+			cache_from_peers: self.ethash_cache_from_peers,
+			prepare_dag_threads: self.ethash_prepare_dag,
+			require_signed_shares: self.require_signed_shares,
+			stratum_addr: self.stratum_port.map(|port| {
+				let interface = if self.stratum_external { Ipv4Addr::UNSPECIFIED } else { Ipv4Addr::LOCALHOST };
+				SocketAddr::new(interface.into(), port)
+			}),
+			mining_rpc_addr: self.mining_rpc_port.map(|port| {
+				let interface = if self.mining_rpc_external { Ipv4Addr::UNSPECIFIED } else { Ipv4Addr::LOCALHOST };
+				SocketAddr::new(interface.into(), port)
+			}),
+			work_rpc: WorkRpcConfig {
+				keep_alive: Duration::from_secs(self.work_keep_alive),
+				idle_timeout: Duration::from_secs(self.work_idle_timeout),
+				max_long_polls: self.max_long_polls,
+			},
+			notify_work: self.notify_work.clone(),
+			min_peers: self.min_mining_peers.filter(|peers| *peers > 0),
+			control_secret: self.miner_control_secret.clone().filter(|secret| !secret.is_empty()),
+			miner_tokens: self.miner_token.clone(),
+			miner_rate_limit: self.miner_rate_limit.filter(|limit| *limit > 0),
+			fixed_difficulty: self.fixed_difficulty.map(Into::into),
+			verification_threads: self.seal_verification_threads,
+			fast_sync: self.pow_fast_sync,
+			checkpoint: None,
+			runtime_verification: self.runtime_seal_verification,
+			instant_seal: self.dev_instant_seal,
+			stats_file: self.mining_stats_file.clone(),
+			audit_log: self.mining_audit_log.clone(),
+		})
+	}
+}
+
+fn parse_account(address: &str) -> Result<AccountId, String> {
+	AccountId::from_ss58check(address).map_err(|err| format!("Invalid account {}: {:?}", address, err))
 }
 
 #[derive(Debug, StructOpt)]
@@ -32,4 +407,26 @@ pub enum Subcommand {
 
 	/// Revert the chain to a previous state.
 	Revert(sc_cli::RevertCmd),
+
+	/// Export the chain headers as JSON, optionally with decoded seals and authors.
+	ExportChain(crate::export_chain::ExportChainCmd),
+
+	/// Import an ethash light cache generated by another node, geth or ethminer.
+	ImportEthashCache(crate::import_cache::ImportEthashCacheCmd),
+
+	/// Print the commitment to the ethash light cache of an epoch, for the runtime to verify seals.
+	EpochCache(crate::epoch_cache::EpochCacheCmd),
+
+	/// Simulate block times and difficulty for a given hashrate.
+	SimulateMining(crate::simulate::SimulateMiningCmd),
+
+	/// Benchmark ethash on this machine: cache and dataset generation, seal verification and
+	/// hashing.
+	BenchmarkPow(crate::benchmark_pow::BenchmarkPowCmd),
+
+	/// Mine for a node with the CPU, over its `eth_getWork` RPC.
+	Mine(crate::mine::MineCmd),
+
+	/// Generate the chain spec of a new PoW chain, from flags or interactively.
+	NewChainSpec(crate::new_chain_spec::NewChainSpecCmd),
 }
@@ -1,6 +1,7 @@
 use crate::chain_spec;
 use crate::cli::{Cli, Subcommand};
 use crate::service;
+use log::{info, warn};
 use sc_cli::{ChainSpec, Role, RuntimeVersion, SubstrateCli};
 use sc_service::PartialComponents;
 
@@ -33,9 +34,7 @@ impl SubstrateCli for Cli {
 		Ok(match id {
 			"dev" => Box::new(chain_spec::dev_config()?),
 			"" | "local" => Box::new(chain_spec::local_testnet_config()?),
-			path => Box::new(chain_spec::ChainSpec::from_json_file(
-				std::path::PathBuf::from(path),
-			)?),
+			path => Box::new(chain_spec::custom_config(path)?),
 		})
 	}
 
@@ -61,7 +60,7 @@ pub fn run() -> sc_cli::Result<()> {
 					task_manager,
 					import_queue,
 					..
-				} = service::new_partial(&config)?;
+				} = service::new_partial(&config, None, Default::default())?;
 				Ok((cmd.run(client, import_queue), task_manager))
 			})
 		}
@@ -72,7 +71,7 @@ pub fn run() -> sc_cli::Result<()> {
 					client,
 					task_manager,
 					..
-				} = service::new_partial(&config)?;
+				} = service::new_partial(&config, None, Default::default())?;
 				Ok((cmd.run(client, config.database), task_manager))
 			})
 		}
@@ -83,7 +82,7 @@ pub fn run() -> sc_cli::Result<()> {
 					client,
 					task_manager,
 					..
-				} = service::new_partial(&config)?;
+				} = service::new_partial(&config, None, Default::default())?;
 				Ok((cmd.run(client, config.chain_spec), task_manager))
 			})
 		}
@@ -95,7 +94,7 @@ pub fn run() -> sc_cli::Result<()> {
 					task_manager,
 					import_queue,
 					..
-				} = service::new_partial(&config)?;
+				} = service::new_partial(&config, None, Default::default())?;
 				Ok((cmd.run(client, import_queue), task_manager))
 			})
 		}
@@ -111,16 +110,56 @@ pub fn run() -> sc_cli::Result<()> {
 					task_manager,
 					backend,
 					..
-				} = service::new_partial(&config)?;
+				} = service::new_partial(&config, None, Default::default())?;
 				Ok((cmd.run(client, backend), task_manager))
 			})
 		}
+		Some(Subcommand::ExportChain(cmd)) => {
+			let runner = cli.create_runner(cmd)?;
+			runner.async_run(|config| {
+				let PartialComponents {
+					client,
+					task_manager,
+					..
+				} = service::new_partial(&config, None, Default::default())?;
+				Ok((cmd.run(client), task_manager))
+			})
+		}
+		Some(Subcommand::ImportEthashCache(cmd)) => cmd.run(),
+		Some(Subcommand::EpochCache(cmd)) => cmd.run(),
+		Some(Subcommand::SimulateMining(cmd)) => cmd.run(),
+		Some(Subcommand::BenchmarkPow(cmd)) => cmd.run(),
+		Some(Subcommand::Mine(cmd)) => cmd.run(),
+		Some(Subcommand::NewChainSpec(cmd)) => cmd.run(),
 		None => {
+			let mut mining_config = cli.mining.mining_config()?;
+			let pool_params = cli.pool.clone();
 			let runner = cli.create_runner(&cli.run)?;
-			runner.run_node_until_exit(|config| async move {
+			if mining_config.fast_sync {
+				mining_config.checkpoint = chain_spec::pow_checkpoint(&*runner.config().chain_spec)?;
+				match mining_config.checkpoint {
+					Some(checkpoint) => info!(
+						"Fast sync up to the checkpoint #{} ({})",
+						checkpoint.number, checkpoint.hash,
+					),
+					None => warn!("--pow-fast-sync ignored, the chain spec has no powCheckpoint"),
+				}
+			}
+			runner.run_node_until_exit(|mut config| async move {
+				pool_params.apply(&mut config.transaction_pool);
 				match config.role {
-					Role::Light => service::new_light(config),
-					_ => service::new_full(config),
+					#[cfg(feature = "light")]
+					Role::Light => service::new_light(
+						config,
+						mining_config.ethash_cache_dir.as_deref(),
+						mining_config.ethash_params(),
+					),
+					#[cfg(not(feature = "light"))]
+					Role::Light => Err(sc_service::Error::Other(
+						"light client support is not compiled in, build with the `light` feature".into(),
+					)),
+					_ if mining_config.instant_seal => service::new_dev(config, mining_config),
+					_ => service::new_full(config, mining_config),
 				}
 				.map_err(sc_cli::Error::Service)
 			})
@@ -0,0 +1,30 @@
+//! The `epoch-cache` subcommand: print the commitment to the ethash light cache of an epoch,
+//! which root sets in the runtime with `ethashSeal.setEpochCache` for `--runtime-seal-verification`.
+
+use ethpow::MinimalEthashAlgorithm;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// Print the commitment to the light cache of an epoch as JSON.
+#[derive(Debug, StructOpt)]
+pub struct EpochCacheCmd {
+	/// The epoch.
+	#[structopt(long)]
+	pub epoch: u64,
+
+	/// Directory the ethash caches of the node are kept in, as given to `--ethash-cache-dir`.
+	/// The cache of the epoch is generated there unless present.
+	#[structopt(long, parse(from_os_str))]
+	pub cache_dir: PathBuf,
+}
+
+impl EpochCacheCmd {
+	/// Compute and print the commitment.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		let cache = MinimalEthashAlgorithm::new(&self.cache_dir).epoch_cache(self.epoch);
+		let json = serde_json::to_string_pretty(&cache).map_err(|err| err.to_string())?;
+		println!("{}", json);
+
+		Ok(())
+	}
+}
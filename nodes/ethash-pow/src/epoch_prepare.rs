@@ -0,0 +1,62 @@
+//! Generation of the ethash caches of the next epoch ahead of time.
+//!
+//! The light cache of an epoch is otherwise generated by the first seal verification needing it,
+//! which stalls the import of the first block of every epoch for several seconds. Whenever the
+//! best block enters a new epoch, the cache of the following one is written to the cache
+//! directory in the background, where the verifications then load it from. With
+//! `--ethash-prepare-dag` the full dataset is prepared as well, for a local `mine`.
+
+use ethash::ETHASH_EPOCH_LENGTH;
+use ethpow::EthashAlgorithm;
+use futures::{future, prelude::*};
+use log::{debug, info, warn};
+use sc_client_api::BlockchainEvents;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{traits::{Block as BlockT, Header as HeaderT}, SaturatedConversion};
+use std::sync::Arc;
+
+/// Prepare the caches of the epoch following the one of the best block of `client`, and its
+/// full dataset on `dag_threads` threads if set, as the chain moves on.
+pub async fn run_epoch_preparation<B, C, AC>(
+	client: Arc<C>,
+	algorithm: EthashAlgorithm<AC>,
+	dag_threads: Option<usize>,
+) where
+	B: BlockT,
+	C: BlockchainEvents<B> + HeaderBackend<B>,
+{
+	let mut best_blocks = client
+		.import_notification_stream()
+		.filter(|notification| future::ready(notification.is_new_best))
+		.map(|notification| (*notification.header.number()).saturated_into::<u64>());
+	let mut best_number = client.info().best_number.saturated_into::<u64>();
+	let mut prepared = None;
+
+	loop {
+		let next_epoch = best_number / ETHASH_EPOCH_LENGTH + 1;
+		if prepared != Some(next_epoch) {
+			prepare_epoch(&algorithm, next_epoch, dag_threads);
+			prepared = Some(next_epoch);
+		}
+		match best_blocks.next().await {
+			Some(number) => best_number = number,
+			None => return,
+		}
+	}
+}
+
+fn prepare_epoch<C>(algorithm: &EthashAlgorithm<C>, epoch: u64, dag_threads: Option<usize>) {
+	match algorithm.prepare_cache(epoch) {
+		Ok(true) => info!(target: "pow", "Generated the ethash light cache of epoch {} ahead of time", epoch),
+		Ok(false) => debug!(target: "pow", "Ethash light cache of epoch {} already there", epoch),
+		Err(err) => warn!(target: "pow", "Failed to prepare the ethash light cache of epoch {}: {}", epoch, err),
+	}
+
+	if let Some(threads) = dag_threads {
+		match algorithm.prepare_dataset(epoch, threads) {
+			Ok(true) => info!(target: "pow", "Generated the ethash dataset of epoch {} ahead of time", epoch),
+			Ok(false) => debug!(target: "pow", "Ethash dataset of epoch {} already there", epoch),
+			Err(err) => warn!(target: "pow", "Failed to prepare the ethash dataset of epoch {}: {}", epoch, err),
+		}
+	}
+}
@@ -0,0 +1,123 @@
+//! The `export-chain` subcommand: dump the headers of the chain as JSON, optionally with their
+//! decoded ethash seal and author, so explorers can backfill without decoding seals themselves.
+
+use crate::types::{seal_and_author, SealInfo};
+use runtime::{opaque::{Block, Header}, AccountId, BlockNumber};
+use sc_cli::{CliConfiguration, DatabaseParams, PruningParams, SharedParams};
+use serde_derive::Serialize;
+use sp_blockchain::HeaderBackend;
+use sp_core::H256;
+use sp_runtime::{generic::BlockId, traits::Header as HeaderT};
+use std::{fs::File, io::{self, BufWriter, Write}, path::PathBuf, sync::Arc};
+use structopt::StructOpt;
+
+/// Export the chain headers as JSON or newline delimited JSON.
+#[derive(Debug, StructOpt)]
+pub struct ExportChainCmd {
+	/// Output file name, stdout when omitted.
+	#[structopt(parse(from_os_str))]
+	pub output: Option<PathBuf>,
+
+	/// First block to export.
+	#[structopt(long, default_value = "0")]
+	pub from: BlockNumber,
+
+	/// Last block to export, the best block when omitted.
+	#[structopt(long)]
+	pub to: Option<BlockNumber>,
+
+	/// Include the decoded seal (nonce, mix digest, difficulty, boundary, timestamp) and the
+	/// author of every block.
+	#[structopt(long)]
+	pub with_seals: bool,
+
+	/// One JSON array, or one JSON object per line.
+	#[structopt(long, default_value = "ndjson", possible_values = &["json", "ndjson"])]
+	pub format: String,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub database_params: DatabaseParams,
+}
+
+/// An exported block.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BlockEntry {
+	hash: H256,
+	#[serde(flatten)]
+	header: Header,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	seal: Option<SealInfo>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	author: Option<AccountId>,
+}
+
+impl ExportChainCmd {
+	/// Run the export.
+	pub async fn run<C>(&self, client: Arc<C>) -> sc_cli::Result<()>
+	where
+		C: HeaderBackend<Block>,
+	{
+		let to = self.to.unwrap_or_else(|| client.info().best_number);
+		let ndjson = self.format == "ndjson";
+
+		let output: Box<dyn Write> = match &self.output {
+			Some(path) => Box::new(File::create(path)?),
+			None => Box::new(io::stdout()),
+		};
+		let mut output = BufWriter::new(output);
+
+		if !ndjson {
+			writeln!(output, "[")?;
+		}
+		for number in self.from..=to {
+			let header = match client.header(BlockId::Number(number))? {
+				Some(header) => header,
+				None => break,
+			};
+			let (seal, author) = if self.with_seals {
+				seal_and_author(&header)
+			} else {
+				(None, None)
+			};
+			let entry = BlockEntry { hash: header.hash(), header, seal, author };
+
+			if !ndjson && number > self.from {
+				writeln!(output, ",")?;
+			}
+			serde_json::to_writer(&mut output, &entry).map_err(io::Error::from)?;
+			if ndjson {
+				writeln!(output)?;
+			}
+		}
+		if !ndjson {
+			writeln!(output, "\n]")?;
+		}
+		output.flush()?;
+
+		Ok(())
+	}
+}
+
+impl CliConfiguration for ExportChainCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+}
@@ -0,0 +1,242 @@
+//! Selection of the transactions of the mined blocks for their fees, like geth miners order
+//! transactions by gas price up to a gas target.
+//!
+//! `sc_basic_authorship` includes the ready transactions in the order of the pool. Seen through
+//! `FeePriorityPool`, the transactions paying the most fee, tip included, per weight come first,
+//! and with a weight target the transactions stop being included once the block weighs that
+//! much, below the limit of the runtime. Like the minimum fee, it is a policy of the node: the
+//! blocks of other miners stay valid whatever their order.
+//!
+//! A transaction never comes before those it depends on, such as the earlier nonces of its
+//! sender: it becomes eligible once the transactions providing its tags are included, and is
+//! left out with them, or with those paying less than the minimum fee.
+//!
+//! The selection applies to `ready_at` and to `ready`, which the proposer falls back to when the
+//! pool is late, the latter pricing the transactions at the best block.
+
+use crate::min_fee::{transaction_fee, MinFee, FEE_WEIGHT_UNIT};
+use futures::FutureExt;
+use pallet_transaction_payment_rpc_runtime_api::TransactionPaymentApi;
+use runtime::{opaque::Block, Balance};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::NumberFor, OpaqueExtrinsic};
+use sp_transaction_pool::{
+	ImportNotificationStream, InPoolTransaction, PoolFuture, PoolStatus, TransactionFor,
+	TransactionPool, TransactionSource, TransactionStatusStreamFor, TxHash,
+};
+use std::{
+	cmp::Reverse,
+	collections::{BinaryHeap, HashMap},
+	future::Future,
+	pin::Pin,
+	sync::Arc,
+};
+
+/// How the transactions of the mined blocks are selected.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FeePriority {
+	/// Include the transactions paying the most per weight first.
+	pub order_by_fee: bool,
+	/// Stop including transactions once the block weighs this much.
+	pub weight_target: Option<u64>,
+}
+
+impl FeePriority {
+	/// Whether the transactions are left in the order of the pool.
+	fn is_pool_order(&self) -> bool {
+		!self.order_by_fee && self.weight_target.is_none()
+	}
+}
+
+/// The transaction pool `P` as seen by the block proposer: the ready transactions paying less
+/// than `MinFee` are skipped and the others selected following `FeePriority`. Everything else is
+/// left to `P`.
+pub struct FeePriorityPool<C, P> {
+	client: Arc<C>,
+	pool: Arc<P>,
+	min_fee: MinFee,
+	priority: FeePriority,
+}
+
+impl<C, P> FeePriorityPool<C, P> {
+	/// Select the ready transactions of `pool` paying at least `min_fee` following `priority`,
+	/// using the fees and weights computed by the runtime of `client`.
+	pub fn new(client: Arc<C>, pool: Arc<P>, min_fee: MinFee, priority: FeePriority) -> Self {
+		Self { client, pool, min_fee, priority }
+	}
+}
+
+/// A ready transaction with what it pays and weighs.
+struct Candidate<T> {
+	tx: Arc<T>,
+	/// Fee per `FEE_WEIGHT_UNIT` of weight, `None` for the unsigned transactions, which pay no
+	/// fee, and for those the fee of can't be computed: the runtime rejects them if invalid.
+	fee_rate: Option<u128>,
+	weight: u64,
+}
+
+/// `ready` priced at block `at` and selected following `min_fee` and `priority`. Every ready
+/// transaction is priced once, before the first is included, so `ready` is left to its lazy
+/// iteration when neither applies.
+fn select_ready<C, T>(
+	client: &C,
+	at: &BlockId<Block>,
+	ready: Box<dyn Iterator<Item = Arc<T>> + Send>,
+	min_fee: u64,
+	priority: FeePriority,
+) -> Box<dyn Iterator<Item = Arc<T>> + Send>
+where
+	C: ProvideRuntimeApi<Block>,
+	C::Api: TransactionPaymentApi<Block, Balance>,
+	T: InPoolTransaction<Transaction = OpaqueExtrinsic> + Send + Sync + 'static,
+{
+	if min_fee == 0 && priority.is_pool_order() {
+		return ready;
+	}
+	let candidates = ready
+		.map(|tx| match transaction_fee(client, at, tx.data()) {
+			Some((fee, weight)) => Candidate {
+				tx,
+				fee_rate: Some(fee.saturating_mul(FEE_WEIGHT_UNIT) / u128::from(weight.max(1))),
+				weight,
+			},
+			None => Candidate { tx, fee_rate: None, weight: 0 },
+		})
+		.collect();
+	Box::new(select(candidates, min_fee, priority).into_iter())
+}
+
+/// The transactions of `candidates`, given in the order of the pool, paying at least `min_fee`
+/// per `FEE_WEIGHT_UNIT`, selected and ordered following `priority`.
+fn select<T: InPoolTransaction>(candidates: Vec<Candidate<T>>, min_fee: u64, priority: FeePriority) -> Vec<Arc<T>> {
+	// The tags required from another ready transaction, rather than from the chain.
+	let mut providers = HashMap::new();
+	for (index, candidate) in candidates.iter().enumerate() {
+		for tag in candidate.tx.provides() {
+			providers.insert(tag.clone(), index);
+		}
+	}
+	let mut missing = vec![0usize; candidates.len()];
+	let mut dependents = vec![Vec::new(); candidates.len()];
+	for (index, candidate) in candidates.iter().enumerate() {
+		for tag in candidate.tx.requires() {
+			if let Some(&provider) = providers.get(tag).filter(|provider| **provider != index) {
+				missing[index] += 1;
+				dependents[provider].push(index);
+			}
+		}
+	}
+
+	// Ties go to the transaction first in the pool.
+	let rank = |index: usize| {
+		let fee_rate = if priority.order_by_fee { candidates[index].fee_rate.unwrap_or(0) } else { 0 };
+		(fee_rate, Reverse(index))
+	};
+	// Those paying less than the minimum never become eligible, and neither do their dependents.
+	let pays_min_fee = |index: &usize| candidates[*index].fee_rate.map_or(true, |fee_rate| fee_rate >= u128::from(min_fee));
+	let mut eligible: BinaryHeap<_> =
+		(0..candidates.len()).filter(|index| missing[*index] == 0).filter(pays_min_fee).map(rank).collect();
+	let mut selected = Vec::with_capacity(candidates.len());
+	let mut weight = 0u64;
+	while let Some((_, Reverse(index))) = eligible.pop() {
+		let candidate = &candidates[index];
+		if let Some(target) = priority.weight_target {
+			// Lighter transactions may still fit, those depending on this one never become
+			// eligible.
+			if weight.saturating_add(candidate.weight) > target {
+				continue;
+			}
+		}
+		weight = weight.saturating_add(candidate.weight);
+		selected.push(candidate.tx.clone());
+		for &dependent in &dependents[index] {
+			missing[dependent] -= 1;
+			if missing[dependent] == 0 && pays_min_fee(&dependent) {
+				eligible.push(rank(dependent));
+			}
+		}
+	}
+	selected
+}
+
+impl<C, P> TransactionPool for FeePriorityPool<C, P>
+where
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: TransactionPaymentApi<Block, Balance>,
+	P: TransactionPool<Block = Block> + 'static,
+	P::InPoolTransaction: Send + Sync,
+{
+	type Block = Block;
+	type Hash = P::Hash;
+	type InPoolTransaction = P::InPoolTransaction;
+	type Error = P::Error;
+
+	fn submit_at(
+		&self,
+		at: &BlockId<Block>,
+		source: TransactionSource,
+		xts: Vec<TransactionFor<Self>>,
+	) -> PoolFuture<Vec<Result<TxHash<Self>, Self::Error>>, Self::Error> {
+		self.pool.submit_at(at, source, xts)
+	}
+
+	fn submit_one(
+		&self,
+		at: &BlockId<Block>,
+		source: TransactionSource,
+		xt: TransactionFor<Self>,
+	) -> PoolFuture<TxHash<Self>, Self::Error> {
+		self.pool.submit_one(at, source, xt)
+	}
+
+	fn submit_and_watch(
+		&self,
+		at: &BlockId<Block>,
+		source: TransactionSource,
+		xt: TransactionFor<Self>,
+	) -> PoolFuture<Box<TransactionStatusStreamFor<Self>>, Self::Error> {
+		self.pool.submit_and_watch(at, source, xt)
+	}
+
+	fn ready_at(
+		&self,
+		at: NumberFor<Block>,
+	) -> Pin<Box<dyn Future<Output = Box<dyn Iterator<Item = Arc<Self::InPoolTransaction>> + Send>> + Send>> {
+		let client = self.client.clone();
+		let min_fee = self.min_fee.get();
+		let priority = self.priority;
+		Box::pin(self.pool.ready_at(at).map(move |ready| {
+			select_ready(&*client, &BlockId::Number(at), ready, min_fee, priority)
+		}))
+	}
+
+	fn ready(&self) -> Box<dyn Iterator<Item = Arc<Self::InPoolTransaction>> + Send> {
+		let at = BlockId::Hash(self.client.info().best_hash);
+		select_ready(&*self.client, &at, self.pool.ready(), self.min_fee.get(), self.priority)
+	}
+
+	fn remove_invalid(&self, hashes: &[TxHash<Self>]) -> Vec<Arc<Self::InPoolTransaction>> {
+		self.pool.remove_invalid(hashes)
+	}
+
+	fn status(&self) -> PoolStatus {
+		self.pool.status()
+	}
+
+	fn import_notification_stream(&self) -> ImportNotificationStream<TxHash<Self>> {
+		self.pool.import_notification_stream()
+	}
+
+	fn on_broadcasted(&self, propagations: HashMap<TxHash<Self>, Vec<String>>) {
+		self.pool.on_broadcasted(propagations)
+	}
+
+	fn hash_of(&self, xt: &TransactionFor<Self>) -> TxHash<Self> {
+		self.pool.hash_of(xt)
+	}
+
+	fn ready_transaction(&self, hash: &TxHash<Self>) -> Option<Arc<Self::InPoolTransaction>> {
+		self.pool.ready_transaction(hash)
+	}
+}
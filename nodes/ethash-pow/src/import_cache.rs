@@ -0,0 +1,45 @@
+//! The `import-ethash-cache` subcommand: copy an ethash light cache generated elsewhere, by
+//! another node, geth or ethminer, into the cache directory of this node.
+
+use ethash::{import_cache, CacheFileFormat};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// Import an ethash light cache file, checked against the seed hash of its epoch.
+#[derive(Debug, StructOpt)]
+pub struct ImportEthashCacheCmd {
+	/// The cache file to import.
+	#[structopt(parse(from_os_str))]
+	pub input: PathBuf,
+
+	/// Epoch the cache belongs to.
+	#[structopt(long)]
+	pub epoch: u64,
+
+	/// Directory the ethash caches of the node are kept in, as given to `--ethash-cache-dir`.
+	#[structopt(long, parse(from_os_str))]
+	pub cache_dir: PathBuf,
+
+	/// Layout of the input file. Detected from its content when omitted.
+	#[structopt(long, possible_values = &["parity", "geth"], case_insensitive = true)]
+	pub format: Option<String>,
+
+	/// Regenerate the cache from the seed hash and compare it with the input.
+	#[structopt(long)]
+	pub verify: bool,
+}
+
+impl ImportEthashCacheCmd {
+	/// Run the import.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		let format = self.format.as_ref().map(|format| match format.to_lowercase().as_str() {
+			"geth" => CacheFileFormat::Geth,
+			_ => CacheFileFormat::Parity,
+		});
+
+		let path = import_cache(&self.cache_dir, self.epoch, &self.input, format, self.verify)?;
+		println!("Imported the cache of epoch {} to {}", self.epoch, path.display());
+
+		Ok(())
+	}
+}
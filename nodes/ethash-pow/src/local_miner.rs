@@ -0,0 +1,289 @@
+//! The CPU miner of the node itself, sized at runtime with `pow_setThreads` and
+//! `pow_startMining`.
+//!
+//! The threads hash the current work package of the mining service as the `mine` subcommand
+//! does over RPC, and hand their solutions to the service like any other miner, credited to
+//! `LOCAL_MINER`. With no thread, the default, the node leaves the hashing to external miners:
+//! the local miner starts once it is given threads, and stops once they are all taken away or the
+//! node stops. The dataset of a new epoch is generated on a thread of its own, so the solutions
+//! found meanwhile are still submitted and the exit of the node isn't held up.
+
+use crate::{
+	mine::{run_worker, Job, Solution},
+	mining::{shutdown::ExitSignal, NoncePartition, WorkSnapshot},
+	rpc::EtheminerCmd,
+	types::SubmittedWork,
+};
+use ethash::{EthashManager, Full, ETHASH_EPOCH_LENGTH};
+use ethereum_types::H64;
+use futures::{
+	channel::{mpsc, oneshot},
+	executor::block_on,
+	FutureExt, SinkExt,
+};
+use log::{debug, info, warn};
+use parking_lot::RwLock;
+use sc_service::SpawnTaskHandle;
+use sp_core::{H256, U256};
+use std::{
+	path::PathBuf,
+	sync::{
+		atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+		mpsc as std_mpsc, Arc,
+	},
+	thread,
+	time::{Duration, Instant},
+};
+
+/// The worker name the solutions of the local threads are credited to.
+pub const LOCAL_MINER: &str = "local";
+
+/// How often the work package and the thread count are checked.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How often the hashrate of the threads is reported to the mining service.
+const HASHRATE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Number of hashing threads of the node, shared by the mining service and the local miner.
+#[derive(Debug, Clone, Default)]
+pub struct MinerThreads(Arc<AtomicUsize>);
+
+impl MinerThreads {
+	/// Hash on `threads` threads, none for no local mining.
+	pub fn new(threads: usize) -> Self {
+		Self(Arc::new(AtomicUsize::new(threads)))
+	}
+
+	/// The number of hashing threads.
+	pub fn get(&self) -> usize {
+		self.0.load(Ordering::Relaxed)
+	}
+
+	/// Hash on `threads` threads from the next poll on.
+	pub fn set(&self, threads: usize) {
+		self.0.store(threads, Ordering::Relaxed)
+	}
+}
+
+/// The local miner of the node, started once it is given threads.
+#[derive(Clone)]
+pub struct LocalMiner {
+	threads: MinerThreads,
+	/// Whether the local miner runs.
+	running: Arc<AtomicBool>,
+	spawn_handle: SpawnTaskHandle,
+	work_snapshot: WorkSnapshot,
+	command_sink: mpsc::Sender<EtheminerCmd>,
+	cache_dir: PathBuf,
+	partition: NoncePartition,
+	exit: ExitSignal,
+}
+
+impl LocalMiner {
+	/// A local miner hashing the work packages of `work_snapshot` on `threads` threads, searching
+	/// the nonces of `partition` and submitting the solutions through `command_sink`, until
+	/// `exit`. The datasets are generated, or loaded when prepared with `--ethash-prepare-dag`,
+	/// in `cache_dir`.
+	pub fn new(
+		threads: MinerThreads,
+		spawn_handle: SpawnTaskHandle,
+		work_snapshot: WorkSnapshot,
+		command_sink: mpsc::Sender<EtheminerCmd>,
+		cache_dir: PathBuf,
+		partition: NoncePartition,
+		exit: ExitSignal,
+	) -> Self {
+		Self {
+			threads,
+			running: Arc::new(AtomicBool::new(false)),
+			spawn_handle,
+			work_snapshot,
+			command_sink,
+			cache_dir,
+			partition,
+			exit,
+		}
+	}
+
+	/// The number of hashing threads.
+	pub fn threads(&self) -> usize {
+		self.threads.get()
+	}
+
+	/// Hash on `threads` threads, starting the local miner if it isn't running.
+	pub fn set_threads(&self, threads: usize) {
+		self.threads.set(threads);
+		self.start();
+	}
+
+	/// Start the local miner, unless it has no thread or runs already.
+	pub fn start(&self) {
+		if self.threads.get() > 0 && !self.running.swap(true, Ordering::SeqCst) {
+			let miner = self.clone();
+			self.spawn_handle.spawn_blocking("local-miner", async move { miner.run() });
+		}
+	}
+
+	/// Hash until the threads are all taken away, the node stops or the mining service is gone.
+	fn run(self) {
+		let ethash = Arc::new(EthashManager::new(&self.cache_dir, None, u64::max_value()));
+		// Identifies the local threads in the hashrate reports.
+		let miner_id = H256(rand::random());
+		let job: Arc<RwLock<Option<Job>>> = Arc::new(RwLock::new(None));
+		let hashes = Arc::new(AtomicU64::new(0));
+		let (solution_sink, solutions) = std_mpsc::channel();
+		// The stop flag of every running thread.
+		let mut workers: Vec<Arc<AtomicBool>> = Vec::new();
+		let mut dataset: Option<Arc<Full>> = None;
+		// The epoch whose dataset is being generated, and where it is handed over.
+		let mut generating: Option<(u64, std_mpsc::Receiver<Full>)> = None;
+		let mut command_sink = self.command_sink.clone();
+		let mut last_report = Instant::now();
+
+		'mining: loop {
+			if self.exit.clone().now_or_never().is_some() {
+				break;
+			}
+			let wanted = self.threads.get();
+			if wanted == 0 {
+				self.running.store(false, Ordering::SeqCst);
+				// Given threads again meanwhile, `start` may have seen this miner still running.
+				if self.threads.get() == 0 || self.running.swap(true, Ordering::SeqCst) {
+					break;
+				}
+				continue;
+			}
+			if wanted != workers.len() {
+				info!(target: "pow", "Mining on {} local threads", wanted);
+			}
+			while workers.len() < wanted {
+				let stop = Arc::new(AtomicBool::new(false));
+				let (job, solution_sink, hashes) = (job.clone(), solution_sink.clone(), hashes.clone());
+				let (worker_stop, partition) = (stop.clone(), self.partition);
+				let spawned = thread::Builder::new()
+					.name(format!("local-miner-{}", workers.len()))
+					.spawn(move || run_worker(job, solution_sink, hashes, worker_stop, partition));
+				if let Err(err) = spawned {
+					warn!(target: "pow", "Failed to start a local mining thread: {}", err);
+					break;
+				}
+				workers.push(stop);
+			}
+			while workers.len() > wanted {
+				if let Some(stop) = workers.pop() {
+					stop.store(true, Ordering::Relaxed);
+				}
+			}
+
+			if let Some((epoch, generated)) = &generating {
+				match generated.try_recv() {
+					Ok(full) => {
+						dataset = Some(Arc::new(full));
+						generating = None;
+					}
+					Err(std_mpsc::TryRecvError::Empty) => {}
+					Err(std_mpsc::TryRecvError::Disconnected) => {
+						warn!(target: "pow", "Failed to generate the ethash dataset of epoch {}", epoch);
+						generating = None;
+					}
+				}
+			}
+
+			let current = self.work_snapshot.load();
+			match current.as_ref().and_then(|current| current.work.number.map(|number| (current, number))) {
+				Some((current, number)) => {
+					let epoch = number / ETHASH_EPOCH_LENGTH;
+					let epoch_dataset = dataset
+						.clone()
+						.filter(|dataset| dataset.block_number() / ETHASH_EPOCH_LENGTH == epoch);
+					match epoch_dataset {
+						Some(epoch_dataset) => {
+							let pow_hash = current.work.pow_hash;
+							if job.read().as_ref().map(|job| job.pow_hash) != Some(pow_hash) {
+								debug!(target: "pow", "Mining block #{} locally: {}", number, pow_hash);
+								// Only full solutions are worth submitting, the shares of the node
+								// itself are of no use.
+								let boundary = current.work.target.0;
+								*job.write() = Some(Job { pow_hash, number, boundary, dataset: epoch_dataset });
+							}
+						}
+						// Generated on a thread of its own, the solutions, the thread count and
+						// the exit are still handled meanwhile.
+						None if generating.as_ref().map(|(generated, _)| *generated) != Some(epoch) => {
+							info!(target: "pow", "Generating the ethash dataset of epoch {} for local mining", epoch);
+							// Drop the job, and the old dataset with it, to make room.
+							job.write().take();
+							dataset.take();
+							let (sink, generated) = std_mpsc::channel();
+							let (ethash, threads) = (ethash.clone(), workers.len());
+							let spawned = thread::Builder::new()
+								.name("local-miner-dataset".into())
+								.spawn(move || {
+									let _ = sink.send(ethash.full(number, threads));
+								});
+							match spawned {
+								Ok(_) => generating = Some((epoch, generated)),
+								Err(err) => warn!(target: "pow", "Failed to generate the ethash dataset: {}", err),
+							}
+						}
+						None => {}
+					}
+				}
+				None => {
+					job.write().take();
+				}
+			}
+
+			// Submit the solutions found until the next poll.
+			let deadline = Instant::now() + POLL_INTERVAL;
+			while let Ok(solution) = solutions.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+				if !submit(&mut command_sink, solution) {
+					break 'mining;
+				}
+			}
+
+			let elapsed = last_report.elapsed();
+			if elapsed >= HASHRATE_INTERVAL {
+				let rate = hashes.swap(0, Ordering::Relaxed) / elapsed.as_secs().max(1);
+				let (sender, receiver) = oneshot::channel();
+				let command = EtheminerCmd::SubmitHashrate { rate: U256::from(rate), id: miner_id, sender: Some(sender) };
+				if block_on(command_sink.send(command)).is_err() || block_on(receiver).is_err() {
+					break;
+				}
+				last_report = Instant::now();
+			}
+		}
+
+		for stop in workers {
+			stop.store(true, Ordering::Relaxed);
+		}
+		info!(target: "pow", "Local mining stopped");
+	}
+}
+
+/// Hand `solution` to the mining service and wait for its verdict. Returns `false` once the
+/// service is gone.
+fn submit(command_sink: &mut mpsc::Sender<EtheminerCmd>, solution: Solution) -> bool {
+	let (sender, receiver) = oneshot::channel();
+	let command = EtheminerCmd::SubmitWork {
+		nonce: H64::from_low_u64_be(solution.nonce),
+		pow_hash: solution.pow_hash,
+		mix_digest: solution.mix_digest,
+		signature: None,
+		miner: Some(LOCAL_MINER.to_owned()),
+		submission_id: None,
+		sender: Some(sender),
+	};
+	if block_on(command_sink.send(command)).is_err() {
+		return false;
+	}
+	match block_on(receiver) {
+		Ok(Ok(SubmittedWork { block_hash: Some(hash) })) => {
+			info!(target: "pow", "Local solution for {} sealed block {}", solution.pow_hash, hash)
+		}
+		Ok(Ok(_)) => info!(target: "pow", "Local solution for {} accepted", solution.pow_hash),
+		Ok(Err(err)) => debug!(target: "pow", "Local solution for {} rejected: {}", solution.pow_hash, err),
+		Err(_) => return false,
+	}
+	true
+}
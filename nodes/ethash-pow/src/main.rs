@@ -1,13 +1,34 @@
 //! Basic POW Node Template CLI library.
 #![warn(missing_docs)]
 
+mod announce;
+mod benchmark_pow;
+mod cache_check;
+mod cache_sync;
 mod chain_spec;
+mod checkpoint_sync;
+mod epoch_cache;
+mod epoch_prepare;
 #[macro_use]
 mod service;
 mod cli;
 mod command;
+mod export_chain;
+mod fee_priority;
+mod import_cache;
+mod local_miner;
+mod mine;
+mod min_fee;
+mod mining;
+mod new_chain_spec;
+mod notify_work;
+mod proposer;
 mod rpc;
+mod seal_prefetch;
+mod simulate;
+mod stratum;
 mod types;
+mod watchdog;
 
 fn main() -> sc_cli::Result<()> {
 	command::run()
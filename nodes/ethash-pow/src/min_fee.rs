@@ -0,0 +1,63 @@
+//! Minimum fee of the transactions included by this node, like the gas price of geth miners.
+//!
+//! The minimum is a policy of the node, not a consensus rule: blocks of other miners including
+//! cheaper transactions stay valid. Transactions paying less are left in the pool rather than
+//! removed, for other miners to include or until the minimum is lowered with `miner_setMinFee`.
+//!
+//! The fee of a transaction is its inclusion fee, as computed by the runtime, plus its tip. It is
+//! compared to the minimum per `FEE_WEIGHT_UNIT` of the weight of the transaction. The ready
+//! transactions are filtered by `FeePriorityPool`, along with their selection.
+
+use log::warn;
+use pallet_transaction_payment_rpc_runtime_api::TransactionPaymentApi;
+use parity_scale_codec::{Compact, Decode, Encode};
+use runtime::{opaque::Block, Balance};
+use sp_api::ProvideRuntimeApi;
+use sp_runtime::{generic::BlockId, OpaqueExtrinsic};
+use std::sync::{atomic::{AtomicU64, Ordering}, Arc};
+
+/// Weight the minimum fee is counted for: a microsecond of execution.
+pub const FEE_WEIGHT_UNIT: u128 = 1_000_000;
+
+/// Shared handle on the minimum fee per `FEE_WEIGHT_UNIT`, 0 to include every transaction.
+#[derive(Clone, Debug, Default)]
+pub struct MinFee(Arc<AtomicU64>);
+
+impl MinFee {
+	/// Require `fee` per `FEE_WEIGHT_UNIT`.
+	pub fn new(fee: u64) -> Self {
+		Self(Arc::new(AtomicU64::new(fee)))
+	}
+
+	/// The minimum fee per `FEE_WEIGHT_UNIT`.
+	pub fn get(&self) -> u64 {
+		self.0.load(Ordering::Relaxed)
+	}
+
+	/// Require `fee` per `FEE_WEIGHT_UNIT` from the transactions of the next blocks built.
+	pub fn set(&self, fee: u64) {
+		self.0.store(fee, Ordering::Relaxed)
+	}
+}
+
+/// The fee `xt` pays at block `at`, its inclusion fee plus its tip, and its weight. `None` for
+/// unsigned transactions, which pay no fee, and for those the fee of can't be computed.
+pub(crate) fn transaction_fee<C>(client: &C, at: &BlockId<Block>, xt: &OpaqueExtrinsic) -> Option<(Balance, u64)>
+where
+	C: ProvideRuntimeApi<Block>,
+	C::Api: TransactionPaymentApi<Block, Balance>,
+{
+	let encoded = xt.encode();
+	// The tip of `ChargeTransactionPayment` is its only, compact, field.
+	let tip = match runtime::UncheckedExtrinsic::decode(&mut &encoded[..]).ok()?.signature {
+		Some((_, _, extra)) => Compact::<Balance>::decode(&mut &extra.6.encode()[..]).map_or(0, |tip| tip.0),
+		None => return None,
+	};
+	match client.runtime_api().query_info(at, xt.clone(), encoded.len() as u32) {
+		Ok(info) => Some((info.partial_fee.saturating_add(tip), info.weight)),
+		Err(err) => {
+			warn!(target: "pow", "Failed to compute the fee of a transaction: {:?}", err);
+			None
+		}
+	}
+}
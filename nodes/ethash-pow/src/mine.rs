@@ -0,0 +1,292 @@
+//! The `mine` subcommand: a standalone CPU miner for a node serving `eth_getWork`.
+//!
+//! The miner polls the work package of the node over HTTP JSON-RPC, hashes it against the full
+//! ethash dataset of its epoch on several threads and submits the solutions with
+//! `eth_submitWork`. It is no match for a GPU miner, but enough to run the recipe end to end on a
+//! dev chain without installing one.
+
+use ethash::{EthashManager, Full, ETHASH_EPOCH_LENGTH};
+use hyper::Uri;
+use parking_lot::RwLock;
+use rand::Rng;
+use serde_json::{json, Value};
+use sp_core::{H256, U256};
+use std::{
+	io::{Read, Write},
+	net::TcpStream,
+	path::PathBuf,
+	sync::{atomic::{AtomicBool, AtomicU64, Ordering}, mpsc, Arc},
+	thread,
+	time::{Duration, Instant},
+};
+use structopt::StructOpt;
+use crate::{mining::NoncePartition, types::Work};
+
+/// Nonces a thread hashes between two checks for new work.
+const NONCES_PER_ROUND: u64 = 1_000;
+
+/// How often the hashrate is printed and reported to the node.
+const HASHRATE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Timeout of the RPC calls to the node.
+const RPC_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Mine for a node with the CPU, over its `eth_getWork` and `eth_submitWork` RPCs.
+#[derive(Debug, StructOpt)]
+pub struct MineCmd {
+	/// HTTP JSON-RPC endpoint of the node to mine for.
+	#[structopt(
+		long,
+		value_name = "URL",
+		default_value = "http://127.0.0.1:9933",
+		parse(try_from_str = crate::notify_work::parse_url),
+	)]
+	pub rpc_url: Uri,
+
+	/// Number of hashing threads, also used to generate the dataset.
+	#[structopt(long, default_value = "1")]
+	pub threads: usize,
+
+	/// Milliseconds between two polls of `eth_getWork`.
+	#[structopt(long, default_value = "500")]
+	pub poll_interval: u64,
+
+	/// Directory the ethash light caches the datasets are generated from are kept in. Defaults to
+	/// `ethash` in the temporary directory. Datasets a node prepared there with
+	/// `--ethash-prepare-dag` are loaded instead of generated.
+	#[structopt(long, parse(from_os_str))]
+	pub ethash_cache_dir: Option<PathBuf>,
+
+	/// Token the solutions and hashrates are submitted with, for a node run with
+	/// `--miner-token`.
+	#[structopt(long, value_name = "TOKEN")]
+	pub token: Option<String>,
+
+	/// First nonce searched, the `--miner-nonce-start` of the node, which rejects the solutions
+	/// of nonces outside its partition.
+	#[structopt(long, default_value = "0")]
+	pub miner_nonce_start: u64,
+
+	/// Distance between two nonces searched, the `--miner-nonce-stride` of the node.
+	#[structopt(long, default_value = "1")]
+	pub miner_nonce_stride: u64,
+}
+
+/// A work package being mined.
+#[derive(Clone)]
+pub(crate) struct Job {
+	pub(crate) pow_hash: H256,
+	pub(crate) number: u64,
+	/// Solutions must hash at or below this boundary.
+	pub(crate) boundary: [u8; 32],
+	pub(crate) dataset: Arc<Full>,
+}
+
+/// A nonce meeting the boundary of a job.
+pub(crate) struct Solution {
+	pub(crate) pow_hash: H256,
+	pub(crate) nonce: u64,
+	pub(crate) mix_digest: H256,
+}
+
+impl MineCmd {
+	/// Mine until the process is killed.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		if self.threads == 0 || self.poll_interval == 0 {
+			return Err("--threads and --poll-interval must be greater than zero".into());
+		}
+		let partition = NoncePartition::new(self.miner_nonce_start, self.miner_nonce_stride)?;
+
+		let cache_dir = self
+			.ethash_cache_dir
+			.clone()
+			.unwrap_or_else(|| std::env::temp_dir().join("ethash"));
+		std::fs::create_dir_all(&cache_dir)?;
+		let ethash = EthashManager::new(&cache_dir, None, u64::max_value());
+		let rpc = RpcClient::new(&self.rpc_url, self.token.clone());
+		// Identifies this miner in the hashrate reports.
+		let miner_id = H256(rand::random());
+
+		let job = Arc::new(RwLock::new(None));
+		let hashes = Arc::new(AtomicU64::new(0));
+		let (solution_sink, solutions) = mpsc::channel();
+		// The threads hash until the process is killed.
+		let stop = Arc::new(AtomicBool::new(false));
+		for _ in 0..self.threads {
+			let job = job.clone();
+			let hashes = hashes.clone();
+			let solution_sink = solution_sink.clone();
+			let stop = stop.clone();
+			thread::spawn(move || run_worker(job, solution_sink, hashes, stop, partition));
+		}
+
+		let poll_interval = Duration::from_millis(self.poll_interval);
+		let mut dataset: Option<Arc<Full>> = None;
+		let mut last_report = Instant::now();
+		loop {
+			match rpc.work() {
+				Ok((pow_hash, number, boundary)) => {
+					let current = job.read().as_ref().map(|job: &Job| job.pow_hash);
+					if current != Some(pow_hash) {
+						let epoch = number / ETHASH_EPOCH_LENGTH;
+						let reusable = dataset
+							.clone()
+							.filter(|dataset| dataset.block_number() / ETHASH_EPOCH_LENGTH == epoch);
+						let epoch_dataset = match reusable {
+							Some(dataset) => dataset,
+							None => {
+								println!("Generating the dataset of epoch {}, this takes a while", epoch);
+								// Drop the job, and the old dataset with it, to make room.
+								*job.write() = None;
+								dataset.take();
+								let generated = Arc::new(ethash.full(number, self.threads));
+								dataset = Some(generated.clone());
+								generated
+							}
+						};
+						println!("New work for block #{}: {}", number, pow_hash);
+						*job.write() = Some(Job { pow_hash, number, boundary, dataset: epoch_dataset });
+					}
+				}
+				Err(err) => {
+					if job.write().take().is_some() {
+						println!("No work from the node, pausing: {}", err);
+					}
+				}
+			}
+
+			// Submit the solutions found until the next poll.
+			let deadline = Instant::now() + poll_interval;
+			while let Ok(solution) = solutions.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+				match rpc.submit(&solution) {
+					Ok(true) => println!("Solution for {} accepted", solution.pow_hash),
+					Ok(false) => println!("Solution for {} rejected", solution.pow_hash),
+					Err(err) => println!("Solution for {} rejected: {}", solution.pow_hash, err),
+				}
+			}
+
+			let elapsed = last_report.elapsed();
+			if elapsed >= HASHRATE_INTERVAL {
+				let rate = hashes.swap(0, Ordering::Relaxed) / elapsed.as_secs().max(1);
+				println!("Hashrate: {} H/s", rate);
+				let params = rpc.with_token(vec![json!(U256::from(rate)), json!(miner_id)]);
+				if let Err(err) = rpc.call("eth_submitHashrate", params) {
+					println!("Failed to report the hashrate: {}", err);
+				}
+				last_report = Instant::now();
+			}
+		}
+	}
+}
+
+/// Hash the current job from a random nonce of `partition` on, every nonce of the partition in
+/// turn, until a new job is posted. Returns once `stop` is set.
+pub(crate) fn run_worker(
+	job: Arc<RwLock<Option<Job>>>,
+	solutions: mpsc::Sender<Solution>,
+	hashes: Arc<AtomicU64>,
+	stop: Arc<AtomicBool>,
+	partition: NoncePartition,
+) {
+	let mut rng = rand::thread_rng();
+	while !stop.load(Ordering::Relaxed) {
+		let current = match job.read().clone() {
+			Some(current) => current,
+			None => {
+				thread::sleep(Duration::from_millis(100));
+				continue;
+			}
+		};
+
+		let mut nonce = partition.first_from(rng.gen());
+		while !stop.load(Ordering::Relaxed)
+			&& job.read().as_ref().map_or(false, |job| job.pow_hash == current.pow_hash)
+		{
+			for _ in 0..NONCES_PER_ROUND {
+				let pow = current.dataset.compute(&current.pow_hash.0, nonce, current.number);
+				if pow.value <= current.boundary {
+					let solution = Solution { pow_hash: current.pow_hash, nonce, mix_digest: H256(pow.mix_hash) };
+					if solutions.send(solution).is_err() {
+						return;
+					}
+				}
+				nonce = partition.next(nonce);
+			}
+			hashes.fetch_add(NONCES_PER_ROUND, Ordering::Relaxed);
+		}
+	}
+}
+
+/// A minimal HTTP JSON-RPC client, one connection per call.
+struct RpcClient {
+	host: String,
+	port: u16,
+	path: String,
+	/// Token passed as the last parameter of the submit calls.
+	token: Option<String>,
+}
+
+impl RpcClient {
+	fn new(url: &Uri, token: Option<String>) -> Self {
+		RpcClient {
+			host: url.host().unwrap_or("127.0.0.1").to_owned(),
+			port: url.port_u16().unwrap_or(80),
+			path: url.path_and_query().map_or("/", |path| path.as_str()).to_owned(),
+			token,
+		}
+	}
+
+	/// The parameters `params` of a submit call, followed by the token if any.
+	fn with_token(&self, mut params: Vec<Value>) -> Value {
+		if let Some(token) = &self.token {
+			params.push(json!(token));
+		}
+		Value::Array(params)
+	}
+
+	/// Call `method` with `params`, returning its result or the message of its error.
+	fn call(&self, method: &str, params: Value) -> Result<Value, String> {
+		let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }).to_string();
+		let mut stream = TcpStream::connect((self.host.as_str(), self.port)).map_err(|err| err.to_string())?;
+		stream.set_read_timeout(Some(RPC_TIMEOUT)).map_err(|err| err.to_string())?;
+		write!(
+			stream,
+			"POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\n\
+			Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+			self.path,
+			self.host,
+			self.port,
+			body.len(),
+			body,
+		).map_err(|err| err.to_string())?;
+
+		let mut response = String::new();
+		stream.read_to_string(&mut response).map_err(|err| err.to_string())?;
+		let body = response.splitn(2, "\r\n\r\n").nth(1).unwrap_or_default();
+		let mut response: Value = serde_json::from_str(body).map_err(|err| err.to_string())?;
+		match response.get("error") {
+			Some(error) => Err(error["message"].as_str().unwrap_or("unknown error").to_owned()),
+			None => Ok(response["result"].take()),
+		}
+	}
+
+	/// The pow hash, block number and boundary of the current work package. Mines for the share
+	/// boundary when the node serves a pool.
+	fn work(&self) -> Result<(H256, u64, [u8; 32]), String> {
+		let work = self.call("eth_getWork", json!([]))?;
+		let work: Work = serde_json::from_value(work).map_err(|err| err.to_string())?;
+		let number = work.number.ok_or("no block number in the work package")?;
+		Ok((work.pow_hash, number, work.share_target.unwrap_or(work.target).0))
+	}
+
+	/// Submit `solution`, returning whether the node accepted it.
+	fn submit(&self, solution: &Solution) -> Result<bool, String> {
+		let params = self.with_token(vec![
+			json!(format!("0x{:016x}", solution.nonce)),
+			json!(solution.pow_hash),
+			json!(solution.mix_digest),
+		]);
+		let accepted = self.call("eth_submitWork", params)?;
+		Ok(accepted.as_bool().unwrap_or_default())
+	}
+}
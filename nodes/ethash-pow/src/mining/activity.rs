@@ -0,0 +1,27 @@
+//! Tracks whether external workers are connected to the mining service.
+//!
+//! Workers talk to the node over stateless RPC, so a worker is considered connected as long as
+//! it keeps polling for work, submitting solutions or reporting its hashrate.
+
+use std::time::{Duration, Instant};
+
+/// Workers silent for longer than this are considered disconnected.
+const WORKER_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Time of the last request received from any worker.
+#[derive(Debug, Default)]
+pub struct WorkerActivity {
+	last_seen: Option<Instant>,
+}
+
+impl WorkerActivity {
+	/// Record a request from a worker.
+	pub fn note(&mut self) {
+		self.last_seen = Some(Instant::now());
+	}
+
+	/// Whether a worker sent a request recently.
+	pub fn is_active(&self) -> bool {
+		self.last_seen.map_or(false, |seen| seen.elapsed() < WORKER_TIMEOUT)
+	}
+}
@@ -0,0 +1,129 @@
+//! Append-only audit log of the work handed out and the solutions received.
+//!
+//! Pool operators resolve disputes about rejected shares with it: every work package built for
+//! the miners and every solution submitted, with its outcome, is appended to the file as one JSON
+//! object per line. The file is never truncated by the node, rotating it is left to the operator.
+
+use ethereum_types::H64;
+use log::warn;
+use serde_derive::Serialize;
+use sp_core::{H256, U256};
+use std::{
+	fs::{File, OpenOptions},
+	io::{self, Write},
+	path::{Path, PathBuf},
+	time::{SystemTime, UNIX_EPOCH},
+};
+use crate::rpc::error::Error as RpcError;
+use crate::types::SubmittedWork;
+use super::CurrentWork;
+
+/// An entry of the audit log.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+enum AuditRecord<'a> {
+	/// A work package was built and handed out to the miners.
+	#[serde(rename_all = "camelCase")]
+	Job {
+		/// Unix timestamp, in milliseconds.
+		timestamp: u64,
+		pow_hash: H256,
+		number: Option<u64>,
+		difficulty: U256,
+		share_target: Option<H256>,
+	},
+	/// A solution was submitted.
+	#[serde(rename_all = "camelCase")]
+	Submission {
+		/// Unix timestamp, in milliseconds.
+		timestamp: u64,
+		pow_hash: H256,
+		nonce: H64,
+		mix_digest: H256,
+		/// The worker name of the miner, if given.
+		miner: Option<&'a str>,
+		/// `accepted`, `block` when the solution sealed a block, or `rejected`.
+		outcome: &'static str,
+		/// Why the solution was rejected.
+		error: Option<String>,
+	},
+}
+
+/// The audit log, writing nothing when disabled.
+#[derive(Debug, Default)]
+pub struct AuditLog {
+	file: Option<(PathBuf, File)>,
+}
+
+impl AuditLog {
+	/// Append to the file at `path`, created if missing. Disabled without a path.
+	pub fn open(path: Option<&Path>) -> io::Result<Self> {
+		let file = match path {
+			Some(path) => Some((path.to_owned(), OpenOptions::new().create(true).append(true).open(path)?)),
+			None => None,
+		};
+		Ok(Self { file })
+	}
+
+	/// Record that `current` was handed out to the miners.
+	pub fn job(&mut self, current: &CurrentWork) {
+		self.append(&AuditRecord::Job {
+			timestamp: now(),
+			pow_hash: current.work.pow_hash,
+			number: current.work.number,
+			difficulty: current.difficulty,
+			share_target: current.work.share_target,
+		});
+	}
+
+	/// Record the submission of a solution by `miner`, answered with `result`. `sealed` tells
+	/// accepted solutions sealing a block from shares.
+	pub fn submission(
+		&mut self,
+		pow_hash: H256,
+		nonce: H64,
+		mix_digest: H256,
+		miner: Option<&str>,
+		result: &Result<SubmittedWork, RpcError>,
+		sealed: bool,
+	) {
+		let (outcome, error) = match result {
+			Ok(_) if sealed => ("block", None),
+			Ok(_) => ("accepted", None),
+			Err(err) => ("rejected", Some(err.to_string())),
+		};
+		self.append(&AuditRecord::Submission {
+			timestamp: now(),
+			pow_hash,
+			nonce,
+			mix_digest,
+			miner,
+			outcome,
+			error,
+		});
+	}
+
+	fn append(&mut self, record: &AuditRecord) {
+		let (path, file) = match &mut self.file {
+			Some(file) => file,
+			None => return,
+		};
+		let mut line = match serde_json::to_vec(record) {
+			Ok(line) => line,
+			Err(err) => {
+				warn!(target: "pow", "Failed to encode an audit record: {}", err);
+				return;
+			}
+		};
+		line.push(b'\n');
+		// One write per record, so concurrent readers never see a line cut in two.
+		if let Err(err) = file.write_all(&line) {
+			warn!(target: "pow", "Failed to write the mining audit log {}, disabling it: {}", path.display(), err);
+			self.file = None;
+		}
+	}
+}
+
+fn now() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
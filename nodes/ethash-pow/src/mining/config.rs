@@ -0,0 +1,117 @@
+//! Settings of the mining service.
+
+use ethpow::{Checkpoint, EthashParams};
+use sp_core::U256;
+use hyper::Uri;
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
+use super::{MinerToken, NoncePartition, RewardAuthors};
+use crate::fee_priority::FeePriority;
+
+/// Settings of the mining service, built from the command line.
+#[derive(Debug, Clone, Default)]
+pub struct MiningConfig {
+	/// Nonces searched by the miners of this node.
+	pub nonce_partition: NoncePartition,
+	/// Difficulty of the shares accepted from miners when the node serves a pool. Solutions
+	/// meeting it are acknowledged, only those also meeting the block difficulty are sealed.
+	pub share_difficulty: Option<U256>,
+	/// Shares for one of this many work packages handed out last are acknowledged even once the
+	/// package is replaced. Full solutions are only sealed while their block can still be built.
+	pub stale_share_window: usize,
+	/// Accounts rewarded for the blocks mined by this node, in turn.
+	pub author: Option<RewardAuthors>,
+	/// Extra data attached to the mined blocks until `pow_setExtra` replaces it, empty for none.
+	pub extra_data: Vec<u8>,
+	/// Longest time spent building a block proposal.
+	pub proposal_time: Duration,
+	/// A new proposal is built after this long without a block imported.
+	pub refresh_interval: Duration,
+	/// Minimum fee per microsecond of weight of the transactions included, 0 for none.
+	pub min_fee: u64,
+	/// How the transactions of the mined blocks are selected for their fees.
+	pub fee_priority: FeePriority,
+	/// Number of CPU threads of the node hashing the work, until `pow_setThreads` changes it.
+	pub threads: usize,
+	/// How long the chain may go without a new best block before the watchdog raises the alarm.
+	pub stall_timeout: Option<Duration>,
+	/// No work is handed out once the worker has been building on the same parent this long.
+	pub max_work_age: Option<Duration>,
+	/// Directory the ethash caches are kept in, `ethash` in the chain directory when unset.
+	pub ethash_cache_dir: Option<PathBuf>,
+	/// Fetch the cache of the current epoch from peers once this many of them agree on it.
+	pub cache_from_peers: Option<usize>,
+	/// Generate the full dataset of the next epoch ahead of time on this many threads, if set.
+	pub prepare_dag_threads: Option<usize>,
+	/// Only accept solutions signed with the key registered for the submitting worker.
+	pub require_signed_shares: bool,
+	/// Address the stratum server listens on, if enabled.
+	pub stratum_addr: Option<SocketAddr>,
+	/// Address the mining RPCs are served on instead of the general RPC, if set.
+	pub mining_rpc_addr: Option<SocketAddr>,
+	/// Connection management of the work long-polls and subscriptions.
+	pub work_rpc: WorkRpcConfig,
+	/// URLs every new work package is POSTed to.
+	pub notify_work: Vec<Uri>,
+	/// Stop issuing work while fewer peers than this are connected.
+	pub min_peers: Option<usize>,
+	/// Secret authenticating the `miner_shutdown` and `miner_restartWorker` calls, which are
+	/// disabled without it.
+	pub control_secret: Option<String>,
+	/// Tokens the miners authenticate their submissions with, anyone may submit without.
+	pub miner_tokens: Vec<MinerToken>,
+	/// Submissions allowed per second and miner, if limited.
+	pub miner_rate_limit: Option<u32>,
+	/// Difficulty required for every block, regardless of the chain history.
+	pub fixed_difficulty: Option<U256>,
+	/// Threads computing the proofs of work of the blocks queued for import, one per CPU when
+	/// unset.
+	pub verification_threads: Option<usize>,
+	/// Import the ancestors of the checkpoint of the chain spec without their proof of work.
+	pub fast_sync: bool,
+	/// The checkpoint of the chain spec, read when `fast_sync` is set.
+	pub checkpoint: Option<Checkpoint>,
+	/// Verify the proofs of work in the runtime, for the epochs it holds a commitment to.
+	pub runtime_verification: bool,
+	/// Seal blocks for the transactions and on RPC calls instead of mining.
+	pub instant_seal: bool,
+	/// File the mining statistics are dumped to, `mining-stats.json` in the chain directory
+	/// when unset.
+	pub stats_file: Option<PathBuf>,
+	/// File the work handed out and the solutions received are appended to, if set.
+	pub audit_log: Option<PathBuf>,
+}
+
+impl MiningConfig {
+	/// Consensus parameters of the ethash algorithm, with the overrides of these settings.
+	pub fn ethash_params(&self) -> EthashParams {
+		EthashParams {
+			fixed_difficulty: self.fixed_difficulty,
+			verification_threads: self.verification_threads,
+			checkpoint: self.checkpoint,
+			runtime_verification: self.runtime_verification,
+			..Default::default()
+		}
+	}
+}
+
+/// Settings of the RPC connections waiting for new work, long-polls and subscriptions alike.
+#[derive(Debug, Clone)]
+pub struct WorkRpcConfig {
+	/// Subscribers get the current work again after this long without new work, so proxies
+	/// don't close their connection as idle.
+	pub keep_alive: Duration,
+	/// A long-poll waiting this long without new work is answered with the current work.
+	pub idle_timeout: Duration,
+	/// Long-polls a WebSocket connection may have in flight at once.
+	pub max_long_polls: usize,
+}
+
+impl Default for WorkRpcConfig {
+	fn default() -> Self {
+		Self {
+			keep_alive: Duration::from_secs(30),
+			idle_timeout: Duration::from_secs(60),
+			max_long_polls: 4,
+		}
+	}
+}
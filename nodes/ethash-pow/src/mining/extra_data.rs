@@ -0,0 +1,50 @@
+//! Extra data attached to the blocks mined by this node, like the tag of a pool.
+//!
+//! The proposer names the data set last in a pre-runtime digest of every block it builds, so a
+//! change made with `pow_setExtra` shows from the next block built, not in the work already
+//! handed out.
+
+use arc_swap::ArcSwap;
+use ethpow::{EXTRA_DATA_ENGINE_ID, MAX_EXTRA_DATA_LEN};
+use sp_runtime::traits::Header as HeaderT;
+use std::sync::Arc;
+use crate::rpc::error::Error as RpcError;
+
+/// Shared handle on the extra data of the blocks to build.
+#[derive(Clone, Default)]
+pub struct ExtraData(Arc<ArcSwap<Vec<u8>>>);
+
+impl ExtraData {
+	/// A handle on `extra`, which must fit in `MAX_EXTRA_DATA_LEN` bytes.
+	pub fn new(extra: Vec<u8>) -> Result<Self, RpcError> {
+		let handle = Self::default();
+		handle.set(extra)?;
+		Ok(handle)
+	}
+
+	/// The extra data of the next block built, empty for none.
+	pub fn load(&self) -> Arc<Vec<u8>> {
+		self.0.load_full()
+	}
+
+	/// Attach `extra` to the blocks built from now on, or nothing if empty.
+	pub fn set(&self, extra: Vec<u8>) -> Result<(), RpcError> {
+		if extra.len() > MAX_EXTRA_DATA_LEN {
+			return Err(RpcError::ExtraDataTooLong(extra.len()));
+		}
+		self.0.store(Arc::new(extra));
+		Ok(())
+	}
+}
+
+/// The extra data attached to `header` by its miner, if any.
+pub fn block_extra<H: HeaderT>(header: &H) -> Option<Vec<u8>> {
+	header
+		.digest()
+		.logs()
+		.iter()
+		.filter_map(|log| log.as_pre_runtime())
+		.find(|(id, _)| *id == EXTRA_DATA_ENGINE_ID)
+		.map(|(_, extra)| extra.to_vec())
+		.filter(|extra| extra.len() <= MAX_EXTRA_DATA_LEN)
+}
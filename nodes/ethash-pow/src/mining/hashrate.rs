@@ -0,0 +1,105 @@
+//! Hashrate reported by miners and its history, persisted in the aux database of the client.
+//!
+//! Snapshots are grouped into one aux entry per UTC day so a range query only touches the days
+//! it covers, and the bucket falling out of the retention window is dropped on every write.
+
+use std::{collections::HashMap, time::{Duration, Instant}};
+use parity_scale_codec::{Decode, Encode};
+use sc_client_api::AuxStore;
+use serde_derive::Serialize;
+use sp_blockchain::Result as ClientResult;
+use sp_core::{H256, U256};
+
+/// How often a hashrate snapshot is written.
+pub const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Reports older than this are considered stale, the miner is gone or stopped reporting.
+const REPORT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Number of days of history kept.
+const RETENTION_DAYS: u64 = 30;
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+const HISTORY_KEY_PREFIX: &[u8] = b"ethash_hashrate_history";
+
+/// Hashrate at a point in time.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HashrateSnapshot {
+	/// Unix timestamp of the snapshot, in seconds.
+	pub timestamp: u64,
+	/// Sum of the hashrates reported by the miners of this node.
+	pub miners: U256,
+	/// Network hashrate estimated from the current difficulty and the target block time.
+	pub network: U256,
+}
+
+/// Latest hashrate reported by every miner, through `eth_submitHashrate`.
+#[derive(Debug, Default)]
+pub struct MinerHashrates {
+	reports: HashMap<H256, (U256, Instant)>,
+}
+
+impl MinerHashrates {
+	/// Record the hashrate reported by the miner identified by `id`.
+	pub fn submit(&mut self, id: H256, rate: U256) {
+		self.reports.insert(id, (rate, Instant::now()));
+	}
+
+	/// Sum of the hashrates of all miners that reported recently.
+	pub fn total(&mut self) -> U256 {
+		self.reports.retain(|_, (_, reported)| reported.elapsed() < REPORT_TIMEOUT);
+		self.reports.values().fold(U256::zero(), |total, (rate, _)| total.saturating_add(*rate))
+	}
+}
+
+fn bucket_key(day: u64) -> Vec<u8> {
+	let mut key = HISTORY_KEY_PREFIX.to_vec();
+	key.extend_from_slice(&day.to_be_bytes());
+	key
+}
+
+fn load_bucket<A: AuxStore>(aux: &A, day: u64) -> ClientResult<Vec<HashrateSnapshot>> {
+	match aux.get_aux(&bucket_key(day))? {
+		Some(raw) => Vec::<HashrateSnapshot>::decode(&mut &raw[..]).map_err(|err| {
+			sp_blockchain::Error::Backend(format!("Corrupted hashrate history: {}", err))
+		}),
+		None => Ok(Vec::new()),
+	}
+}
+
+/// Append `snapshot` to the history, pruning the day that left the retention window.
+pub fn write_snapshot<A: AuxStore>(aux: &A, snapshot: HashrateSnapshot) -> ClientResult<()> {
+	let day = snapshot.timestamp / SECS_PER_DAY;
+	let mut bucket = load_bucket(aux, day)?;
+	bucket.push(snapshot);
+
+	let key = bucket_key(day);
+	let encoded = bucket.encode();
+	let stale = day.checked_sub(RETENTION_DAYS).map(bucket_key);
+	let delete: Vec<&[u8]> = stale.iter().map(|key| &key[..]).collect();
+
+	aux.insert_aux(&[(&key[..], &encoded[..])], &delete)
+}
+
+/// Snapshots taken between the `from` and `to` unix timestamps, inclusive, oldest first.
+pub fn history<A: AuxStore>(aux: &A, from: u64, to: u64) -> ClientResult<Vec<HashrateSnapshot>> {
+	if from > to {
+		return Ok(Vec::new());
+	}
+
+	let last_day = to / SECS_PER_DAY;
+	let first_day = (from / SECS_PER_DAY).max(last_day.saturating_sub(RETENTION_DAYS));
+
+	let mut snapshots = Vec::new();
+	for day in first_day..=last_day {
+		snapshots.extend(
+			load_bucket(aux, day)?
+				.into_iter()
+				.filter(|snapshot| snapshot.timestamp >= from && snapshot.timestamp <= to),
+		);
+	}
+
+	Ok(snapshots)
+}
@@ -0,0 +1,100 @@
+//! Prometheus metrics of the mining service, registered in the registry of the node.
+
+use ethash::ETHASH_EPOCH_LENGTH;
+use ethpow::EpochStats;
+use sp_core::U256;
+use std::time::Duration;
+use substrate_prometheus_endpoint::{
+	register, Counter, CounterVec, Gauge, Opts, PrometheusError, Registry, F64, U64,
+};
+use super::CurrentWork;
+
+/// How often the gauges are refreshed.
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Metrics of the work handed out to the miners and of the solutions they send back.
+#[derive(Clone)]
+pub struct MiningMetrics {
+	difficulty: Gauge<U64>,
+	work_age: Gauge<U64>,
+	get_work_requests: Counter<U64>,
+	shares: CounterVec<U64>,
+	hashrate: Gauge<U64>,
+	cache_generation: Gauge<F64>,
+	epoch_stats: EpochStats,
+}
+
+impl MiningMetrics {
+	/// Register the metrics in `registry`. The cache generation time is read from `epoch_stats`.
+	pub fn register(registry: &Registry, epoch_stats: EpochStats) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			difficulty: register(
+				Gauge::new("ethash_difficulty", "Difficulty of the block being mined")?,
+				registry,
+			)?,
+			work_age: register(
+				Gauge::new("ethash_work_package_age_seconds", "Time since the current work package was built")?,
+				registry,
+			)?,
+			get_work_requests: register(
+				Counter::new("ethash_get_work_requests", "Number of eth_getWork requests")?,
+				registry,
+			)?,
+			shares: register(
+				CounterVec::new(
+					Opts::new("ethash_submitted_shares", "Number of solutions submitted by the miners, by result"),
+					&["result"],
+				)?,
+				registry,
+			)?,
+			hashrate: register(
+				Gauge::new("ethash_reported_hashrate", "Sum of the hashrates reported by the miners")?,
+				registry,
+			)?,
+			cache_generation: register(
+				Gauge::new(
+					"ethash_cache_generation_seconds",
+					"Time taken to generate the light cache of the current epoch, 0 if it was loaded from disk",
+				)?,
+				registry,
+			)?,
+			epoch_stats,
+		})
+	}
+
+	/// Count an `eth_getWork` request.
+	pub fn note_get_work(&self) {
+		self.get_work_requests.inc();
+	}
+
+	/// Count a submitted solution, `accepted` or not.
+	pub fn note_share(&self, accepted: bool) {
+		let result = if accepted { "accepted" } else { "rejected" };
+		self.shares.with_label_values(&[result]).inc();
+	}
+
+	/// Refresh the gauges for the `current` work package and the reported `hashrate`.
+	pub fn refresh(&self, current: Option<&CurrentWork>, hashrate: U256) {
+		self.hashrate.set(saturated(hashrate));
+		let current = match current {
+			Some(current) => current,
+			None => {
+				self.work_age.set(0);
+				return;
+			}
+		};
+		self.difficulty.set(saturated(current.difficulty));
+		self.work_age.set(current.created.elapsed().as_secs());
+		let epoch = current.work.number.unwrap_or_default() / ETHASH_EPOCH_LENGTH;
+		let generation = self.epoch_stats.cache_generation_time(epoch).unwrap_or_default();
+		self.cache_generation.set(generation.as_secs_f64());
+	}
+}
+
+fn saturated(value: U256) -> u64 {
+	if value > U256::from(u64::max_value()) {
+		u64::max_value()
+	} else {
+		value.low_u64()
+	}
+}
@@ -0,0 +1,234 @@
+//! Index of the blocks mined by this node, persisted in the aux database of the client.
+//!
+//! A pool pays its miners for the blocks that stay on the chain, so it needs to know which of
+//! the blocks it mined were reorged out. The index records every block the node imports from its
+//! own mining worker. The status of a block is derived from the chain when queried: on the best
+//! chain or off it, and final once at or below the last finalized block.
+//!
+//! Every block also records the difficulty and nonce of its seal, so solo miners can audit their
+//! rewards. The blocks recorded before the index carried them have none.
+
+use ethpow::decode_seal;
+use futures::prelude::*;
+use log::warn;
+use parity_scale_codec::{Decode, Encode};
+use sc_client_api::{AuxStore, BlockchainEvents};
+use serde_derive::Serialize;
+use sp_blockchain::{HeaderBackend, Result as ClientResult};
+use sp_consensus::BlockOrigin;
+use sp_consensus_pow::POW_ENGINE_ID;
+use sp_core::{H256, U256};
+use sp_runtime::{traits::{Block as BlockT, Header as HeaderT}, DigestItem, SaturatedConversion};
+use std::{sync::Arc, time::{SystemTime, UNIX_EPOCH}};
+
+/// Number of mined blocks remembered, the oldest are forgotten first.
+const RETAINED_BLOCKS: usize = 1_000;
+
+/// Key of the index in the original layout, without the seals, read until the index is updated.
+const LEGACY_INDEX_KEY: &[u8] = b"ethash_mined_blocks";
+
+const INDEX_KEY: &[u8] = b"ethash_mined_blocks_v2";
+
+/// A block mined by this node.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct MinedBlock {
+	/// The block hash.
+	pub hash: H256,
+	/// The block number.
+	pub number: u64,
+	/// Unix timestamp of the import of the block, in seconds.
+	pub timestamp: u64,
+	/// The difficulty of the seal, if recorded.
+	pub difficulty: Option<U256>,
+	/// The nonce of the seal, if recorded.
+	pub nonce: Option<u64>,
+}
+
+/// A block mined by this node, as recorded in the original layout of the index.
+#[derive(Decode)]
+struct LegacyMinedBlock {
+	hash: H256,
+	number: u64,
+	timestamp: u64,
+}
+
+impl From<LegacyMinedBlock> for MinedBlock {
+	fn from(block: LegacyMinedBlock) -> Self {
+		let LegacyMinedBlock { hash, number, timestamp } = block;
+		MinedBlock { hash, number, timestamp, difficulty: None, nonce: None }
+	}
+}
+
+/// Where a mined block stands in the chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BlockStatus {
+	/// On the best chain, and may still be reorged out.
+	Canonical,
+	/// On the finalized chain, for good.
+	Finalized,
+	/// Off the best chain, and may still come back.
+	ReorgedOut,
+	/// Off the finalized chain, for good.
+	Orphaned,
+}
+
+/// A mined block and its status.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MinedBlockStatus {
+	/// The block hash.
+	pub hash: H256,
+	/// The block number.
+	pub number: u64,
+	/// Unix timestamp of the import of the block, in seconds.
+	pub mined_at: u64,
+	/// The difficulty of the seal, if recorded.
+	pub difficulty: Option<U256>,
+	/// The nonce of the seal, if recorded.
+	pub nonce: Option<u64>,
+	/// Where the block stands in the chain.
+	pub status: BlockStatus,
+}
+
+/// A page of the mined blocks, answered by `pow_listAuthoredBlocks`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MinedBlocksPage {
+	/// Number of mined blocks remembered, on every page.
+	pub total: u64,
+	/// The blocks of the page, newest first.
+	pub blocks: Vec<MinedBlockStatus>,
+}
+
+/// The mined blocks remembered, oldest first.
+pub fn load<A: AuxStore>(aux: &A) -> ClientResult<Vec<MinedBlock>> {
+	let corrupted = |err| sp_blockchain::Error::Backend(format!("Corrupted mined block index: {}", err));
+	if let Some(raw) = aux.get_aux(INDEX_KEY)? {
+		return Vec::<MinedBlock>::decode(&mut &raw[..]).map_err(corrupted);
+	}
+	match aux.get_aux(LEGACY_INDEX_KEY)? {
+		Some(raw) => Vec::<LegacyMinedBlock>::decode(&mut &raw[..])
+			.map(|blocks| blocks.into_iter().map(Into::into).collect())
+			.map_err(corrupted),
+		None => Ok(Vec::new()),
+	}
+}
+
+/// Add `block` to the index, forgetting the oldest block beyond the retained ones.
+pub fn record<A: AuxStore>(aux: &A, block: MinedBlock) -> ClientResult<()> {
+	let mut blocks = load(aux)?;
+	blocks.push(block);
+	let excess = blocks.len().saturating_sub(RETAINED_BLOCKS);
+	blocks.drain(..excess);
+
+	let encoded = blocks.encode();
+	aux.insert_aux(&[(INDEX_KEY, &encoded[..])], &[LEGACY_INDEX_KEY])
+}
+
+/// The status of `block` in the chain of `client`.
+pub fn status<B, C>(client: &C, block: &MinedBlock) -> ClientResult<BlockStatus>
+where
+	B: BlockT<Hash = H256>,
+	C: HeaderBackend<B>,
+{
+	let number = block.number.saturated_into();
+	let canonical = client.hash(number)? == Some(block.hash);
+	let finalized = block.number <= client.info().finalized_number.saturated_into::<u64>();
+	Ok(match (canonical, finalized) {
+		(true, false) => BlockStatus::Canonical,
+		(true, true) => BlockStatus::Finalized,
+		(false, false) => BlockStatus::ReorgedOut,
+		(false, true) => BlockStatus::Orphaned,
+	})
+}
+
+/// The `count` latest mined blocks, newest first, with their status.
+pub fn latest<B, C>(client: &C, count: usize) -> ClientResult<Vec<MinedBlockStatus>>
+where
+	B: BlockT<Hash = H256>,
+	C: HeaderBackend<B> + AuxStore,
+{
+	load(client)?
+		.iter()
+		.rev()
+		.take(count)
+		.map(|block| with_status::<B, C>(client, block))
+		.collect()
+}
+
+/// The page of `count` mined blocks after the `start` newest ones, newest first, with their
+/// status.
+pub fn page<B, C>(client: &C, start: usize, count: usize) -> ClientResult<MinedBlocksPage>
+where
+	B: BlockT<Hash = H256>,
+	C: HeaderBackend<B> + AuxStore,
+{
+	let blocks = load(client)?;
+	Ok(MinedBlocksPage {
+		total: blocks.len() as u64,
+		blocks: blocks
+			.iter()
+			.rev()
+			.skip(start)
+			.take(count)
+			.map(|block| with_status::<B, C>(client, block))
+			.collect::<ClientResult<_>>()?,
+	})
+}
+
+/// The mined block `hash` with its status, `None` if this node didn't mine it.
+pub fn find<B, C>(client: &C, hash: H256) -> ClientResult<Option<MinedBlockStatus>>
+where
+	B: BlockT<Hash = H256>,
+	C: HeaderBackend<B> + AuxStore,
+{
+	load(client)?
+		.iter()
+		.find(|block| block.hash == hash)
+		.map(|block| with_status::<B, C>(client, block))
+		.transpose()
+}
+
+fn with_status<B, C>(client: &C, block: &MinedBlock) -> ClientResult<MinedBlockStatus>
+where
+	B: BlockT<Hash = H256>,
+	C: HeaderBackend<B>,
+{
+	Ok(MinedBlockStatus {
+		hash: block.hash,
+		number: block.number,
+		mined_at: block.timestamp,
+		difficulty: block.difficulty,
+		nonce: block.nonce,
+		status: status::<B, C>(client, block)?,
+	})
+}
+
+/// Record the blocks `client` imports from the mining worker of this node.
+pub async fn run_mined_block_index<B, C>(client: Arc<C>)
+where
+	B: BlockT<Hash = H256>,
+	C: BlockchainEvents<B> + AuxStore,
+{
+	let mut imports = client.import_notification_stream();
+	while let Some(notification) = imports.next().await {
+		if notification.origin != BlockOrigin::Own {
+			continue;
+		}
+		let seal = match notification.header.digest().logs().last() {
+			Some(DigestItem::Seal(id, seal)) if *id == POW_ENGINE_ID => decode_seal(seal).ok(),
+			_ => None,
+		};
+		let block = MinedBlock {
+			hash: notification.hash,
+			number: (*notification.header.number()).saturated_into(),
+			timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+			difficulty: seal.as_ref().map(|seal| seal.difficulty),
+			nonce: seal.as_ref().map(|seal| seal.nonce),
+		};
+		if let Err(err) = record(&*client, block) {
+			warn!(target: "pow", "Failed to record mined block {}: {:?}", notification.hash, err);
+		}
+	}
+}
@@ -0,0 +1,121 @@
+//! Authentication and rate limiting of the submissions of solutions and hashrates.
+//!
+//! Anyone reaching the RPC or stratum port can submit solutions, and every submission costs the
+//! mining service a verification or a lock. With `--miner-token`, the submit RPCs take the token
+//! of the miner as an extra last parameter, stratum workers authorize with it as their password,
+//! and submissions without one of the configured tokens are rejected before reaching the mining
+//! service. With `--miner-rate-limit`, every miner, told apart by its token, may submit that many
+//! times per second. The limit needs the tokens: the RPC calls carry nothing else telling their
+//! callers apart, and a limit shared by every caller would let one of them lock the others out.
+
+use crate::rpc::error::Error as RpcError;
+use parking_lot::Mutex;
+use sp_core::hashing::blake2_256;
+use std::{
+	collections::HashMap,
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+/// A token miners authenticate with, and the name of the miner it identifies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinerToken {
+	/// Name of the miner, in the logs and for the rate limit.
+	pub name: String,
+	/// The token passed to the submit RPCs.
+	pub token: String,
+}
+
+/// Parse a `--miner-token`, `NAME:TOKEN`.
+pub fn parse_miner_token(value: &str) -> Result<MinerToken, String> {
+	match value.split_once(':') {
+		Some((name, token)) if !name.is_empty() && !token.is_empty() => {
+			Ok(MinerToken { name: name.into(), token: token.into() })
+		}
+		_ => Err(format!("Invalid miner token {}: expected NAME:TOKEN", value)),
+	}
+}
+
+/// Submissions of a miner allowed right now, refilled at the rate limit up to one second's worth.
+struct Allowance {
+	left: f64,
+	updated: Instant,
+}
+
+/// The tokens of the miners and their submissions, shared by every RPC server.
+#[derive(Clone, Default)]
+pub struct MinerAuth {
+	/// Miner names by digest of their token, so the time taken to look a token up doesn't tell
+	/// how much of it is right.
+	tokens: Arc<HashMap<[u8; 32], String>>,
+	/// Submissions allowed per second and miner, if limited.
+	rate_limit: Option<u32>,
+	allowances: Arc<Mutex<HashMap<String, Allowance>>>,
+}
+
+impl MinerAuth {
+	/// Authenticate the submissions with `tokens`, none for anyone to submit, and allow every
+	/// miner `rate_limit` submissions per second, if set. The rate limit needs tokens.
+	pub fn new(tokens: &[MinerToken], rate_limit: Option<u32>) -> Self {
+		Self {
+			tokens: Arc::new(
+				tokens.iter().map(|token| (blake2_256(token.token.as_bytes()), token.name.clone())).collect(),
+			),
+			rate_limit,
+			allowances: Default::default(),
+		}
+	}
+
+	/// Whether submissions must carry one of the tokens.
+	pub fn requires_token(&self) -> bool {
+		!self.tokens.is_empty()
+	}
+
+	/// The name of the miner `token` identifies, `None` if no token is required.
+	pub fn authenticate(&self, token: Option<&str>) -> Result<Option<String>, RpcError> {
+		if !self.requires_token() {
+			return Ok(None);
+		}
+		let token = token.ok_or(RpcError::MinerTokenRequired)?;
+		self.tokens
+			.get(&blake2_256(token.as_bytes()))
+			.cloned()
+			.map(Some)
+			.ok_or(RpcError::InvalidMinerToken)
+	}
+
+	/// Check a submission made with `token` against the tokens and the rate limit of its miner,
+	/// returning the name of the miner, `None` if no token is required.
+	pub fn check(&self, token: Option<&str>) -> Result<Option<String>, RpcError> {
+		let miner = self.authenticate(token)?;
+		if let Some(miner) = &miner {
+			self.limit(miner)?;
+		}
+		Ok(miner)
+	}
+
+	/// Count a submission of `miner` against the rate limit.
+	pub fn limit(&self, miner: &str) -> Result<(), RpcError> {
+		let limit = match self.rate_limit {
+			Some(limit) => limit,
+			None => return Ok(()),
+		};
+		let now = Instant::now();
+		let mut allowances = self.allowances.lock();
+		if !allowances.contains_key(miner) {
+			// An allowance untouched for a second is full again, as good as forgotten.
+			allowances.retain(|_, allowance| now.duration_since(allowance.updated) < Duration::from_secs(1));
+		}
+		let allowance = allowances
+			.entry(miner.to_string())
+			.or_insert(Allowance { left: f64::from(limit), updated: now });
+		let refill = now.duration_since(allowance.updated).as_secs_f64() * f64::from(limit);
+		allowance.left = (allowance.left + refill).min(f64::from(limit));
+		allowance.updated = now;
+		if allowance.left < 1.0 {
+			return Err(RpcError::RequestRejectedLimit(limit));
+		}
+		allowance.left -= 1.0;
+		Ok(())
+	}
+}
@@ -0,0 +1,132 @@
+//! Shares and blocks credited to every miner of the node, persisted in the aux database of the
+//! client.
+//!
+//! With these the node runs as a minimal solo pool: every solution accepted is credited to the
+//! miner submitting it, by the worker name of a stratum or signed submission. The totals are
+//! written with the hashrate snapshots, so they survive restarts. The hashrate of a miner is
+//! estimated from its shares of the last `HASHRATE_WINDOW`, a share of difficulty `d` standing
+//! for `d` hashes on average.
+
+use std::{collections::{BTreeMap, HashMap, VecDeque}, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+use parity_scale_codec::{Decode, Encode};
+use sc_client_api::AuxStore;
+use serde_derive::Serialize;
+use sp_blockchain::Result as ClientResult;
+use sp_core::U256;
+
+/// The miner credited with the solutions submitted without a worker name.
+pub const UNNAMED_MINER: &str = "unnamed";
+
+/// Period the hashrate of a miner is estimated over.
+const HASHRATE_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+/// Number of miners tracked, the solutions of any other are credited to `UNNAMED_MINER`.
+const MAX_MINERS: usize = 4_096;
+
+const STATS_KEY: &[u8] = b"ethash_miner_stats";
+
+/// Totals of a miner, as persisted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Encode, Decode)]
+struct MinerTotals {
+	shares: u64,
+	blocks: u64,
+	last_share: u64,
+}
+
+/// The shares and blocks credited to a miner.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MinerStat {
+	/// The worker name of the miner.
+	pub miner: String,
+	/// Solutions accepted, as shares or blocks.
+	pub shares: u64,
+	/// Solutions meeting the block difficulty.
+	pub blocks: u64,
+	/// Hashrate estimated from the recent shares, in hashes per second.
+	pub hashrate: U256,
+	/// Unix timestamp of the last accepted solution, in seconds.
+	pub last_share: u64,
+}
+
+/// Shares and blocks of every miner.
+#[derive(Debug, Default)]
+pub struct MinerShares {
+	totals: BTreeMap<String, MinerTotals>,
+	/// Time and difficulty of the shares of the last `HASHRATE_WINDOW`, oldest first.
+	recent: HashMap<String, VecDeque<(Instant, U256)>>,
+	/// Whether `totals` changed since they were persisted.
+	dirty: bool,
+}
+
+impl MinerShares {
+	/// The totals persisted in `aux`, without any recent share.
+	pub fn load<A: AuxStore>(aux: &A) -> ClientResult<Self> {
+		let totals = match aux.get_aux(STATS_KEY)? {
+			Some(raw) => Vec::<(String, MinerTotals)>::decode(&mut &raw[..]).map_err(|err| {
+				sp_blockchain::Error::Backend(format!("Corrupted miner stats: {}", err))
+			})?,
+			None => Vec::new(),
+		};
+		Ok(Self { totals: totals.into_iter().collect(), ..Default::default() })
+	}
+
+	/// Credit `miner` with a solution of `difficulty`, meeting the block difficulty if `block`.
+	pub fn credit(&mut self, miner: &str, difficulty: U256, block: bool) {
+		let miner = if self.totals.contains_key(miner) || self.totals.len() < MAX_MINERS {
+			miner
+		} else {
+			UNNAMED_MINER
+		};
+		let totals = self.totals.entry(miner.to_owned()).or_default();
+		totals.shares += 1;
+		if block {
+			totals.blocks += 1;
+		}
+		totals.last_share = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+		self.dirty = true;
+
+		let now = Instant::now();
+		let recent = self.recent.entry(miner.to_owned()).or_default();
+		recent.push_back((now, difficulty));
+		while recent.front().map_or(false, |(at, _)| now.duration_since(*at) > HASHRATE_WINDOW) {
+			recent.pop_front();
+		}
+	}
+
+	/// The stats of every miner, by worker name.
+	pub fn stats(&mut self) -> Vec<MinerStat> {
+		self.recent.retain(|_, recent| {
+			recent.retain(|(at, _)| at.elapsed() <= HASHRATE_WINDOW);
+			!recent.is_empty()
+		});
+		let recent = &self.recent;
+		self.totals
+			.iter()
+			.map(|(miner, totals)| {
+				let hashes = recent.get(miner).map_or(U256::zero(), |recent| {
+					recent.iter().fold(U256::zero(), |total, (_, difficulty)| total.saturating_add(*difficulty))
+				});
+				MinerStat {
+					miner: miner.clone(),
+					shares: totals.shares,
+					blocks: totals.blocks,
+					hashrate: hashes / U256::from(HASHRATE_WINDOW.as_secs()),
+					last_share: totals.last_share,
+				}
+			})
+			.collect()
+	}
+
+	/// Write the totals to `aux` if they changed since the last time.
+	pub fn persist<A: AuxStore>(&mut self, aux: &A) -> ClientResult<()> {
+		if !self.dirty {
+			return Ok(());
+		}
+		let totals: Vec<(&String, &MinerTotals)> = self.totals.iter().collect();
+		let encoded = totals.encode();
+		aux.insert_aux(&[(STATS_KEY, &encoded[..])], &[])?;
+		self.dirty = false;
+		Ok(())
+	}
+}
@@ -0,0 +1,44 @@
+pub mod activity;
+pub mod audit_log;
+pub mod config;
+pub mod extra_data;
+pub mod hashrate;
+pub mod metrics;
+pub mod mined_blocks;
+pub mod miner_auth;
+pub mod miner_stats;
+pub mod nonce;
+pub mod nonce_leases;
+pub mod reward_authors;
+pub mod share_difficulty;
+pub mod shutdown;
+pub mod snapshot;
+pub mod stats_dump;
+pub mod submission_queue;
+pub mod submissions;
+pub mod work_age;
+pub mod work_cache;
+pub mod work_tracker;
+pub mod worker_keys;
+
+pub use self::activity::WorkerActivity;
+pub use self::audit_log::AuditLog;
+pub use self::config::{MiningConfig, WorkRpcConfig};
+pub use self::extra_data::ExtraData;
+pub use self::hashrate::{HashrateSnapshot, MinerHashrates};
+pub use self::metrics::MiningMetrics;
+pub use self::mined_blocks::{BlockStatus, MinedBlockStatus, MinedBlocksPage};
+pub use self::miner_auth::{MinerAuth, MinerToken};
+pub use self::miner_stats::{MinerShares, MinerStat};
+pub use self::nonce::NoncePartition;
+pub use self::nonce_leases::{NonceLease, NonceLeases};
+pub use self::reward_authors::{RewardAuthors, RotationPolicy};
+pub use self::share_difficulty::{ShareDifficulties};
+pub use self::snapshot::{CurrentWork, WorkSnapshot};
+pub use self::stats_dump::{DumpedWork, MiningStatsDump};
+pub use self::submission_queue::{solution_difficulty, QueuedCommand, SubmissionQueue, WorkRequest};
+pub use self::submissions::Submissions;
+pub use self::work_age::WorkAge;
+pub use self::work_cache::WorkCache;
+pub use self::work_tracker::{JobRecord, WorkTracker};
+pub use self::worker_keys::WorkerKeys;
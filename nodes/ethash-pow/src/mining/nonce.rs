@@ -0,0 +1,53 @@
+//! Partitioning of the nonce space between the nodes of a farm.
+//!
+//! The local miner and the `mine` subcommand search the nonces of their partition only, and the
+//! mining service rejects the solutions of nonces outside the partition of the node.
+
+/// The nonces searched by the miners of one node: every `stride`-th nonce starting at `start`.
+///
+/// Nodes mining the same chain get the same stride and distinct starts below it, so they never
+/// search the same nonce of a work package twice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoncePartition {
+	start: u64,
+	stride: u64,
+}
+
+impl Default for NoncePartition {
+	fn default() -> Self {
+		Self { start: 0, stride: 1 }
+	}
+}
+
+impl NoncePartition {
+	/// A partition starting at `start`, which must be below the non-zero `stride`.
+	pub fn new(start: u64, stride: u64) -> Result<Self, String> {
+		if stride == 0 {
+			return Err("--miner-nonce-stride must be greater than zero".into());
+		}
+		if start >= stride {
+			return Err("--miner-nonce-start must be lower than --miner-nonce-stride".into());
+		}
+		Ok(Self { start, stride })
+	}
+
+	/// Whether `nonce` belongs to this partition.
+	pub fn contains(&self, nonce: u64) -> bool {
+		nonce % self.stride == self.start
+	}
+
+	/// The first nonce of the partition at or after `nonce`, wrapping around to `start`.
+	pub fn first_from(&self, nonce: u64) -> u64 {
+		let first = (nonce - nonce % self.stride).checked_add(self.start);
+		match first {
+			Some(first) if first >= nonce => first,
+			Some(first) => first.checked_add(self.stride).unwrap_or(self.start),
+			None => self.start,
+		}
+	}
+
+	/// The nonce of the partition after `nonce`, one of its nonces, wrapping around to `start`.
+	pub fn next(&self, nonce: u64) -> u64 {
+		nonce.checked_add(self.stride).unwrap_or(self.start)
+	}
+}
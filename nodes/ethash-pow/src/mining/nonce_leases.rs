@@ -0,0 +1,55 @@
+//! Ranges of nonces leased to the miners of a farm for the current job.
+//!
+//! Miners polling the same work package with `eth_getWork` each pick their own nonces, and
+//! nothing keeps two of them from searching the same ones. A farm without a stratum server in
+//! front of its rigs can instead lease a range of nonces per miner with `ethash_leaseNonceRange`:
+//! the ranges leased for a job never overlap, and the leases are forgotten once the job is
+//! replaced.
+//!
+//! The ranges are contiguous and ignore the nonce partition of the node, a farm leasing nonces
+//! from one node mines with the default `--miner-nonce-start` and `--miner-nonce-stride`.
+
+use serde_derive::Serialize;
+use sp_core::{H256, U256};
+use crate::rpc::error::Error as RpcError;
+
+/// Nonces from `start` up to, excluding, `end` leased for the job `job_id`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NonceLease {
+	/// The pow hash of the work package the lease is for.
+	pub job_id: H256,
+	/// First nonce of the range.
+	pub start: U256,
+	/// First nonce after the range.
+	pub end: U256,
+}
+
+/// The nonces of the current job leased so far.
+#[derive(Debug, Default)]
+pub struct NonceLeases {
+	/// The job the leases are for.
+	job: Option<H256>,
+	/// First nonce not leased yet.
+	next: u64,
+}
+
+impl NonceLeases {
+	/// Lease the next `size` nonces of `job_id`, which must be the `current` job.
+	pub fn lease(&mut self, job_id: H256, size: u64, current: H256) -> Result<NonceLease, RpcError> {
+		if job_id != current {
+			return Err(RpcError::stale_work());
+		}
+		if size == 0 {
+			return Err(RpcError::EmptyNonceLease);
+		}
+		if self.job != Some(current) {
+			self.job = Some(current);
+			self.next = 0;
+		}
+		let start = self.next;
+		let end = start.checked_add(size).ok_or(RpcError::NonceSpaceExhausted(job_id))?;
+		self.next = end;
+		Ok(NonceLease { job_id, start: start.into(), end: end.into() })
+	}
+}
@@ -0,0 +1,105 @@
+//! Rotation of the block reward across several author accounts.
+//!
+//! Large miners split their rewards across accounts for accounting. The node is given the
+//! accounts and a rotation policy, and every block it proposes rewards the account the policy
+//! picks for the block number: the author is a function of the block alone, so restarts and
+//! reorgs don't shift the rotation.
+
+use runtime::AccountId;
+use sp_core::crypto::Ss58Codec;
+use std::{fmt, fs, path::Path, str::FromStr};
+
+/// How the reward moves from one author account to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RotationPolicy {
+	/// Every block rewards the next account.
+	PerBlock,
+	/// Eras of this many blocks reward the same account, every era the next one.
+	PerEra(u64),
+}
+
+impl Default for RotationPolicy {
+	fn default() -> Self {
+		RotationPolicy::PerBlock
+	}
+}
+
+impl FromStr for RotationPolicy {
+	type Err = String;
+
+	/// Parses `block`, or `era:<BLOCKS>` for eras of `BLOCKS` blocks.
+	fn from_str(policy: &str) -> Result<Self, String> {
+		if policy == "block" {
+			return Ok(RotationPolicy::PerBlock);
+		}
+		match policy.strip_prefix("era:") {
+			Some(blocks) => match blocks.parse::<u64>() {
+				Ok(blocks) if blocks > 0 => Ok(RotationPolicy::PerEra(blocks)),
+				_ => Err(format!("Invalid era length {}, expected a number of blocks above zero", blocks)),
+			},
+			None => Err(format!("Invalid rotation {}, expected block or era:<BLOCKS>", policy)),
+		}
+	}
+}
+
+impl fmt::Display for RotationPolicy {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			RotationPolicy::PerBlock => write!(f, "block"),
+			RotationPolicy::PerEra(blocks) => write!(f, "era:{}", blocks),
+		}
+	}
+}
+
+/// The accounts rewarded for the blocks mined by this node, in turn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RewardAuthors {
+	authors: Vec<AccountId>,
+	policy: RotationPolicy,
+}
+
+impl RewardAuthors {
+	/// Rotate the reward across `authors` following `policy`. Fails without any author.
+	pub fn new(authors: Vec<AccountId>, policy: RotationPolicy) -> Result<Self, String> {
+		if authors.is_empty() {
+			return Err("No mining author given".into());
+		}
+		Ok(Self { authors, policy })
+	}
+
+	/// Reward `author` for every block.
+	pub fn single(author: AccountId) -> Self {
+		Self { authors: vec![author], policy: RotationPolicy::PerBlock }
+	}
+
+	/// The author accounts, in rotation order.
+	pub fn authors(&self) -> &[AccountId] {
+		&self.authors
+	}
+
+	/// The account rewarded for block `number`.
+	pub fn author_at(&self, number: u64) -> &AccountId {
+		let turn = match self.policy {
+			RotationPolicy::PerBlock => number,
+			RotationPolicy::PerEra(blocks) => number / blocks,
+		};
+		&self.authors[(turn % self.authors.len() as u64) as usize]
+	}
+}
+
+/// Read the author accounts listed in `path`, one SS58 address per line. Blank lines and lines
+/// starting with `#` are skipped.
+pub fn read_authors(path: &Path) -> Result<Vec<AccountId>, String> {
+	let listed = fs::read_to_string(path)
+		.map_err(|err| format!("Failed to read the mining authors from {}: {}", path.display(), err))?;
+	listed
+		.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty() && !line.starts_with('#'))
+		.map(|address| {
+			AccountId::from_ss58check(address).map_err(|err| {
+				format!("Invalid account {} in {}: {:?}", address, path.display(), err)
+			})
+		})
+		.collect()
+}
@@ -0,0 +1,53 @@
+//! Share difficulties assigned to individual workers by the pool operator.
+//!
+//! Workers are told apart by the name they authorize with over stratum, sign their solutions
+//! with in `ethash_submitSignedWork`, or pass to `pow_getWorkEx`. The override of a worker sets
+//! the share boundary of the jobs pushed to it and of the work packages it asks for, and the
+//! difficulty its solutions are credited as shares from.
+
+use crate::mining::{work_cache::difficulty_boundary, CurrentWork};
+use crate::types::Work;
+use parking_lot::RwLock;
+use sp_core::U256;
+use std::{collections::HashMap, sync::Arc};
+
+/// Per-worker share difficulty overrides, shared by the clones.
+///
+/// A worker without an entry gets the `--pool-share-difficulty` of the node; an override pins
+/// it to a fixed value until it is cleared again, which is handy when debugging a specific rig.
+#[derive(Debug, Clone, Default)]
+pub struct ShareDifficulties {
+	overrides: Arc<RwLock<HashMap<String, U256>>>,
+}
+
+impl ShareDifficulties {
+	/// Pin the share difficulty of `worker`, or clear its override when `difficulty` is `None`.
+	pub fn set(&self, worker: String, difficulty: Option<U256>) {
+		let mut overrides = self.overrides.write();
+		match difficulty {
+			Some(difficulty) => { overrides.insert(worker, difficulty); },
+			None => { overrides.remove(&worker); },
+		}
+	}
+
+	/// The share difficulty pinned for `worker`, if any.
+	pub fn get(&self, worker: &str) -> Option<U256> {
+		self.overrides.read().get(worker).copied()
+	}
+
+	/// The share difficulty of `worker`: its override, or `default` for unnamed workers and
+	/// those without one.
+	pub fn of(&self, worker: Option<&str>, default: Option<U256>) -> Option<U256> {
+		worker.and_then(|worker| self.get(worker)).or(default)
+	}
+
+	/// The work package of `current` as handed out to `worker`, with the share boundary of its
+	/// override.
+	pub fn work_for(&self, worker: Option<&str>, current: &CurrentWork) -> Work {
+		let mut work = current.work.clone();
+		if let Some(difficulty) = worker.and_then(|worker| self.get(worker)) {
+			work.share_target = Some(difficulty_boundary(difficulty));
+		}
+		work
+	}
+}
@@ -0,0 +1,38 @@
+//! Shutdown of the mining service before the node stops.
+//!
+//! The tasks of the node are dropped when it stops, and with them the oneshot senders of the
+//! requests the mining service was about to handle: their RPC clients would only see a
+//! cancelled request. On `SIGINT` or `SIGTERM`, the signals the node stops on, the service
+//! answers the commands waiting for it with `ServerShuttingDown` and ends first.
+
+use futures::{channel::oneshot, future::{BoxFuture, Shared}, prelude::*};
+
+/// Resolves once the process receives `SIGINT` or `SIGTERM`, for all its clones.
+pub type ExitSignal = Shared<BoxFuture<'static, ()>>;
+
+/// Resolves once the process receives `SIGINT` or `SIGTERM`. Never resolves on other platforms.
+pub fn exit_signal() -> ExitSignal {
+	let (sender, receiver) = oneshot::channel();
+	#[cfg(unix)]
+	match signal_hook::iterator::Signals::new(&[signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM]) {
+		Ok(mut signals) => {
+			let spawned = std::thread::Builder::new().name("mining-exit-signal".into()).spawn(move || {
+				if signals.forever().next().is_some() {
+					let _ = sender.send(());
+				}
+			});
+			if let Err(err) = spawned {
+				log::warn!(target: "pow", "Failed to listen for the exit signals: {}", err);
+			}
+		}
+		Err(err) => log::warn!(target: "pow", "Failed to listen for the exit signals: {}", err),
+	}
+	#[cfg(not(unix))]
+	drop(sender);
+	// A dropped sender means the signals can't be listened for, not an exit.
+	receiver.then(|received| async move {
+		if received.is_err() {
+			future::pending::<()>().await
+		}
+	}).boxed().shared()
+}
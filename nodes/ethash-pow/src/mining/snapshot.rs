@@ -0,0 +1,55 @@
+//! Lock-free snapshot of the current work package.
+//!
+//! Miners poll `eth_getWork` far more often than they submit solutions. The snapshot is rebuilt
+//! every time the mining worker starts or drops a build, so polls are served without taking the
+//! worker lock, which stays reserved for submissions. Transports pushing work to miners, like
+//! stratum or the `eth_subscribe("newWork")` subscriptions, subscribe to the snapshot to learn
+//! about every new work package.
+
+use arc_swap::ArcSwapOption;
+use futures::channel::mpsc;
+use parking_lot::Mutex;
+use sp_core::U256;
+use std::{sync::Arc, time::Instant};
+use crate::types::Work;
+
+/// The work package handed out to miners, with the block difficulty it was built for.
+#[derive(Debug, Clone)]
+pub struct CurrentWork {
+	/// The work package.
+	pub work: Work,
+	/// The difficulty of the block being mined.
+	pub difficulty: U256,
+	/// When the work package was built.
+	pub created: Instant,
+}
+
+/// Shared handle on the current work package, `None` while there is nothing to mine.
+#[derive(Clone, Default)]
+pub struct WorkSnapshot {
+	current: Arc<ArcSwapOption<CurrentWork>>,
+	subscribers: Arc<Mutex<Vec<mpsc::UnboundedSender<Option<Arc<CurrentWork>>>>>>,
+}
+
+impl WorkSnapshot {
+	/// The current work package.
+	pub fn load(&self) -> Option<Arc<CurrentWork>> {
+		self.current.load_full()
+	}
+
+	/// Replace the current work package and notify the subscribers.
+	pub fn store(&self, work: Option<CurrentWork>) {
+		let work = work.map(Arc::new);
+		self.current.store(work.clone());
+		self.subscribers
+			.lock()
+			.retain(|subscriber| subscriber.unbounded_send(work.clone()).is_ok());
+	}
+
+	/// Stream of the work packages stored from now on.
+	pub fn subscribe(&self) -> mpsc::UnboundedReceiver<Option<Arc<CurrentWork>>> {
+		let (subscriber, stream) = mpsc::unbounded();
+		self.subscribers.lock().push(subscriber);
+		stream
+	}
+}
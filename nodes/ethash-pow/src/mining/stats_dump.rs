@@ -0,0 +1,93 @@
+//! Dump of the mining statistics to a JSON file, on `SIGUSR1` or `miner_dumpStats`.
+//!
+//! When mining misbehaves in production, the state of the mining service is what tells why: the
+//! work being mined, the jobs handed out and their solutions, the shares and hashrate of every
+//! miner. A dump takes it without attaching a debugger or restarting the node. Every dump
+//! replaces the previous one.
+
+use futures::{channel::mpsc, prelude::*};
+use serde_derive::Serialize;
+use sp_core::{H256, U256};
+use std::{fs, io, path::Path};
+use super::{CurrentWork, JobRecord, MinerStat};
+
+/// The statistics of the mining service at a point in time.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MiningStatsDump {
+	/// Unix timestamp of the dump, in seconds.
+	pub timestamp: u64,
+	/// The work package being mined, if any.
+	pub current_work: Option<DumpedWork>,
+	/// Whether `miner_shutdown` stopped the issuing of work.
+	pub stopped: bool,
+	/// Peers connected, when too few to mine.
+	pub short_of_peers: Option<usize>,
+	/// Sum of the hashrates reported by the miners.
+	pub reported_hashrate: U256,
+	/// Solutions accepted from all miners, as shares or blocks.
+	pub shares: u64,
+	/// Solutions meeting the block difficulty.
+	pub blocks: u64,
+	/// The shares, blocks and estimated hashrate of every miner.
+	pub miners: Vec<MinerStat>,
+	/// The last work packages handed out, oldest first.
+	pub jobs: Vec<JobRecord>,
+}
+
+/// The work package being mined.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DumpedWork {
+	/// The pow hash of the package.
+	pub pow_hash: H256,
+	/// Number of the block the package seals.
+	pub number: Option<u64>,
+	/// Difficulty of the block.
+	pub difficulty: U256,
+	/// Seconds since the package was built.
+	pub age: u64,
+}
+
+impl From<&CurrentWork> for DumpedWork {
+	fn from(current: &CurrentWork) -> Self {
+		Self {
+			pow_hash: current.work.pow_hash,
+			number: current.work.number,
+			difficulty: current.difficulty,
+			age: current.created.elapsed().as_secs(),
+		}
+	}
+}
+
+/// Write `dump` to `path`, through a temporary file so readers never see half a dump.
+pub fn write(path: &Path, dump: &MiningStatsDump) -> io::Result<()> {
+	let json = serde_json::to_vec_pretty(dump).map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+	let tmp = path.with_extension("json.tmp");
+	fs::write(&tmp, json)?;
+	fs::rename(&tmp, path)
+}
+
+/// Yields whenever the process receives `SIGUSR1`. Never yields on other platforms.
+pub fn dump_signals() -> impl Stream<Item = ()> + Send + Unpin {
+	let (sender, receiver) = mpsc::unbounded();
+	#[cfg(unix)]
+	match signal_hook::iterator::Signals::new(&[signal_hook::consts::SIGUSR1]) {
+		Ok(mut signals) => {
+			let spawned = std::thread::Builder::new().name("mining-stats-signal".into()).spawn(move || {
+				for _ in signals.forever() {
+					if sender.unbounded_send(()).is_err() {
+						return;
+					}
+				}
+			});
+			if let Err(err) = spawned {
+				log::warn!(target: "pow", "Failed to listen for SIGUSR1: {}", err);
+			}
+		}
+		Err(err) => log::warn!(target: "pow", "Failed to listen for SIGUSR1: {}", err),
+	}
+	#[cfg(not(unix))]
+	drop(sender);
+	receiver
+}
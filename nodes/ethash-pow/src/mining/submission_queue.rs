@@ -0,0 +1,137 @@
+//! Queue of the commands waiting for the mining service, full solutions first.
+//!
+//! A node serving a pool receives many more shares than full solutions, and shares only feed the
+//! statistics. Every time the mining service asks for its next command, the queue drains what is
+//! waiting in the command channel and hands out the solutions meeting the block difficulty ahead
+//! of everything else, so a winning block never waits behind a backlog of shares.
+//!
+//! When a new block comes out, every rig of a farm polls `eth_getWork` at once. The work
+//! requests waiting together are handed out as one batch, answered from a single read of the
+//! current work, instead of going through the service one by one.
+
+use ethereum_types::H256 as EH256;
+use futures::{prelude::*, task::{Context, Poll}};
+use sp_core::{H256, U256};
+use std::{collections::VecDeque, pin::Pin};
+use crate::rpc::{ethash::Sender, EtheminerCmd};
+use crate::types::Work;
+use super::WorkSnapshot;
+
+/// Commands held back from the channel at most, so it still pushes back on flooding clients.
+const MAX_QUEUED: usize = 1_000;
+
+/// Difficulty met by the solution `nonce`, `mix_digest` of the work package `pow_hash`.
+pub fn solution_difficulty(pow_hash: &H256, nonce: u64, mix_digest: &H256) -> U256 {
+	let boundary = ethash::quick_get_difficulty(&pow_hash.0, nonce, &mix_digest.0, false);
+	let tmp: [u8; 32] = ethash::boundary_to_difficulty(&EH256(boundary)).into();
+	U256::from(tmp)
+}
+
+/// A work request of `EtheminerCmd::GetWork`.
+pub struct WorkRequest {
+	/// Fail the request when the work is older than this many seconds.
+	pub no_new_work_timeout: Option<u64>,
+	/// The worker asking, whose share difficulty override applies, if named.
+	pub worker: Option<String>,
+	/// sender to report errors/success to the rpc.
+	pub sender: Sender<Work>,
+}
+
+/// A command handed out by the queue.
+pub enum QueuedCommand {
+	/// The `EtheminerCmd::GetWork` requests waiting, to be answered at once.
+	WorkRequests(Vec<WorkRequest>),
+	/// Any other command.
+	Command(EtheminerCmd),
+}
+
+/// Stream of the commands of `commands`, solutions meeting the block difficulty first and work
+/// requests batched.
+pub struct SubmissionQueue<S> {
+	commands: S,
+	work: WorkSnapshot,
+	solutions: VecDeque<EtheminerCmd>,
+	others: VecDeque<QueuedCommand>,
+	/// Work requests in `others`, each counting against `MAX_QUEUED`.
+	work_requests: usize,
+	closed: bool,
+}
+
+impl<S> SubmissionQueue<S> {
+	/// Prioritize the commands of `commands` against the current work of `work`.
+	pub fn new(commands: S, work: WorkSnapshot) -> Self {
+		Self {
+			commands,
+			work,
+			solutions: VecDeque::new(),
+			others: VecDeque::new(),
+			work_requests: 0,
+			closed: false,
+		}
+	}
+
+	fn is_full_solution(&self, command: &EtheminerCmd) -> bool {
+		match (command, self.work.load()) {
+			(EtheminerCmd::SubmitWork { nonce, pow_hash, mix_digest, .. }, Some(current)) => {
+				*pow_hash == current.work.pow_hash
+					&& solution_difficulty(pow_hash, nonce.to_low_u64_be(), mix_digest) >= current.difficulty
+			}
+			_ => false,
+		}
+	}
+
+	/// Queue `command` behind the others, adding a work request to the batch already waiting.
+	fn push_other(&mut self, command: EtheminerCmd) {
+		match command {
+			EtheminerCmd::GetWork { no_new_work_timeout, worker, sender } => {
+				self.work_requests += 1;
+				let request = WorkRequest { no_new_work_timeout, worker, sender };
+				let batch = self.others.iter_mut().find_map(|queued| match queued {
+					QueuedCommand::WorkRequests(requests) => Some(requests),
+					QueuedCommand::Command(_) => None,
+				});
+				match batch {
+					Some(requests) => requests.push(request),
+					None => self.others.push_back(QueuedCommand::WorkRequests(vec![request])),
+				}
+			}
+			command => self.others.push_back(QueuedCommand::Command(command)),
+		}
+	}
+
+	fn len(&self) -> usize {
+		let batches = self.others.iter().filter(|queued| matches!(queued, QueuedCommand::WorkRequests(_))).count();
+		self.solutions.len() + self.others.len() - batches + self.work_requests
+	}
+}
+
+impl<S: Stream<Item = EtheminerCmd> + Unpin> Stream for SubmissionQueue<S> {
+	type Item = QueuedCommand;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<QueuedCommand>> {
+		let this = &mut *self;
+		while !this.closed && this.len() < MAX_QUEUED {
+			match this.commands.poll_next_unpin(cx) {
+				Poll::Ready(Some(command)) => {
+					if this.is_full_solution(&command) {
+						this.solutions.push_back(command);
+					} else {
+						this.push_other(command);
+					}
+				}
+				Poll::Ready(None) => this.closed = true,
+				Poll::Pending => break,
+			}
+		}
+
+		let next = this.solutions.pop_front().map(QueuedCommand::Command).or_else(|| this.others.pop_front());
+		if let Some(QueuedCommand::WorkRequests(requests)) = &next {
+			this.work_requests -= requests.len();
+		}
+		match next {
+			Some(command) => Poll::Ready(Some(command)),
+			None if this.closed => Poll::Ready(None),
+			None => Poll::Pending,
+		}
+	}
+}
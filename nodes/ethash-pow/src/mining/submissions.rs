@@ -0,0 +1,74 @@
+//! Outcomes of the solutions submitted with a submission id, so retries get the same answer.
+//!
+//! Behind a load balancer, a miner whose `eth_submitWork` timed out can't tell whether the node
+//! got it. Retrying would be answered as a duplicate share, even when the first attempt sealed a
+//! block. Solutions submitted with `ethash_submitWorkWithId` carry the job id, the pow hash of
+//! their work package, and a submission id generated by the miner; the node remembers the outcome
+//! of each of them and answers a retry with it instead of checking the solution again.
+
+use ethereum_types::H64;
+use sp_core::H256;
+use std::collections::{HashMap, VecDeque};
+use crate::rpc::error::Error as RpcError;
+use crate::types::SubmittedWork;
+
+/// Submissions remembered at most, the oldest are forgotten first.
+const CAPACITY: usize = 4_096;
+
+struct Submission {
+	nonce: H64,
+	mix_digest: H256,
+	outcome: Result<SubmittedWork, RpcError>,
+}
+
+/// The outcomes of the latest submissions, by job id and submission id.
+#[derive(Default)]
+pub struct Submissions {
+	order: VecDeque<(H256, String)>,
+	outcomes: HashMap<(H256, String), Submission>,
+}
+
+impl Submissions {
+	/// The outcome of an earlier attempt of this submission, `None` if it is the first one.
+	///
+	/// A submission id reused for another solution of the same job is an error.
+	pub fn replay(
+		&self,
+		job_id: H256,
+		submission_id: &str,
+		nonce: H64,
+		mix_digest: H256,
+	) -> Option<Result<SubmittedWork, RpcError>> {
+		let submission = self.outcomes.get(&(job_id, submission_id.to_owned()))?;
+		if submission.nonce != nonce || submission.mix_digest != mix_digest {
+			return Some(Err(RpcError::SubmissionIdConflict(submission_id.to_owned())));
+		}
+		Some(match &submission.outcome {
+			Ok(accepted) => Ok(*accepted),
+			Err(err) => Err(err.replay()),
+		})
+	}
+
+	/// Remember the `outcome` of the first attempt of a submission.
+	pub fn record(
+		&mut self,
+		job_id: H256,
+		submission_id: String,
+		nonce: H64,
+		mix_digest: H256,
+		outcome: &Result<SubmittedWork, RpcError>,
+	) {
+		if self.order.len() == CAPACITY {
+			if let Some(oldest) = self.order.pop_front() {
+				self.outcomes.remove(&oldest);
+			}
+		}
+		let outcome = match outcome {
+			Ok(accepted) => Ok(*accepted),
+			Err(err) => Err(err.replay()),
+		};
+		let key = (job_id, submission_id);
+		self.order.push_back(key.clone());
+		self.outcomes.insert(key, Submission { nonce, mix_digest, outcome });
+	}
+}
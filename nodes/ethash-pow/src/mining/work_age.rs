@@ -0,0 +1,47 @@
+//! Age of the work handed out to the miners.
+//!
+//! The mining worker builds a new proposal every `--work-refresh-interval`, so a fresh pow hash
+//! doesn't mean fresh work: a node which stopped importing blocks, cut from the network for
+//! instance, keeps building on a parent the rest of the chain left behind long ago. The age of
+//! the work is the time since the worker started building on its parent.
+
+use sp_core::H256;
+use std::time::{Duration, Instant};
+
+/// The block the work is built on, and since when.
+#[derive(Debug, Default)]
+pub struct WorkAge {
+	parent: Option<(H256, Instant)>,
+	/// Whether the work was reported older than the maximum age, until it moves on.
+	reported: bool,
+}
+
+impl WorkAge {
+	/// Note the work is now built on `parent`.
+	pub fn note(&mut self, parent: H256) {
+		if self.parent.map_or(true, |(current, _)| current != parent) {
+			self.parent = Some((parent, Instant::now()));
+			self.reported = false;
+		}
+	}
+
+	/// Time since the work was first built on its parent, `None` before any work.
+	pub fn age(&self) -> Option<Duration> {
+		self.parent.map(|(_, since)| since.elapsed())
+	}
+
+	/// Whether the work is older than `max_age`, never without a maximum age.
+	pub fn is_ancient(&self, max_age: Option<Duration>) -> bool {
+		match (self.age(), max_age) {
+			(Some(age), Some(max_age)) => age > max_age,
+			_ => false,
+		}
+	}
+
+	/// Whether the work is older than `max_age` and wasn't reported yet: true once per parent.
+	pub fn report(&mut self, max_age: Option<Duration>) -> bool {
+		let report = self.is_ancient(max_age) && !self.reported;
+		self.reported |= report;
+		report
+	}
+}
@@ -0,0 +1,85 @@
+//! Cache of the work package built from the latest metadata of the mining worker.
+//!
+//! The worker rebuilds its proposal every `--work-refresh-interval`, and every build is a new
+//! pre hash for the same block number and, most of the time, the same difficulty. The cache keeps
+//! the work package of the latest build, keyed by its pre hash, and the boundaries of the last
+//! difficulties, so a rebuild doesn't redo the `difficulty_to_boundary` conversions. The mining
+//! service feeds it from the metadata stream, so resuming the work after a pause doesn't take the
+//! worker lock the submissions contend on either.
+
+use crate::mining::CurrentWork;
+use crate::types::Work;
+use ethpow::EpochSeeds;
+use ethereum_types::U256 as EU256;
+use sc_consensus_pow::MiningMetadata;
+use sp_core::{H256, U256};
+use sp_runtime::traits::{Block as BlockT, UniqueSaturatedInto};
+use std::time::Instant;
+
+/// The work package of the latest metadata, `None` while the worker has no build.
+#[derive(Default)]
+pub struct WorkCache {
+	current: Option<CurrentWork>,
+	/// The block difficulty and its boundary.
+	target: Option<(U256, H256)>,
+	/// The share difficulty and its boundary.
+	share_target: Option<(U256, H256)>,
+}
+
+impl WorkCache {
+	/// Note the latest metadata of the worker and return its work package, rebuilt only if its
+	/// pre hash changed.
+	pub fn update<B: BlockT>(
+		&mut self,
+		metadata: Option<MiningMetadata<H256, U256, B>>,
+		seeds: &EpochSeeds,
+		share_difficulty: Option<U256>,
+	) -> Option<CurrentWork> {
+		let metadata = match metadata {
+			Some(metadata) => metadata,
+			None => {
+				self.current = None;
+				return None;
+			}
+		};
+		let fresh = self.current.as_ref().map_or(true, |current| {
+			current.work.pow_hash != metadata.pre_hash || current.difficulty != metadata.difficulty
+		});
+		if fresh {
+			let number: u64 = UniqueSaturatedInto::<u64>::unique_saturated_into(metadata.number);
+			let work = Work {
+				pow_hash: metadata.pre_hash,
+				seed_hash: seeds.hash_block_number(number),
+				target: boundary(&mut self.target, metadata.difficulty),
+				number: Some(number),
+				share_target: share_difficulty.map(|difficulty| boundary(&mut self.share_target, difficulty)),
+			};
+			self.current = Some(CurrentWork { work, difficulty: metadata.difficulty, created: Instant::now() });
+		}
+		self.current.clone()
+	}
+
+	/// The work package of the latest metadata.
+	pub fn latest(&self) -> Option<CurrentWork> {
+		self.current.clone()
+	}
+}
+
+/// The boundary of `difficulty`, reusing `cached` when it was computed for the same difficulty.
+fn boundary(cached: &mut Option<(U256, H256)>, difficulty: U256) -> H256 {
+	match cached {
+		Some((known, target)) if *known == difficulty => *target,
+		_ => {
+			let target = difficulty_boundary(difficulty);
+			*cached = Some((difficulty, target));
+			target
+		}
+	}
+}
+
+/// The boundary solutions of `difficulty` hash at or below.
+pub fn difficulty_boundary(difficulty: U256) -> H256 {
+	let tmp: [u8; 32] = difficulty.into();
+	let tmp: [u8; 32] = ethash::difficulty_to_boundary(&EU256::from(tmp)).into();
+	H256::from(tmp)
+}
@@ -0,0 +1,145 @@
+//! Work packages handed out to miners and the solutions received for them.
+
+use std::{collections::{HashSet, VecDeque}, time::Instant};
+use ethash::ETHASH_EPOCH_LENGTH;
+use serde_derive::Serialize;
+use sp_core::{H256, U256};
+use crate::rpc::error::Error;
+
+/// Number of work packages remembered to classify late solutions.
+pub const TRACKED_WORK: usize = 16;
+
+/// A work package handed out to miners.
+#[derive(Debug)]
+struct IssuedWork {
+	pow_hash: H256,
+	/// Number of the block the package seals.
+	number: u64,
+	/// Difficulty of the block the package seals.
+	difficulty: U256,
+	/// When the package was first handed out.
+	issued_at: Instant,
+}
+
+/// A tracked work package and the solutions accepted for it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobRecord {
+	/// The pow hash of the package.
+	pub pow_hash: H256,
+	/// Number of the block the package seals.
+	pub number: u64,
+	/// Difficulty of the block the package seals.
+	pub difficulty: U256,
+	/// Seconds since the package was first handed out.
+	pub age: u64,
+	/// Solutions accepted for the package.
+	pub solutions: usize,
+}
+
+/// Classifies submitted solutions so every rejection gets its own error.
+#[derive(Debug, Default)]
+pub struct WorkTracker {
+	/// Work packages handed out, oldest first.
+	issued: VecDeque<IssuedWork>,
+	/// `(pow_hash, nonce)` of the solutions accepted for the tracked work packages.
+	solutions: HashSet<(H256, u64)>,
+}
+
+impl WorkTracker {
+	/// Remember that the work package `pow_hash`, sealing block `number` of `difficulty`, was
+	/// handed out.
+	pub fn issue(&mut self, pow_hash: H256, number: u64, difficulty: U256) {
+		if self.issued.iter().any(|work| work.pow_hash == pow_hash) {
+			return;
+		}
+		if self.issued.len() == TRACKED_WORK {
+			if let Some(expired) = self.issued.pop_front() {
+				self.solutions.retain(|(hash, _)| *hash != expired.pow_hash);
+			}
+		}
+		self.issued.push_back(IssuedWork { pow_hash, number, difficulty, issued_at: Instant::now() });
+	}
+
+	/// The tracked work packages, oldest first.
+	pub fn jobs(&self) -> Vec<JobRecord> {
+		self.issued
+			.iter()
+			.map(|work| JobRecord {
+				pow_hash: work.pow_hash,
+				number: work.number,
+				difficulty: work.difficulty,
+				age: work.issued_at.elapsed().as_secs(),
+				solutions: self.solutions.iter().filter(|(hash, _)| *hash == work.pow_hash).count(),
+			})
+			.collect()
+	}
+
+	/// The number and difficulty of the block of `pow_hash`, if it is a tracked work package.
+	pub fn job(&self, pow_hash: &H256) -> Option<(u64, U256)> {
+		self.issued
+			.iter()
+			.find(|work| work.pow_hash == *pow_hash)
+			.map(|work| (work.number, work.difficulty))
+	}
+
+	/// Whether the solution `nonce` for `pow_hash` was already accepted.
+	pub fn is_duplicate(&self, pow_hash: H256, nonce: u64) -> bool {
+		self.solutions.contains(&(pow_hash, nonce))
+	}
+
+	/// Record the solution `nonce` for `pow_hash`, once its seal is verified and accepted, so it
+	/// isn't accepted twice. A solution rejected on the way may be submitted again.
+	pub fn accept(&mut self, pow_hash: H256, nonce: u64) {
+		self.solutions.insert((pow_hash, nonce));
+	}
+
+	/// Check a solution against the current work package `current`, sealing block `number`.
+	pub fn check(&mut self, pow_hash: H256, nonce: u64, current: H256, number: u64) -> Result<(), Error> {
+		if pow_hash != current {
+			let epoch = number / ETHASH_EPOCH_LENGTH;
+			return match self.issued.iter().find(|work| work.pow_hash == pow_hash) {
+				Some(work) if work.number / ETHASH_EPOCH_LENGTH != epoch =>
+					Err(Error::wrong_epoch(epoch, work.number / ETHASH_EPOCH_LENGTH)),
+				_ => Err(Error::stale_work()),
+			};
+		}
+		if self.is_duplicate(pow_hash, nonce) {
+			return Err(Error::duplicate_share());
+		}
+		Ok(())
+	}
+
+	/// Check a solution for `pow_hash`, a work package replaced since it was handed out, while
+	/// the chain is at block `number`. Solutions for one of the last `window` packages handed out
+	/// pass, as the miner may not have had the newer ones yet. Solutions for a package no longer
+	/// tracked are for a wrong pow hash.
+	///
+	/// Returns the number and difficulty of the block the package was for.
+	pub fn check_replaced(
+		&mut self,
+		pow_hash: H256,
+		nonce: u64,
+		number: u64,
+		window: usize,
+	) -> Result<(u64, U256), Error> {
+		let epoch = number / ETHASH_EPOCH_LENGTH;
+		let (age, work) = self.issued
+			.iter()
+			.rev()
+			.enumerate()
+			.find(|(_, work)| work.pow_hash == pow_hash)
+			.ok_or_else(|| Error::wrong_pow_hash(pow_hash))?;
+		if work.number / ETHASH_EPOCH_LENGTH != epoch {
+			return Err(Error::wrong_epoch(epoch, work.number / ETHASH_EPOCH_LENGTH));
+		}
+		if age >= window {
+			return Err(Error::stale_work());
+		}
+		let found = (work.number, work.difficulty);
+		if self.is_duplicate(pow_hash, nonce) {
+			return Err(Error::duplicate_share());
+		}
+		Ok(found)
+	}
+}
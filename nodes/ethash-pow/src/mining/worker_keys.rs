@@ -0,0 +1,39 @@
+//! Keys of the workers allowed to submit solutions when the node requires signed shares.
+//!
+//! Workers behind the same NAT look alike to the node, so a rig can steal the shares of its
+//! neighbours by replaying their solutions under its own name. With signed shares, every
+//! solution carries an sr25519 signature over `nonce ‖ pow_hash` made with the key registered
+//! for the worker, and is rejected unless the signature checks out.
+
+use sp_core::{sr25519, Pair, H256};
+use std::collections::HashMap;
+
+/// The message a worker signs for a solution: the nonce, little endian, followed by the pow hash.
+pub fn signed_payload(nonce: u64, pow_hash: &H256) -> Vec<u8> {
+	let mut payload = nonce.to_le_bytes().to_vec();
+	payload.extend_from_slice(pow_hash.as_bytes());
+	payload
+}
+
+/// Public keys registered by the pool operator, by worker name.
+#[derive(Debug, Default)]
+pub struct WorkerKeys {
+	keys: HashMap<String, sr25519::Public>,
+}
+
+impl WorkerKeys {
+	/// Register the key of `worker`, or remove it when `key` is `None`.
+	pub fn set(&mut self, worker: String, key: Option<sr25519::Public>) {
+		match key {
+			Some(key) => { self.keys.insert(worker, key); },
+			None => { self.keys.remove(&worker); },
+		}
+	}
+
+	/// Whether `signature` was made by the key registered for `worker` over the solution.
+	pub fn verify(&self, worker: &str, nonce: u64, pow_hash: &H256, signature: &sr25519::Signature) -> bool {
+		self.keys.get(worker).map_or(false, |key| {
+			sr25519::Pair::verify(signature, signed_payload(nonce, pow_hash), key)
+		})
+	}
+}
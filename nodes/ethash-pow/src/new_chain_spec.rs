@@ -0,0 +1,372 @@
+//! The `new-chain-spec` subcommand: the chain spec of a new PoW chain built from this node, for
+//! teams launching their own network rather than joining `dev` or `local`.
+//!
+//! Every choice is a flag, so the spec can be generated from a script. With `--interactive`, the
+//! choices left out on the command line are asked for on the terminal instead.
+
+use crate::chain_spec::ChainSpec;
+use runtime::{
+	genesis::{EmissionCurve, PowGenesis, RetargetStrategy},
+	AccountId, Balance, BlockNumber, WASM_BINARY,
+};
+use sc_network::config::MultiaddrWithPeerId;
+use sc_service::ChainType;
+use sp_core::crypto::Ss58Codec;
+use sp_finality_grandpa::AuthorityId as GrandpaId;
+use std::{
+	fmt::Display,
+	fs,
+	io::{self, BufRead, Write},
+	path::PathBuf,
+	str::FromStr,
+};
+use structopt::StructOpt;
+
+/// Balance of the accounts endowed without one, as in the built-in chains.
+const DEFAULT_ENDOWMENT: Balance = 1 << 60;
+
+/// An account endowed at genesis: `ADDRESS` or `ADDRESS=BALANCE`.
+#[derive(Debug, Clone)]
+pub struct Endowment {
+	account: AccountId,
+	balance: Balance,
+}
+
+impl FromStr for Endowment {
+	type Err = String;
+
+	fn from_str(endowment: &str) -> Result<Self, String> {
+		let mut parts = endowment.splitn(2, '=');
+		let address = parts.next().unwrap_or_default().trim();
+		let account = AccountId::from_ss58check(address)
+			.map_err(|err| format!("Invalid account {}: {:?}", address, err))?;
+		let balance = match parts.next() {
+			Some(balance) => balance
+				.trim()
+				.parse()
+				.map_err(|_| format!("Invalid balance {} of {}", balance, address))?,
+			None => DEFAULT_ENDOWMENT,
+		};
+		Ok(Self { account, balance })
+	}
+}
+
+/// A GRANDPA voter of the chain, by the SS58 address of its ed25519 key.
+#[derive(Debug, Clone)]
+pub struct GrandpaAuthority(GrandpaId);
+
+impl FromStr for GrandpaAuthority {
+	type Err = String;
+
+	fn from_str(address: &str) -> Result<Self, String> {
+		GrandpaId::from_ss58check(address.trim())
+			.map(GrandpaAuthority)
+			.map_err(|err| format!("Invalid GRANDPA key {}: {:?}", address, err))
+	}
+}
+
+/// The reward curve from block 0: `halving:<REWARD>:<INTERVAL>` or
+/// `linear:<REWARD>:<STEP>:<INTERVAL>:<FLOOR>`.
+#[derive(Debug, Clone, Copy)]
+pub struct RewardCurve(EmissionCurve<Balance, BlockNumber>);
+
+impl FromStr for RewardCurve {
+	type Err = String;
+
+	fn from_str(curve: &str) -> Result<Self, String> {
+		let invalid = || {
+			format!(
+				"Invalid reward curve {}, expected halving:<REWARD>:<INTERVAL> or \
+				 linear:<REWARD>:<STEP>:<INTERVAL>:<FLOOR>",
+				curve,
+			)
+		};
+		let parts: Vec<&str> = curve.split(':').collect();
+		let balance = |part: &str| part.parse::<Balance>().map_err(|_| invalid());
+		let blocks = |part: &str| part.parse::<BlockNumber>().map_err(|_| invalid());
+		match parts[..] {
+			["halving", reward, interval] => Ok(RewardCurve(EmissionCurve::Halving {
+				reward: balance(reward)?,
+				interval: blocks(interval)?,
+			})),
+			["linear", reward, step, interval, floor] => {
+				let (reward, floor) = (balance(reward)?, balance(floor)?);
+				if floor > reward {
+					return Err(format!("The floor {} of the reward curve is above its reward {}", floor, reward));
+				}
+				Ok(RewardCurve(EmissionCurve::Linear {
+					reward,
+					step: balance(step)?,
+					interval: blocks(interval)?,
+					floor,
+				}))
+			}
+			_ => Err(invalid()),
+		}
+	}
+}
+
+/// Generate the chain spec of a new PoW chain: its name, genesis accounts, difficulty, block
+/// reward, finality voters and bootnodes.
+#[derive(Debug, StructOpt)]
+pub struct NewChainSpecCmd {
+	/// Human readable name of the chain.
+	#[structopt(long)]
+	pub name: Option<String>,
+
+	/// Identifier of the chain, also naming its database directory. Derived from the name when
+	/// omitted.
+	#[structopt(long)]
+	pub id: Option<String>,
+
+	/// Type of the chain.
+	#[structopt(long, possible_values = &["live", "local", "development"], case_insensitive = true)]
+	pub chain_type: Option<String>,
+
+	/// SS58 address of the sudo key.
+	#[structopt(long)]
+	pub root: Option<AccountId>,
+
+	/// Account endowed at genesis, as `ADDRESS=BALANCE`, or `ADDRESS` for the balance of the
+	/// built-in chains. Repeat the flag for every account.
+	#[structopt(long = "endow", value_name = "ADDRESS=BALANCE")]
+	pub endowed: Vec<Endowment>,
+
+	/// Difficulty of the first block.
+	#[structopt(long)]
+	pub difficulty: Option<u64>,
+
+	/// The retargeted difficulty never drops below this value. Defaults to the difficulty of the
+	/// first block.
+	#[structopt(long)]
+	pub minimum_difficulty: Option<u64>,
+
+	/// The difficulty retarget rule.
+	#[structopt(long, possible_values = &["parent-delta", "eip100", "lwma", "fixed"])]
+	pub difficulty_strategy: Option<String>,
+
+	/// Reward of the block authors from block 0, as `halving:<REWARD>:<INTERVAL>` or
+	/// `linear:<REWARD>:<STEP>:<INTERVAL>:<FLOOR>`. Defaults to the halving schedule of the
+	/// runtime.
+	#[structopt(long)]
+	pub reward: Option<RewardCurve>,
+
+	/// SS58 address of the ed25519 key of a GRANDPA voter finalizing the mined blocks. Repeat
+	/// the flag for every voter. Without any, the blocks are never finalized.
+	#[structopt(long = "grandpa-authority", value_name = "ADDRESS")]
+	pub grandpa_authorities: Vec<GrandpaAuthority>,
+
+	/// Bootnodes of the chain, as multiaddresses ending with the peer id.
+	#[structopt(long, value_name = "ADDR")]
+	pub bootnodes: Vec<MultiaddrWithPeerId>,
+
+	/// Write the spec in the raw storage format, as loaded by the nodes of the chain.
+	#[structopt(long)]
+	pub raw: bool,
+
+	/// File to write the spec to. Printed on the standard output when omitted.
+	#[structopt(long, short, parse(from_os_str))]
+	pub output: Option<PathBuf>,
+
+	/// Ask on the terminal for the choices not given on the command line.
+	#[structopt(long, short)]
+	pub interactive: bool,
+}
+
+impl NewChainSpecCmd {
+	/// Generate the spec and write it out.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		let wasm_binary = WASM_BINARY.ok_or_else(|| "Development wasm not available".to_string())?;
+		let mut wizard = Wizard { interactive: self.interactive, input: io::stdin() };
+
+		let name = wizard.ask("Chain name", self.name.clone(), Some("Custom PoW Chain".into()))?;
+		let id = wizard.ask("Chain id", self.id.clone(), Some(chain_id(&name)))?;
+		let chain_type = wizard.ask(
+			"Chain type (live, local or development)",
+			self.chain_type.clone(),
+			Some("live".into()),
+		)?;
+		let chain_type = parse_chain_type(&chain_type)?;
+		let root = wizard.ask("SS58 address of the sudo key", self.root.clone(), None)?;
+
+		let mut endowed = self.endowed.clone();
+		if endowed.is_empty() {
+			while let Some(endowment) = wizard.ask_optional("Endowed account, ADDRESS=BALANCE (empty to stop)")? {
+				endowed.push(endowment);
+			}
+		}
+		if endowed.is_empty() {
+			return Err("No account endowed at genesis: give at least one --endow".into());
+		}
+
+		let difficulty = wizard.ask("Difficulty of the first block", self.difficulty, Some(1_000_000))?;
+		let minimum_difficulty = wizard.ask("Minimum difficulty", self.minimum_difficulty, Some(difficulty))?;
+		if minimum_difficulty == 0 || minimum_difficulty > difficulty {
+			return Err(
+				"The minimum difficulty must be above zero and at most the difficulty of the first block".into(),
+			);
+		}
+		let strategy = wizard.ask(
+			"Difficulty retarget rule (parent-delta, eip100, lwma or fixed)",
+			self.difficulty_strategy.clone(),
+			Some("parent-delta".into()),
+		)?;
+		let strategy = parse_strategy(&strategy)?;
+		let reward = match self.reward {
+			Some(reward) => Some(reward),
+			None => wizard.ask_optional("Reward curve (empty for the halving schedule of the runtime)")?,
+		};
+
+		let mut grandpa_authorities = self.grandpa_authorities.clone();
+		if grandpa_authorities.is_empty() {
+			while let Some(authority) =
+				wizard.ask_optional("SS58 address of a GRANDPA voter (empty to stop)")?
+			{
+				grandpa_authorities.push(authority);
+			}
+		}
+
+		let mut bootnodes = self.bootnodes.clone();
+		if bootnodes.is_empty() {
+			while let Some(bootnode) = wizard.ask_optional("Bootnode multiaddress (empty to stop)")? {
+				bootnodes.push(bootnode);
+			}
+		}
+
+		let genesis = PowGenesis {
+			root_key: root,
+			balances: endowed.into_iter().map(|Endowment { account, balance }| (account, balance)).collect(),
+			difficulty: difficulty.into(),
+			minimum_difficulty: minimum_difficulty.into(),
+			strategy,
+			reward: reward.map(|RewardCurve(curve)| curve),
+			grandpa_authorities: grandpa_authorities.into_iter().map(|GrandpaAuthority(id)| id).collect(),
+		};
+		let spec = ChainSpec::from_genesis(
+			&name,
+			&id,
+			chain_type,
+			move || genesis.clone().build(wasm_binary),
+			bootnodes,
+			None,
+			None,
+			None,
+			None,
+		);
+		let json = spec.as_json(self.raw)?;
+
+		match &self.output {
+			Some(path) => {
+				fs::write(path, json)?;
+				eprintln!("Wrote the chain spec of {} to {}", name, path.display());
+			}
+			None => println!("{}", json),
+		}
+		Ok(())
+	}
+}
+
+/// Asks for the choices missing on the command line, when interactive.
+struct Wizard {
+	interactive: bool,
+	input: io::Stdin,
+}
+
+impl Wizard {
+	/// `given` if any, else the answer to `question`, else `default`. Asks again after an invalid
+	/// answer.
+	fn ask<T>(&mut self, question: &str, given: Option<T>, default: Option<T>) -> Result<T, String>
+	where
+		T: FromStr + Display,
+		T::Err: Display,
+	{
+		if let Some(given) = given {
+			return Ok(given);
+		}
+		if !self.interactive {
+			return default.ok_or_else(|| {
+				format!("Missing the {}: give it on the command line or run with --interactive", question)
+			});
+		}
+		let prompt = match &default {
+			Some(default) => format!("{} [{}]", question, default),
+			None => question.to_string(),
+		};
+		let mut default = default;
+		loop {
+			match self.read_answer(&prompt)? {
+				None => {
+					if let Some(default) = default.take() {
+						return Ok(default);
+					}
+				}
+				Some(answer) => match answer.parse() {
+					Ok(value) => return Ok(value),
+					Err(err) => eprintln!("{}", err),
+				},
+			}
+		}
+	}
+
+	/// The answer to `question`, or `None` when left empty or not interactive. Asks again after
+	/// an invalid answer.
+	fn ask_optional<T>(&mut self, question: &str) -> Result<Option<T>, String>
+	where
+		T: FromStr,
+		T::Err: Display,
+	{
+		if !self.interactive {
+			return Ok(None);
+		}
+		loop {
+			match self.read_answer(question)? {
+				None => return Ok(None),
+				Some(answer) => match answer.parse() {
+					Ok(value) => return Ok(Some(value)),
+					Err(err) => eprintln!("{}", err),
+				},
+			}
+		}
+	}
+
+	/// Print `prompt` and read a line, `None` when empty. The prompts go to the standard error,
+	/// keeping the standard output for the spec.
+	fn read_answer(&mut self, prompt: &str) -> Result<Option<String>, String> {
+		eprint!("{}: ", prompt);
+		io::stderr().flush().map_err(|err| err.to_string())?;
+		let mut answer = String::new();
+		let read = self.input.lock().read_line(&mut answer).map_err(|err| err.to_string())?;
+		if read == 0 {
+			return Err("The standard input was closed before the chain spec was complete".into());
+		}
+		let answer = answer.trim();
+		Ok(if answer.is_empty() { None } else { Some(answer.to_string()) })
+	}
+}
+
+/// The id of the chain named `name`: its alphanumeric characters in lower case, the others
+/// replaced by underscores.
+fn chain_id(name: &str) -> String {
+	name.chars()
+		.map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+		.collect()
+}
+
+fn parse_chain_type(chain_type: &str) -> Result<ChainType, String> {
+	match chain_type.to_lowercase().as_str() {
+		"live" => Ok(ChainType::Live),
+		"local" => Ok(ChainType::Local),
+		"development" => Ok(ChainType::Development),
+		_ => Err(format!("Invalid chain type {}, expected live, local or development", chain_type)),
+	}
+}
+
+fn parse_strategy(strategy: &str) -> Result<RetargetStrategy, String> {
+	match strategy {
+		"parent-delta" => Ok(RetargetStrategy::ParentDelta),
+		"eip100" => Ok(RetargetStrategy::Eip100),
+		"lwma" => Ok(RetargetStrategy::Lwma),
+		"fixed" => Ok(RetargetStrategy::Fixed),
+		_ => Err(format!("Invalid retarget rule {}, expected parent-delta, eip100, lwma or fixed", strategy)),
+	}
+}
@@ -0,0 +1,50 @@
+//! `--notify-work` hook, as in OpenEthereum.
+//!
+//! Pool software and mining proxies often prefer being called back over polling the node. Every
+//! time the mining worker starts a new build, the `eth_getWork` payload of the new work package is
+//! POSTed as JSON to each configured URL. Notifications are fire and forget: a failing endpoint
+//! is logged and retried with the next work package only.
+
+use futures::{future, prelude::*};
+use hyper::{header::CONTENT_TYPE, Body, Client, Request, Uri};
+use log::{debug, warn};
+use crate::mining::WorkSnapshot;
+
+/// Parse a `--notify-work` URL, only plain HTTP endpoints are supported.
+pub fn parse_url(url: &str) -> Result<Uri, String> {
+	let uri: Uri = url.parse().map_err(|err| format!("Invalid URL {}: {}", url, err))?;
+	match uri.scheme_str() {
+		Some("http") => Ok(uri),
+		_ => Err(format!("Invalid URL {}: only http:// URLs are supported", url)),
+	}
+}
+
+/// POST every new work package of `work` to each of `urls`.
+pub async fn run_work_notifier(urls: Vec<Uri>, work: WorkSnapshot) {
+	let client = Client::new();
+	let mut notifications = work.subscribe();
+
+	while let Some(current) = notifications.next().await {
+		let current = match current {
+			Some(current) => current,
+			None => continue,
+		};
+		let payload = serde_json::to_vec(&current.work).expect("work packages are serializable; qed");
+
+		future::join_all(urls.iter().map(|url| {
+			let request = Request::post(url.clone())
+				.header(CONTENT_TYPE, "application/json")
+				.body(Body::from(payload.clone()))
+				.expect("the URL was validated and the header is well formed; qed");
+			client.request(request).map(move |response| match response {
+				Ok(response) if response.status().is_success() => {
+					debug!(target: "pow", "Notified {} of new work", url)
+				}
+				Ok(response) => {
+					warn!(target: "pow", "Work notification to {} answered {}", url, response.status())
+				}
+				Err(err) => warn!(target: "pow", "Failed to notify {} of new work: {}", url, err),
+			})
+		})).await;
+	}
+}
@@ -0,0 +1,105 @@
+//! A proposer naming the block author to the runtime.
+//!
+//! It wraps another proposer, usually the one of `sc_basic_authorship`, and adds the author
+//! account to the inherent data of every block it proposes. The block-reward pallet of the
+//! runtime turns it into a `claim_reward` inherent placed at the start of the block. The author
+//! is named in the PoW pre-runtime digest as well, for the pallets finding it there.
+//!
+//! With several author accounts configured, the account rewarded is the one of the rotation for
+//! the number of the proposed block. The extra data set for the mined blocks, if any, goes in a
+//! pre-runtime digest of its own.
+
+use std::{pin::Pin, sync::Arc, time::Duration};
+use futures::{Future, TryFutureExt};
+use log::warn;
+use parity_scale_codec::Encode;
+use runtime::AccountId;
+use sp_consensus::{Environment, Proposer, RecordProof};
+use ethpow::EXTRA_DATA_ENGINE_ID;
+use sp_consensus_pow::POW_ENGINE_ID;
+use sp_inherents::InherentData;
+use sp_runtime::{
+	traits::{Block as BlockT, DigestFor, Header as HeaderT, UniqueSaturatedInto},
+	DigestItem,
+};
+use crate::mining::{ExtraData, RewardAuthors};
+
+/// Creates `RewardProposer`s on top of the proposers of `F`.
+pub struct RewardProposerFactory<F> {
+	inner: F,
+	authors: Option<RewardAuthors>,
+	extra_data: ExtraData,
+}
+
+impl<F> RewardProposerFactory<F> {
+	/// Wrap `inner`, rewarding the author `authors` name for every proposed block and attaching
+	/// the current `extra_data`. Blocks are proposed without author when none is given.
+	pub fn new(inner: F, authors: Option<RewardAuthors>, extra_data: ExtraData) -> Self {
+		Self { inner, authors, extra_data }
+	}
+}
+
+impl<B, F> Environment<B> for RewardProposerFactory<F>
+where
+	B: BlockT,
+	F: Environment<B>,
+	F::CreateProposer: Send + 'static,
+{
+	type Proposer = RewardProposer<F::Proposer>;
+	type CreateProposer =
+		Pin<Box<dyn Future<Output = Result<Self::Proposer, Self::Error>> + Send + 'static>>;
+	type Error = F::Error;
+
+	fn init(&mut self, parent_header: &B::Header) -> Self::CreateProposer {
+		let number = UniqueSaturatedInto::<u64>::unique_saturated_into(*parent_header.number()) + 1;
+		let author = self.authors.as_ref().map(|authors| authors.author_at(number).clone());
+		let extra_data = self.extra_data.load();
+		Box::pin(
+			self.inner
+				.init(parent_header)
+				.map_ok(move |inner| RewardProposer { inner, author, extra_data }),
+		)
+	}
+}
+
+/// Proposer adding the block author to the inherent data, and the author and extra data to the
+/// digests, before delegating to `P`.
+pub struct RewardProposer<P> {
+	inner: P,
+	author: Option<AccountId>,
+	extra_data: Arc<Vec<u8>>,
+}
+
+impl<B, P> Proposer<B> for RewardProposer<P>
+where
+	B: BlockT,
+	P: Proposer<B>,
+{
+	type Error = P::Error;
+	type Transaction = P::Transaction;
+	type Proposal = P::Proposal;
+
+	fn propose(
+		self,
+		mut inherent_data: InherentData,
+		mut inherent_digests: DigestFor<B>,
+		max_duration: Duration,
+		record_proof: RecordProof,
+	) -> Self::Proposal {
+		if let Some(author) = &self.author {
+			if let Err(err) = inherent_data.put_data(block_reward::INHERENT_IDENTIFIER, author) {
+				warn!(target: "pow", "Unable to name the block author: {:?}", err);
+			}
+			// Replaces the digest of a worker configured with a fixed author.
+			inherent_digests
+				.logs
+				.retain(|item| item.as_pre_runtime().map_or(true, |(id, _)| id != POW_ENGINE_ID));
+			inherent_digests.push(DigestItem::PreRuntime(POW_ENGINE_ID, author.encode()));
+		}
+		if !self.extra_data.is_empty() {
+			inherent_digests.push(DigestItem::PreRuntime(EXTRA_DATA_ENGINE_ID, self.extra_data.to_vec()));
+		}
+
+		self.inner.propose(inherent_data, inherent_digests, max_duration, record_proof)
+	}
+}
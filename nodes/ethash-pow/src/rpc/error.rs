@@ -23,16 +23,71 @@ use sp_consensus::{Error as ConsensusError, ImportResult};
 use sp_blockchain::Error as BlockchainError;
 use sp_inherents::Error as InherentsError;
 use futures::channel::{oneshot, mpsc::SendError};
+use sp_core::{H256, U256};
+use ethpow::{SealError, MAX_EXTRA_DATA_LEN};
+use crate::types::SubmitResult;
 
 /// Error code for rpc
 mod codes {
+	/// The node is shutting down, the request wasn't handled.
 	pub const SERVER_SHUTTING_DOWN: i64 = 10_000;
+	/// The mining service channel is closed or full, the request may be retried later.
+	pub const MINING_SERVICE_UNAVAILABLE: i64 = 10_001;
+	/// The mining service dropped the request without answering it.
+	pub const REQUEST_CANCELLED: i64 = 10_002;
 	pub const BLOCK_IMPORT_FAILED: i64 = 11_000;
 	pub const EMPTY_TRANSACTION_POOL: i64 = 12_000;
 	pub const BLOCK_NOT_FOUND: i64 = 13_000;
 	pub const CONSENSUS_ERROR: i64 = 14_000;
 	pub const INHERENTS_ERROR: i64 = 15_000;
 	pub const BLOCKCHAIN_ERROR: i64 = 16_000;
+	/// The solution is for a work package that is no longer current.
+	pub const STALE_WORK: i64 = 17_000;
+	/// The solution doesn't meet the difficulty of the work package.
+	pub const LOW_DIFFICULTY_SHARE: i64 = 17_001;
+	/// The solution is for a work package of another ethash epoch.
+	pub const WRONG_EPOCH: i64 = 17_002;
+	/// The solution has already been submitted.
+	pub const DUPLICATE_SHARE: i64 = 17_003;
+	/// The node only accepts solutions signed by a registered worker key.
+	pub const UNSIGNED_SHARE: i64 = 17_004;
+	/// The signature of the solution doesn't match the key registered for the worker.
+	pub const INVALID_WORKER_SIGNATURE: i64 = 17_005;
+	/// The connection already has as many long-polls in flight as allowed.
+	pub const TOO_MANY_LONG_POLLS: i64 = 17_006;
+	/// No account is configured to receive the rewards of the blocks mined by this node.
+	pub const NO_AUTHOR: i64 = 17_007;
+	/// The submission id was already used for another solution of the same job.
+	pub const SUBMISSION_ID_CONFLICT: i64 = 17_008;
+	/// The node has fewer peers than required to mine.
+	pub const NOT_ENOUGH_PEERS: i64 = 17_009;
+	/// The miner control RPCs are disabled, or called with the wrong secret.
+	pub const MINER_CONTROL_DENIED: i64 = 17_010;
+	/// Mining was stopped with `miner_shutdown`.
+	pub const MINING_STOPPED: i64 = 17_011;
+	/// The nonce and mix digest of the solution don't check out against the ethash cache.
+	pub const INVALID_PROOF_OF_WORK: i64 = 17_012;
+	/// The nonce range requested with `ethash_leaseNonceRange` can't be leased.
+	pub const INVALID_NONCE_LEASE: i64 = 17_013;
+	/// The native runtime of the node can't author on top of the best block.
+	pub const CANNOT_AUTHOR: i64 = 17_014;
+	/// The node doesn't mine, it is a light client.
+	pub const NO_WORK_REQUIRED: i64 = 17_015;
+	/// The mining statistics couldn't be written to their file.
+	pub const STATS_DUMP_FAILED: i64 = 17_016;
+	/// The work is older than the `no_new_work_timeout` of `eth_getWork`.
+	pub const NO_NEW_WORK: i64 = 17_017;
+	pub const EXTRA_DATA_TOO_LONG: i64 = 17_018;
+	/// The solution is for a work package the node didn't hand out lately.
+	pub const WRONG_POW_HASH: i64 = 17_019;
+	/// The node is syncing, it seals no block until it catches up.
+	pub const NODE_SYNCING: i64 = 17_020;
+	/// The submission has no token, or one not configured with `--miner-token`.
+	pub const MINER_UNAUTHORIZED: i64 = 17_021;
+	/// The miner submitted more often than `--miner-rate-limit` allows.
+	pub const REQUEST_REJECTED_LIMIT: i64 = 17_022;
+	/// The nonce of the solution is outside the nonce partition of the node.
+	pub const NONCE_OUTSIDE_PARTITION: i64 = 17_023;
 	pub const UNKNOWN_ERROR: i64 = 20_000;
 }
 
@@ -63,11 +118,14 @@ pub enum Error {
 	#[display(fmt = "{}", _0)]
 	#[from(ignore)]
 	StringError(String),
-	///send error
-	#[display(fmt = "Consensus process is terminating")]
+	/// The mining service dropped the request without answering it
+	#[display(fmt = "Request cancelled by the mining service")]
 	Canceled(oneshot::Canceled),
-	///send error
-	#[display(fmt = "Consensus process is terminating")]
+	/// The node shut the mining service down before handling the request
+	#[display(fmt = "Server is shutting down")]
+	ServerShuttingDown,
+	/// The request could not be handed to the mining service
+	#[display(fmt = "Mining service unavailable: {}", _0)]
 	SendError(SendError),
 	/// no work
 	#[display(fmt = "No work now")]
@@ -79,6 +137,110 @@ pub enum Error {
 	//#[display(fmt = "Invalid ProofOfWork: expected: {}, found: {}", _0, _1)]
 	#[display(fmt = "Invalid ProofOfWork, Invalid Difficulty")]
 	InvalidProofOfWork,
+	#[display(fmt = "Share difficulty must be greater than zero")]
+	ZeroShareDifficulty,
+	/// The solution is for a work package that is no longer current
+	#[display(fmt = "Stale work: the work package is no longer current")]
+	StaleWork,
+	/// The solution is for a work package that wasn't handed out lately
+	#[display(fmt = "Wrong pow hash: no recent work package for {}", _0)]
+	#[from(ignore)]
+	WrongPowHash(H256),
+	/// The node is syncing, a block sealed now would be far behind the chain
+	#[display(fmt = "Node is syncing: solutions are sealed once it catches up")]
+	NodeSyncing,
+	/// The solution doesn't meet the difficulty of the work package
+	#[display(fmt = "Low difficulty share: found {}, expected {}", found, expected)]
+	#[from(ignore)]
+	LowDifficultyShare { expected: U256, found: U256 },
+	/// The solution is for a work package of another epoch
+	#[display(fmt = "Wrong epoch: work of epoch {}, current epoch is {}", found, expected)]
+	#[from(ignore)]
+	WrongEpoch { expected: u64, found: u64 },
+	/// The solution has already been submitted
+	#[display(fmt = "Duplicate share")]
+	DuplicateShare,
+	/// The solution isn't signed while the node requires signed shares
+	#[display(fmt = "Unsigned share: submit with ethash_submitSignedWork")]
+	UnsignedShare,
+	/// The solution isn't signed by the key registered for the worker
+	#[display(fmt = "Invalid signature for worker {}", _0)]
+	#[from(ignore)]
+	InvalidWorkerSignature(String),
+	/// The connection already has `_0` long-polls in flight
+	#[display(fmt = "Too many long-polls in flight on this connection, at most {}", _0)]
+	#[from(ignore)]
+	TooManyLongPolls(usize),
+	/// No mining author is configured
+	#[display(fmt = "No author: set the account rewarded for mined blocks with --author")]
+	NoAuthor,
+	/// The submission id was already used for another solution of the job
+	#[display(fmt = "Submission id {} already used for another solution", _0)]
+	#[from(ignore)]
+	SubmissionIdConflict(String),
+	/// Mining is paused until enough peers are connected
+	#[display(fmt = "Not enough peers: {} connected, mining needs {}", connected, required)]
+	#[from(ignore)]
+	NotEnoughPeers { connected: usize, required: usize },
+	/// No secret is configured for the miner control RPCs
+	#[display(fmt = "Miner control disabled: start the node with --miner-control-secret")]
+	MinerControlDisabled,
+	/// The miner control RPC was called with the wrong secret
+	#[display(fmt = "Invalid miner control secret")]
+	InvalidMinerControlSecret,
+	/// The node requires a miner token and none was given
+	#[display(fmt = "Miner token required: pass the token configured with --miner-token")]
+	MinerTokenRequired,
+	/// The miner token isn't one of those configured
+	#[display(fmt = "Invalid miner token")]
+	InvalidMinerToken,
+	/// The miner submitted more than `_0` times in the last second
+	#[display(fmt = "Request rejected: at most {} submissions per second", _0)]
+	#[from(ignore)]
+	RequestRejectedLimit(u32),
+	/// The nonce is searched by another node of the farm
+	#[display(fmt = "Nonce {} is outside the nonce partition of this node", _0)]
+	#[from(ignore)]
+	NonceOutsidePartition(u64),
+	/// Mining was stopped with `miner_shutdown`
+	#[display(fmt = "Mining stopped: call miner_restartWorker to resume")]
+	MiningStopped,
+	/// The solution fails the ethash verification
+	#[display(fmt = "Invalid proof of work: {}", _0)]
+	InvalidSeal(SealError),
+	/// The solution checked out but its block could not be imported
+	#[display(fmt = "Failed to import the block sealed for {}", _0)]
+	#[from(ignore)]
+	SealedBlockNotImported(H256),
+	/// A nonce range of no nonces was requested
+	#[display(fmt = "Nonce lease size must be greater than zero")]
+	EmptyNonceLease,
+	/// The nonces left for the job don't fill the requested range
+	#[display(fmt = "Not enough nonces left to lease for job {}", _0)]
+	#[from(ignore)]
+	NonceSpaceExhausted(H256),
+	/// The local executor can't author against the runtime of the best block
+	#[display(fmt = "Cannot author with the current runtime, the node probably needs an update: {}", _0)]
+	#[from(ignore)]
+	CannotAuthor(String),
+	/// The node is a light client, it issues no work and seals no block
+	#[display(fmt = "No work required: light clients don't mine, use a full node")]
+	NoWorkRequired,
+	/// The work hasn't changed for longer than the timeout of the request
+	#[display(fmt = "Work has not changed")]
+	NoNewWork,
+	/// The mining statistics couldn't be written
+	#[display(fmt = "Failed to dump the mining statistics: {}", _0)]
+	#[from(ignore)]
+	StatsDump(String),
+	/// The extra data doesn't fit in a block
+	#[display(fmt = "Extra data of {} bytes, at most {} fit in a block", _0, MAX_EXTRA_DATA_LEN)]
+	#[from(ignore)]
+	ExtraDataTooLong(usize),
+	/// The error an earlier attempt of a submission was answered with
+	#[display(fmt = "{}", message)]
+	#[from(ignore)]
+	Replayed { code: i64, message: String },
 	#[display(fmt = "Unimplemented")]
 	Unimplemented,
 	/// Some other error.
@@ -86,6 +248,68 @@ pub enum Error {
 }
 
 impl Error {
+	/// A solution for a work package that is no longer current.
+	pub fn stale_work() -> Self {
+		Error::StaleWork
+	}
+
+	/// A solution for `pow_hash`, which isn't one of the work packages handed out lately.
+	pub fn wrong_pow_hash(pow_hash: H256) -> Self {
+		Error::WrongPowHash(pow_hash)
+	}
+
+	/// A solution submitted while the node is syncing.
+	pub fn node_syncing() -> Self {
+		Error::NodeSyncing
+	}
+
+	/// A solution whose difficulty `found` is below the `expected` one.
+	pub fn low_difficulty_share(expected: U256, found: U256) -> Self {
+		Error::LowDifficultyShare { expected, found }
+	}
+
+	/// A solution for a work package of epoch `found` while the chain is at epoch `expected`.
+	pub fn wrong_epoch(expected: u64, found: u64) -> Self {
+		Error::WrongEpoch { expected, found }
+	}
+
+	/// A solution that has already been submitted.
+	pub fn duplicate_share() -> Self {
+		Error::DuplicateShare
+	}
+
+	/// Work requested from a node that doesn't know whom to reward for it.
+	pub fn no_author() -> Self {
+		Error::NoAuthor
+	}
+
+	/// Work older than the `no_new_work_timeout` of `eth_getWork`.
+	pub fn no_new_work() -> Self {
+		Error::NoNewWork
+	}
+
+	/// A mining RPC called on a light client.
+	pub fn no_work_required() -> Self {
+		Error::NoWorkRequired
+	}
+
+	/// What became of the solution rejected with this error, `None` for the errors unrelated to
+	/// the solution itself. Told by the code, so retried submissions are classified alike.
+	pub fn submit_result(&self) -> Option<SubmitResult> {
+		match self.to_code() {
+			codes::STALE_WORK | codes::WRONG_EPOCH => Some(SubmitResult::Stale),
+			codes::INVALID_PROOF_OF_WORK | codes::LOW_DIFFICULTY_SHARE => Some(SubmitResult::InvalidPow),
+			codes::WRONG_POW_HASH => Some(SubmitResult::WrongPowHash),
+			codes::NODE_SYNCING => Some(SubmitResult::NodeSyncing),
+			_ => None,
+		}
+	}
+
+	/// This error again, to answer a retried submission consistently.
+	pub fn replay(&self) -> Self {
+		Error::Replayed { code: self.to_code(), message: self.to_string() }
+	}
+
 	fn to_code(&self) -> i64 {
 		use Error::*;
 		match self {
@@ -95,7 +319,35 @@ impl Error {
 			ConsensusError(_) => codes::CONSENSUS_ERROR,
 			InherentError(_) => codes::INHERENTS_ERROR,
 			BlockchainError(_) => codes::BLOCKCHAIN_ERROR,
-			SendError(_) | Canceled(_) => codes::SERVER_SHUTTING_DOWN,
+			SendError(_) => codes::MINING_SERVICE_UNAVAILABLE,
+			Canceled(_) => codes::REQUEST_CANCELLED,
+			ServerShuttingDown => codes::SERVER_SHUTTING_DOWN,
+			StaleWork => codes::STALE_WORK,
+			WrongPowHash(_) => codes::WRONG_POW_HASH,
+			NodeSyncing => codes::NODE_SYNCING,
+			LowDifficultyShare { .. } => codes::LOW_DIFFICULTY_SHARE,
+			WrongEpoch { .. } => codes::WRONG_EPOCH,
+			DuplicateShare => codes::DUPLICATE_SHARE,
+			UnsignedShare => codes::UNSIGNED_SHARE,
+			InvalidWorkerSignature(_) => codes::INVALID_WORKER_SIGNATURE,
+			TooManyLongPolls(_) => codes::TOO_MANY_LONG_POLLS,
+			NoAuthor => codes::NO_AUTHOR,
+			SubmissionIdConflict(_) => codes::SUBMISSION_ID_CONFLICT,
+			NotEnoughPeers { .. } => codes::NOT_ENOUGH_PEERS,
+			MinerControlDisabled | InvalidMinerControlSecret => codes::MINER_CONTROL_DENIED,
+			MinerTokenRequired | InvalidMinerToken => codes::MINER_UNAUTHORIZED,
+			RequestRejectedLimit(_) => codes::REQUEST_REJECTED_LIMIT,
+			NonceOutsidePartition(_) => codes::NONCE_OUTSIDE_PARTITION,
+			MiningStopped => codes::MINING_STOPPED,
+			InvalidSeal(_) | InvalidProofOfWork | MismatchedH256SealElement => codes::INVALID_PROOF_OF_WORK,
+			SealedBlockNotImported(_) => codes::BLOCK_IMPORT_FAILED,
+			EmptyNonceLease | NonceSpaceExhausted(_) => codes::INVALID_NONCE_LEASE,
+			CannotAuthor(_) => codes::CANNOT_AUTHOR,
+			NoWorkRequired => codes::NO_WORK_REQUIRED,
+			StatsDump(_) => codes::STATS_DUMP_FAILED,
+			NoNewWork => codes::NO_NEW_WORK,
+			ExtraDataTooLong(_) => codes::EXTRA_DATA_TOO_LONG,
+			Replayed { code, .. } => *code,
 			_ => codes::UNKNOWN_ERROR
 		}
 	}
@@ -103,10 +355,15 @@ impl Error {
 
 impl std::convert::From<Error> for jsonrpc_core::Error {
 	fn from(error: Error) -> Self {
+		let data = match &error {
+			Error::SendError(err) if err.is_full() => Some("full".into()),
+			Error::SendError(_) => Some("closed".into()),
+			_ => None,
+		};
 		jsonrpc_core::Error {
 			code: jsonrpc_core::ErrorCode::ServerError(error.to_code()),
 			message: format!("{}", error),
-			data: None
+			data,
 		}
 	}
 }
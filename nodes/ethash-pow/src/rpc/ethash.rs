@@ -0,0 +1,1063 @@
+//! The mining RPCs: the `eth_` methods of ethash miners and the `ethash_`/`miner_` extensions.
+//!
+//! Every call is turned into an `EtheminerCmd` handed to the mining service, which answers it
+//! through the oneshot `Sender` of the command.
+
+use jsonrpc_core::Result;
+use jsonrpc_core::Error;
+use jsonrpc_derive::rpc;
+use crate::rpc::error::{Error as RpcError}; 
+use futures::{
+	channel::{mpsc, oneshot},
+	TryFutureExt,
+	FutureExt,
+	SinkExt
+};
+
+use runtime::{self, opaque::Block, AccountId, RuntimeApi};
+use sc_rpc_api::DenyUnsafe;
+use std::sync::Arc;
+use sp_core::{sr25519, Bytes, H256, U256};
+use crate::types::{MiningStatus, SubmitResult, SubmittedWork, Work, WorkEx};
+use crate::mining::{
+	extra_data, hashrate, mined_blocks, HashrateSnapshot, MinedBlockStatus, MinedBlocksPage, MinerAuth, MinerStat,
+	NonceLease,
+};
+use ethpow::{EpochStat, EpochStats};
+use sc_client_api::AuxStore;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::generic::BlockId;
+use ethereum_types::H64;
+
+/// Number of blocks `ethash_minedBlocks` and `pow_listAuthoredBlocks` return when not told.
+const DEFAULT_MINED_BLOCKS: u32 = 20;
+
+/// Future's type for jsonrpc
+type FutureResult<T> = Box<dyn jsonrpc_core::futures::Future<Item = T, Error = Error> + Send>;
+/// sender passed to the authorship task to report errors or successes.
+pub type Sender<T> = Option<oneshot::Sender<std::result::Result<T, RpcError>>>;
+
+/// Message sent to the background authorship task, usually by RPC.
+pub enum EtheminerCmd {
+	GetWork {
+		/// Fail with `no_new_work` when the work is older than this many seconds.
+		no_new_work_timeout: Option<u64>,
+		/// The worker asking, whose share difficulty override sets the share boundary, if named.
+		worker: Option<String>,
+		/// sender to report errors/success to the rpc.
+		sender: Sender<Work>,
+	},
+	/// Tells the engine to finalize the block with the supplied hash
+	SubmitWork {
+		/// The found nonce, big endian as miners send it.
+		nonce: H64,
+		/// The proof-of-work hash of header.
+		pow_hash: H256,
+		/// The mix digest of the solution.
+		mix_digest: H256,
+		/// The submitting worker and its signature over the solution, if signed.
+		signature: Option<(String, sr25519::Signature)>,
+		/// The worker name the solution is credited to, if given.
+		miner: Option<String>,
+		/// Id generated by the miner to match retries of the submission, if any.
+		submission_id: Option<String>,
+		/// sender to report the accepted solution, or errors, to the rpc.
+		sender: Sender<SubmittedWork>,
+	},
+	/// Lease a range of nonces of the current job.
+	LeaseNonceRange {
+		/// The pow hash of the current work package.
+		job_id: H256,
+		/// Number of nonces to lease.
+		size: u64,
+		/// sender to report errors/success to the rpc.
+		sender: Sender<NonceLease>,
+	},
+	/// Report the hashrate of a miner.
+	SubmitHashrate {
+		/// The hashrate, in hashes per second.
+		rate: U256,
+		/// Random id identifying the miner.
+		id: H256,
+		/// sender to report errors/success to the rpc.
+		sender: Sender<bool>,
+	},
+	/// Query the sum of the hashrates reported by the miners.
+	Hashrate {
+		/// sender to report errors/success to the rpc.
+		sender: Sender<U256>,
+	},
+	/// Query the shares and blocks credited to every miner.
+	MinerStats {
+		/// sender to report errors/success to the rpc.
+		sender: Sender<Vec<MinerStat>>,
+	},
+	/// Query the account rewarded for the blocks mined by the node.
+	Coinbase {
+		/// sender to report errors/success to the rpc.
+		sender: Sender<AccountId>,
+	},
+	/// Attach extra data to the blocks built from now on.
+	SetExtra {
+		/// The extra data, empty for none.
+		extra: Vec<u8>,
+		/// sender to report errors/success to the rpc.
+		sender: Sender<bool>,
+	},
+	/// Set the minimum fee of the transactions included in the next blocks built.
+	SetMinFee {
+		/// The fee per microsecond of weight.
+		fee: u64,
+		/// sender to report errors/success to the rpc.
+		sender: Sender<bool>,
+	},
+	/// Query whether the node is mining.
+	Mining {
+		/// sender to report errors/success to the rpc.
+		sender: Sender<bool>,
+	},
+	/// Pin the share difficulty of a worker, or clear the override with `None`.
+	SetShareDifficulty {
+		/// The worker name.
+		worker: String,
+		/// The share difficulty to assign.
+		difficulty: Option<U256>,
+		/// sender to report errors/success to the rpc.
+		sender: Sender<bool>,
+	},
+	/// Query the share difficulty pinned for a worker.
+	ShareDifficulty {
+		/// The worker name.
+		worker: String,
+		/// sender to report errors/success to the rpc.
+		sender: Sender<Option<U256>>,
+	},
+	/// Register the key signing the solutions of a worker, or remove it with `None`.
+	SetWorkerKey {
+		/// The worker name.
+		worker: String,
+		/// The public key of the worker.
+		key: Option<sr25519::Public>,
+		/// sender to report errors/success to the rpc.
+		sender: Sender<bool>,
+	},
+	/// Dump the mining statistics to their file.
+	DumpStats {
+		/// sender to report errors/success to the rpc.
+		sender: Sender<String>,
+	},
+	/// Stop issuing work, once the solutions of the work already issued are in.
+	Shutdown {
+		/// The secret configured with `--miner-control-secret`.
+		secret: String,
+		/// sender to report errors/success to the rpc.
+		sender: Sender<bool>,
+	},
+	/// Resume issuing work with fresh mining state.
+	RestartWorker {
+		/// The secret configured with `--miner-control-secret`.
+		secret: String,
+		/// sender to report errors/success to the rpc.
+		sender: Sender<bool>,
+	},
+	/// Resume issuing work, keeping the mining state.
+	StartMining {
+		/// Number of hashing threads of the node, unchanged if not given.
+		threads: Option<usize>,
+		/// sender to report errors/success to the rpc.
+		sender: Sender<bool>,
+	},
+	/// Stop issuing work, once the solutions of the work already issued are in.
+	StopMining {
+		/// sender to report errors/success to the rpc.
+		sender: Sender<bool>,
+	},
+	/// Set the number of hashing threads of the node.
+	SetThreads {
+		/// Number of threads, 0 for none.
+		threads: usize,
+		/// sender to report errors/success to the rpc.
+		sender: Sender<bool>,
+	},
+	/// Query whether and how the node mines.
+	MiningStatus {
+		/// sender to report errors/success to the rpc.
+		sender: Sender<MiningStatus>,
+	},
+	/// Query the difficulty of the block being mined.
+	Difficulty {
+		/// sender to report errors/success to the rpc.
+		sender: Sender<U256>,
+	},
+	/// Query the last block mined by the node.
+	LastMinedBlock {
+		/// sender to report errors/success to the rpc.
+		sender: Sender<Option<MinedBlockStatus>>,
+	},
+	/// Query how long the work has been built on the same parent block.
+	WorkAge {
+		/// sender to report errors/success to the rpc.
+		sender: Sender<Option<u64>>,
+	},
+}
+
+impl EtheminerCmd {
+	/// Answer the command with `error` without handling it.
+	pub fn reject(self, error: RpcError) {
+		match self {
+			EtheminerCmd::GetWork { mut sender, .. } => send_result(&mut sender, Err(error)),
+			EtheminerCmd::SubmitWork { mut sender, .. } => send_result(&mut sender, Err(error)),
+			EtheminerCmd::LeaseNonceRange { mut sender, .. } => send_result(&mut sender, Err(error)),
+			EtheminerCmd::SubmitHashrate { mut sender, .. } => send_result(&mut sender, Err(error)),
+			EtheminerCmd::Hashrate { mut sender, .. } => send_result(&mut sender, Err(error)),
+			EtheminerCmd::MinerStats { mut sender, .. } => send_result(&mut sender, Err(error)),
+			EtheminerCmd::Coinbase { mut sender, .. } => send_result(&mut sender, Err(error)),
+			EtheminerCmd::SetExtra { mut sender, .. } => send_result(&mut sender, Err(error)),
+			EtheminerCmd::SetMinFee { mut sender, .. } => send_result(&mut sender, Err(error)),
+			EtheminerCmd::Mining { mut sender, .. } => send_result(&mut sender, Err(error)),
+			EtheminerCmd::SetShareDifficulty { mut sender, .. } => send_result(&mut sender, Err(error)),
+			EtheminerCmd::ShareDifficulty { mut sender, .. } => send_result(&mut sender, Err(error)),
+			EtheminerCmd::SetWorkerKey { mut sender, .. } => send_result(&mut sender, Err(error)),
+			EtheminerCmd::DumpStats { mut sender, .. } => send_result(&mut sender, Err(error)),
+			EtheminerCmd::Shutdown { mut sender, .. } => send_result(&mut sender, Err(error)),
+			EtheminerCmd::RestartWorker { mut sender, .. } => send_result(&mut sender, Err(error)),
+			EtheminerCmd::StartMining { mut sender, .. } => send_result(&mut sender, Err(error)),
+			EtheminerCmd::StopMining { mut sender, .. } => send_result(&mut sender, Err(error)),
+			EtheminerCmd::SetThreads { mut sender, .. } => send_result(&mut sender, Err(error)),
+			EtheminerCmd::MiningStatus { mut sender, .. } => send_result(&mut sender, Err(error)),
+			EtheminerCmd::Difficulty { mut sender, .. } => send_result(&mut sender, Err(error)),
+			EtheminerCmd::LastMinedBlock { mut sender, .. } => send_result(&mut sender, Err(error)),
+			EtheminerCmd::WorkAge { mut sender, .. } => send_result(&mut sender, Err(error)),
+		}
+	}
+}
+
+#[rpc(server)]
+pub trait EthashRpc {
+	/// The current work package. With `no_new_work_timeout`, fails with the "no new work" error
+	/// when the package is older than that many seconds, so miners can tell the node is stalled.
+	#[rpc(name = "eth_getWork")]
+	fn eth_getWork(&self, no_new_work_timeout: Option<u64>) -> FutureResult<Work>;
+
+	/// The current work package as `eth_getWork` hands it out, with the ethash epoch, DAG size
+	/// and cache size it is mined with. Given a `worker`, the share boundary is the one of its
+	/// `ethash_setShareDifficulty` override.
+	#[rpc(name = "pow_getWorkEx")]
+	fn pow_getWorkEx(&self, no_new_work_timeout: Option<u64>, worker: Option<String>) -> FutureResult<WorkEx>;
+
+	/// Submit a solution. `token`, the last parameter, is required with `--miner-token`, the
+	/// solution is then credited to the miner of the token.
+	#[rpc(name = "eth_submitWork")]
+	fn eth_submitWork(&self, nonce: H64, pow_hash: H256, mix_digest: H256, token: Option<String>) -> FutureResult<bool>;
+
+	/// Submit a solution as `eth_submitWork`, answered with what became of it: accepted, stale,
+	/// invalid proof of work, wrong pow hash or node syncing. Other failures are errors.
+	#[rpc(name = "pow_submitWorkDetailed")]
+	fn pow_submitWorkDetailed(
+		&self,
+		nonce: H64,
+		pow_hash: H256,
+		mix_digest: H256,
+		token: Option<String>,
+	) -> FutureResult<SubmitResult>;
+
+	/// Submit a solution as `eth_submitWork`, answered with the hash of the block it sealed, or
+	/// `null` if it only counted as a share.
+	#[rpc(name = "pow_submitWorkEx")]
+	fn pow_submitWorkEx(
+		&self,
+		nonce: H64,
+		pow_hash: H256,
+		mix_digest: H256,
+		token: Option<String>,
+	) -> FutureResult<SubmittedWork>;
+
+	/// Submit a solution signed by `worker` over `nonce ‖ pow_hash`, required when the node runs
+	/// with `--require-signed-shares`.
+	#[rpc(name = "ethash_submitSignedWork")]
+	fn ethash_submitSignedWork(
+		&self,
+		nonce: H64,
+		pow_hash: H256,
+		mix_digest: H256,
+		worker: String,
+		signature: sr25519::Signature,
+		token: Option<String>,
+	) -> FutureResult<bool>;
+
+	/// Submit a solution for the job `job_id`, the pow hash of the work package, under an id
+	/// generated by the miner. A retry with the same job and submission ids gets the answer of
+	/// the first attempt.
+	#[rpc(name = "ethash_submitWorkWithId")]
+	fn ethash_submitWorkWithId(
+		&self,
+		job_id: H256,
+		submission_id: String,
+		nonce: H64,
+		mix_digest: H256,
+		token: Option<String>,
+	) -> FutureResult<bool>;
+
+	/// Lease `size` nonces of the job `job_id`, the pow hash of the current work package. The
+	/// ranges leased for a job never overlap, so the miners of a farm never search the same nonce.
+	#[rpc(name = "ethash_leaseNonceRange")]
+	fn ethash_leaseNonceRange(&self, job_id: H256, size: u64) -> FutureResult<NonceLease>;
+
+	/// Sum of the hashrates recently reported by the miners of this node.
+	#[rpc(name = "eth_hashrate")]
+	fn eth_hashrate(&self) -> FutureResult<U256>;
+
+	/// Report the hashrate of the miner `id`. `token`, the last parameter, is required with
+	/// `--miner-token`.
+	#[rpc(name = "eth_submitHashrate")]
+	fn eth_submitHashrate(&self, rate: U256, id: H256, token: Option<String>) -> FutureResult<bool>;
+
+	/// The solutions accepted from every miner, by worker name, with their hashrate estimated
+	/// from the shares of the last ten minutes. Solutions submitted without a worker name are
+	/// credited to `"unnamed"`.
+	#[rpc(name = "pow_minerStats")]
+	fn pow_minerStats(&self) -> FutureResult<Vec<MinerStat>>;
+
+	/// The account rewarded for the blocks mined by this node.
+	#[rpc(name = "eth_coinbase")]
+	fn eth_coinbase(&self) -> FutureResult<AccountId>;
+
+	/// Attach `extra`, at most 32 bytes like a pool tag, to the blocks mined from the next one
+	/// built on; empty to attach nothing.
+	#[rpc(name = "pow_setExtra")]
+	fn pow_setExtra(&self, extra: Bytes) -> FutureResult<bool>;
+
+	/// The extra data attached to the block `hash` by its miner, `None` if the block has none or
+	/// is unknown.
+	#[rpc(name = "pow_getBlockExtra")]
+	fn pow_getBlockExtra(&self, hash: H256) -> Result<Option<Bytes>>;
+
+	/// Whether the node is an authority with a block to mine and workers connected.
+	#[rpc(name = "eth_mining")]
+	fn eth_mining(&self) -> FutureResult<bool>;
+
+	/// Pin the share difficulty of `worker`, overriding `--pool-share-difficulty` for its jobs
+	/// and shares; `None` clears the override.
+	#[rpc(name = "ethash_setShareDifficulty")]
+	fn ethash_setShareDifficulty(&self, worker: String, difficulty: Option<U256>) -> FutureResult<bool>;
+
+	/// The share difficulty pinned for `worker`, `None` if `--pool-share-difficulty` applies.
+	#[rpc(name = "ethash_getShareDifficulty")]
+	fn ethash_getShareDifficulty(&self, worker: String) -> FutureResult<Option<U256>>;
+
+	/// Register the key `worker` signs its solutions with; `None` removes it.
+	#[rpc(name = "ethash_setWorkerKey")]
+	fn ethash_setWorkerKey(&self, worker: String, key: Option<sr25519::Public>) -> FutureResult<bool>;
+
+	/// Hashrate snapshots taken between the `from` and `to` unix timestamps, oldest first.
+	#[rpc(name = "ethash_hashrateHistory")]
+	fn ethash_hashrateHistory(&self, from: u64, to: u64) -> Result<Vec<HashrateSnapshot>>;
+
+	/// Seal verification statistics of the ethash epochs seen since the node started, oldest
+	/// first.
+	#[rpc(name = "ethash_epochStats")]
+	fn ethash_epochStats(&self) -> Result<Vec<EpochStat>>;
+
+	/// The `count` latest blocks mined by this node, 20 by default, newest first, with whether
+	/// they are still on the chain.
+	#[rpc(name = "ethash_minedBlocks")]
+	fn ethash_minedBlocks(&self, count: Option<u32>) -> Result<Vec<MinedBlockStatus>>;
+
+	/// A page of `count` blocks mined by this node, 20 by default, after the `start` newest ones,
+	/// newest first: with the difficulty and nonce of their seal, whether they are still on the
+	/// chain, and the number of mined blocks remembered.
+	#[rpc(name = "pow_listAuthoredBlocks")]
+	fn pow_listAuthoredBlocks(&self, start: Option<u32>, count: Option<u32>) -> Result<MinedBlocksPage>;
+
+	/// The block `hash` mined by this node, with whether it is still on the chain. `None` if the
+	/// node didn't mine it, or mined it too long ago to remember.
+	#[rpc(name = "ethash_blockStatus")]
+	fn ethash_blockStatus(&self, hash: H256) -> Result<Option<MinedBlockStatus>>;
+
+	/// Only include the transactions paying at least `fee`, tip included, per microsecond of
+	/// weight in the next blocks built, like `--min-fee`. 0 includes every transaction.
+	#[rpc(name = "miner_setMinFee")]
+	fn miner_setMinFee(&self, fee: u64) -> FutureResult<bool>;
+
+	/// Dump the mining statistics to the `--mining-stats-file` as JSON, like `SIGUSR1` does.
+	/// Returns the path of the file.
+	#[rpc(name = "miner_dumpStats")]
+	fn miner_dumpStats(&self) -> FutureResult<String>;
+
+	/// Stop issuing work, without stopping the node. Solutions for the work already issued are
+	/// still accepted, so the block being mined can be finished. Needs the secret configured
+	/// with `--miner-control-secret`.
+	#[rpc(name = "miner_shutdown")]
+	fn miner_shutdown(&self, secret: String) -> FutureResult<bool>;
+
+	/// Issue work again after `miner_shutdown`, starting over with fresh mining state. Needs the
+	/// secret configured with `--miner-control-secret`.
+	#[rpc(name = "miner_restartWorker")]
+	fn miner_restartWorker(&self, secret: String) -> FutureResult<bool>;
+
+	/// Issue work again after `pow_stopMining`, keeping the mining state, and hash on `threads`
+	/// threads of the node if given.
+	#[rpc(name = "pow_startMining")]
+	fn pow_startMining(&self, threads: Option<u32>) -> FutureResult<bool>;
+
+	/// Stop issuing work, without stopping the node, like `miner_shutdown` but without the
+	/// secret. Solutions for the work already issued are still accepted.
+	#[rpc(name = "pow_stopMining")]
+	fn pow_stopMining(&self) -> FutureResult<bool>;
+
+	/// Hash on `threads` threads of the node, like `--mining-threads`; 0 leaves the hashing to
+	/// external miners.
+	#[rpc(name = "pow_setThreads")]
+	fn pow_setThreads(&self, threads: u32) -> FutureResult<bool>;
+
+	/// Whether the node mines, on how many threads, and the work package being mined.
+	#[rpc(name = "pow_miningStatus")]
+	fn pow_miningStatus(&self) -> FutureResult<MiningStatus>;
+
+	/// The difficulty of the block being mined.
+	#[rpc(name = "pow_difficulty")]
+	fn pow_difficulty(&self) -> FutureResult<U256>;
+
+	/// The last block mined by this node, with whether it is still on the chain. `None` if the
+	/// node mined none yet.
+	#[rpc(name = "pow_lastMinedBlock")]
+	fn pow_lastMinedBlock(&self) -> FutureResult<Option<MinedBlockStatus>>;
+
+	/// Number of seconds the work has been built on the same parent block, `None` before any
+	/// work. A growing age tells the node stopped importing blocks; past `--max-work-age`, no
+	/// work is handed out.
+	#[rpc(name = "pow_workAge")]
+	fn pow_workAge(&self) -> FutureResult<Option<u64>>;
+}
+
+/// A struct that implements the `EthashRpc`
+pub struct EthashData<C> {
+	client: Arc<C>,
+	command_sink: mpsc::Sender<EtheminerCmd>,
+	deny_unsafe: DenyUnsafe,
+	epoch_stats: EpochStats,
+	auth: MinerAuth,
+}
+
+impl<C> EthashData<C> {
+	/// Create new `EthashData` instance with the given reference to the client. The submissions
+	/// are checked against `auth` before they are handed to the mining service.
+	pub fn new(
+		client: Arc<C>,
+		command_sink: mpsc::Sender<EtheminerCmd>,
+		deny_unsafe: DenyUnsafe,
+		epoch_stats: EpochStats,
+		auth: MinerAuth,
+	) -> Self {
+		Self {
+			client,
+			command_sink,
+			deny_unsafe,
+			epoch_stats,
+			auth,
+		}
+	}
+}
+
+impl<C> EthashRpc for EthashData<C>
+where
+	C: AuxStore + HeaderBackend<Block> + Send + Sync + 'static,
+{
+	fn eth_getWork(&self, no_new_work_timeout: Option<u64>) -> FutureResult<Work> {
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::GetWork {
+				no_new_work_timeout,
+				worker: None,
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn pow_getWorkEx(&self, no_new_work_timeout: Option<u64>, worker: Option<String>) -> FutureResult<WorkEx> {
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::GetWork {
+				no_new_work_timeout,
+				worker,
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?.map(WorkEx::from)
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn eth_submitWork(&self, nonce: H64, pow_hash: H256, mix_digest: H256, token: Option<String>) -> FutureResult<bool> {
+		let miner = match self.auth.check(token.as_deref()) {
+			Ok(miner) => miner,
+			Err(err) => return Box::new(jsonrpc_core::futures::future::err(err.into())),
+		};
+
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::SubmitWork {
+				nonce,
+				pow_hash,
+				mix_digest,
+				signature: None,
+				miner,
+				submission_id: None,
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?.map(|_| true)
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn pow_submitWorkDetailed(
+		&self,
+		nonce: H64,
+		pow_hash: H256,
+		mix_digest: H256,
+		token: Option<String>,
+	) -> FutureResult<SubmitResult> {
+		let miner = match self.auth.check(token.as_deref()) {
+			Ok(miner) => miner,
+			Err(err) => return Box::new(jsonrpc_core::futures::future::err(err.into())),
+		};
+
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::SubmitWork {
+				nonce,
+				pow_hash,
+				mix_digest,
+				signature: None,
+				miner,
+				submission_id: None,
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			SubmitResult::from_submission(receiver.await?)
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn pow_submitWorkEx(
+		&self,
+		nonce: H64,
+		pow_hash: H256,
+		mix_digest: H256,
+		token: Option<String>,
+	) -> FutureResult<SubmittedWork> {
+		let miner = match self.auth.check(token.as_deref()) {
+			Ok(miner) => miner,
+			Err(err) => return Box::new(jsonrpc_core::futures::future::err(err.into())),
+		};
+
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::SubmitWork {
+				nonce,
+				pow_hash,
+				mix_digest,
+				signature: None,
+				miner,
+				submission_id: None,
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn ethash_submitSignedWork(
+		&self,
+		nonce: H64,
+		pow_hash: H256,
+		mix_digest: H256,
+		worker: String,
+		signature: sr25519::Signature,
+		token: Option<String>,
+	) -> FutureResult<bool> {
+		if let Err(err) = self.auth.check(token.as_deref()) {
+			return Box::new(jsonrpc_core::futures::future::err(err.into()));
+		}
+
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::SubmitWork {
+				nonce,
+				pow_hash,
+				mix_digest,
+				signature: Some((worker.clone(), signature)),
+				miner: Some(worker),
+				submission_id: None,
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?.map(|_| true)
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn ethash_submitWorkWithId(
+		&self,
+		job_id: H256,
+		submission_id: String,
+		nonce: H64,
+		mix_digest: H256,
+		token: Option<String>,
+	) -> FutureResult<bool> {
+		let miner = match self.auth.check(token.as_deref()) {
+			Ok(miner) => miner,
+			Err(err) => return Box::new(jsonrpc_core::futures::future::err(err.into())),
+		};
+
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::SubmitWork {
+				nonce,
+				pow_hash: job_id,
+				mix_digest,
+				signature: None,
+				miner,
+				submission_id: Some(submission_id),
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?.map(|_| true)
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn ethash_leaseNonceRange(&self, job_id: H256, size: u64) -> FutureResult<NonceLease> {
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::LeaseNonceRange {
+				job_id,
+				size,
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn eth_hashrate(&self) -> FutureResult<U256> {
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::Hashrate {
+				sender: Some(sender),
+			};
+			// No mining service, no miners reporting to this node.
+			if sink.send(command).await.is_err() {
+				return Ok(U256::zero());
+			}
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn eth_submitHashrate(&self, rate: U256, id: H256, token: Option<String>) -> FutureResult<bool> {
+		if let Err(err) = self.auth.check(token.as_deref()) {
+			return Box::new(jsonrpc_core::futures::future::err(err.into()));
+		}
+
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::SubmitHashrate {
+				rate,
+				id,
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn pow_minerStats(&self) -> FutureResult<Vec<MinerStat>> {
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::MinerStats {
+				sender: Some(sender),
+			};
+			// No mining service, no miners submitting to this node.
+			if sink.send(command).await.is_err() {
+				return Ok(Vec::new());
+			}
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn eth_coinbase(&self) -> FutureResult<AccountId> {
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::Coinbase {
+				sender: Some(sender),
+			};
+			// The mining service only runs on authorities, other nodes reward no one.
+			if sink.send(command).await.is_err() {
+				return Err(RpcError::no_author());
+			}
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn pow_setExtra(&self, extra: Bytes) -> FutureResult<bool> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(jsonrpc_core::futures::future::err(err.into()));
+		}
+
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::SetExtra {
+				extra: extra.0,
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn pow_getBlockExtra(&self, hash: H256) -> Result<Option<Bytes>> {
+		let header = self.client.header(BlockId::Hash(hash))
+			.map_err(|err| Error::from(RpcError::from(err)))?;
+		Ok(header.as_ref().and_then(extra_data::block_extra).map(Bytes))
+	}
+
+	fn eth_mining(&self) -> FutureResult<bool> {
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::Mining {
+				sender: Some(sender),
+			};
+			// The mining service only runs on authorities, other nodes never mine.
+			if sink.send(command).await.is_err() {
+				return Ok(false);
+			}
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn ethash_setShareDifficulty(&self, worker: String, difficulty: Option<U256>) -> FutureResult<bool> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(jsonrpc_core::futures::future::err(err.into()));
+		}
+
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::SetShareDifficulty {
+				worker,
+				difficulty,
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn ethash_getShareDifficulty(&self, worker: String) -> FutureResult<Option<U256>> {
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::ShareDifficulty {
+				worker,
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn ethash_setWorkerKey(&self, worker: String, key: Option<sr25519::Public>) -> FutureResult<bool> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(jsonrpc_core::futures::future::err(err.into()));
+		}
+
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::SetWorkerKey {
+				worker,
+				key,
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn ethash_hashrateHistory(&self, from: u64, to: u64) -> Result<Vec<HashrateSnapshot>> {
+		hashrate::history(&*self.client, from, to)
+			.map_err(|err| Error::from(RpcError::from(err)))
+	}
+
+	fn ethash_epochStats(&self) -> Result<Vec<EpochStat>> {
+		Ok(self.epoch_stats.get())
+	}
+
+	fn ethash_minedBlocks(&self, count: Option<u32>) -> Result<Vec<MinedBlockStatus>> {
+		mined_blocks::latest::<Block, _>(&*self.client, count.unwrap_or(DEFAULT_MINED_BLOCKS) as usize)
+			.map_err(|err| Error::from(RpcError::from(err)))
+	}
+
+	fn pow_listAuthoredBlocks(&self, start: Option<u32>, count: Option<u32>) -> Result<MinedBlocksPage> {
+		let count = count.unwrap_or(DEFAULT_MINED_BLOCKS) as usize;
+		mined_blocks::page::<Block, _>(&*self.client, start.unwrap_or_default() as usize, count)
+			.map_err(|err| Error::from(RpcError::from(err)))
+	}
+
+	fn ethash_blockStatus(&self, hash: H256) -> Result<Option<MinedBlockStatus>> {
+		mined_blocks::find::<Block, _>(&*self.client, hash)
+			.map_err(|err| Error::from(RpcError::from(err)))
+	}
+
+	fn miner_setMinFee(&self, fee: u64) -> FutureResult<bool> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(jsonrpc_core::futures::future::err(err.into()));
+		}
+
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::SetMinFee {
+				fee,
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn miner_dumpStats(&self) -> FutureResult<String> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(jsonrpc_core::futures::future::err(err.into()));
+		}
+
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::DumpStats {
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn miner_shutdown(&self, secret: String) -> FutureResult<bool> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(jsonrpc_core::futures::future::err(err.into()));
+		}
+
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::Shutdown {
+				secret,
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn miner_restartWorker(&self, secret: String) -> FutureResult<bool> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(jsonrpc_core::futures::future::err(err.into()));
+		}
+
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::RestartWorker {
+				secret,
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn pow_startMining(&self, threads: Option<u32>) -> FutureResult<bool> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(jsonrpc_core::futures::future::err(err.into()));
+		}
+
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::StartMining {
+				threads: threads.map(|threads| threads as usize),
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn pow_stopMining(&self) -> FutureResult<bool> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(jsonrpc_core::futures::future::err(err.into()));
+		}
+
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::StopMining {
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn pow_setThreads(&self, threads: u32) -> FutureResult<bool> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(jsonrpc_core::futures::future::err(err.into()));
+		}
+
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::SetThreads {
+				threads: threads as usize,
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn pow_miningStatus(&self) -> FutureResult<MiningStatus> {
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::MiningStatus {
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn pow_difficulty(&self) -> FutureResult<U256> {
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::Difficulty {
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn pow_lastMinedBlock(&self) -> FutureResult<Option<MinedBlockStatus>> {
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::LastMinedBlock {
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn pow_workAge(&self) -> FutureResult<Option<u64>> {
+		let mut sink = self.command_sink.clone();
+		let future = async move {
+			let (sender, receiver) = oneshot::channel();
+			let command = EtheminerCmd::WorkAge {
+				sender: Some(sender),
+			};
+			sink.send(command).await?;
+			receiver.await?
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+}
+
+/// report any errors or successes encountered by the authorship task back
+/// to the rpc
+pub fn send_result<T: std::fmt::Debug>(
+	sender: &mut Sender<T>,
+	result: std::result::Result<T, RpcError>
+) {
+	if let Some(sender) = sender.take() {
+		if let Err(err) = sender.send(result) {
+			log::warn!("Server is shutting down: {:?}", err)
+		}
+	} else {
+		// instant seal doesn't report errors over rpc, simply log them.
+		match result {
+			Ok(r) => log::info!("Instant Seal success: {:?}", r),
+			Err(e) => log::error!("Instant Seal encountered an error: {}", e)
+		}
+	}
+}
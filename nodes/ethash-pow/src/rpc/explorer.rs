@@ -0,0 +1,85 @@
+//! RPC methods for block explorers, bundling in one call what they would otherwise gather with
+//! several requests and their own seal decoding.
+
+use jsonrpc_core::{Error, Result};
+use jsonrpc_derive::rpc;
+use runtime::{opaque::{Block, Header}, AccountId, BlockNumber};
+use sc_client_api::AuxStore;
+use sc_consensus_pow::PowAux;
+use serde_derive::{Deserialize, Serialize};
+use sp_blockchain::HeaderBackend;
+use sp_core::{H256, U256};
+use sp_runtime::{generic::BlockId, traits::Header as HeaderT};
+use std::sync::Arc;
+use crate::rpc::error::Error as RpcError;
+use crate::types::{seal_and_author, SealInfo};
+
+/// A block designated by its hash or its number.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(untagged)]
+pub enum HashOrNumber {
+	/// The hash of the block.
+	Hash(H256),
+	/// The number of the block on the best chain.
+	Number(BlockNumber),
+}
+
+/// Everything an explorer shows about a mined block.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockDetails {
+	/// The block hash.
+	pub hash: H256,
+	/// The block header.
+	#[serde(flatten)]
+	pub header: Header,
+	/// The decoded seal, `None` for the genesis block.
+	pub seal: Option<SealInfo>,
+	/// Sum of the difficulties of the chain up to and including this block.
+	pub total_difficulty: U256,
+	/// The author named in the PoW pre-runtime digest.
+	pub author: Option<AccountId>,
+}
+
+#[rpc]
+pub trait ExplorerApi {
+	/// Header, decoded seal, boundary, total difficulty and author of a block.
+	#[rpc(name = "ethashExplorer_getBlockDetails")]
+	fn block_details(&self, block: HashOrNumber) -> Result<Option<BlockDetails>>;
+}
+
+/// A struct that implements the `ExplorerApi`.
+pub struct Explorer<C> {
+	client: Arc<C>,
+}
+
+impl<C> Explorer<C> {
+	/// Create new `Explorer` instance with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client }
+	}
+}
+
+impl<C> ExplorerApi for Explorer<C>
+where
+	C: HeaderBackend<Block> + AuxStore + Send + Sync + 'static,
+{
+	fn block_details(&self, block: HashOrNumber) -> Result<Option<BlockDetails>> {
+		let id = match block {
+			HashOrNumber::Hash(hash) => BlockId::Hash(hash),
+			HashOrNumber::Number(number) => BlockId::Number(number),
+		};
+		let header = match self.client.header(id).map_err(|err| Error::from(RpcError::from(err)))? {
+			Some(header) => header,
+			None => return Ok(None),
+		};
+
+		let hash = header.hash();
+		let total_difficulty = PowAux::<U256>::read::<_, Block>(&*self.client, &hash)
+			.map_err(|err| Error::from(RpcError::StringError(format!("{:?}", err))))?
+			.total_difficulty;
+		let (seal, author) = seal_and_author(&header);
+
+		Ok(Some(BlockDetails { hash, header, seal, total_difficulty, author }))
+	}
+}
@@ -0,0 +1,88 @@
+//! The mining RPCs of light clients.
+//!
+//! A light client never authors, so it has no mining service to hand commands to. It still
+//! answers the read-only part of the mining RPCs, so a dashboard pointed at it doesn't break:
+//! `eth_getWork` and `eth_submitWork` fail with a "no work required" error, and the statistics
+//! report a node that doesn't mine.
+
+use jsonrpc_core::{Error, Result};
+use jsonrpc_derive::rpc;
+use crate::rpc::error::{Error as RpcError};
+use crate::types::Work;
+use crate::mining::{hashrate, HashrateSnapshot};
+use ethereum_types::H64;
+use ethpow::{EpochStat, EpochStats};
+use sc_client_api::AuxStore;
+use sp_core::{H256, U256};
+use std::sync::Arc;
+
+#[rpc(server)]
+pub trait EthashLightRpc {
+	/// Always fails, light clients issue no work.
+	#[rpc(name = "eth_getWork")]
+	fn eth_getWork(&self, _: Option<u64>) -> Result<Work>;
+
+	/// Always fails, light clients seal no blocks.
+	#[rpc(name = "eth_submitWork")]
+	fn eth_submitWork(&self, nonce: H64, pow_hash: H256, mix_digest: H256, token: Option<String>) -> Result<bool>;
+
+	/// Always zero, no miner reports to a light client.
+	#[rpc(name = "eth_hashrate")]
+	fn eth_hashrate(&self) -> Result<U256>;
+
+	/// Always false.
+	#[rpc(name = "eth_mining")]
+	fn eth_mining(&self) -> Result<bool>;
+
+	/// Hashrate snapshots taken between the `from` and `to` unix timestamps, oldest first.
+	#[rpc(name = "ethash_hashrateHistory")]
+	fn ethash_hashrateHistory(&self, from: u64, to: u64) -> Result<Vec<HashrateSnapshot>>;
+
+	/// Seal verification statistics of the ethash epochs seen since the node started, oldest
+	/// first.
+	#[rpc(name = "ethash_epochStats")]
+	fn ethash_epochStats(&self) -> Result<Vec<EpochStat>>;
+}
+
+/// A struct that implements the `EthashLightRpc`
+pub struct EthashLightData<C> {
+	client: Arc<C>,
+	epoch_stats: EpochStats,
+}
+
+impl<C> EthashLightData<C> {
+	/// Create new `EthashLightData` instance with the given reference to the client.
+	pub fn new(client: Arc<C>, epoch_stats: EpochStats) -> Self {
+		Self { client, epoch_stats }
+	}
+}
+
+impl<C> EthashLightRpc for EthashLightData<C>
+where
+	C: AuxStore + Send + Sync + 'static,
+{
+	fn eth_getWork(&self, _: Option<u64>) -> Result<Work> {
+		Err(RpcError::no_work_required().into())
+	}
+
+	fn eth_submitWork(&self, _: H64, _: H256, _: H256, _: Option<String>) -> Result<bool> {
+		Err(RpcError::no_work_required().into())
+	}
+
+	fn eth_hashrate(&self) -> Result<U256> {
+		Ok(U256::zero())
+	}
+
+	fn eth_mining(&self) -> Result<bool> {
+		Ok(false)
+	}
+
+	fn ethash_hashrateHistory(&self, from: u64, to: u64) -> Result<Vec<HashrateSnapshot>> {
+		hashrate::history(&*self.client, from, to)
+			.map_err(|err| Error::from(RpcError::from(err)))
+	}
+
+	fn ethash_epochStats(&self) -> Result<Vec<EpochStat>> {
+		Ok(self.epoch_stats.get())
+	}
+}
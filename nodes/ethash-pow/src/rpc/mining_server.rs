@@ -0,0 +1,31 @@
+//! A dedicated HTTP listener for the mining RPCs.
+//!
+//! Farms usually open the mining endpoint to their rigs only and the wallet-facing RPC to their
+//! users, which can't be done while both share a port. With `--mining-rpc-port`, the methods of
+//! `EthashRpc` are served on their own listener and left out of the general RPC. The work
+//! subscriptions need a WebSocket session and stay on the general WebSocket RPC.
+
+use futures::future;
+use jsonrpc_core::IoHandler;
+use jsonrpc_http_server::ServerBuilder;
+use log::{info, warn};
+use std::net::SocketAddr;
+
+/// Threads answering the mining RPCs, which only hand commands to the mining service.
+const SERVER_THREADS: usize = 2;
+
+/// Serve `io` over HTTP on `addr` until the node shuts down.
+pub async fn run_mining_rpc_server(addr: SocketAddr, io: IoHandler) {
+	let server = match ServerBuilder::new(io).threads(SERVER_THREADS).start_http(&addr) {
+		Ok(server) => server,
+		Err(err) => {
+			warn!(target: "pow", "Failed to start the mining RPC server on {}: {:?}", addr, err);
+			return;
+		}
+	};
+	info!(target: "pow", "Mining RPC listening on http://{}", addr);
+
+	// The server closes when dropped, along with this task at shutdown.
+	let _server = server;
+	future::pending::<()>().await
+}
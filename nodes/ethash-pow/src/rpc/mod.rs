@@ -1,13 +1,31 @@
 
 mod rpc;
-pub mod ethash_rpc;
+pub mod ethash;
 pub mod error;
+pub mod explorer;
+#[cfg(feature = "light")]
+pub mod light;
+pub mod mining_server;
+pub mod work_pubsub;
 
 pub use self::rpc::{
+    DevDeps,
     FullDeps,
+    GrandpaDeps,
+    create_dev,
     create_full,
+    extend_with_mining,
 };
 
-pub use self::ethash_rpc::{
+#[cfg(feature = "light")]
+pub use self::rpc::{
+    LightDeps,
+    create_light,
+};
+
+pub use self::ethash::{
+    send_result,
+    EthashData,
+    EthashRpc,
     EtheminerCmd,
 };
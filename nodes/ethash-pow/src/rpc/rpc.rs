@@ -3,17 +3,44 @@
 use std::sync::Arc;
 
 use futures::channel::mpsc::Sender;
-use runtime::{opaque::Block, Hash};
+use runtime::{opaque::Block, AccountId, Balance, BlockNumber, Hash};
 
-use crate::rpc::ethash_rpc::EtheminerCmd;
+use crate::mining::{MinerAuth, WorkRpcConfig, WorkSnapshot};
+use ethpow::EpochStats;
+use crate::rpc::{EthashData, EthashRpc, EtheminerCmd};
 pub use sc_rpc_api::DenyUnsafe;
+use sc_client_api::{backend::StateBackend, AuxStore};
+use sc_consensus_manual_seal::{
+	rpc::{ManualSeal, ManualSealApi},
+	EngineCommand,
+};
+use sc_finality_grandpa::{
+	FinalityProofProvider, GrandpaJustificationStream, SharedAuthoritySet, SharedVoterState,
+};
+use sc_finality_grandpa_rpc::GrandpaRpcHandler;
+use sc_rpc::SubscriptionTaskExecutor;
 use sp_api::ProvideRuntimeApi;
 use sp_block_builder::BlockBuilder;
 use sp_blockchain::{Error as BlockChainError, HeaderBackend, HeaderMetadata};
+use sp_runtime::traits::HashFor;
 use sp_transaction_pool::TransactionPool;
 
+/// Dependencies of the GRANDPA RPCs.
+pub struct GrandpaDeps<B> {
+	/// Voting round info.
+	pub shared_voter_state: SharedVoterState,
+	/// Authority set info.
+	pub shared_authority_set: SharedAuthoritySet<Hash, BlockNumber>,
+	/// Receives notifications about justification events from GRANDPA.
+	pub justification_stream: GrandpaJustificationStream<Block>,
+	/// Executor to drive the subscription manager in the GRANDPA RPC handler.
+	pub subscription_executor: SubscriptionTaskExecutor,
+	/// Finality proof provider.
+	pub finality_provider: Arc<FinalityProofProvider<B, Block>>,
+}
+
 /// Full client dependencies.
-pub struct FullDeps<C, P> {
+pub struct FullDeps<C, P, B> {
 	/// The client instance to use.
 	pub client: Arc<C>,
 	/// Transaction pool instance.
@@ -21,36 +48,189 @@ pub struct FullDeps<C, P> {
 	/// Whether to deny unsafe calls
 	pub deny_unsafe: DenyUnsafe,
 	/// A command stream to send authoring commands to manual seal consensus engine
-	pub command_sink: Sender<EtheminerCmd<Hash>>,
+	pub command_sink: Sender<EtheminerCmd>,
+	/// The work package notified to the subscribed miners.
+	pub work: WorkSnapshot,
+	/// Connection management of the work long-polls and subscriptions.
+	pub work_rpc: WorkRpcConfig,
+	/// Seal verification statistics of the ethash epochs.
+	pub epoch_stats: EpochStats,
+	/// Whether the mining RPCs are served on their own listener instead.
+	pub dedicated_mining_rpc: bool,
+	/// The tokens and rate limit the solutions and hashrates are submitted under.
+	pub miner_auth: MinerAuth,
+	/// The finality of the mined blocks.
+	pub grandpa: GrandpaDeps<B>,
 }
 
 /// Instantiate all full RPC extensions.
-pub fn create_full<C, P>(deps: FullDeps<C, P>) -> jsonrpc_core::IoHandler<sc_rpc::Metadata>
+pub fn create_full<C, P, B>(
+	deps: FullDeps<C, P, B>,
+	subscription_executor: SubscriptionTaskExecutor,
+) -> jsonrpc_core::IoHandler<sc_rpc::Metadata>
 where
 	C: ProvideRuntimeApi<Block>,
 	C: HeaderBackend<Block> + HeaderMetadata<Block, Error = BlockChainError> + 'static,
-	C: Send + Sync + 'static,
+	C: AuxStore + Send + Sync + 'static,
 	C::Api: BlockBuilder<Block>,
 	C::Api: sum_storage_runtime_api::SumStorageApi<Block>,
+	C::Api: author_history_runtime_api::AuthorHistoryApi<Block, BlockNumber, AccountId>,
+	C::Api: block_reward_runtime_api::BlockRewardApi<Block, BlockNumber, Balance>,
 	P: TransactionPool + 'static,
+	B: sc_client_api::Backend<Block> + Send + Sync + 'static,
+	B::State: StateBackend<HashFor<Block>>,
 {
 	let mut io = jsonrpc_core::IoHandler::default();
 	let FullDeps {
 		command_sink,
 		client,
+		deny_unsafe,
+		work,
+		work_rpc,
+		epoch_stats,
+		dedicated_mining_rpc,
+		miner_auth,
+		grandpa,
 		..
 	} = deps;
+	let GrandpaDeps {
+		shared_voter_state,
+		shared_authority_set,
+		justification_stream,
+		subscription_executor: grandpa_executor,
+		finality_provider,
+	} = grandpa;
+
+	extend_with_chain_data(&mut io, client.clone());
+
+	// Add a EthashRpc RPC
+	if !dedicated_mining_rpc {
+		extend_with_mining(&mut io, client, command_sink, deny_unsafe, epoch_stats, miner_auth);
+	}
+
+	// Work notifications for the miners long-polling or subscribed over WebSocket.
+	io.extend_with(crate::rpc::work_pubsub::WorkPubSubApi::to_delegate(
+		crate::rpc::work_pubsub::WorkPubSub::new(work, work_rpc, subscription_executor),
+	));
+
+	// Rounds, justifications and finality proofs of the blocks finalized by GRANDPA.
+	io.extend_with(sc_finality_grandpa_rpc::GrandpaApi::to_delegate(GrandpaRpcHandler::new(
+		shared_authority_set,
+		shared_voter_state,
+		justification_stream,
+		grandpa_executor,
+		finality_provider,
+	)));
+
+	io
+}
+
+/// Dependencies of a full client sealing blocks without mining, see `--dev-instant-seal`.
+pub struct DevDeps<C> {
+	/// The client instance to use.
+	pub client: Arc<C>,
+	/// A command stream to send authoring commands to manual seal consensus engine
+	pub engine_sink: Sender<EngineCommand<Hash>>,
+}
+
+/// Instantiate the RPC extensions of a full client sealing blocks without mining: the chain data
+/// RPCs, and `engine_createBlock` and `engine_finalizeBlock` in place of the mining RPCs.
+pub fn create_dev<C>(deps: DevDeps<C>) -> jsonrpc_core::IoHandler<sc_rpc::Metadata>
+where
+	C: ProvideRuntimeApi<Block>,
+	C: HeaderBackend<Block> + HeaderMetadata<Block, Error = BlockChainError> + 'static,
+	C: AuxStore + Send + Sync + 'static,
+	C::Api: BlockBuilder<Block>,
+	C::Api: sum_storage_runtime_api::SumStorageApi<Block>,
+	C::Api: author_history_runtime_api::AuthorHistoryApi<Block, BlockNumber, AccountId>,
+	C::Api: block_reward_runtime_api::BlockRewardApi<Block, BlockNumber, Balance>,
+{
+	let mut io = jsonrpc_core::IoHandler::default();
+	let DevDeps { client, engine_sink } = deps;
+
+	extend_with_chain_data(&mut io, client);
+
+	// Block production driven by the RPC callers, on top of the blocks sealed for transactions.
+	io.extend_with(ManualSealApi::to_delegate(ManualSeal::new(engine_sink)));
+
+	io
+}
 
+/// Add the RPCs reading the chain to `io`: the runtime APIs of the pallets and the explorer.
+fn extend_with_chain_data<C>(io: &mut jsonrpc_core::IoHandler<sc_rpc::Metadata>, client: Arc<C>)
+where
+	C: ProvideRuntimeApi<Block>,
+	C: HeaderBackend<Block> + HeaderMetadata<Block, Error = BlockChainError> + 'static,
+	C: AuxStore + Send + Sync + 'static,
+	C::Api: BlockBuilder<Block>,
+	C::Api: sum_storage_runtime_api::SumStorageApi<Block>,
+	C::Api: author_history_runtime_api::AuthorHistoryApi<Block, BlockNumber, AccountId>,
+	C::Api: block_reward_runtime_api::BlockRewardApi<Block, BlockNumber, Balance>,
+{
 	// Add a second RPC extension
 	// Because this one calls a Runtime API it needs a reference to the client.
 	io.extend_with(sum_storage_rpc::SumStorageApi::to_delegate(
 		sum_storage_rpc::SumStorage::new(client.clone()),
 	));
-	
-	// Add a EthashRpc RPC
-	io.extend_with(crate::rpc::ethash_rpc::EthashRpc::to_delegate(
-		crate::rpc::ethash_rpc::EthashData::new(client, command_sink),
+
+	// Author distribution statistics, read from the author-history pallet.
+	io.extend_with(author_history_rpc::AuthorHistoryApi::to_delegate(
+		author_history_rpc::AuthorHistory::<_, (Block, BlockNumber)>::new(client.clone()),
+	));
+
+	// Emission schedule, read from the block-reward pallet.
+	io.extend_with(block_reward_rpc::BlockRewardApi::to_delegate(
+		block_reward_rpc::BlockReward::<_, (Block, Balance)>::new(client.clone()),
+	));
+
+	// Block details for explorers, decoded from headers and the PoW aux data.
+	io.extend_with(crate::rpc::explorer::ExplorerApi::to_delegate(
+		crate::rpc::explorer::Explorer::new(client),
+	));
+}
+
+/// Light client dependencies.
+#[cfg(feature = "light")]
+pub struct LightDeps<C> {
+	/// The client instance to use.
+	pub client: Arc<C>,
+	/// Seal verification statistics of the ethash epochs.
+	pub epoch_stats: EpochStats,
+}
+
+/// Instantiate the RPC extensions of light clients: the read-only mining RPCs.
+#[cfg(feature = "light")]
+pub fn create_light<C>(deps: LightDeps<C>) -> jsonrpc_core::IoHandler<sc_rpc::Metadata>
+where
+	C: AuxStore + Send + Sync + 'static,
+{
+	use crate::rpc::light::{EthashLightData, EthashLightRpc};
+
+	let mut io = jsonrpc_core::IoHandler::default();
+	let LightDeps { client, epoch_stats } = deps;
+
+	io.extend_with(EthashLightRpc::to_delegate(
+		EthashLightData::new(client, epoch_stats),
 	));
 
 	io
 }
+
+/// Add the mining RPCs to `io`, the general RPC or their own listener. The submissions are
+/// authenticated and rate limited by `miner_auth` before reaching the mining service.
+pub fn extend_with_mining<C, M>(
+	io: &mut jsonrpc_core::IoHandler<M>,
+	client: Arc<C>,
+	command_sink: Sender<EtheminerCmd>,
+	deny_unsafe: DenyUnsafe,
+	epoch_stats: EpochStats,
+	miner_auth: MinerAuth,
+)
+where
+	C: AuxStore + HeaderBackend<Block> + Send + Sync + 'static,
+	M: jsonrpc_core::Metadata,
+{
+	io.extend_with(EthashRpc::to_delegate(
+		EthashData::new(client, command_sink, deny_unsafe, epoch_stats, miner_auth),
+	));
+}
@@ -0,0 +1,210 @@
+//! Work pushed to miners instead of polled with `eth_getWork`.
+//!
+//! A miner either subscribes with `eth_subscribe("newWork")` and receives the current work
+//! package, then every new one as soon as the mining worker starts a build, e.g. right after a
+//! block import; or it long-polls with `ethash_pollWork`, answered as soon as the work differs
+//! from the package it already has. Subscriptions need a transport with sessions, so they are
+//! only served over WebSocket.
+//!
+//! Idle connections are kept in check by the `WorkRpcConfig`: subscribers get the current work
+//! again every keep-alive interval, long-polls are answered after the idle timeout even without
+//! new work, and a WebSocket connection only gets a few long-polls in flight at once. An HTTP
+//! connection carries a single request at a time, so it never has more than one.
+
+use futures::{
+	future::{self, Either},
+	stream, FutureExt, StreamExt, TryFutureExt, TryStreamExt,
+};
+use futures_timer::Delay;
+use jsonrpc_core::{futures::{Future as _, Sink as _}, Error, Result};
+use jsonrpc_derive::rpc;
+use jsonrpc_pubsub::{manager::SubscriptionManager, typed::Subscriber, PubSubMetadata, SubscriptionId};
+use log::warn;
+use parking_lot::Mutex;
+use sc_rpc::{Metadata, SubscriptionTaskExecutor};
+use serde_derive::Deserialize;
+use sp_core::H256;
+use std::{collections::HashMap, sync::Arc};
+use crate::mining::{CurrentWork, WorkRpcConfig, WorkSnapshot};
+use crate::rpc::error::Error as RpcError;
+use crate::types::Work;
+
+/// Future's type for jsonrpc
+type FutureResult<T> = Box<dyn jsonrpc_core::futures::Future<Item = T, Error = Error> + Send>;
+
+/// The kinds of subscriptions of `eth_subscribe`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Kind {
+	/// Every new work package.
+	NewWork,
+}
+
+#[rpc(server)]
+pub trait WorkPubSubApi {
+	/// RPC Metadata
+	type Metadata;
+
+	/// The current work package once it differs from `known`, or after the idle timeout.
+	#[rpc(meta, name = "ethash_pollWork")]
+	fn poll_work(&self, metadata: Self::Metadata, known: Option<H256>) -> FutureResult<Work>;
+
+	/// Subscribe to the work packages of `kind`.
+	#[pubsub(subscription = "eth_subscription", subscribe, name = "eth_subscribe")]
+	fn subscribe(&self, metadata: Self::Metadata, subscriber: Subscriber<Work>, kind: Kind);
+
+	/// Cancel a subscription.
+	#[pubsub(subscription = "eth_subscription", unsubscribe, name = "eth_unsubscribe")]
+	fn unsubscribe(&self, metadata: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool>;
+}
+
+/// Long-polls in flight, by WebSocket session.
+#[derive(Clone, Default)]
+struct LongPolls(Arc<Mutex<HashMap<usize, usize>>>);
+
+impl LongPolls {
+	/// Count a long-poll of `session`, unless it already has `max` in flight.
+	fn acquire(&self, session: usize, max: usize) -> Option<LongPoll> {
+		let mut polls = self.0.lock();
+		let count = polls.entry(session).or_default();
+		if *count >= max {
+			return None;
+		}
+		*count += 1;
+		Some(LongPoll { polls: self.clone(), session })
+	}
+}
+
+/// A long-poll in flight, counted until dropped.
+struct LongPoll {
+	polls: LongPolls,
+	session: usize,
+}
+
+impl Drop for LongPoll {
+	fn drop(&mut self) {
+		let mut polls = self.polls.0.lock();
+		if let Some(count) = polls.get_mut(&self.session) {
+			*count -= 1;
+			if *count == 0 {
+				polls.remove(&self.session);
+			}
+		}
+	}
+}
+
+/// A struct that implements the `WorkPubSubApi`.
+pub struct WorkPubSub {
+	work: WorkSnapshot,
+	config: WorkRpcConfig,
+	long_polls: LongPolls,
+	manager: SubscriptionManager,
+}
+
+impl WorkPubSub {
+	/// Create new `WorkPubSub` instance pushing the work packages of `work`.
+	pub fn new(work: WorkSnapshot, config: WorkRpcConfig, executor: SubscriptionTaskExecutor) -> Self {
+		Self {
+			work,
+			config,
+			long_polls: LongPolls::default(),
+			manager: SubscriptionManager::new(Arc::new(executor)),
+		}
+	}
+}
+
+impl WorkPubSubApi for WorkPubSub {
+	type Metadata = Metadata;
+
+	fn poll_work(&self, metadata: Self::Metadata, known: Option<H256>) -> FutureResult<Work> {
+		let long_poll = match metadata.session() {
+			Some(session) => {
+				let session = Arc::as_ptr(&session) as usize;
+				match self.long_polls.acquire(session, self.config.max_long_polls) {
+					Some(long_poll) => Some(long_poll),
+					None => {
+						let err = RpcError::TooManyLongPolls(self.config.max_long_polls);
+						return Box::new(jsonrpc_core::futures::future::err(err.into()));
+					}
+				}
+			}
+			None => None,
+		};
+
+		// Subscribe before loading the current work so no package falls in between.
+		let mut updates = self.work.subscribe();
+		let work = self.work.clone();
+		let idle_timeout = self.config.idle_timeout;
+		let future = async move {
+			let _long_poll = long_poll;
+			let is_new = |current: &Option<Arc<CurrentWork>>| match current {
+				Some(current) => known != Some(current.work.pow_hash),
+				None => false,
+			};
+
+			let mut current = work.load();
+			if !is_new(&current) {
+				let mut idle = Delay::new(idle_timeout);
+				loop {
+					match future::select(updates.next(), &mut idle).await {
+						Either::Left((Some(update), _)) => {
+							current = update;
+							if is_new(&current) {
+								break;
+							}
+						}
+						Either::Left((None, _)) | Either::Right(_) => break,
+					}
+				}
+			}
+
+			current
+				.map(|current| current.work.clone())
+				.ok_or(RpcError::NoWork)
+		}.boxed();
+
+		Box::new(future.map_err(Error::from).compat())
+	}
+
+	fn subscribe(&self, _metadata: Self::Metadata, subscriber: Subscriber<Work>, kind: Kind) {
+		match kind {
+			Kind::NewWork => {
+				// Subscribe before loading the current work so no package falls in between.
+				let updates = self.work.subscribe();
+				let current = self.work.load();
+				let keep_alive = self.config.keep_alive;
+				let work = self.work.clone();
+				let notifications = stream::unfold(updates, move |mut updates| {
+					let work = work.clone();
+					async move {
+						let next = match future::select(updates.next(), Delay::new(keep_alive)).await {
+							Either::Left((Some(update), _)) => update,
+							Either::Left((None, _)) => return None,
+							// Nothing new for a while, the current work keeps the connection alive.
+							Either::Right(_) => work.load(),
+						};
+						Some((next, updates))
+					}
+				});
+				let works = stream::iter(current.map(Some))
+					.chain(notifications)
+					.filter_map(|current| future::ready(current.map(|current| current.work.clone())))
+					.map(|work| Ok::<_, ()>(Ok(work)))
+					.boxed()
+					.compat();
+
+				self.manager.add(subscriber, |sink| {
+					sink
+						.sink_map_err(|err| warn!("Error sending work notifications: {:?}", err))
+						.send_all(works)
+						// The subscription is over once the notifications stop.
+						.map(|_| ())
+				});
+			}
+		}
+	}
+
+	fn unsubscribe(&self, _metadata: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool> {
+		Ok(self.manager.cancel(id))
+	}
+}
@@ -0,0 +1,64 @@
+//! Computation of the proofs of work of the blocks queued for import ahead of their verification.
+//!
+//! The import queue verifies the blocks of a range one after the other. Handing their seals to
+//! the verification threads of the algorithm as the range is queued computes the proofs of work
+//! in parallel, so syncing a long chain isn't bound by the ethash computation of a single core.
+
+use ethpow::EthashAlgorithm;
+use futures::task::Context;
+use sp_consensus::{
+	import_queue::{ImportQueue, IncomingBlock, Link, Origin},
+	BlockOrigin,
+};
+use sp_consensus_pow::POW_ENGINE_ID;
+use sp_runtime::{
+	traits::{Block as BlockT, Header as HeaderT, NumberFor},
+	DigestItem, Justification,
+};
+
+/// Wraps the import queue `Q`, prefetching the proofs of work of the blocks imported with it.
+pub struct SealPrefetchQueue<Q, C> {
+	inner: Q,
+	algorithm: EthashAlgorithm<C>,
+}
+
+impl<Q, C> SealPrefetchQueue<Q, C> {
+	/// Prefetch the proofs of work of the blocks imported with `inner` with `algorithm`, the one
+	/// verifying them.
+	pub fn new(inner: Q, algorithm: EthashAlgorithm<C>) -> Self {
+		Self { inner, algorithm }
+	}
+}
+
+impl<B, Q, C> ImportQueue<B> for SealPrefetchQueue<Q, C>
+where
+	B: BlockT,
+	Q: ImportQueue<B>,
+	C: Send + Sync,
+{
+	fn import_blocks(&mut self, origin: BlockOrigin, blocks: Vec<IncomingBlock<B>>) {
+		for header in blocks.iter().filter_map(|block| block.header.as_ref()) {
+			// The seal is the last digest item, the verification rejects the headers without it.
+			if let Some(DigestItem::Seal(id, seal)) = header.digest().logs().last() {
+				if *id == POW_ENGINE_ID {
+					self.algorithm.prefetch_seal(seal);
+				}
+			}
+		}
+		self.inner.import_blocks(origin, blocks)
+	}
+
+	fn import_justification(
+		&mut self,
+		who: Origin,
+		hash: B::Hash,
+		number: NumberFor<B>,
+		justification: Justification,
+	) {
+		self.inner.import_justification(who, hash, number, justification)
+	}
+
+	fn poll_actions(&mut self, cx: &mut Context, link: &mut dyn Link<B>) {
+		self.inner.poll_actions(cx, link)
+	}
+}
@@ -1,28 +1,49 @@
 //! Service and ServiceFactory implementation. Specialized wrapper over substrate service.
 
 use runtime::{self, opaque::Block, RuntimeApi};
-use sc_client_api::{ExecutorProvider, RemoteBackend};
+use sc_client_api::{AuxStore, BlockchainEvents, ExecutorProvider};
+#[cfg(feature = "light")]
+use sc_client_api::RemoteBackend;
+use sc_consensus_manual_seal::{EngineCommand, ManualSealParams};
 use sc_executor::native_executor_instance;
+use sc_finality_grandpa::GrandpaBlockImport;
+use sc_rpc_api::DenyUnsafe;
 pub use sc_executor::NativeExecutor;
 use sc_service::{error::Error as ServiceError, Configuration, PartialComponents, TaskManager};
 use sp_api::TransactionFor;
 use sp_consensus::import_queue::BasicQueue;
 use sp_inherents::InherentDataProviders;
-use std::{sync::Arc, time::{Duration, SystemTime, UNIX_EPOCH}};
+use std::{path::{Path, PathBuf}, sync::Arc, time::{Duration, SystemTime, UNIX_EPOCH}};
 use std::thread;
-use sp_core::{U256, H256};
-use crate::rpc::{ethash_rpc, EtheminerCmd, error::{Error as RpcError}};
-use crate::types::{Work};
-use ethpow::{MinimalEthashAlgorithm, EthashAlgorithm, WorkSeal};
+use sp_core::{hashing::blake2_256, hexdisplay::HexDisplay, U256, H256};
+use crate::rpc::{send_result, EtheminerCmd, error::{Error as RpcError}};
+use crate::types::{MiningStatus, SubmittedWork};
+use crate::local_miner::{LocalMiner, MinerThreads};
+use crate::fee_priority::FeePriorityPool;
+use crate::min_fee::MinFee;
+use crate::proposer::RewardProposerFactory;
+use crate::seal_prefetch::SealPrefetchQueue;
+use crate::mining::{
+	hashrate, metrics, mined_blocks, miner_stats, shutdown, solution_difficulty, stats_dump, AuditLog,
+	CurrentWork,
+	ExtraData, HashrateSnapshot, MinerAuth, MinerHashrates, MinerShares, MiningConfig, MiningMetrics, MiningStatsDump, NonceLeases, QueuedCommand,
+	WorkRequest,
+	ShareDifficulties, SubmissionQueue, Submissions, WorkAge, WorkerActivity, WorkerKeys, WorkSnapshot,
+	WorkCache, WorkTracker,
+};
+use ethpow::{EthashAlgorithm, EthashParams, MinimalEthashAlgorithm, WorkSeal};
 use sp_api::ProvideRuntimeApi;
 use sc_consensus_pow::{MiningWorker, MiningMetadata, MiningBuild};
 use sc_consensus_pow::{PowAlgorithm};
-use sp_runtime::traits::{Block as BlockT, Header as HeaderT, UniqueSaturatedInto};
+use sp_runtime::{generic::BlockId, traits::{Block as BlockT, Header as HeaderT, UniqueSaturatedInto}};
+use sp_blockchain::HeaderBackend;
+use sp_consensus::{CanAuthorWith, SyncOracle};
 use parking_lot::Mutex;
-use futures::prelude::*;
-use ethash::{self, SeedHashCompute};
-use parity_scale_codec::{Decode, Encode};
-use ethereum_types::{self, U256 as EU256, H256 as EH256};
+use futures::{prelude::*, stream};
+use futures_timer::Delay;
+use ethash::ETHASH_EPOCH_LENGTH;
+use parity_scale_codec::Decode;
+use ethereum_types::{self, H64};
 use log::{error, info, debug, trace, warn};
 
 // Our native executor instance.
@@ -47,26 +68,55 @@ pub fn build_inherent_data_providers() -> Result<InherentDataProviders, ServiceE
 	Ok(providers)
 }
 
+/// File the mining statistics are dumped to: `stats_file` when given, `mining-stats.json` in
+/// the chain directory otherwise.
+pub fn mining_stats_file(config: &Configuration, stats_file: Option<&Path>) -> PathBuf {
+	match (stats_file, &config.base_path) {
+		(Some(stats_file), _) => stats_file.to_path_buf(),
+		(None, Some(base_path)) => base_path.config_dir(config.chain_spec.id()).join("mining-stats.json"),
+		(None, None) => std::env::temp_dir().join("mining-stats.json"),
+	}
+}
+
+/// Directory the ethash caches are kept in across restarts: `cache_dir` when given, `ethash`
+/// in the chain directory otherwise.
+pub fn ethash_cache_dir(config: &Configuration, cache_dir: Option<&Path>) -> Result<PathBuf, ServiceError> {
+	let cache_dir = match (cache_dir, &config.base_path) {
+		(Some(cache_dir), _) => cache_dir.to_path_buf(),
+		(None, Some(base_path)) => base_path.config_dir(config.chain_spec.id()).join("ethash"),
+		(None, None) => std::env::temp_dir().join("ethash"),
+	};
+	std::fs::create_dir_all(&cache_dir).map_err(|err| {
+		ServiceError::Other(format!("Failed to create the ethash cache directory {}: {}", cache_dir.display(), err))
+	})?;
+	Ok(cache_dir)
+}
+
 /// Returns most parts of a service. Not enough to run a full chain,
 /// But enough to perform chain operations like purge-chain
 #[allow(clippy::type_complexity)]
 pub fn new_partial(
 	config: &Configuration,
+	cache_dir: Option<&Path>,
+	params: EthashParams,
 ) -> Result<
 	PartialComponents<
 		FullClient,
 		FullBackend,
 		FullSelectChain,
-		BasicQueue<Block, TransactionFor<FullClient, Block>>,
+		SealPrefetchQueue<BasicQueue<Block, TransactionFor<FullClient, Block>>, FullClient>,
 		sc_transaction_pool::FullPool<Block, FullClient>,
-		sc_consensus_pow::PowBlockImport<
-			Block,
-			Arc<FullClient>,
-			FullClient,
-			FullSelectChain,
-			EthashAlgorithm<FullClient>,
-			impl sp_consensus::CanAuthorWith<Block>,
-		>,
+		(
+			sc_consensus_pow::PowBlockImport<
+				Block,
+				GrandpaBlockImport<FullBackend, Block, FullClient, FullSelectChain>,
+				FullClient,
+				FullSelectChain,
+				EthashAlgorithm<FullClient>,
+				impl sp_consensus::CanAuthorWith<Block>,
+			>,
+			sc_finality_grandpa::LinkHalf<Block, FullClient, FullSelectChain>,
+		),
 	>,
 	ServiceError,
 > {
@@ -86,11 +136,20 @@ pub fn new_partial(
 		client.clone(),
 	);
 
+	// The PoW import checks the seal, then hands the block to the GRANDPA import, which tracks
+	// the authority set changes and imports the justifications finalizing the blocks.
+	let (grandpa_block_import, grandpa_link) = sc_finality_grandpa::block_import(
+		client.clone(),
+		&(client.clone() as Arc<_>),
+		select_chain.clone(),
+	)?;
+
 	let can_author_with = sp_consensus::CanAuthorWithNativeVersion::new(client.executor().clone());
-	let ethash_alg = EthashAlgorithm::new(client.clone());
+	let ethash_alg = EthashAlgorithm::with_params(client.clone(), params, &ethash_cache_dir(config, cache_dir)?)
+		.with_prometheus(config.prometheus_registry());
 	
 	let pow_block_import = sc_consensus_pow::PowBlockImport::new(
-		client.clone(),
+		grandpa_block_import.clone(),
 		client.clone(),
 		ethash_alg.clone(),
 		0, // check inherents starting at block 0
@@ -101,12 +160,13 @@ pub fn new_partial(
 
 	let import_queue = sc_consensus_pow::import_queue(
 		Box::new(pow_block_import.clone()),
-		None,
+		Some(Box::new(grandpa_block_import)),
 		ethash_alg.clone(),
 		inherent_data_providers.clone(),
 		&task_manager.spawn_handle(),
 		config.prometheus_registry(),
 	)?;
+	let import_queue = SealPrefetchQueue::new(import_queue, ethash_alg);
 
 	Ok(PartialComponents {
 		client,
@@ -117,12 +177,15 @@ pub fn new_partial(
 		transaction_pool,
 		select_chain,
 		inherent_data_providers,
-		other: pow_block_import,
+		other: (pow_block_import, grandpa_link),
 	})
 }
 
 /// Builds a new service for a full client.
-pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
+pub fn new_full(
+	mut config: Configuration,
+	mut mining_config: MiningConfig,
+) -> Result<TaskManager, ServiceError> {
 	
 	let sc_service::PartialComponents {
 		client,
@@ -133,9 +196,33 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 		select_chain,
 		transaction_pool,
 		inherent_data_providers,
-		other: pow_block_import,
-	} = new_partial(&config)?;
+		other: (pow_block_import, grandpa_link),
+	} = new_partial(&config, mining_config.ethash_cache_dir.as_deref(), mining_config.ethash_params())?;
+
+	if let Some(difficulty) = mining_config.fixed_difficulty {
+		warn!(
+			target: "pow",
+			"Difficulty fixed at {}: nodes started without the same --fixed-difficulty reject the \
+			blocks of this node",
+			difficulty,
+		);
+	}
 
+	let cache_dir = ethash_cache_dir(&config, mining_config.ethash_cache_dir.as_deref())?;
+	mining_config.stats_file = Some(mining_stats_file(&config, mining_config.stats_file.as_deref()));
+	let (cache_requests_sink, cache_requests) = futures::channel::mpsc::channel(16);
+	config
+		.network
+		.request_response_protocols
+		.push(crate::cache_sync::request_response_config(cache_requests_sink));
+	let (ancestry_requests_sink, ancestry_requests) = futures::channel::mpsc::channel(16);
+	config
+		.network
+		.request_response_protocols
+		.push(crate::checkpoint_sync::request_response_config(ancestry_requests_sink));
+	config.network.extra_sets.push(sc_finality_grandpa::grandpa_peers_set_config());
+
+	let announce_algorithm = pow_block_import.algorithm.clone();
 	let (network, network_status_sinks, system_rpc_tx, network_starter) =
 		sc_service::build_network(sc_service::BuildNetworkParams {
 			config: &config,
@@ -144,7 +231,9 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 			spawn_handle: task_manager.spawn_handle(),
 			import_queue,
 			on_demand: None,
-			block_announce_validator_builder: None,
+			block_announce_validator_builder: Some(Box::new(move |client| {
+				Box::new(crate::announce::SealAnnounceValidator::new(client, announce_algorithm))
+			})),
 		})?;
 
 	if config.offchain_worker.enabled {
@@ -157,53 +246,200 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 		);
 	}
 
+	task_manager.spawn_handle().spawn_blocking(
+		"ethash-cache-check",
+		crate::cache_check::run_cache_check(
+			pow_block_import.algorithm.clone(),
+			client.info().best_number.into(),
+		),
+	);
+	task_manager.spawn_handle().spawn_blocking(
+		"ethash-epoch-prepare",
+		crate::epoch_prepare::run_epoch_preparation(
+			client.clone(),
+			pow_block_import.algorithm.clone(),
+			mining_config.prepare_dag_threads,
+		),
+	);
+	task_manager.spawn_handle().spawn_blocking(
+		"ethash-cache-server",
+		crate::cache_sync::run_cache_server(client.clone(), cache_dir.clone(), cache_requests),
+	);
+	if let Some(min_peers) = mining_config.cache_from_peers {
+		let algorithm = pow_block_import.algorithm.clone();
+		let best_hash = client.info().best_hash;
+		let commitment = move |epoch| {
+			algorithm.committed_cache(&BlockId::Hash(best_hash), epoch).map_err(|err| format!("{:?}", err))
+		};
+		task_manager.spawn_handle().spawn(
+			"ethash-cache-fetch",
+			crate::cache_sync::fetch_cache(network.clone(), client.clone(), commitment, cache_dir.clone(), min_peers),
+		);
+	}
+	task_manager.spawn_handle().spawn_blocking(
+		"ethash-checkpoint-server",
+		crate::checkpoint_sync::run_ancestry_server(client.clone(), ancestry_requests),
+	);
+	if let Some(checkpoint) = mining_config.checkpoint {
+		task_manager.spawn_handle().spawn(
+			"ethash-checkpoint-fetch",
+			crate::checkpoint_sync::fetch_ancestry(
+				network.clone(),
+				client.clone(),
+				pow_block_import.algorithm.checkpoint_ancestry(),
+				checkpoint,
+			),
+		);
+	}
+
 	let is_authority = config.role.is_authority();
 	let prometheus_registry = config.prometheus_registry().cloned();
+	let enable_grandpa = !config.disable_grandpa;
+	let node_name = config.network.node_name.clone();
+
+	let justification_stream = grandpa_link.justification_stream();
+	let shared_authority_set = grandpa_link.shared_authority_set().clone();
+	let shared_voter_state = sc_finality_grandpa::SharedVoterState::empty();
+	let finality_proof_provider = sc_finality_grandpa::FinalityProofProvider::new_for_service(
+		backend.clone(),
+		Some(shared_authority_set.clone()),
+	);
 
 	// Channel for the rpc handler to communicate with the authorship task.
 	let (command_sink, commands_stream) = futures::channel::mpsc::channel(1000);
+	let work_snapshot = WorkSnapshot::default();
+	let extra_data = ExtraData::new(mining_config.extra_data.clone())
+		.map_err(|err| ServiceError::Other(err.to_string()))?;
+	let min_fee = MinFee::new(mining_config.min_fee);
+	let miner_threads = MinerThreads::new(mining_config.threads);
+	// Shared with the stratum server, which sets the share boundary of the jobs of each worker.
+	let share_difficulties = ShareDifficulties::default();
+	// Shared by every RPC server, so a miner has the same rate limit on all of them.
+	let miner_auth = MinerAuth::new(&mining_config.miner_tokens, mining_config.miner_rate_limit);
 
 	let rpc_extensions_builder = {
 		let client = client.clone();
 		let pool = transaction_pool.clone();
-		Box::new(move |deny_unsafe, _| {
+		let command_sink = command_sink.clone();
+		let work_snapshot = work_snapshot.clone();
+		let work_rpc = mining_config.work_rpc.clone();
+		let epoch_stats = pow_block_import.algorithm.epoch_stats();
+		let mining_rpc_addr = mining_config.mining_rpc_addr;
+		let miner_auth = miner_auth.clone();
+		let shared_voter_state = shared_voter_state.clone();
+		Box::new(move |deny_unsafe, subscription_executor: sc_rpc::SubscriptionTaskExecutor| {
 			let deps = crate::rpc::FullDeps {
 				client: client.clone(),
 				pool: pool.clone(),
 				deny_unsafe,
 				command_sink: command_sink.clone(),
+				work: work_snapshot.clone(),
+				work_rpc: work_rpc.clone(),
+				epoch_stats: epoch_stats.clone(),
+				dedicated_mining_rpc: mining_rpc_addr.is_some(),
+				miner_auth: miner_auth.clone(),
+				grandpa: crate::rpc::GrandpaDeps {
+					shared_voter_state: shared_voter_state.clone(),
+					shared_authority_set: shared_authority_set.clone(),
+					justification_stream: justification_stream.clone(),
+					subscription_executor: subscription_executor.clone(),
+					finality_provider: finality_proof_provider.clone(),
+				},
 			};
 
-			crate::rpc::create_full(deps)
+			crate::rpc::create_full(deps, subscription_executor)
 		})
 	};
 
-	sc_service::spawn_tasks(sc_service::SpawnTasksParams {
-		network: network.clone(),
-		client: client.clone(),
-		keystore: keystore_container.sync_keystore(),
-		task_manager: &mut task_manager,
-		transaction_pool: transaction_pool.clone(),
-		rpc_extensions_builder,
-		on_demand: None,
-		remote_blockchain: None,
-		backend,
-		network_status_sinks,
-		system_rpc_tx,
-		config,
-	})?;
+	let (_rpc_handlers, telemetry_connection_notifier) =
+		sc_service::spawn_tasks(sc_service::SpawnTasksParams {
+			network: network.clone(),
+			client: client.clone(),
+			keystore: keystore_container.sync_keystore(),
+			task_manager: &mut task_manager,
+			transaction_pool: transaction_pool.clone(),
+			rpc_extensions_builder,
+			on_demand: None,
+			remote_blockchain: None,
+			backend,
+			network_status_sinks,
+			system_rpc_tx,
+			config,
+		})?;
 
-	if is_authority {
-		let proposer = sc_basic_authorship::ProposerFactory::new(
-			task_manager.spawn_handle(),
+	if enable_grandpa {
+		// The mined blocks are final once two thirds of the voters of the runtime signed them.
+		// Every node runs the full voter, which only votes with a key of the current set found
+		// in the keystore: `--alice` on `dev`, `author_insertKey` with the `gran` key type on a
+		// chain of its own. The PoW fork choice never reverts a block GRANDPA finalized.
+		let grandpa_config = sc_finality_grandpa::Config {
+			gossip_duration: Duration::from_millis(333),
+			justification_period: 512,
+			name: Some(node_name),
+			observer_enabled: false,
+			keystore: Some(keystore_container.sync_keystore()),
+			is_authority,
+		};
+		let grandpa_params = sc_finality_grandpa::GrandpaParams {
+			config: grandpa_config,
+			link: grandpa_link,
+			network: network.clone(),
+			telemetry_on_connect: telemetry_connection_notifier.map(|x| x.on_connect_stream()),
+			voting_rule: sc_finality_grandpa::VotingRulesBuilder::default().build(),
+			prometheus_registry: prometheus_registry.clone(),
+			shared_voter_state,
+		};
+
+		// the GRANDPA voter task is considered infallible, i.e.
+		// if it fails we take down the service with it.
+		task_manager.spawn_essential_handle().spawn_blocking(
+			"grandpa-voter",
+			sc_finality_grandpa::run_grandpa_voter(grandpa_params)?,
+		);
+	}
+
+	if let Some(addr) = mining_config.mining_rpc_addr {
+		// Unsafe mining RPCs are only allowed to the local rigs.
+		let deny_unsafe = if addr.ip().is_loopback() { DenyUnsafe::No } else { DenyUnsafe::Yes };
+		let mut io = jsonrpc_core::IoHandler::default();
+		crate::rpc::extend_with_mining(
+			&mut io,
 			client.clone(),
-			transaction_pool.clone(),
-			prometheus_registry.as_ref(),
+			command_sink.clone(),
+			deny_unsafe,
+			pow_block_import.algorithm.epoch_stats(),
+			miner_auth.clone(),
+		);
+		task_manager.spawn_handle().spawn(
+			"mining-rpc",
+			crate::rpc::mining_server::run_mining_rpc_server(addr, io),
+		);
+	}
+
+	if is_authority {
+		// The block weight limit is read by the runtime from storage when applying every
+		// extrinsic, so a limit changed by root is honored from the next proposed block.
+		let proposer = RewardProposerFactory::new(
+			sc_basic_authorship::ProposerFactory::new(
+				task_manager.spawn_handle(),
+				client.clone(),
+				Arc::new(FeePriorityPool::new(
+					client.clone(),
+					transaction_pool.clone(),
+					min_fee.clone(),
+					mining_config.fee_priority,
+				)),
+				prometheus_registry.as_ref(),
+			),
+			mining_config.author.clone(),
+			extra_data.clone(),
 		);
 
 		let can_author_with =
 			sp_consensus::CanAuthorWithNativeVersion::new(client.executor().clone());
 		let ethash_alg = pow_block_import.algorithm.clone();
+		// In seconds, the runtime counts the block times in milliseconds.
+		let target_block_time = runtime::Difficulty::retarget_params().target_block_time() / 1000;
 
 		// Parameter details:
 		//   https://substrate.dev/rustdocs/v3.0.0/sc_consensus_pow/fn.start_mining_worker.html
@@ -213,27 +449,241 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 			Box::new(pow_block_import),
 			client.clone(),
 			select_chain,
-			ethash_alg,
+			ethash_alg.clone(),
 			proposer,
 			network.clone(),
+			// The proposer names the author of every block in the PoW pre-runtime digest.
 			None,
 			inherent_data_providers,
 			// time to wait for a new block before starting to mine a new one
-			Duration::from_secs(10),
+			mining_config.refresh_interval,
 			// how long to take to actually build the block (i.e. executing extrinsics)
-			Duration::from_secs(10),
-			can_author_with,
+			mining_config.proposal_time,
+			can_author_with.clone(),
 		);
 
 		task_manager
 			.spawn_essential_handle()
 			.spawn_blocking("pow", worker_task);
+
+		task_manager.spawn_handle().spawn(
+			"mined-block-index",
+			crate::mining::mined_blocks::run_mined_block_index(client.clone()),
+		);
+
+		if let Some(timeout) = mining_config.stall_timeout {
+			task_manager.spawn_handle().spawn(
+				"stall-watchdog",
+				crate::watchdog::run_stall_watchdog(client.clone(), timeout, prometheus_registry.clone()),
+			);
+		}
 		
-		// Start Mining
+		let exit = shutdown::exit_signal();
+		let local_miner = LocalMiner::new(
+			miner_threads,
+			task_manager.spawn_handle(),
+			work_snapshot.clone(),
+			command_sink.clone(),
+			cache_dir,
+			mining_config.nonce_partition,
+			exit.clone(),
+		);
+		local_miner.start();
+
+		if let Some(addr) = mining_config.stratum_addr {
+			task_manager.spawn_handle().spawn(
+				"stratum",
+				crate::stratum::run_stratum_server(
+					addr,
+					work_snapshot.clone(),
+					share_difficulties.clone(),
+					miner_auth.clone(),
+					command_sink,
+				),
+			);
+		}
+
+		if !mining_config.notify_work.is_empty() {
+			task_manager.spawn_handle().spawn(
+				"notify-work",
+				crate::notify_work::run_work_notifier(mining_config.notify_work.clone(), work_snapshot.clone()),
+			);
+		}
+
+		let mining_metrics = prometheus_registry.as_ref().and_then(|registry| {
+			MiningMetrics::register(registry, ethash_alg.epoch_stats())
+				.map_err(|err| warn!(target: "pow", "Failed to register the mining metrics: {:?}", err))
+				.ok()
+		});
+
+		let peer_counts = stream::unfold(network.clone(), |network| async move {
+			Delay::new(PEER_CHECK_INTERVAL).await;
+			Some((network.num_connected(), network))
+		});
+
+		// Hashes of the new best blocks, with the number of blocks each retracted from the best
+		// chain, so the work of a stale branch is dropped before the worker builds on the new one.
+		let new_best_blocks = client.import_notification_stream().filter_map(|notification| {
+			let retracted = notification.tree_route.as_ref().map_or(0, |route| route.retracted().len());
+			future::ready(Some((notification.hash, retracted)).filter(|_| notification.is_new_best))
+		});
+
+		// Start Mining. Not essential: the service ends first when the node stops, see
+		// `mining::shutdown`.
 		task_manager
-			.spawn_essential_handle()
-			.spawn_blocking("mining", run_mining_svc(_worker.clone(), commands_stream));
+			.spawn_handle()
+			.spawn_blocking("mining", run_mining_svc(
+				_worker.clone(),
+				client.clone(),
+				target_block_time,
+				mining_config,
+				work_snapshot,
+				extra_data,
+				min_fee,
+				local_miner,
+				share_difficulties,
+				commands_stream,
+				new_best_blocks,
+				peer_counts,
+				stats_dump::dump_signals(),
+				exit,
+				mining_metrics,
+				ethash_alg.minimal(),
+				can_author_with,
+				network.clone(),
+			));
+
+	}
+
+	network_starter.start_network();
+	Ok(task_manager)
+}
+
+/// Builds a new service for a full client sealing blocks without mining, for `--dev-instant-seal`:
+/// a block is sealed for every transaction entering the pool and on `engine_createBlock`. The
+/// blocks carry no PoW seal, so no other kind of node imports them.
+pub fn new_dev(config: Configuration, mining_config: MiningConfig) -> Result<TaskManager, ServiceError> {
+	let inherent_data_providers = build_inherent_data_providers()?;
+
+	let (client, backend, keystore_container, mut task_manager) =
+		sc_service::new_full_parts::<Block, RuntimeApi, Executor>(&config)?;
+	let client = Arc::new(client);
+
+	let select_chain = sc_consensus::LongestChain::new(backend.clone());
+
+	let transaction_pool = sc_transaction_pool::BasicPool::new_full(
+		config.transaction_pool.clone(),
+		config.role.is_authority().into(),
+		config.prometheus_registry(),
+		task_manager.spawn_handle(),
+		client.clone(),
+	);
+
+	let import_queue = sc_consensus_manual_seal::import_queue(
+		Box::new(client.clone()),
+		&task_manager.spawn_handle(),
+		config.prometheus_registry(),
+	);
+
+	let (network, network_status_sinks, system_rpc_tx, network_starter) =
+		sc_service::build_network(sc_service::BuildNetworkParams {
+			config: &config,
+			client: client.clone(),
+			transaction_pool: transaction_pool.clone(),
+			spawn_handle: task_manager.spawn_handle(),
+			import_queue,
+			on_demand: None,
+			block_announce_validator_builder: None,
+		})?;
+
+	if config.offchain_worker.enabled {
+		sc_service::build_offchain_workers(
+			&config,
+			backend.clone(),
+			task_manager.spawn_handle(),
+			client.clone(),
+			network.clone(),
+		);
+	}
+
+	let is_authority = config.role.is_authority();
+	let prometheus_registry = config.prometheus_registry().cloned();
+
+	// Channel for the rpc handler to communicate with the authorship task.
+	let (engine_sink, engine_commands) = futures::channel::mpsc::channel(1000);
+
+	let rpc_extensions_builder = {
+		let client = client.clone();
+		Box::new(move |_, _| {
+			let deps = crate::rpc::DevDeps {
+				client: client.clone(),
+				engine_sink: engine_sink.clone(),
+			};
+
+			crate::rpc::create_dev(deps)
+		})
+	};
+
+	sc_service::spawn_tasks(sc_service::SpawnTasksParams {
+		network,
+		client: client.clone(),
+		keystore: keystore_container.sync_keystore(),
+		task_manager: &mut task_manager,
+		transaction_pool: transaction_pool.clone(),
+		rpc_extensions_builder,
+		on_demand: None,
+		remote_blockchain: None,
+		backend,
+		network_status_sinks,
+		system_rpc_tx,
+		config,
+	})?;
+
+	if is_authority {
+		warn!(
+			target: "pow",
+			"Sealing blocks without mining: nodes started without --dev-instant-seal reject the \
+			blocks of this node",
+		);
+
+		let proposer = RewardProposerFactory::new(
+			sc_basic_authorship::ProposerFactory::new(
+				task_manager.spawn_handle(),
+				client.clone(),
+				transaction_pool.clone(),
+				prometheus_registry.as_ref(),
+			),
+			mining_config.author.clone(),
+			ExtraData::new(mining_config.extra_data.clone())
+				.map_err(|err| ServiceError::Other(err.to_string()))?,
+		);
+
+		// A block for every transaction, as `run_instant_seal` does, on top of the RPC calls.
+		let transaction_commands = transaction_pool
+			.pool()
+			.validated_pool()
+			.import_notification_stream()
+			.map(|_| EngineCommand::SealNewBlock {
+				create_empty: false,
+				finalize: false,
+				parent_hash: None,
+				sender: None,
+			});
 
+		let authorship_future = sc_consensus_manual_seal::run_manual_seal(ManualSealParams {
+			block_import: client.clone(),
+			env: proposer,
+			client,
+			pool: transaction_pool.pool().clone(),
+			commands_stream: stream::select(transaction_commands, engine_commands),
+			select_chain,
+			inherent_data_providers,
+			consensus_data_provider: None,
+		});
+
+		task_manager
+			.spawn_essential_handle()
+			.spawn_blocking("instant-seal", authorship_future);
 	}
 
 	network_starter.start_network();
@@ -241,7 +691,12 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 }
 
 /// Builds a new service for a light client.
-pub fn new_light(config: Configuration) -> Result<TaskManager, ServiceError> {
+#[cfg(feature = "light")]
+pub fn new_light(
+	config: Configuration,
+	cache_dir: Option<&Path>,
+	params: EthashParams,
+) -> Result<TaskManager, ServiceError> {
 	let (client, backend, keystore_container, mut task_manager, on_demand) =
 		sc_service::new_light_parts::<Block, RuntimeApi, Executor>(&config)?;
 
@@ -255,29 +710,54 @@ pub fn new_light(config: Configuration) -> Result<TaskManager, ServiceError> {
 
 	let select_chain = sc_consensus::LongestChain::new(backend.clone());
 	let inherent_data_providers = build_inherent_data_providers()?;
-	// FixMe #375
-	let _can_author_with = sp_consensus::CanAuthorWithNativeVersion::new(client.executor().clone());
-	let ethash_alg = EthashAlgorithm::new(client.clone());
+	let ethash_alg = EthashAlgorithm::with_params(client.clone(), params, &ethash_cache_dir(&config, cache_dir)?)
+		.with_prometheus(config.prometheus_registry());
 
-	let pow_block_import = sc_consensus_pow::PowBlockImport::new(
+	let (grandpa_block_import, _) = sc_finality_grandpa::block_import(
 		client.clone(),
+		&(client.clone() as Arc<_>),
+		select_chain.clone(),
+	)?;
+
+	let pow_block_import = sc_consensus_pow::PowBlockImport::new(
+		grandpa_block_import.clone(),
 		client.clone(),
 		ethash_alg.clone(),
 		0, // check inherents starting at block 0
 		select_chain,
 		inherent_data_providers.clone(),
-		// FixMe #375
-		sp_consensus::AlwaysCanAuthor,
+		// Light clients import headers only: the seals are verified, but there is no body to
+		// check the inherents of, and no authoring to check the native runtime for.
+		sp_consensus::NeverCanAuthor,
+	);
+
+	task_manager.spawn_handle().spawn_blocking(
+		"ethash-cache-check",
+		crate::cache_check::run_cache_check(ethash_alg.clone(), client.info().best_number.into()),
 	);
 
 	let import_queue = sc_consensus_pow::import_queue(
 		Box::new(pow_block_import),
-		None,
+		Some(Box::new(grandpa_block_import)),
 		ethash_alg.clone(),
 		inherent_data_providers,
 		&task_manager.spawn_handle(),
 		config.prometheus_registry(),
 	)?;
+	let import_queue = SealPrefetchQueue::new(import_queue, ethash_alg.clone());
+
+	let rpc_extensions_builder = {
+		let client = client.clone();
+		let epoch_stats = ethash_alg.epoch_stats();
+		Box::new(move |_, _| {
+			let deps = crate::rpc::LightDeps {
+				client: client.clone(),
+				epoch_stats: epoch_stats.clone(),
+			};
+
+			crate::rpc::create_light(deps)
+		})
+	};
 
 	let (network, network_status_sinks, system_rpc_tx, network_starter) =
 		sc_service::build_network(sc_service::BuildNetworkParams {
@@ -295,7 +775,7 @@ pub fn new_light(config: Configuration) -> Result<TaskManager, ServiceError> {
 		transaction_pool,
 		task_manager: &mut task_manager,
 		on_demand: Some(on_demand),
-		rpc_extensions_builder: Box::new(|_, _| ()),
+		rpc_extensions_builder,
 		config,
 		client,
 		keystore: keystore_container.sync_keystore(),
@@ -310,63 +790,853 @@ pub fn new_light(config: Configuration) -> Result<TaskManager, ServiceError> {
 	Ok(task_manager)
 }
 
-pub async fn run_mining_svc<B, Algorithm, C, CS>(
+/// Events driving the mining service.
+enum MiningEvent<B: BlockT> {
+	/// A command sent by the RPC, or a batch of work requests.
+	Command(QueuedCommand),
+	/// The mining worker started a new build, or dropped the current one.
+	Metadata(Option<MiningMetadata<H256, U256, B>>),
+	/// A new best block was imported, retracting this many blocks from the best chain.
+	NewBest { hash: H256, retracted: usize },
+	/// Time to persist a hashrate snapshot.
+	SnapshotHashrate,
+	/// The number of connected peers.
+	Peers(usize),
+	/// Time to refresh the metric gauges.
+	RefreshMetrics,
+	/// The process was asked to dump the mining statistics.
+	DumpStats,
+	/// The node is stopping.
+	Shutdown,
+}
+
+/// How often the mining service checks the peer count against `--min-mining-peers`.
+const PEER_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A solution accepted by `submit_work`.
+struct AcceptedSolution {
+	/// Difficulty the solution meets, at most the block difficulty.
+	difficulty: U256,
+	/// The hash of the block it sealed, if it met the block difficulty.
+	block: Option<H256>,
+}
+
+/// Check a solution without taking the worker lock: its signature, that its nonce is in the
+/// nonce partition of the node, and against the work snapshot and the work tracker, that its job
+/// is of the current epoch, that it isn't a duplicate and that it meets the share boundary.
+/// Solutions failing these cheap checks never contend with the mining worker. Passing them, a
+/// solution still goes through `submit_work`.
+///
+/// Jobs unknown to the tracker may be recent builds of the current block pushed to stratum or
+/// subscribers, only the boundary of the current block is checked for them. The share boundary
+/// is the one of `share_difficulty`, the share difficulty of the submitting worker.
+#[allow(clippy::too_many_arguments)]
+fn prevalidate_solution(
+	config: &MiningConfig,
+	work_snapshot: &WorkSnapshot,
+	work_tracker: &WorkTracker,
+	worker_keys: &WorkerKeys,
+	nonce: u64,
+	pow_hash: H256,
+	mix_digest: &H256,
+	signature: Option<&(String, sp_core::sr25519::Signature)>,
+	share_difficulty: Option<U256>,
+) -> Result<(), RpcError> {
+	// Checked before the work tracker, so a forged submission doesn't take the
+	// place of the genuine one.
+	match signature {
+		Some((worker, signature)) => {
+			if !worker_keys.verify(worker, nonce, &pow_hash, signature) {
+				return Err(RpcError::InvalidWorkerSignature(worker.clone()));
+			}
+		}
+		None if config.require_signed_shares => return Err(RpcError::UnsignedShare),
+		None => {}
+	}
+	// Another node of the farm searches this nonce, its solution would be paid twice.
+	if !config.nonce_partition.contains(nonce) {
+		return Err(RpcError::NonceOutsidePartition(nonce));
+	}
+	// Without work handed out, as after `miner_shutdown`, the worker knows best.
+	let current = match work_snapshot.load() {
+		Some(current) => current,
+		None => return Ok(()),
+	};
+	let current_nr = current.work.number.unwrap_or_default();
+	let difficulty = match work_tracker.job(&pow_hash) {
+		Some((number, difficulty)) => {
+			let epoch = current_nr / ETHASH_EPOCH_LENGTH;
+			if number / ETHASH_EPOCH_LENGTH != epoch {
+				return Err(RpcError::wrong_epoch(epoch, number / ETHASH_EPOCH_LENGTH));
+			}
+			if work_tracker.is_duplicate(pow_hash, nonce) {
+				return Err(RpcError::duplicate_share());
+			}
+			difficulty
+		}
+		None => current.difficulty,
+	};
+	let found = solution_difficulty(&pow_hash, nonce, mix_digest);
+	let expected = share_difficulty.map_or(difficulty, |share| share.min(difficulty));
+	if found < expected {
+		return Err(RpcError::low_difficulty_share(expected, found));
+	}
+	Ok(())
+}
+
+/// Check a solution submitted by a miner, sealing the block if it meets the block difficulty, or
+/// crediting it as a share if it meets `share_difficulty`, the one of the submitting worker.
+/// Its signature is checked by `prevalidate_solution`.
+#[allow(clippy::too_many_arguments)]
+fn submit_work<B, Algorithm, C>(
+	worker: &Mutex<MiningWorker<B, Algorithm, C>>,
+	verifier: &MinimalEthashAlgorithm,
+	config: &MiningConfig,
+	work_tracker: &mut WorkTracker,
+	nonce: H64,
+	pow_hash: H256,
+	mix_digest: H256,
+	share_difficulty: Option<U256>,
+) -> Result<AcceptedSolution, RpcError>
+	where
+	B: BlockT<Hash = H256>,
+	Algorithm: PowAlgorithm<B, Difficulty = U256>,
+	C: sp_api::ProvideRuntimeApi<B>,
+{
+	let mut worker = worker.lock();
+	let current = worker.metadata().ok_or(RpcError::NoMetaData)?;
+	// Jobs replaced by a newer build of the same block stay open, so a miner reconnecting
+	// mid-round can still submit the job it got before the disconnect.
+	let open = worker.metadata_for(&pow_hash);
+	let non_nr = nonce.to_low_u64_be();
+	// Number and difficulty of the block the work package seals.
+	let work = match &open {
+		Some(metadata) => {
+			let header_nr :u64 = UniqueSaturatedInto::<u64>::unique_saturated_into(metadata.number);
+			work_tracker.check(pow_hash, non_nr, metadata.pre_hash, header_nr)
+				.map(|()| (header_nr, metadata.difficulty))
+		}
+		// The block of the package can't be built anymore, its solutions only count as shares.
+		None => {
+			let current_nr :u64 = UniqueSaturatedInto::<u64>::unique_saturated_into(current.number);
+			let window = share_difficulty.map_or(0, |_| config.stale_share_window);
+			work_tracker.check_replaced(pow_hash, non_nr, current_nr, window)
+		}
+	};
+	let accepted = work
+		.and_then(|(header_nr, difficulty)| {
+			let found = solution_difficulty(&pow_hash, non_nr, &mix_digest);
+			let expected = share_difficulty.map_or(difficulty, |share| share.min(difficulty));
+			if found < expected {
+				Err(RpcError::low_difficulty_share(expected, found))
+			} else {
+				Ok((found, header_nr, difficulty))
+			}
+		})
+		.and_then(|(found, header_nr, difficulty)| {
+			// The difficulty above trusts the mix digest of the miner, recompute it.
+			// The seal commits to the timestamp inherent of the block; the solutions of replaced
+			// work only count as shares and are never sealed.
+			let timestamp = open.as_ref().map_or(0, |metadata| metadata.timestamp);
+			let seal = WorkSeal{nonce:non_nr, pow_hash, mix_digest, difficulty: found.min(difficulty), header_nr, timestamp};
+			verifier.verify_seal(&seal, header_nr).map(|()| (seal, difficulty)).map_err(RpcError::InvalidSeal)
+		});
+	match (accepted, open) {
+		(Ok((seal, difficulty)), _) if seal.difficulty < difficulty => {
+			trace!(target:"pow", "share for {} below the block difficulty", pow_hash);
+			work_tracker.accept(pow_hash, non_nr);
+			Ok(AcceptedSolution { difficulty: seal.difficulty, block: None })
+		}
+		(Ok((seal, _)), Some(metadata)) => {
+			debug!(target:"pow", "worker.submit pow_hash: {}", pow_hash);
+			match worker.submit_for_block(&metadata.pre_hash, seal.encode_versioned()) {
+				Some(hash) => {
+					work_tracker.accept(pow_hash, non_nr);
+					Ok(AcceptedSolution { difficulty: seal.difficulty, block: Some(hash) })
+				}
+				None => Err(RpcError::SealedBlockNotImported(pow_hash)),
+			}
+		}
+		(Ok(_), None) => {
+			debug!(target:"pow", "block solution for replaced work {}", pow_hash);
+			Err(RpcError::stale_work())
+		}
+		(Err(err), _) => {
+			debug!(target:"pow", "rejected solution for {}: {}", pow_hash, err);
+			Err(err)
+		}
+	}
+}
+
+/// Persist a snapshot of the hashrate of the miners and of the network.
+fn persist_hashrate<A: AuxStore>(
+	aux: &A,
+	miner_hashrates: &mut MinerHashrates,
+	work_snapshot: &WorkSnapshot,
+	target_block_time: u64,
+) {
+	// Every hash meets the boundary with probability 1 / difficulty, so the network
+	// needs `difficulty` hashes per block on average.
+	let network = work_snapshot.load()
+		.map(|current| current.difficulty / U256::from(target_block_time.max(1)))
+		.unwrap_or_default();
+	let snapshot = HashrateSnapshot {
+		timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+		miners: miner_hashrates.total(),
+		network,
+	};
+	if let Err(err) = hashrate::write_snapshot(aux, snapshot) {
+		warn!(target:"pow", "Failed to persist hashrate snapshot: {:?}", err);
+	}
+}
+
+/// Persist the totals of the miners, if they changed.
+fn persist_miner_shares<A: AuxStore>(aux: &A, miner_shares: &mut MinerShares) {
+	if let Err(err) = miner_shares.persist(aux) {
+		warn!(target:"pow", "Failed to persist the miner stats: {:?}", err);
+	}
+}
+
+/// Check that the local executor can author on top of the best block of `client`, so no work is
+/// issued for blocks whose proposals would fail. The outcome is kept in `checked` until the best
+/// block changes, as running the check on every poll would be too costly.
+fn check_can_author<B, C, CAW>(
+	client: &C,
+	can_author_with: &CAW,
+	checked: &mut Option<(H256, Result<(), String>)>,
+) -> Result<(), RpcError>
+	where
+	B: BlockT<Hash = H256>,
+	C: HeaderBackend<B>,
+	CAW: CanAuthorWith<B>,
+{
+	let best_hash = client.info().best_hash;
+	if checked.as_ref().map(|(hash, _)| *hash) != Some(best_hash) {
+		let outcome = can_author_with.can_author_with(&BlockId::Hash(best_hash));
+		if let Err(reason) = &outcome {
+			warn!(target:"pow", "Cannot author on top of {}: {}", best_hash, reason);
+		}
+		*checked = Some((best_hash, outcome));
+	}
+	match checked {
+		Some((_, Err(reason))) => Err(RpcError::CannotAuthor(reason.clone())),
+		_ => Ok(()),
+	}
+}
+
+/// Whether `current` is older than the `no_new_work_timeout` of a work request, in seconds: as
+/// with Parity, the miner learns that no new block came for that long.
+fn is_stalled(current: Option<&CurrentWork>, no_new_work_timeout: Option<u64>) -> bool {
+	match (current, no_new_work_timeout) {
+		(Some(current), Some(timeout)) => current.created.elapsed() > Duration::from_secs(timeout),
+		_ => false,
+	}
+}
+
+/// Answer `requests` from one read of the work snapshot, issuing the work once for all of them.
+#[allow(clippy::too_many_arguments)]
+fn answer_work_requests<B, A, CAW>(
+	requests: Vec<WorkRequest>,
+	aux: &A,
+	config: &MiningConfig,
+	work_snapshot: &WorkSnapshot,
+	share_difficulties: &ShareDifficulties,
+	work_tracker: &mut WorkTracker,
+	work_age: &WorkAge,
+	can_author_with: &CAW,
+	can_author: &mut Option<(H256, Result<(), String>)>,
+	short_of_peers: Option<usize>,
+	stopped: bool,
+)
+	where
+	B: BlockT<Hash = H256>,
+	A: HeaderBackend<B>,
+	CAW: CanAuthorWith<B>,
+{
+	let current = work_snapshot.load();
+	let mut issued = false;
+	for WorkRequest { no_new_work_timeout, worker, mut sender } in requests {
+		// Each request gets its own error, `RpcError` can't be cloned.
+		let result = if config.author.is_none() {
+			Err(RpcError::no_author())
+		} else if stopped {
+			Err(RpcError::MiningStopped)
+		} else if let Some(connected) = short_of_peers {
+			let required = config.min_peers.unwrap_or_default();
+			Err(RpcError::NotEnoughPeers { connected, required })
+		} else if let Err(err) = check_can_author(aux, can_author_with, can_author) {
+			Err(err)
+		} else if is_stalled(current.as_deref(), no_new_work_timeout) {
+			Err(RpcError::no_new_work())
+		} else if work_age.is_ancient(config.max_work_age) {
+			// Until the next tick drops it from the snapshot.
+			Err(RpcError::NoWork)
+		} else if let Some(current) = &current {
+			let work = share_difficulties.work_for(worker.as_deref(), current);
+			if !issued {
+				work_tracker.issue(work.pow_hash, work.number.unwrap_or_default(), current.difficulty);
+				issued = true;
+			}
+			Ok(work)
+		} else {
+			Err(RpcError::NoWork)
+		};
+		send_result(&mut sender, result)
+	}
+}
+
+/// The statistics of the mining service, for `stats_dump`.
+fn mining_stats(
+	work_snapshot: &WorkSnapshot,
+	work_tracker: &WorkTracker,
+	miner_hashrates: &mut MinerHashrates,
+	miner_shares: &mut MinerShares,
+	short_of_peers: Option<usize>,
+	stopped: bool,
+) -> MiningStatsDump {
+	let miners = miner_shares.stats();
+	MiningStatsDump {
+		timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+		current_work: work_snapshot.load().as_deref().map(Into::into),
+		stopped,
+		short_of_peers,
+		reported_hashrate: miner_hashrates.total(),
+		shares: miners.iter().map(|miner| miner.shares).sum(),
+		blocks: miners.iter().map(|miner| miner.blocks).sum(),
+		miners,
+		jobs: work_tracker.jobs(),
+	}
+}
+
+/// Check `secret` against the one configured for the miner control RPCs.
+fn check_control_secret(config: &MiningConfig, secret: &str) -> Result<(), RpcError> {
+	match &config.control_secret {
+		None => Err(RpcError::MinerControlDisabled),
+		// Compared by digest, so the time taken doesn't tell how much of the secret is right.
+		Some(expected) if blake2_256(expected.as_bytes()) == blake2_256(secret.as_bytes()) => Ok(()),
+		Some(_) => Err(RpcError::InvalidMinerControlSecret),
+	}
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn run_mining_svc<B, Algorithm, C, A, CS, NB, PS, DS, EX, CAW, SO>(
 	worker : Arc<Mutex<MiningWorker<B, Algorithm, C>>>,
-	mut commands_stream: CS,
+	aux: Arc<A>,
+	target_block_time: u64,
+	config: MiningConfig,
+	work_snapshot: WorkSnapshot,
+	extra_data: ExtraData,
+	min_fee: MinFee,
+	local_miner: LocalMiner,
+	share_difficulties: ShareDifficulties,
+	commands_stream: CS,
+	new_best_blocks: NB,
+	peer_counts: PS,
+	dump_signals: DS,
+	exit: EX,
+	metrics: Option<MiningMetrics>,
+	verifier: MinimalEthashAlgorithm,
+	can_author_with: CAW,
+	mut sync_oracle: SO,
 )
 	where 
 	B: BlockT<Hash = H256>,
 	Algorithm: PowAlgorithm<B, Difficulty = U256>,
 	C: sp_api::ProvideRuntimeApi<B>,
-	CS: Stream<Item=EtheminerCmd<<B as BlockT>::Hash>> + Unpin + 'static,
+	A: AuxStore + HeaderBackend<B>,
+	CS: Stream<Item=EtheminerCmd> + Unpin + 'static,
+	NB: Stream<Item=(H256, usize)> + Send + 'static,
+	PS: Stream<Item=usize> + Send + 'static,
+	DS: Stream<Item=()> + Send + Unpin + 'static,
+	EX: Future<Output=()> + Send + Unpin + 'static,
+	CAW: CanAuthorWith<B>,
+	SO: SyncOracle,
 {
-	let seed_compute = SeedHashCompute::default();
+	let stats_file = config.stats_file.clone().unwrap_or_else(|| PathBuf::from("mining-stats.json"));
+	// Shared with the algorithm checking the seals, which computes the same seed hashes.
+	let seeds = verifier.seeds().clone();
+	let mut work_cache = WorkCache::default();
+	let mut miner_hashrates = MinerHashrates::default();
+	let mut miner_shares = MinerShares::load(&*aux).unwrap_or_else(|err| {
+		warn!(target:"pow", "Failed to load the miner stats, starting over: {:?}", err);
+		MinerShares::default()
+	});
+	let mut audit_log = AuditLog::open(config.audit_log.as_deref()).unwrap_or_else(|err| {
+		warn!(target:"pow", "Failed to open the mining audit log, not auditing: {}", err);
+		AuditLog::default()
+	});
+	let mut work_tracker = WorkTracker::default();
+	let mut work_age = WorkAge::default();
+	let mut worker_activity = WorkerActivity::default();
+	let mut worker_keys = WorkerKeys::default();
+	let mut submissions = Submissions::default();
+	let mut nonce_leases = NonceLeases::default();
+	// Best block the runtime compatibility was last checked against, and the outcome.
+	let mut can_author: Option<(H256, Result<(), String>)> = None;
+	// Peers connected when below `config.min_peers`: no work is issued nor solution sealed meanwhile.
+	let mut short_of_peers: Option<usize> = None;
+	// Set by `miner_shutdown` and `pow_stopMining`: no work is issued, solutions for the work
+	// already out are sealed.
+	let mut stopped = false;
+
+	// `eth_getWork` is served from the snapshot, the worker lock is only taken for submissions.
+	let (metadata_stream, metadata) = {
+		let mut worker = worker.lock();
+		(worker.metadata_stream(), worker.metadata())
+	};
+	if let Some(metadata) = &metadata {
+		work_age.note(metadata.best_hash);
+	}
+	let current = work_cache.update(metadata, &seeds, config.share_difficulty);
+	if let Some(current) = &current {
+		audit_log.job(current);
+	}
+	work_snapshot.store(current);
+
+	let snapshot_ticks = stream::unfold((), |()| async {
+		Delay::new(hashrate::SNAPSHOT_INTERVAL).await;
+		Some((MiningEvent::SnapshotHashrate, ()))
+	});
+	let metrics_ticks = stream::unfold((), |()| async {
+		Delay::new(metrics::REFRESH_INTERVAL).await;
+		Some((MiningEvent::RefreshMetrics, ()))
+	});
+	let mut events = stream::select(
+		stream::select(
+			// Full solutions are handled ahead of the shares waiting in the channel, and the
+			// work requests waiting are answered together.
+			SubmissionQueue::new(commands_stream, work_snapshot.clone()).map(MiningEvent::Command),
+			stream::select(
+				metadata_stream.map(MiningEvent::Metadata),
+				Box::pin(new_best_blocks.map(|(hash, retracted)| MiningEvent::NewBest { hash, retracted })),
+			),
+		),
+		stream::select(
+			stream::select(Box::pin(snapshot_ticks), Box::pin(metrics_ticks)),
+			stream::select(
+				Box::pin(peer_counts.map(MiningEvent::Peers)),
+				stream::select(
+					dump_signals.map(|()| MiningEvent::DumpStats),
+					exit.into_stream().map(|()| MiningEvent::Shutdown),
+				),
+			),
+		),
+	);
+
+	while let Some(event) = events.next().await {
+		let command = match event {
+			MiningEvent::Command(QueuedCommand::Command(command)) => command,
+			MiningEvent::Command(QueuedCommand::WorkRequests(requests)) => {
+				worker_activity.note();
+				if let Some(metrics) = &metrics {
+					for _ in &requests {
+						metrics.note_get_work();
+					}
+				}
+				answer_work_requests(
+					requests,
+					&*aux,
+					&config,
+					&work_snapshot,
+					&share_difficulties,
+					&mut work_tracker,
+					&work_age,
+					&can_author_with,
+					&mut can_author,
+					short_of_peers,
+					stopped,
+				);
+				continue;
+			}
+			MiningEvent::Metadata(metadata) => {
+				if let Some(metadata) = &metadata {
+					let ancient = work_age.is_ancient(config.max_work_age);
+					work_age.note(metadata.best_hash);
+					if ancient && !work_age.is_ancient(config.max_work_age) {
+						info!(target:"pow", "Building on the new block {}: handing out work again", metadata.best_hash);
+					}
+				}
+				// Cached even while no work is issued, to resume from without the worker lock.
+				let current = work_cache.update(metadata, &seeds, config.share_difficulty);
+				// The worker keeps rebuilding on an ancient parent, its proposals aren't work.
+				if short_of_peers.is_none() && !stopped && !work_age.is_ancient(config.max_work_age) {
+					if let Some(current) = &current {
+						audit_log.job(current);
+					}
+					work_snapshot.store(current);
+				}
+				continue;
+			}
+			MiningEvent::NewBest { hash, retracted } => {
+				// The worker notifies the dropped build, which clears the snapshot, and the new
+				// build, which hands out the new work.
+				if worker.lock().on_new_best(&hash) {
+					if retracted > 0 {
+						info!(
+							target:"pow",
+							"Reorg of {} blocks to {}, dropped the work of the stale branch",
+							retracted,
+							hash,
+						);
+					} else {
+						debug!(target:"pow", "New best block {}, dropped the stale work", hash);
+					}
+				}
+				continue;
+			}
+			MiningEvent::Peers(connected) => {
+				let required = match config.min_peers {
+					Some(required) => required,
+					None => continue,
+				};
+				match (short_of_peers.is_some(), connected < required) {
+					(false, true) => {
+						warn!(target:"pow", "Only {} peers connected, {} required: pausing mining", connected, required);
+						// Subscribers, stratum clients and `eth_mining` see there is no work.
+						work_snapshot.store(None);
+						short_of_peers = Some(connected);
+					}
+					(true, true) => short_of_peers = Some(connected),
+					(true, false) => {
+						info!(target:"pow", "{} peers connected: resuming mining", connected);
+						short_of_peers = None;
+						if !stopped && !work_age.is_ancient(config.max_work_age) {
+							work_snapshot.store(work_cache.latest());
+						}
+					}
+					(false, false) => {}
+				}
+				continue;
+			}
+			MiningEvent::SnapshotHashrate => {
+				persist_hashrate(&*aux, &mut miner_hashrates, &work_snapshot, target_block_time);
+				persist_miner_shares(&*aux, &mut miner_shares);
+				continue;
+			}
+			MiningEvent::RefreshMetrics => {
+				if work_age.is_ancient(config.max_work_age) {
+					if work_age.report(config.max_work_age) {
+						warn!(
+							target:"pow",
+							"No new block for {}s: the work is too old to be worth mining, no work is \
+							handed out until a block is imported",
+							work_age.age().unwrap_or_default().as_secs(),
+						);
+					}
+					if work_snapshot.load().is_some() {
+						// Subscribers, stratum clients and `eth_mining` see there is no work.
+						work_snapshot.store(None);
+					}
+				}
+				if let Some(metrics) = &metrics {
+					metrics.refresh(work_snapshot.load().as_deref(), miner_hashrates.total());
+				}
+				continue;
+			}
+			MiningEvent::DumpStats => {
+				let stats = mining_stats(
+					&work_snapshot,
+					&work_tracker,
+					&mut miner_hashrates,
+					&mut miner_shares,
+					short_of_peers,
+					stopped,
+				);
+				match stats_dump::write(&stats_file, &stats) {
+					Ok(()) => info!(target:"pow", "Mining statistics dumped to {}", stats_file.display()),
+					Err(err) => warn!(target:"pow", "Failed to dump the mining statistics to {}: {}", stats_file.display(), err),
+				}
+				continue;
+			}
+			MiningEvent::Shutdown => {
+				info!(target:"pow", "Mining service shutting down");
+				break;
+			}
+		};
 
-	while let Some(command) = commands_stream.next().await {
 		match command {
-			EtheminerCmd::GetWork { mut sender } => {
-				let metadata = worker.lock().metadata();
-				if let Some(metadata) = metadata {
-					let nr :u64 = UniqueSaturatedInto::<u64>::unique_saturated_into(metadata.number);
-					let pow_hash:H256 = metadata.pre_hash;
-					let seed_hash:H256 = seed_compute.hash_block_number(nr).into();
-					let tmp:[u8; 32] = metadata.difficulty.into();
-					let tmp:[u8; 32] = ethash::difficulty_to_boundary(&EU256::from(tmp)).into();
-					let target:H256 = H256::from(tmp);
-
-					let ret = Ok(Work { 
-						pow_hash, 
-						seed_hash,
-						target, 
-						number: Some(nr),
-					 });
-
-					ethash_rpc::send_result(&mut sender, ret)
-					// ethash_rpc::send_result(&mut sender, future.await)
-				} else {
-					ethash_rpc::send_result(&mut sender, Err(RpcError::NoWork))
+			// Batched by the submission queue, answered with the other requests above.
+			EtheminerCmd::GetWork { no_new_work_timeout, worker, sender } => {
+				worker_activity.note();
+				if let Some(metrics) = &metrics {
+					metrics.note_get_work();
+				}
+				answer_work_requests(
+					vec![WorkRequest { no_new_work_timeout, worker, sender }],
+					&*aux,
+					&config,
+					&work_snapshot,
+					&share_difficulties,
+					&mut work_tracker,
+					&work_age,
+					&can_author_with,
+					&mut can_author,
+					short_of_peers,
+					stopped,
+				);
+			}
+			EtheminerCmd::SubmitWork { nonce, pow_hash, mix_digest, signature, miner, submission_id, mut sender } => {
+				worker_activity.note();
+				let replayed = submission_id
+					.as_ref()
+					.and_then(|id| submissions.replay(pow_hash, id, nonce, mix_digest));
+				let retry = replayed.is_some();
+				let mut sealed = false;
+				let result = match (replayed, short_of_peers) {
+					(Some(replayed), _) => {
+						debug!(target:"pow", "retried submission for {}", pow_hash);
+						replayed
+					}
+					// A block sealed now would most likely be orphaned.
+					(None, Some(connected)) => {
+						let required = config.min_peers.unwrap_or_default();
+						Err(RpcError::NotEnoughPeers { connected, required })
+					}
+					// The worker builds no block meanwhile, the work is for a block far behind.
+					(None, None) if sync_oracle.is_major_syncing() => Err(RpcError::node_syncing()),
+					(None, None) => {
+						let share_difficulty = share_difficulties.of(miner.as_deref(), config.share_difficulty);
+						let accepted = prevalidate_solution(
+							&config,
+							&work_snapshot,
+							&work_tracker,
+							&worker_keys,
+							nonce.to_low_u64_be(),
+							pow_hash,
+							&mix_digest,
+							signature.as_ref(),
+							share_difficulty,
+						)
+						.map_err(|err| {
+							debug!(target:"pow", "rejected solution for {} before sealing: {}", pow_hash, err);
+							err
+						})
+						.and_then(|()| submit_work(
+							&worker,
+							&verifier,
+							&config,
+							&mut work_tracker,
+							nonce,
+							pow_hash,
+							mix_digest,
+							share_difficulty,
+						));
+						if let Ok(solution) = &accepted {
+							let miner = miner.as_deref().unwrap_or(miner_stats::UNNAMED_MINER);
+							miner_shares.credit(miner, solution.difficulty, solution.block.is_some());
+							sealed = solution.block.is_some();
+						}
+						let result = accepted.map(|solution| SubmittedWork { block_hash: solution.block });
+						if let Some(id) = submission_id {
+							submissions.record(pow_hash, id, nonce, mix_digest, &result);
+						}
+						result
+					}
+				};
+				if let (Some(metrics), false) = (&metrics, retry) {
+					metrics.note_share(result.is_ok());
+				}
+				audit_log.submission(pow_hash, nonce, mix_digest, miner.as_deref(), &result, sealed);
+				send_result(&mut sender, result)
+			}
+			EtheminerCmd::LeaseNonceRange { job_id, size, mut sender } => {
+				let result = match work_snapshot.load() {
+					Some(current) => nonce_leases.lease(job_id, size, current.work.pow_hash),
+					None => Err(RpcError::NoWork),
+				};
+				if let Ok(lease) = &result {
+					debug!(target:"pow", "leased nonces {} to {} of {}", lease.start, lease.end, job_id);
 				}
+				send_result(&mut sender, result)
 			}
-			EtheminerCmd::SubmitWork {  nonce, pow_hash, mix_digest, mut sender } => {
-				let mut worker = worker.lock();
-				let metadata = worker.metadata();
-				if let Some(metadata) = metadata {
-					let non_nr :u64 = UniqueSaturatedInto::<u64>::unique_saturated_into(nonce);
-					let header_nr :u64 = UniqueSaturatedInto::<u64>::unique_saturated_into(metadata.number);
-					let timestamp :u64 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-					let seal = WorkSeal{nonce:non_nr, pow_hash, mix_digest, difficulty:metadata.difficulty, header_nr, timestamp};
-					debug!(target:"pow", "worker.submit pow_hash: {}", pow_hash);
-					worker.submit(seal.encode());
-					ethash_rpc::send_result(&mut sender, Ok(true))
+			EtheminerCmd::SubmitHashrate { rate, id, mut sender } => {
+				trace!(target:"pow", "miner {} reported {} H/s", id, rate);
+				miner_hashrates.submit(id, rate);
+				worker_activity.note();
+				send_result(&mut sender, Ok(true))
+			}
+			EtheminerCmd::Hashrate { mut sender } => {
+				send_result(&mut sender, Ok(miner_hashrates.total()))
+			}
+			EtheminerCmd::MinerStats { mut sender } => {
+				send_result(&mut sender, Ok(miner_shares.stats()))
+			}
+			EtheminerCmd::DumpStats { mut sender } => {
+				let stats = mining_stats(
+					&work_snapshot,
+					&work_tracker,
+					&mut miner_hashrates,
+					&mut miner_shares,
+					short_of_peers,
+					stopped,
+				);
+				let result = stats_dump::write(&stats_file, &stats)
+					.map(|()| stats_file.display().to_string())
+					.map_err(|err| RpcError::StatsDump(err.to_string()));
+				send_result(&mut sender, result)
+			}
+			EtheminerCmd::SetExtra { extra, mut sender } => {
+				let result = extra_data.set(extra.clone()).map(|()| {
+					info!(target:"pow", "Extra data of the next blocks set to 0x{}", HexDisplay::from(&extra));
+					true
+				});
+				send_result(&mut sender, result)
+			}
+			EtheminerCmd::SetMinFee { fee, mut sender } => {
+				info!(target:"pow", "Minimum fee of the transactions included set to {}", fee);
+				min_fee.set(fee);
+				send_result(&mut sender, Ok(true))
+			}
+			EtheminerCmd::Coinbase { mut sender } => {
+				// The account rewarded for the block being mined, or the next one without work.
+				let number = match work_snapshot.load() {
+					Some(current) => current.work.number.unwrap_or_default(),
+					None => UniqueSaturatedInto::<u64>::unique_saturated_into(aux.info().best_number) + 1,
+				};
+				let author = config.author.as_ref().map(|authors| authors.author_at(number).clone());
+				send_result(&mut sender, author.ok_or_else(RpcError::no_author))
+			}
+			EtheminerCmd::Mining { mut sender } => {
+				let mining = work_snapshot.load().is_some() && worker_activity.is_active();
+				send_result(&mut sender, Ok(mining))
+			}
+			EtheminerCmd::SetShareDifficulty { worker, difficulty, mut sender } => {
+				if difficulty.map_or(false, |d| d.is_zero()) {
+					send_result(&mut sender, Err(RpcError::ZeroShareDifficulty))
 				} else {
-					ethash_rpc::send_result(&mut sender, Err(RpcError::NoMetaData))
+					debug!(target:"pow", "share difficulty of {} set to {:?}", worker, difficulty);
+					share_difficulties.set(worker, difficulty);
+					send_result(&mut sender, Ok(true))
+				}
+			}
+			EtheminerCmd::ShareDifficulty { worker, mut sender } => {
+				send_result(&mut sender, Ok(share_difficulties.get(&worker)))
+			}
+			EtheminerCmd::SetWorkerKey { worker, key, mut sender } => {
+				debug!(target:"pow", "key of {} set to {:?}", worker, key);
+				worker_keys.set(worker, key);
+				send_result(&mut sender, Ok(true))
+			}
+			EtheminerCmd::Shutdown { secret, mut sender } => {
+				let result = check_control_secret(&config, &secret).map(|()| {
+					if !stopped {
+						info!(target:"pow", "Mining stopped by miner_shutdown");
+						persist_hashrate(&*aux, &mut miner_hashrates, &work_snapshot, target_block_time);
+						persist_miner_shares(&*aux, &mut miner_shares);
+						work_snapshot.store(None);
+						stopped = true;
+					}
+					true
+				});
+				send_result(&mut sender, result)
+			}
+			EtheminerCmd::StopMining { mut sender } => {
+				if !stopped {
+					info!(target:"pow", "Mining stopped by pow_stopMining");
+					persist_hashrate(&*aux, &mut miner_hashrates, &work_snapshot, target_block_time);
+					persist_miner_shares(&*aux, &mut miner_shares);
+					work_snapshot.store(None);
+					stopped = true;
 				}
+				send_result(&mut sender, Ok(true))
+			}
+			EtheminerCmd::StartMining { threads, mut sender } => {
+				if let Some(threads) = threads {
+					local_miner.set_threads(threads);
+				}
+				if stopped {
+					info!(target:"pow", "Mining started by pow_startMining");
+					stopped = false;
+					// Ancient work stays withheld until a block is imported.
+					if short_of_peers.is_none() && !work_age.is_ancient(config.max_work_age) {
+						let current = work_cache.latest();
+						if let Some(current) = &current {
+							audit_log.job(current);
+						}
+						work_snapshot.store(current);
+					}
+				}
+				send_result(&mut sender, Ok(true))
+			}
+			EtheminerCmd::SetThreads { threads, mut sender } => {
+				info!(target:"pow", "Local mining threads set to {}", threads);
+				local_miner.set_threads(threads);
+				send_result(&mut sender, Ok(true))
+			}
+			EtheminerCmd::MiningStatus { mut sender } => {
+				let current = work_snapshot.load();
+				let status = MiningStatus {
+					mining: current.is_some() && worker_activity.is_active(),
+					stopped,
+					short_of_peers,
+					threads: local_miner.threads(),
+					hashrate: miner_hashrates.total(),
+					pow_hash: current.as_ref().map(|current| current.work.pow_hash),
+					number: current.as_ref().and_then(|current| current.work.number),
+					difficulty: current.as_ref().map(|current| current.difficulty),
+				};
+				send_result(&mut sender, Ok(status))
+			}
+			EtheminerCmd::Difficulty { mut sender } => {
+				// The block being built is still known to the worker while no work is issued.
+				let difficulty = match work_snapshot.load() {
+					Some(current) => Some(current.difficulty),
+					None => work_cache.latest().map(|current| current.difficulty),
+				};
+				send_result(&mut sender, difficulty.ok_or(RpcError::NoWork))
+			}
+			EtheminerCmd::LastMinedBlock { mut sender } => {
+				let result = mined_blocks::latest::<B, _>(&*aux, 1)
+					.map(|mut latest| latest.pop())
+					.map_err(RpcError::from);
+				send_result(&mut sender, result)
+			}
+			EtheminerCmd::WorkAge { mut sender } => {
+				send_result(&mut sender, Ok(work_age.age().map(|age| age.as_secs())))
+			}
+			EtheminerCmd::RestartWorker { secret, mut sender } => {
+				let result = check_control_secret(&config, &secret).map(|()| {
+					info!(target:"pow", "Mining restarted by miner_restartWorker");
+					stopped = false;
+					miner_hashrates = MinerHashrates::default();
+					work_tracker = WorkTracker::default();
+					worker_activity = WorkerActivity::default();
+					submissions = Submissions::default();
+					nonce_leases = NonceLeases::default();
+					if short_of_peers.is_none() && !work_age.is_ancient(config.max_work_age) {
+						let current = work_cache.latest();
+						// The tracker starts afresh with the job handed out again.
+						if let Some(current) = &current {
+							work_tracker.issue(current.work.pow_hash, current.work.number.unwrap_or_default(), current.difficulty);
+						}
+						work_snapshot.store(current);
+					}
+					true
+				});
+				send_result(&mut sender, result)
+			}
+		}
+	}
 
-						
+	// Answer the commands queued and waiting in the channel rather than dropping their senders.
+	// The requests sent once the service is gone fail to reach it.
+	let mut rejected = 0;
+	while let Some(Some(event)) = events.next().now_or_never() {
+		match event {
+			MiningEvent::Command(QueuedCommand::Command(command)) => {
+				command.reject(RpcError::ServerShuttingDown);
+				rejected += 1;
 			}
-			EtheminerCmd::SubmitHashrate { hash, mut sender } => {
-				
+			MiningEvent::Command(QueuedCommand::WorkRequests(requests)) => {
+				for mut request in requests {
+					send_result(&mut request.sender, Err(RpcError::ServerShuttingDown));
+					rejected += 1;
+				}
 			}
+			_ => {}
 		}
 	}
+	if rejected > 0 {
+		info!(target:"pow", "Answered {} pending mining requests before shutting down", rejected);
+	}
 }
@@ -0,0 +1,173 @@
+//! The `simulate-mining` subcommand: a quick way to see how the retarget rules of this chain
+//! react to a given hashrate before launching a network with them.
+//!
+//! The difficulty is retargeted by the `RetargetStrategy` of the difficulty pallet, with the
+//! retarget parameters of the runtime, so the simulation follows the chain block for block.
+
+use difficulty::{BlockSample, DifficultyStrategy, RetargetParams, RetargetStrategy};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use sp_core::U256;
+use std::cmp;
+use structopt::StructOpt;
+
+/// Simulate block production for a constant hashrate and report the resulting block intervals
+/// and difficulty trajectory.
+#[derive(Debug, StructOpt)]
+pub struct SimulateMiningCmd {
+	/// Network hashrate in hashes per second.
+	#[structopt(long)]
+	pub hashrate: f64,
+
+	/// Number of blocks to simulate.
+	#[structopt(long, default_value = "1000")]
+	pub blocks: u64,
+
+	/// Difficulty of the first simulated block. Defaults to the minimum difficulty.
+	#[structopt(long)]
+	pub initial_difficulty: Option<u64>,
+
+	/// The retarget rule, by the name chain specs choose it with: parent-delta, eip100, lwma or
+	/// fixed.
+	#[structopt(long, default_value = "parent-delta", parse(try_from_str = parse_strategy))]
+	pub strategy: RetargetStrategy,
+
+	/// The retargeted difficulty never drops below this value. Defaults to the one of the local
+	/// testnet chain spec.
+	#[structopt(long, default_value = "1000000")]
+	pub minimum_difficulty: u64,
+
+	/// Override the difficulty bound divisor of the retarget parameters of the runtime.
+	#[structopt(long)]
+	pub difficulty_bound_divisor: Option<u64>,
+
+	/// Override the width, in milliseconds, of the block time buckets of the retarget parameters
+	/// of the runtime.
+	#[structopt(long)]
+	pub increment_divisor: Option<u64>,
+
+	/// Print the difficulty trajectory every this many blocks.
+	#[structopt(long, default_value = "100")]
+	pub report_every: u64,
+
+	/// Seed of the random number generator, for reproducible runs.
+	#[structopt(long)]
+	pub seed: Option<u64>,
+}
+
+impl SimulateMiningCmd {
+	/// The retarget parameters of the runtime with the command line overrides applied.
+	fn params(&self) -> RetargetParams {
+		let mut params = runtime::Difficulty::retarget_params();
+		if let Some(divisor) = self.difficulty_bound_divisor {
+			params.bound_divisor = divisor.into();
+		}
+		if let Some(increment_divisor) = self.increment_divisor {
+			params.increment_divisor = increment_divisor;
+		}
+		params
+	}
+
+	/// Run the simulation and print the report.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		if !self.hashrate.is_finite() || self.hashrate <= 0.0 {
+			return Err("--hashrate must be greater than zero".into());
+		}
+		if self.blocks == 0 || self.report_every == 0 {
+			return Err("--blocks and --report-every must be greater than zero".into());
+		}
+		if self.difficulty_bound_divisor == Some(0) || self.increment_divisor == Some(0) {
+			return Err("--difficulty-bound-divisor and --increment-divisor must be greater than zero".into());
+		}
+
+		let params = self.params();
+		let rule = self.strategy.rule();
+		let minimum_difficulty = U256::from(self.minimum_difficulty);
+		let mut rng = match self.seed {
+			Some(seed) => SmallRng::seed_from_u64(seed),
+			None => SmallRng::from_entropy(),
+		};
+
+		let mut difficulty = self
+			.initial_difficulty
+			.map(U256::from)
+			.unwrap_or(minimum_difficulty);
+		let mut now = 0f64;
+		let mut last_timestamp = 0u64;
+		let mut intervals = Vec::with_capacity(self.blocks as usize);
+		// The latest blocks, for the rules retargeting from several.
+		let mut recent = Vec::with_capacity(rule.window());
+
+		println!("{:>10} {:>14} {:>24} {:>12}", "block", "elapsed (s)", "difficulty", "avg time");
+		for number in 1..=self.blocks {
+			// Every hash meets the boundary with probability 1 / difficulty, so the time to find
+			// a block is exponentially distributed with mean `difficulty / hashrate`.
+			let mean = u256_to_f64(difficulty) / self.hashrate;
+			let sample: f64 = rng.gen();
+			now += -(1.0 - sample).ln() * mean;
+
+			// Timestamps are whole milliseconds, so retarget on the same granularity.
+			let timestamp = (now * 1000.0) as u64;
+			let block_time = timestamp - last_timestamp;
+			last_timestamp = timestamp;
+			intervals.push(block_time as f64 / 1000.0);
+
+			if number % self.report_every == 0 {
+				let window = &intervals[intervals.len() - self.report_every as usize..];
+				let average = window.iter().sum::<f64>() / window.len() as f64;
+				println!("{:>10} {:>14.0} {:>24} {:>12.2}", number, now, difficulty, average);
+			}
+
+			// As the pallet does on every timestamp.
+			if recent.len() == rule.window() {
+				recent.remove(0);
+			}
+			recent.push(BlockSample { difficulty, block_time });
+			difficulty = cmp::max(minimum_difficulty, rule.next_difficulty(&params, &recent));
+		}
+
+		intervals.sort_unstable_by(|a, b| a.partial_cmp(b).expect("block times are finite; qed"));
+		let count = intervals.len() as f64;
+		let mean = intervals.iter().sum::<f64>() / count;
+		let variance = intervals
+			.iter()
+			.map(|t| (t - mean).powi(2))
+			.sum::<f64>() / count;
+		let percentile = |p: f64| intervals[((count - 1.0) * p).round() as usize];
+
+		println!();
+		println!(
+			"Simulated {} blocks at {} H/s with {:?}, {:?} and a minimum difficulty of {}",
+			self.blocks,
+			self.hashrate,
+			self.strategy,
+			params,
+			minimum_difficulty,
+		);
+		println!("block time mean:   {:.2}s", mean);
+		println!("block time stddev: {:.2}s", variance.sqrt());
+		println!(
+			"block time p10/p50/p90/p99: {:.2}s / {:.2}s / {:.2}s / {:.2}s",
+			percentile(0.1),
+			percentile(0.5),
+			percentile(0.9),
+			percentile(0.99),
+		);
+		println!("final difficulty:  {}", difficulty);
+
+		Ok(())
+	}
+}
+
+/// The retarget strategy named `name`, as in the chain specs.
+fn parse_strategy(name: &str) -> Result<RetargetStrategy, String> {
+	serde_json::from_value(serde_json::Value::String(name.into())).map_err(|_| format!("unknown strategy {}", name))
+}
+
+/// Lossy conversion of a difficulty into a float, good enough for the simulation.
+fn u256_to_f64(value: U256) -> f64 {
+	value
+		.0
+		.iter()
+		.rev()
+		.fold(0f64, |acc, limb| acc * 18_446_744_073_709_551_616f64 + *limb as f64)
+}
@@ -0,0 +1,184 @@
+//! Stratum server for external miners.
+//!
+//! GPU miners usually speak stratum instead of polling `eth_getWork` over HTTP. The server
+//! pushes a `mining.notify` job to every authorized worker as soon as the mining worker starts a
+//! new build, and routes `mining.submit` solutions through the same `EtheminerCmd::SubmitWork`
+//! path as `eth_submitWork`, so they get the same checks.
+//!
+//! Jobs are the `eth_getWork` array: pow hash, seed hash, boundary and block number, with the
+//! share boundary of the `ethash_setShareDifficulty` override of the worker, if any.
+//! Submissions carry the worker name and job id followed by the nonce, pow hash and mix digest,
+//! the solutions are credited to the worker name in the miner stats.
+//!
+//! The submissions go through the `MinerAuth` of the RPCs: with `--miner-token`, workers
+//! authorize with a token as their password, and only the connections authorized with one may
+//! submit, within the rate limit of its miner. The worker is then the miner of the token, whatever
+//! name the connection authorized or submits under: its jobs, share difficulty and credit are the
+//! miner's.
+
+use ethcore_stratum::{Error as StratumError, JobDispatcher, PushWorkHandler, Stratum, SubmitFuture};
+use futures::{channel::{mpsc, oneshot}, prelude::*};
+use log::{debug, info, warn};
+use ethereum_types::H64;
+use parking_lot::RwLock;
+use sp_core::H256;
+use std::{collections::HashMap, net::SocketAddr, str::FromStr, sync::Arc};
+use crate::mining::{MinerAuth, ShareDifficulties, WorkSnapshot};
+use crate::rpc::{error::Error as RpcError, EtheminerCmd};
+use crate::types::Work;
+
+/// The job pushed to miners for `work`.
+fn job(work: &Work) -> String {
+	serde_json::to_string(work).expect("work packages are serializable; qed")
+}
+
+fn parse_hex<T: FromStr>(payload: &[String], index: usize, what: &str) -> Result<T, StratumError> {
+	payload
+		.get(index)
+		.and_then(|value| T::from_str(value.trim_start_matches("0x")).ok())
+		.ok_or_else(|| StratumError::Dispatch(format!("missing or invalid {}", what)))
+}
+
+/// Hands the jobs out of the work snapshot and the solutions to the mining service.
+struct StratumJobDispatcher {
+	work: WorkSnapshot,
+	share_difficulties: ShareDifficulties,
+	auth: MinerAuth,
+	/// The miner of the token each connection authorized with, when tokens are required.
+	miners: RwLock<HashMap<SocketAddr, String>>,
+	command_sink: mpsc::Sender<EtheminerCmd>,
+}
+
+impl StratumJobDispatcher {
+	/// The worker `peer` works as, going by the name `worker`: the miner of its token when tokens
+	/// are required, else the name, if any.
+	fn worker(&self, worker: &str, peer: &SocketAddr) -> Option<String> {
+		if self.auth.requires_token() {
+			self.miners.read().get(peer).cloned()
+		} else {
+			Some(worker.to_owned()).filter(|worker| !worker.is_empty())
+		}
+	}
+
+	/// The nonce, pow hash and mix digest `peer` submits in `payload`, counted against the rate
+	/// limit of its miner when tokens are required.
+	fn check_submission(&self, peer: &SocketAddr, payload: &[String]) -> Result<(H64, H256, H256), StratumError> {
+		if self.auth.requires_token() {
+			let allowed = match self.miners.read().get(peer) {
+				Some(miner) => self.auth.limit(miner),
+				None => Err(RpcError::MinerTokenRequired),
+			};
+			allowed.map_err(|err| StratumError::Dispatch(err.to_string()))?;
+		}
+
+		let nonce = parse_hex(payload, 0, "nonce")?;
+		let pow_hash = parse_hex(payload, 1, "pow hash")?;
+		let mix_digest = parse_hex(payload, 2, "mix digest")?;
+		Ok((nonce, pow_hash, mix_digest))
+	}
+}
+
+impl JobDispatcher for StratumJobDispatcher {
+	fn initial(&self) -> Option<String> {
+		self.job()
+	}
+
+	fn job(&self) -> Option<String> {
+		self.work.load().map(|current| job(&current.work))
+	}
+
+	fn job_for(&self, worker: &str, peer: &SocketAddr) -> Option<String> {
+		let worker = self.worker(worker, peer);
+		self.work.load().map(|current| job(&self.share_difficulties.work_for(worker.as_deref(), &current)))
+	}
+
+	fn authorize(&self, worker: &str, token: &str, peer: &SocketAddr) -> bool {
+		match self.auth.authenticate(Some(token)) {
+			Ok(Some(miner)) => {
+				self.miners.write().insert(*peer, miner);
+				true
+			}
+			Ok(None) => true,
+			Err(err) => {
+				debug!(target: "stratum", "{} refused to authorize {}: {}", peer, worker, err);
+				self.miners.write().remove(peer);
+				false
+			}
+		}
+	}
+
+	fn submit(&self, _payload: Vec<String>) -> Result<(), StratumError> {
+		Err(StratumError::Dispatch("submissions come with their peer".into()))
+	}
+
+	fn submit_async(&self, worker: &str, peer: &SocketAddr, payload: Vec<String>) -> SubmitFuture {
+		let checked = self.check_submission(peer, &payload);
+		let miner = self.worker(worker, peer);
+		let mut sink = self.command_sink.clone();
+		// Answered once the mining service checked the solution, the stratum server threads
+		// serve the other workers meanwhile.
+		let submission = async move {
+			let (nonce, pow_hash, mix_digest) = checked?;
+			let accepted = async move {
+				let (sender, receiver) = oneshot::channel();
+				let command = EtheminerCmd::SubmitWork {
+					nonce,
+					pow_hash,
+					mix_digest,
+					signature: None,
+					miner,
+					submission_id: None,
+					sender: Some(sender),
+				};
+				sink.send(command).await?;
+				receiver.await?
+			};
+			match accepted.await {
+				Ok(_) => Ok(()),
+				Err(err) => {
+					debug!(target: "stratum", "rejected solution for {}: {}", pow_hash, err);
+					Err(StratumError::Dispatch(err.to_string()))
+				}
+			}
+		};
+		Box::new(submission.boxed().compat())
+	}
+
+	fn disconnected(&self, peer: &SocketAddr) {
+		self.miners.write().remove(peer);
+	}
+}
+
+/// Serve stratum on `addr`, pushing every new work package of `work` to the workers, with the
+/// share boundary of their `share_difficulties` override. The submissions are checked against
+/// `auth` before they are handed to the mining service.
+pub async fn run_stratum_server(
+	addr: SocketAddr,
+	work: WorkSnapshot,
+	share_difficulties: ShareDifficulties,
+	auth: MinerAuth,
+	command_sink: mpsc::Sender<EtheminerCmd>,
+) {
+	let mut notifications = work.subscribe();
+	let dispatcher = StratumJobDispatcher {
+		work,
+		share_difficulties,
+		auth,
+		miners: Default::default(),
+		command_sink,
+	};
+	let stratum = match Stratum::start(&addr, Arc::new(dispatcher), None) {
+		Ok(stratum) => stratum,
+		Err(err) => {
+			warn!(target: "stratum", "Failed to start the stratum server on {}: {:?}", addr, err);
+			return;
+		}
+	};
+	info!(target: "stratum", "Stratum server listening on {}", addr);
+
+	while let Some(current) = notifications.next().await {
+		if current.is_some() {
+			stratum.push_work_each();
+		}
+	}
+}
@@ -0,0 +1,26 @@
+//! The state of the mining of the node, answered by `pow_miningStatus`.
+
+use serde_derive::Serialize;
+use sp_core::{H256, U256};
+
+/// Whether and how the node mines.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MiningStatus {
+	/// Whether work is handed out and miners are fetching it, as `eth_mining`.
+	pub mining: bool,
+	/// Whether `pow_stopMining` or `miner_shutdown` stopped the issuing of work.
+	pub stopped: bool,
+	/// Peers connected, when too few to mine.
+	pub short_of_peers: Option<usize>,
+	/// Number of hashing threads of the node itself.
+	pub threads: usize,
+	/// Sum of the hashrates reported by the miners, the threads of the node included.
+	pub hashrate: U256,
+	/// The pow hash of the work package being mined, if any.
+	pub pow_hash: Option<H256>,
+	/// Number of the block being mined, if any.
+	pub number: Option<u64>,
+	/// Difficulty of the block being mined, if any.
+	pub difficulty: Option<U256>,
+}
@@ -1,5 +1,12 @@
+mod mining_status;
+mod seal;
+mod submit_result;
+mod submitted_work;
+mod work_ex;
 
-
-pub mod work;
-
-pub use self::work::{Work};
+pub use ethpow_primitives::Work;
+pub use self::mining_status::MiningStatus;
+pub use self::seal::{seal_and_author, SealInfo};
+pub use self::submit_result::SubmitResult;
+pub use self::submitted_work::SubmittedWork;
+pub use self::work_ex::WorkEx;
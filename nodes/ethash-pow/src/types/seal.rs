@@ -0,0 +1,63 @@
+//! The decoded ethash seal and author of a block, in the form handed to explorers.
+
+use ethpow::{decode_seal, WorkSeal};
+use log::warn;
+use parity_scale_codec::Decode;
+use runtime::{opaque::Header, AccountId};
+use serde_derive::Serialize;
+use sp_consensus_pow::POW_ENGINE_ID;
+use sp_core::{H256, U256};
+use sp_runtime::{traits::Header as HeaderT, DigestItem};
+
+/// The decoded ethash seal of a block.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SealInfo {
+	/// Hex encoded, like ethereum nonces.
+	pub nonce: String,
+	/// The proof-of-work hash of the header.
+	pub pow_hash: H256,
+	/// The mix digest.
+	pub mix_digest: H256,
+	/// The difficulty the block was sealed at.
+	pub difficulty: U256,
+	/// The boundary the ethash value of the block had to meet.
+	pub boundary: H256,
+	/// Unix timestamp of the seal, in seconds.
+	pub timestamp: u64,
+}
+
+impl From<WorkSeal> for SealInfo {
+	fn from(seal: WorkSeal) -> Self {
+		let difficulty: [u8; 32] = seal.difficulty.into();
+		let boundary: [u8; 32] =
+			ethash::difficulty_to_boundary(&ethereum_types::U256::from(difficulty)).into();
+		SealInfo {
+			nonce: format!("0x{:016x}", seal.nonce),
+			pow_hash: seal.pow_hash,
+			mix_digest: seal.mix_digest,
+			difficulty: seal.difficulty,
+			boundary: H256::from(boundary),
+			timestamp: seal.timestamp,
+		}
+	}
+}
+
+/// The seal and author of `header`, from its PoW seal and pre-runtime digests.
+pub fn seal_and_author(header: &Header) -> (Option<SealInfo>, Option<AccountId>) {
+	let mut seal = None;
+	let mut author = None;
+	for log in header.digest().logs() {
+		match log {
+			DigestItem::Seal(id, raw) if *id == POW_ENGINE_ID => match decode_seal(raw) {
+				Ok(decoded) => seal = Some(decoded.into()),
+				Err(err) => warn!("Undecodable seal in block {}: {:?}", header.number(), err),
+			},
+			DigestItem::PreRuntime(id, raw) if *id == POW_ENGINE_ID => {
+				author = AccountId::decode(&mut &raw[..]).ok();
+			}
+			_ => {}
+		}
+	}
+	(seal, author)
+}
@@ -0,0 +1,33 @@
+//! The outcome of a solution, answered by `pow_submitWorkDetailed`.
+
+use crate::rpc::error::Error as RpcError;
+use crate::types::SubmittedWork;
+use serde_derive::Serialize;
+
+/// What became of a solution submitted by a miner, for pools to tell their miners why a share
+/// was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SubmitResult {
+	/// The solution sealed a block or counted as a share.
+	Accepted,
+	/// The solution is for a work package that is no longer current.
+	Stale,
+	/// The nonce and mix digest don't check out, or miss the difficulty of the work package.
+	InvalidPow,
+	/// The solution is for a work package the node didn't hand out lately.
+	WrongPowHash,
+	/// The node is syncing and seals no block until it catches up.
+	NodeSyncing,
+}
+
+impl SubmitResult {
+	/// The outcome of a submission answered with `result`. The errors unrelated to the solution
+	/// itself are passed on.
+	pub fn from_submission(result: Result<SubmittedWork, RpcError>) -> Result<Self, RpcError> {
+		match result {
+			Ok(_) => Ok(SubmitResult::Accepted),
+			Err(err) => err.submit_result().ok_or(err),
+		}
+	}
+}
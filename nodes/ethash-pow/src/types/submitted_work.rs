@@ -0,0 +1,13 @@
+//! The outcome of an accepted solution, answered by `pow_submitWorkEx`.
+
+use serde_derive::Serialize;
+use sp_core::H256;
+
+/// A solution accepted by the mining service, for miners to track the blocks they sealed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmittedWork {
+	/// The hash of the block the solution sealed and imported, `None` for a share below the
+	/// block difficulty.
+	pub block_hash: Option<H256>,
+}
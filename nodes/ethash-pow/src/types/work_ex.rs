@@ -0,0 +1,46 @@
+//! The work package with the ethash parameters of its epoch, answered by `pow_getWorkEx`.
+
+use ethash::{get_cache_size, get_data_size, ETHASH_EPOCH_LENGTH};
+use serde_derive::Serialize;
+use sp_core::H256;
+use super::Work;
+
+/// A work package and the epoch, DAG size and cache size it is mined with, so miners can check
+/// they generated the right DAG before hashing instead of having every share rejected.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkEx {
+	/// The proof-of-work hash.
+	pub pow_hash: H256,
+	/// The seed hash of the epoch.
+	pub seed_hash: H256,
+	/// The boundary of the block difficulty.
+	pub target: H256,
+	/// The block number.
+	pub number: u64,
+	/// The boundary of the share difficulty, set when the node serves a pool.
+	pub share_target: Option<H256>,
+	/// The ethash epoch of the block.
+	pub epoch: u64,
+	/// Size of the full dataset of the epoch, in bytes.
+	pub dag_size: u64,
+	/// Size of the light cache of the epoch, in bytes.
+	pub cache_size: u64,
+}
+
+impl From<Work> for WorkEx {
+	fn from(work: Work) -> Self {
+		// The node always hands out the number of the block.
+		let number = work.number.unwrap_or_default();
+		WorkEx {
+			pow_hash: work.pow_hash,
+			seed_hash: work.seed_hash,
+			target: work.target,
+			number,
+			share_target: work.share_target,
+			epoch: number / ETHASH_EPOCH_LENGTH,
+			dag_size: get_data_size(number) as u64,
+			cache_size: get_cache_size(number) as u64,
+		}
+	}
+}
@@ -0,0 +1,71 @@
+//! Watchdog raising the alarm when the chain stops moving.
+//!
+//! A wedged miner, a DAG that can't be generated or a node cut from its peers all look the same
+//! from the outside: no new best block. The watchdog logs an error, sends a telemetry message and
+//! sets the `ethash_mining_stalled` gauge whenever that lasts longer than the configured timeout,
+//! and clears the gauge as soon as a new best block is produced or imported.
+
+use std::{sync::Arc, time::{Duration, Instant}};
+use futures::{future::{self, Either}, prelude::*};
+use futures_timer::Delay;
+use log::{error, info, warn};
+use sc_client_api::BlockchainEvents;
+use sc_telemetry::{telemetry, CONSENSUS_WARN};
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
+use substrate_prometheus_endpoint::{register, Gauge, PrometheusError, Registry, U64};
+
+fn register_gauge(registry: &Registry) -> Result<Gauge<U64>, PrometheusError> {
+	register(
+		Gauge::new(
+			"ethash_mining_stalled",
+			"Whether no new best block was produced or imported within the stall timeout",
+		)?,
+		registry,
+	)
+}
+
+/// Watch the best block of `client`, raising the alarm when it doesn't change for `timeout`.
+pub async fn run_stall_watchdog<B, C>(client: Arc<C>, timeout: Duration, registry: Option<Registry>)
+where
+	B: BlockT,
+	C: BlockchainEvents<B>,
+{
+	let gauge = registry.and_then(|registry| {
+		register_gauge(&registry)
+			.map_err(|err| warn!(target: "pow", "Failed to register the stall gauge: {:?}", err))
+			.ok()
+	});
+	let mut best_blocks = client
+		.import_notification_stream()
+		.filter(|notification| future::ready(notification.is_new_best));
+	let mut last_best = Instant::now();
+	let mut stalled = false;
+
+	loop {
+		match future::select(best_blocks.next(), Delay::new(timeout)).await {
+			Either::Left((Some(notification), _)) => {
+				if stalled {
+					info!(target: "pow", "Chain moving again at block {}", notification.header.number());
+					stalled = false;
+				}
+				if let Some(gauge) = &gauge {
+					gauge.set(0);
+				}
+				last_best = Instant::now();
+			}
+			Either::Left((None, _)) => return,
+			Either::Right(_) => {
+				let since = last_best.elapsed().as_secs();
+				error!(
+					target: "pow",
+					"No new best block for {}s, check the miners and the ethash cache", since,
+				);
+				telemetry!(CONSENSUS_WARN; "pow.stalled"; "since" => since);
+				if let Some(gauge) = &gauge {
+					gauge.set(1);
+				}
+				stalled = true;
+			}
+		}
+	}
+}
@@ -0,0 +1,295 @@
+//! Helpers shared by the end-to-end tests: node processes driven over RPC and a miner solving
+//! their work packages with the light ethash cache.
+
+#![allow(dead_code)]
+
+use ethash::EthashManager;
+use serde_json::{json, Value};
+use std::{
+	io::{BufRead, BufReader, Read, Write},
+	net::TcpStream,
+	path::{Path, PathBuf},
+	process::{Child, Command, Stdio},
+	thread,
+	time::{Duration, Instant},
+};
+use tempdir::TempDir;
+
+/// How long to wait for a node to reach an expected state.
+const TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A work package returned by `eth_getWork`.
+pub struct Work {
+	pub pow_hash: [u8; 32],
+	pub seed_hash: [u8; 32],
+	pub target: [u8; 32],
+	pub number: u64,
+}
+
+/// A node process, killed when dropped.
+pub struct Node {
+	process: Child,
+	chain_spec: PathBuf,
+	rpc_port: u16,
+	p2p_port: u16,
+	ws_port: u16,
+	/// Extra command line arguments of the node.
+	args: Vec<String>,
+	base_path: TempDir,
+}
+
+impl Node {
+	pub fn start(chain_spec: &Path, rpc_port: u16, p2p_port: u16, ws_port: u16) -> Self {
+		Self::start_with(chain_spec, rpc_port, p2p_port, ws_port, &[])
+	}
+
+	/// Start a node with the extra command line arguments `args`.
+	pub fn start_with(chain_spec: &Path, rpc_port: u16, p2p_port: u16, ws_port: u16, args: &[&str]) -> Self {
+		let base_path = TempDir::new("ethash-pow-node").unwrap();
+		let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+		let process = Self::spawn(chain_spec, base_path.path(), rpc_port, p2p_port, ws_port, &args);
+		let node = Node {
+			process,
+			chain_spec: chain_spec.to_owned(),
+			rpc_port,
+			p2p_port,
+			ws_port,
+			args,
+			base_path,
+		};
+		node.wait_for_rpc();
+		node
+	}
+
+	/// Stop the node and start it again on the same database and ethash caches.
+	pub fn restart(&mut self) {
+		self.stop();
+		self.process = Self::spawn(
+			&self.chain_spec,
+			self.base_path.path(),
+			self.rpc_port,
+			self.p2p_port,
+			self.ws_port,
+			&self.args,
+		);
+		self.wait_for_rpc();
+	}
+
+	fn spawn(chain_spec: &Path, base_path: &Path, rpc_port: u16, p2p_port: u16, ws_port: u16, args: &[String]) -> Child {
+		Command::new(env!("CARGO_BIN_EXE_ethash-pow"))
+			.arg("--chain").arg(chain_spec)
+			.arg("--base-path").arg(base_path)
+			.args(&["--port", &p2p_port.to_string()])
+			.args(&["--rpc-port", &rpc_port.to_string()])
+			.args(&["--ws-port", &ws_port.to_string()])
+			.args(&["--validator", "--rpc-methods", "Unsafe", "--stall-timeout", "0"])
+			// No work is handed out without an account to reward, Alice's here.
+			.args(&["--author", "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"])
+			// Keeps the nodes partitioned until they are explicitly connected.
+			.args(&["--no-mdns", "--reserved-only", "--no-telemetry", "--no-prometheus"])
+			.args(args)
+			.stdout(Stdio::null())
+			.stderr(Stdio::null())
+			.spawn()
+			.unwrap()
+	}
+
+	fn wait_for_rpc(&self) {
+		wait_until("RPC server up", || self.call("system_health", json!([])).is_ok());
+	}
+
+	fn stop(&mut self) {
+		let _ = self.process.kill();
+		let _ = self.process.wait();
+	}
+
+	/// Call `method`, returning its result or its error object.
+	pub fn call(&self, method: &str, params: Value) -> Result<Value, Value> {
+		let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }).to_string();
+		let mut stream = TcpStream::connect(("127.0.0.1", self.rpc_port))
+			.map_err(|err| json!(err.to_string()))?;
+		write!(
+			stream,
+			"POST / HTTP/1.1\r\nHost: 127.0.0.1\r\nContent-Type: application/json\r\n\
+			Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+			body.len(),
+			body,
+		).map_err(|err| json!(err.to_string()))?;
+
+		let mut response = String::new();
+		stream.read_to_string(&mut response).map_err(|err| json!(err.to_string()))?;
+		let body = response.splitn(2, "\r\n\r\n").nth(1).unwrap_or_default();
+		let mut response: Value = serde_json::from_str(body).map_err(|err| json!(err.to_string()))?;
+		match response.get("error") {
+			Some(error) => Err(error.clone()),
+			None => Ok(response["result"].take()),
+		}
+	}
+
+	pub fn best_hash(&self) -> String {
+		self.call("chain_getBlockHash", json!([])).unwrap().as_str().unwrap().to_owned()
+	}
+
+	pub fn best_number(&self) -> u64 {
+		let header = self.call("chain_getHeader", json!([])).unwrap();
+		u64::from_str_radix(header["number"].as_str().unwrap().trim_start_matches("0x"), 16).unwrap()
+	}
+
+	pub fn address(&self) -> String {
+		let peer_id = self.call("system_localPeerId", json!([])).unwrap();
+		format!("/ip4/127.0.0.1/tcp/{}/p2p/{}", self.p2p_port, peer_id.as_str().unwrap())
+	}
+
+	/// Current work package.
+	pub fn work(&self) -> Work {
+		let mut work = None;
+		wait_until("work available", || {
+			work = self.call("eth_getWork", json!([])).ok();
+			work.is_some()
+		});
+		let work = work.unwrap();
+		let number = work[3].as_str().unwrap().trim_start_matches("0x");
+		Work {
+			pow_hash: hash(&work[0]),
+			seed_hash: hash(&work[1]),
+			target: hash(&work[2]),
+			number: u64::from_str_radix(number, 16).unwrap(),
+		}
+	}
+
+	/// Submit the solution `nonce`, `mix_digest` of the work package `pow_hash`.
+	pub fn submit(&self, nonce: u64, pow_hash: &[u8; 32], mix_digest: &[u8; 32]) -> Result<Value, Value> {
+		self.call("eth_submitWork", json!([hex_nonce(nonce), hex(pow_hash), hex(mix_digest)]))
+	}
+
+	/// Mine on top of the current best block and wait for the mined block to become the best.
+	/// Returns its hash.
+	pub fn mine_block(&self, ethash: &EthashManager) -> String {
+		let work = self.work();
+		let (nonce, mix_digest) = solve(ethash, &work, 0);
+		let submitted = self
+			.call("pow_submitWorkEx", json!([hex_nonce(nonce), hex(&work.pow_hash), hex(&mix_digest)]))
+			.unwrap();
+		let hash = submitted["blockHash"].as_str().expect("solution meets the block difficulty").to_owned();
+		wait_until("mined block imported", || self.best_hash() == hash);
+		hash
+	}
+}
+
+impl Drop for Node {
+	fn drop(&mut self) {
+		self.stop();
+	}
+}
+
+/// A stratum connection to a node.
+pub struct StratumClient {
+	stream: BufReader<TcpStream>,
+	next_id: u64,
+}
+
+impl StratumClient {
+	pub fn connect(port: u16) -> Self {
+		let mut stream = None;
+		wait_until("stratum server up", || {
+			stream = TcpStream::connect(("127.0.0.1", port)).ok();
+			stream.is_some()
+		});
+		StratumClient { stream: BufReader::new(stream.unwrap()), next_id: 1 }
+	}
+
+	/// Call `method`, returning its result and skipping the jobs pushed meanwhile.
+	pub fn call(&mut self, method: &str, params: Value) -> Value {
+		let id = self.next_id;
+		self.next_id += 1;
+		let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+		writeln!(self.stream.get_mut(), "{}", request).unwrap();
+		loop {
+			let mut line = String::new();
+			self.stream.read_line(&mut line).unwrap();
+			let mut response: Value = serde_json::from_str(&line).unwrap();
+			if response.get("method").is_none() && response["id"] == json!(id) {
+				return response["result"].take();
+			}
+		}
+	}
+
+	/// Authorize as `worker` with `secret`.
+	pub fn authorize(&mut self, worker: &str, secret: &str) -> bool {
+		self.call("mining.authorize", json!([worker, secret])) == json!(true)
+	}
+
+	/// Submit the solution `nonce`, `mix_digest` of the work package `pow_hash` as `worker`,
+	/// returning whether it was accepted.
+	pub fn submit(&mut self, worker: &str, nonce: u64, pow_hash: &[u8; 32], mix_digest: &[u8; 32]) -> bool {
+		let params = json!([worker, hex(pow_hash), hex_nonce(nonce), hex(pow_hash), hex(mix_digest)]);
+		self.call("mining.submit", params) == json!(true)
+	}
+}
+
+pub fn wait_until(what: &str, mut condition: impl FnMut() -> bool) {
+	let start = Instant::now();
+	while !condition() {
+		assert!(start.elapsed() < TIMEOUT, "timed out waiting for {}", what);
+		thread::sleep(Duration::from_millis(200));
+	}
+}
+
+fn hash(value: &Value) -> [u8; 32] {
+	let mut hash = [0u8; 32];
+	let digits = value.as_str().unwrap().trim_start_matches("0x");
+	for (i, byte) in hash.iter_mut().enumerate() {
+		*byte = u8::from_str_radix(&digits[2 * i..2 * i + 2], 16).unwrap();
+	}
+	hash
+}
+
+fn hex(bytes: &[u8]) -> String {
+	bytes.iter().fold(String::from("0x"), |hex, byte| hex + &format!("{:02x}", byte))
+}
+
+fn hex_nonce(nonce: u64) -> String {
+	format!("0x{:016x}", nonce)
+}
+
+/// Search, from `first_nonce` on, a nonce whose ethash value meets the target of `work`,
+/// returning it with its mix digest.
+pub fn solve(ethash: &EthashManager, work: &Work, first_nonce: u64) -> (u64, [u8; 32]) {
+	solve_matching(ethash, work, first_nonce, &work.target, |_| true)
+}
+
+/// Search, from `first_nonce` on, a nonce accepted by `wanted` whose ethash value meets `target`,
+/// returning it with its mix digest.
+pub fn solve_matching(
+	ethash: &EthashManager,
+	work: &Work,
+	first_nonce: u64,
+	target: &[u8; 32],
+	wanted: impl Fn(u64) -> bool,
+) -> (u64, [u8; 32]) {
+	(first_nonce..)
+		.filter(|nonce| wanted(*nonce))
+		.map(|nonce| (nonce, ethash.compute_light(work.number, &work.pow_hash, nonce)))
+		.find(|(_, pow)| pow.value <= *target)
+		.map(|(nonce, pow)| (nonce, pow.mix_hash))
+		.unwrap()
+}
+
+/// The dev chain spec with a genesis difficulty low enough to mine blocks in a few hashes.
+pub fn low_difficulty_spec(dir: &Path) -> PathBuf {
+	let output = Command::new(env!("CARGO_BIN_EXE_ethash-pow"))
+		.args(&["build-spec", "--chain", "dev", "--disable-default-bootnode"])
+		.stderr(Stdio::null())
+		.output()
+		.unwrap();
+	assert!(output.status.success());
+
+	let mut spec: Value = serde_json::from_slice(&output.stdout).unwrap();
+	spec["genesis"]["runtime"]["difficulty"] = json!({
+		"difficulty": "0x10",
+		"minimumDifficulty": "0x10",
+	});
+	let path = dir.join("low-difficulty.json");
+	std::fs::write(&path, spec.to_string()).unwrap();
+	path
+}
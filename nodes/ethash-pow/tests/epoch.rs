@@ -0,0 +1,60 @@
+//! End-to-end mining across an ethash epoch boundary.
+//!
+//! Epochs are 30000 blocks long on a regular build, so this test needs the shortened epochs of
+//! the `short-epochs` feature and is ignored by default:
+//! `cargo test -p ethash-pow --features short-epochs --test epoch -- --ignored`.
+
+#![cfg(feature = "short-epochs")]
+
+mod common;
+
+use common::{low_difficulty_spec, solve, wait_until, Node};
+use ethash::{EthashManager, ETHASH_EPOCH_LENGTH};
+use serde_json::json;
+use tempdir::TempDir;
+
+/// Error code of a solution for a work package of another epoch.
+const WRONG_EPOCH: i64 = 17_002;
+
+#[test]
+#[ignore]
+fn mining_crosses_epoch_boundary() {
+	let dir = TempDir::new("ethash-pow-epoch").unwrap();
+	let spec = low_difficulty_spec(dir.path());
+	let ethash = EthashManager::new(dir.path(), None, u64::max_value());
+
+	let mut miner = Node::start(&spec, 39935, 40335, 39946);
+	while miner.best_number() < ETHASH_EPOCH_LENGTH - 2 {
+		miner.mine_block(&ethash);
+	}
+
+	// The last block of epoch 0, solved twice: once to seal it, once to replay later.
+	let last = miner.work();
+	assert_eq!(last.number, ETHASH_EPOCH_LENGTH - 1);
+	let (nonce, mix_digest) = solve(&ethash, &last, 0);
+	let (replayed_nonce, replayed_mix_digest) = solve(&ethash, &last, nonce + 1);
+	miner.submit(nonce, &last.pow_hash, &mix_digest).unwrap();
+	wait_until("last block of epoch 0 imported", || miner.best_number() == ETHASH_EPOCH_LENGTH - 1);
+
+	// The work of the first block of epoch 1 comes with the seed hash of the new epoch.
+	wait_until("work of epoch 1", || miner.work().number == ETHASH_EPOCH_LENGTH);
+	let first = miner.work();
+	assert_ne!(first.seed_hash, last.seed_hash);
+	miner.mine_block(&ethash);
+
+	// A solution for a work package of epoch 0 is now rejected as such.
+	let error = miner.submit(replayed_nonce, &last.pow_hash, &replayed_mix_digest).unwrap_err();
+	assert_eq!(error["code"], WRONG_EPOCH);
+
+	// After a restart, the miner verifies its own blocks again and keeps mining.
+	let best = miner.best_hash();
+	miner.restart();
+	assert_eq!(miner.best_hash(), best);
+	miner.mine_block(&ethash);
+
+	// A fresh node syncing from the miner verifies the blocks of both epochs.
+	let follower = Node::start(&spec, 39936, 40336, 39947);
+	follower.call("system_addReservedPeer", json!([miner.address()])).unwrap();
+	wait_until("follower synced", || follower.best_hash() == miner.best_hash());
+	assert!(follower.best_number() > ETHASH_EPOCH_LENGTH);
+}
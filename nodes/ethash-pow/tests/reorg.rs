@@ -0,0 +1,57 @@
+//! End-to-end reorg between two mining nodes.
+//!
+//! Two nodes are started on a low difficulty chain with no way to find each other, and each one
+//! is mined through its RPC, like an external miner would. Once they are connected, the node with
+//! the lighter chain must reorg onto the heavier one and classify a solution for its orphaned work
+//! as stale.
+//!
+//! The test spawns the node binary and generates the ethash cache of epoch 0, which takes a
+//! while, so it is ignored by default: `cargo test -p ethash-pow --test reorg -- --ignored`.
+
+mod common;
+
+use common::{low_difficulty_spec, solve, wait_until, Node};
+use ethash::EthashManager;
+use serde_json::json;
+use tempdir::TempDir;
+
+/// Error code of a solution for a work package that is no longer current.
+const STALE_WORK: i64 = 17_000;
+
+#[test]
+#[ignore]
+fn heavier_chain_wins_after_partition() {
+	let dir = TempDir::new("ethash-pow-reorg").unwrap();
+	let spec = low_difficulty_spec(dir.path());
+	let ethash = EthashManager::new(dir.path(), None, u64::max_value());
+
+	let light = Node::start(&spec, 39933, 40333, 39944);
+	let heavy = Node::start(&spec, 39934, 40334, 39945);
+
+	// Both nodes build their own chain on top of genesis, the heavy one twice as long.
+	for _ in 0..2 {
+		light.mine_block(&ethash);
+	}
+	for _ in 0..4 {
+		heavy.mine_block(&ethash);
+	}
+	assert_ne!(light.best_hash(), heavy.best_hash());
+
+	// Work handed out on the light chain just before it gets orphaned.
+	let orphaned = light.work();
+	let (nonce, mix_digest) = solve(&ethash, &orphaned, 0);
+
+	light.call("system_addReservedPeer", json!([heavy.address()])).unwrap();
+	wait_until("reorg onto the heavy chain", || light.best_hash() == heavy.best_hash());
+	assert_eq!(light.best_number(), 4);
+
+	// Once the light node mines on the heavy chain, the orphaned work is stale.
+	wait_until("work on the heavy chain", || light.work().pow_hash != orphaned.pow_hash);
+	let error = light.submit(nonce, &orphaned.pow_hash, &mix_digest).unwrap_err();
+	assert_eq!(error["code"], STALE_WORK);
+
+	// Both nodes keep mining the same chain.
+	light.mine_block(&ethash);
+	wait_until("heavy node follows", || heavy.best_hash() == light.best_hash());
+	assert_eq!(light.call("eth_mining", json!([])).unwrap(), json!(true));
+}
@@ -0,0 +1,156 @@
+//! End-to-end checks of the solutions a node accepts from its miners.
+//!
+//! The tests spawn the node binary and generate the ethash cache of epoch 0, which takes a while,
+//! so they are ignored by default: `cargo test -p ethash-pow --test submit -- --ignored`.
+
+mod common;
+
+use common::{low_difficulty_spec, solve, solve_matching, Node, StratumClient, Work};
+use ethash::{quick_get_difficulty, EthashManager};
+use serde_json::json;
+use tempdir::TempDir;
+
+/// Error code of a solution whose mix digest doesn't check out.
+const INVALID_PROOF_OF_WORK: i64 = 17_012;
+
+/// Error code of a solution whose nonce is outside the nonce partition of the node.
+const NONCE_OUTSIDE_PARTITION: i64 = 17_023;
+
+#[test]
+#[ignore]
+fn solutions_outside_the_nonce_partition_are_rejected() {
+	let dir = TempDir::new("ethash-pow-submit").unwrap();
+	let spec = low_difficulty_spec(dir.path());
+	let ethash = EthashManager::new(dir.path(), None, u64::max_value());
+	let node = Node::start_with(&spec, 39937, 40337, 39948, &["--miner-nonce-start", "1", "--miner-nonce-stride", "2"]);
+
+	// A valid solution, but searched by the other node of the farm.
+	let work = node.work();
+	let (nonce, mix_digest) = solve_matching(&ethash, &work, 0, &work.target, |nonce| nonce % 2 == 0);
+	let error = node.submit(nonce, &work.pow_hash, &mix_digest).unwrap_err();
+	assert_eq!(error["code"], NONCE_OUTSIDE_PARTITION);
+
+	let (nonce, mix_digest) = solve_matching(&ethash, &work, 0, &work.target, |nonce| nonce % 2 == 1);
+	assert_eq!(node.submit(nonce, &work.pow_hash, &mix_digest).unwrap(), json!(true));
+}
+
+#[test]
+#[ignore]
+fn shares_are_checked_against_the_share_difficulty_of_their_worker() {
+	let dir = TempDir::new("ethash-pow-submit").unwrap();
+	let spec = low_difficulty_spec(dir.path());
+	let ethash = EthashManager::new(dir.path(), None, u64::max_value());
+	let node = Node::start_with(&spec, 39938, 40338, 39949, &["--stratum-port", "39960"]);
+
+	// The rig gets shares of difficulty 2, every other worker the block difficulty of 16.
+	node.call("ethash_setShareDifficulty", json!(["rig", "0x2"])).unwrap();
+	let work_ex = node.call("pow_getWorkEx", json!([null, "rig"])).unwrap();
+	assert_eq!(work_ex["shareTarget"], json!(format!("0x80{}", "0".repeat(62))));
+
+	// Two solutions of a difficulty between 2 and 16, shares for the rig only.
+	let work = node.work();
+	let shares: Vec<_> = (0..)
+		.map(|nonce| (nonce, ethash.compute_light(work.number, &work.pow_hash, nonce)))
+		.filter(|(_, pow)| pow.value[0] < 0x80 && pow.value > work.target)
+		.map(|(nonce, pow)| (nonce, pow.mix_hash))
+		.take(2)
+		.collect();
+
+	let mut rig = StratumClient::connect(39960);
+	assert!(rig.authorize("rig", ""));
+	assert!(rig.submit("rig", shares[0].0, &work.pow_hash, &shares[0].1));
+
+	let mut other = StratumClient::connect(39960);
+	assert!(other.authorize("other", ""));
+	assert!(!other.submit("other", shares[1].0, &work.pow_hash, &shares[1].1));
+}
+
+#[test]
+#[ignore]
+fn solution_rejected_for_its_mix_digest_can_be_submitted_again() {
+	let dir = TempDir::new("ethash-pow-submit").unwrap();
+	let spec = low_difficulty_spec(dir.path());
+	let ethash = EthashManager::new(dir.path(), None, u64::max_value());
+	let node = Node::start(&spec, 39939, 40339, 39950);
+
+	// A forged mix digest the quick difficulty check lets through, caught by the verification.
+	let work = node.work();
+	let (nonce, mix_digest) = solve(&ethash, &work, 0);
+	let forged = (0u8..)
+		.map(|byte| [byte; 32])
+		.find(|forged| *forged != mix_digest && quick_get_difficulty(&work.pow_hash, nonce, forged, false) <= work.target)
+		.unwrap();
+	let error = node.submit(nonce, &work.pow_hash, &forged).unwrap_err();
+	assert_eq!(error["code"], INVALID_PROOF_OF_WORK);
+
+	// The nonce isn't used up by the rejected submission.
+	assert_eq!(node.submit(nonce, &work.pow_hash, &mix_digest).unwrap(), json!(true));
+}
+
+/// Two shares of difficulty 2 for the work package of `node`, the rig being set to difficulty 2.
+fn rig_shares(node: &Node, ethash: &EthashManager) -> (Work, Vec<(u64, [u8; 32])>) {
+	node.call("ethash_setShareDifficulty", json!(["rig", "0x2"])).unwrap();
+	let work = node.work();
+	let shares = (0..)
+		.map(|nonce| (nonce, ethash.compute_light(work.number, &work.pow_hash, nonce)))
+		.filter(|(_, pow)| pow.value[0] < 0x80 && pow.value > work.target)
+		.map(|(nonce, pow)| (nonce, pow.mix_hash))
+		.take(2)
+		.collect();
+	(work, shares)
+}
+
+#[test]
+#[ignore]
+fn stratum_submissions_require_a_miner_token() {
+	let dir = TempDir::new("ethash-pow-submit").unwrap();
+	let spec = low_difficulty_spec(dir.path());
+	let ethash = EthashManager::new(dir.path(), None, u64::max_value());
+	let node = Node::start_with(
+		&spec,
+		39940,
+		40340,
+		39951,
+		&["--stratum-port", "39961", "--miner-token", "rig:hunter2,other:s3cret"],
+	);
+	let (work, shares) = rig_shares(&node, &ethash);
+
+	let mut intruder = StratumClient::connect(39961);
+	assert!(!intruder.authorize("rig", "guess"));
+	assert!(!intruder.submit("rig", shares[0].0, &work.pow_hash, &shares[0].1));
+
+	let mut rig = StratumClient::connect(39961);
+	assert!(rig.authorize("rig", "hunter2"));
+	assert!(rig.submit("rig", shares[0].0, &work.pow_hash, &shares[0].1));
+
+	// Another miner going by the name of the rig works as itself, without the share difficulty
+	// of the rig.
+	let mut other = StratumClient::connect(39961);
+	assert!(other.authorize("rig", "s3cret"));
+	assert!(!other.submit("rig", shares[1].0, &work.pow_hash, &shares[1].1));
+}
+
+#[test]
+#[ignore]
+fn stratum_rate_limit_applies_per_miner() {
+	let dir = TempDir::new("ethash-pow-submit").unwrap();
+	let spec = low_difficulty_spec(dir.path());
+	let ethash = EthashManager::new(dir.path(), None, u64::max_value());
+	let node = Node::start_with(
+		&spec,
+		39941,
+		40341,
+		39952,
+		&["--stratum-port", "39962", "--miner-token", "rig:hunter2,other:s3cret", "--miner-rate-limit", "1"],
+	);
+	let (work, shares) = rig_shares(&node, &ethash);
+
+	let mut first = StratumClient::connect(39962);
+	assert!(first.authorize("rig", "hunter2"));
+	assert!(first.submit("rig", shares[0].0, &work.pow_hash, &shares[0].1));
+
+	// Another connection of the same miner shares its allowance.
+	let mut second = StratumClient::connect(39962);
+	assert!(second.authorize("rig", "hunter2"));
+	assert!(!second.submit("rig", shares[1].0, &work.pow_hash, &shares[1].1));
+}
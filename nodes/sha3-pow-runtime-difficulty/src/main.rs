@@ -0,0 +1,19 @@
+//! A Sha3 PoW node whose difficulty is retargeted by the runtime.
+//!
+//! The `basic-pow` node mines at a difficulty fixed in the client, by `MinimalSha3Algorithm`.
+//! This node plugs `Sha3Algorithm` instead, which asks the runtime for the difficulty through
+//! `sp_consensus_pow::DifficultyApi`, at the state of the parent of every block it mines or
+//! imports. The runtime implements the API with the difficulty pallet, which retargets the
+//! difficulty from the block timestamps: the rule lives on chain, and a runtime upgrade changes
+//! it for every node at once, without a client release.
+#![warn(missing_docs)]
+
+mod chain_spec;
+#[macro_use]
+mod service;
+mod cli;
+mod command;
+
+fn main() -> sc_cli::Result<()> {
+	command::run()
+}
@@ -0,0 +1,120 @@
+//! RPC interface reporting how block production is distributed among authors.
+
+use author_history_runtime_api::AuthorHistoryApi as AuthorHistoryRuntimeApi;
+use codec::Codec;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use serde::{Deserialize, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+use std::{collections::BTreeMap, sync::Arc};
+
+/// Number of blocks mined by one author within the requested window.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorShare<AccountId> {
+	/// The block author.
+	pub author: AccountId,
+	/// How many blocks of the window this author mined.
+	pub blocks: u32,
+	/// The author's share of the window, in percent.
+	pub share: f64,
+}
+
+/// Author distribution over the most recent blocks.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorStats<AccountId> {
+	/// Number of blocks the statistics were computed from.
+	pub blocks: u32,
+	/// Per-author counts, most productive author first.
+	pub authors: Vec<AuthorShare<AccountId>>,
+}
+
+#[rpc]
+pub trait AuthorHistoryApi<BlockHash, AccountId> {
+	/// Author distribution over the last `window` blocks known to the author-history pallet.
+	/// All remembered blocks are used when no window is given.
+	#[rpc(name = "ethash_authorStats")]
+	fn author_stats(
+		&self,
+		window: Option<u32>,
+		at: Option<BlockHash>,
+	) -> Result<AuthorStats<AccountId>>;
+}
+
+/// A struct that implements the `AuthorHistoryApi`.
+pub struct AuthorHistory<C, M> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<M>,
+}
+
+impl<C, M> AuthorHistory<C, M> {
+	/// Create new `AuthorHistory` instance with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		Self {
+			client,
+			_marker: Default::default(),
+		}
+	}
+}
+
+/// Count how many of the last `window` blocks every author produced.
+fn compute_stats<BlockNumber, AccountId: Ord + Clone>(
+	authors: Vec<(BlockNumber, AccountId)>,
+	window: Option<u32>,
+) -> AuthorStats<AccountId> {
+	let skip = window
+		.map(|w| authors.len().saturating_sub(w as usize))
+		.unwrap_or(0);
+
+	let mut counts = BTreeMap::<AccountId, u32>::new();
+	for (_, author) in authors.into_iter().skip(skip) {
+		*counts.entry(author).or_default() += 1;
+	}
+
+	let blocks: u32 = counts.values().sum();
+	let mut authors: Vec<_> = counts
+		.into_iter()
+		.map(|(author, count)| AuthorShare {
+			author,
+			blocks: count,
+			share: f64::from(count) * 100.0 / f64::from(blocks),
+		})
+		.collect();
+	authors.sort_by(|a, b| b.blocks.cmp(&a.blocks));
+
+	AuthorStats { blocks, authors }
+}
+
+impl<C, Block, BlockNumber, AccountId> AuthorHistoryApi<<Block as BlockT>::Hash, AccountId>
+	for AuthorHistory<C, (Block, BlockNumber)>
+where
+	Block: BlockT,
+	BlockNumber: Codec + Send + Sync + 'static,
+	AccountId: Codec + Ord + Clone + Send + Sync + 'static,
+	C: Send + Sync + 'static,
+	C: ProvideRuntimeApi<Block>,
+	C: HeaderBackend<Block>,
+	C::Api: AuthorHistoryRuntimeApi<Block, BlockNumber, AccountId>,
+{
+	fn author_stats(
+		&self,
+		window: Option<u32>,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<AuthorStats<AccountId>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(||
+			// If the block hash is not supplied assume the best block.
+			self.client.info().best_hash));
+
+		let authors = api.recent_authors(&at).map_err(|e| RpcError {
+			code: ErrorCode::ServerError(1),
+			message: "Unable to query the author history.".into(),
+			data: Some(format!("{:?}", e).into()),
+		})?;
+
+		Ok(compute_stats(authors, window))
+	}
+}
@@ -0,0 +1,18 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![allow(clippy::too_many_arguments)]
+#![allow(clippy::unnecessary_mut_passed)]
+
+use parity_scale_codec::Codec;
+use sp_std::prelude::*;
+
+// Here we declare the runtime API. It is implemented it the `impl` block in
+// runtime amalgamator file (the `runtime/src/lib.rs`)
+sp_api::decl_runtime_apis! {
+	pub trait AuthorHistoryApi<BlockNumber, AccountId> where
+		BlockNumber: Codec,
+		AccountId: Codec,
+	{
+		/// The remembered block authors, ordered from the oldest to the most recent block.
+		fn recent_authors() -> Vec<(BlockNumber, AccountId)>;
+	}
+}
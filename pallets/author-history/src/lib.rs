@@ -0,0 +1,103 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! A pallet that remembers who authored each of the most recent blocks.
+//!
+//! The authors are kept in a ringbuffer in storage: once `HistoryDepth` blocks have been
+//! recorded, the oldest entry is overwritten by the newest one. This keeps the storage footprint
+//! bounded while still letting statistics, dashboards and other pallets look at the recent
+//! distribution of authorship.
+//!
+//! The author of a block is found through the `FindAuthor` configuration trait. For proof of
+//! work chains the `PowDigestAuthor` helper reads the account from the PoW pre-runtime digest.
+
+use frame_support::{
+	decl_module, decl_storage,
+	traits::{FindAuthor, Get},
+	weights::Weight,
+};
+use parity_scale_codec::Decode;
+use sp_consensus_pow::POW_ENGINE_ID;
+use sp_runtime::ConsensusEngineId;
+use sp_std::{marker::PhantomData, prelude::*};
+
+#[cfg(test)]
+mod tests;
+
+pub trait Config: frame_system::Config {
+	/// Finds the author of the current block from its pre-runtime digests.
+	type FindAuthor: FindAuthor<Self::AccountId>;
+
+	/// Number of recent blocks whose author is remembered.
+	type HistoryDepth: Get<u32>;
+}
+
+decl_storage! {
+	trait Store for Module<T: Config> as AuthorHistory {
+		/// Slots of the ringbuffer, each holding a block number and its author.
+		Authors get(fn author_slot):
+			map hasher(twox_64_concat) u32 => Option<(T::BlockNumber, T::AccountId)>;
+		/// Slot the next author will be written to.
+		NextSlot get(fn next_slot): u32;
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Config> for enum Call where origin: T::Origin {
+		const HistoryDepth: u32 = T::HistoryDepth::get();
+
+		fn on_initialize(n: T::BlockNumber) -> Weight {
+			let digest = <frame_system::Module<T>>::digest();
+			let pre_runtime_digests = digest.logs.iter().filter_map(|d| d.as_pre_runtime());
+
+			match T::FindAuthor::find_author(pre_runtime_digests) {
+				Some(author) => {
+					Self::note_author(n, author);
+					T::DbWeight::get().reads_writes(2, 2)
+				}
+				None => T::DbWeight::get().reads(1),
+			}
+		}
+	}
+}
+
+impl<T: Config> Module<T> {
+	/// Record `author` as the author of block `n`, overwriting the oldest entry once the
+	/// ringbuffer is full.
+	pub fn note_author(n: T::BlockNumber, author: T::AccountId) {
+		let depth = T::HistoryDepth::get();
+		if depth == 0 {
+			return;
+		}
+
+		let slot = NextSlot::get() % depth;
+		<Authors<T>>::insert(slot, (n, author));
+		NextSlot::put((slot + 1) % depth);
+	}
+
+	/// The remembered authors, ordered from the oldest to the most recent block.
+	pub fn recent_authors() -> Vec<(T::BlockNumber, T::AccountId)> {
+		let depth = T::HistoryDepth::get();
+		let next = NextSlot::get();
+
+		(0..depth)
+			.filter_map(|i| <Authors<T>>::get((next + i) % depth))
+			.collect()
+	}
+}
+
+/// Finds the block author in the pre-runtime digest inserted by the PoW mining worker.
+///
+/// The digest payload is expected to be the SCALE encoded account id of the author.
+pub struct PowDigestAuthor<AccountId>(PhantomData<AccountId>);
+
+impl<AccountId: Decode> FindAuthor<AccountId> for PowDigestAuthor<AccountId> {
+	fn find_author<'a, I>(digests: I) -> Option<AccountId>
+	where
+		I: 'a + IntoIterator<Item = (ConsensusEngineId, &'a [u8])>,
+	{
+		digests
+			.into_iter()
+			.find(|(id, _)| *id == POW_ENGINE_ID)
+			.and_then(|(_, mut data)| AccountId::decode(&mut data).ok())
+	}
+}
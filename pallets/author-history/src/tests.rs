@@ -0,0 +1,117 @@
+use crate::{self as author_history, Config, PowDigestAuthor};
+use frame_support::{construct_runtime, parameter_types, traits::OnInitialize};
+use frame_system::InitKind;
+use parity_scale_codec::Encode;
+use sp_consensus_pow::POW_ENGINE_ID;
+use sp_core::H256;
+use sp_io::TestExternalities;
+use sp_runtime::{
+	testing::{Digest, DigestItem, Header},
+	traits::{BlakeTwo256, IdentityLookup},
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<TestRuntime>;
+type Block = frame_system::mocking::MockBlock<TestRuntime>;
+
+construct_runtime!(
+	pub enum TestRuntime where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Module, Call, Config, Storage, Event<T>},
+		AuthorHistory: author_history::{Module, Storage},
+	}
+);
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+}
+impl frame_system::Config for TestRuntime {
+	type BaseCallFilter = ();
+	type BlockWeights = ();
+	type BlockLength = ();
+	type Origin = Origin;
+	type Index = u64;
+	type Call = Call;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type DbWeight = ();
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+}
+
+parameter_types! {
+	pub const HistoryDepth: u32 = 3;
+}
+impl Config for TestRuntime {
+	type FindAuthor = PowDigestAuthor<u64>;
+	type HistoryDepth = HistoryDepth;
+}
+
+struct ExternalityBuilder;
+
+impl ExternalityBuilder {
+	pub fn build() -> TestExternalities {
+		let storage = frame_system::GenesisConfig::default()
+			.build_storage::<TestRuntime>()
+			.unwrap();
+		let mut ext = TestExternalities::from(storage);
+		ext.execute_with(|| System::set_block_number(1));
+		ext
+	}
+}
+
+/// Start block `n` with a PoW pre-runtime digest naming `author`, then run the pallet hook.
+fn mine_block(n: u64, author: u64) {
+	let digest = Digest {
+		logs: vec![DigestItem::PreRuntime(POW_ENGINE_ID, author.encode())],
+	};
+	System::initialize(&n, &Default::default(), &digest, InitKind::Full);
+	AuthorHistory::on_initialize(n);
+}
+
+#[test]
+fn records_author_from_pre_digest() {
+	ExternalityBuilder::build().execute_with(|| {
+		mine_block(1, 42);
+
+		assert_eq!(AuthorHistory::recent_authors(), vec![(1, 42)]);
+	})
+}
+
+#[test]
+fn ignores_blocks_without_author() {
+	ExternalityBuilder::build().execute_with(|| {
+		System::initialize(&1, &Default::default(), &Default::default(), InitKind::Full);
+		AuthorHistory::on_initialize(1);
+
+		assert!(AuthorHistory::recent_authors().is_empty());
+	})
+}
+
+#[test]
+fn ringbuffer_overwrites_oldest_author() {
+	ExternalityBuilder::build().execute_with(|| {
+		for (n, author) in (1..=5).zip(vec![10, 11, 12, 13, 14]) {
+			mine_block(n, author);
+		}
+
+		assert_eq!(
+			AuthorHistory::recent_authors(),
+			vec![(3, 12), (4, 13), (5, 14)]
+		);
+		assert_eq!(AuthorHistory::next_slot(), 2);
+	})
+}
@@ -0,0 +1,63 @@
+//! RPC interface to the emission schedule of the block-reward pallet.
+
+use block_reward_runtime_api::BlockRewardApi as BlockRewardRuntimeApi;
+use codec::Codec;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::U256;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+use std::sync::Arc;
+
+#[rpc]
+pub trait BlockRewardApi<BlockHash, BlockNumber> {
+	/// Reward of the author of block `number`, which may lie in the future, according to the
+	/// schedule of the runtime at `at`.
+	#[rpc(name = "ethash_blockReward")]
+	fn block_reward(&self, number: BlockNumber, at: Option<BlockHash>) -> Result<U256>;
+}
+
+/// A struct that implements the `BlockRewardApi`.
+pub struct BlockReward<C, M> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<M>,
+}
+
+impl<C, M> BlockReward<C, M> {
+	/// Create new `BlockReward` instance with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		Self {
+			client,
+			_marker: Default::default(),
+		}
+	}
+}
+
+impl<C, Block, BlockNumber, Balance> BlockRewardApi<<Block as BlockT>::Hash, BlockNumber>
+	for BlockReward<C, (Block, Balance)>
+where
+	Block: BlockT,
+	BlockNumber: Codec + Send + Sync + 'static,
+	Balance: Codec + Into<u128> + Send + Sync + 'static,
+	C: Send + Sync + 'static,
+	C: ProvideRuntimeApi<Block>,
+	C: HeaderBackend<Block>,
+	C::Api: BlockRewardRuntimeApi<Block, BlockNumber, Balance>,
+{
+	fn block_reward(&self, number: BlockNumber, at: Option<<Block as BlockT>::Hash>) -> Result<U256> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(||
+			// If the block hash is not supplied assume the best block.
+			self.client.info().best_hash));
+
+		// Balances are 128 bits wide, beyond what JSON numbers hold exactly.
+		api.block_reward(&at, number)
+			.map(|reward| U256::from(reward.into()))
+			.map_err(|e| RpcError {
+				code: ErrorCode::ServerError(1),
+				message: "Unable to query the block reward.".into(),
+				data: Some(format!("{:?}", e).into()),
+			})
+	}
+}
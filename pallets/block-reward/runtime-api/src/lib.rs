@@ -0,0 +1,17 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![allow(clippy::too_many_arguments)]
+#![allow(clippy::unnecessary_mut_passed)]
+
+use parity_scale_codec::Codec;
+
+// Here we declare the runtime API. It is implemented it the `impl` block in
+// runtime amalgamator file (the `runtime/src/lib.rs`)
+sp_api::decl_runtime_apis! {
+	pub trait BlockRewardApi<BlockNumber, Balance> where
+		BlockNumber: Codec,
+		Balance: Codec,
+	{
+		/// Reward of the author of block `number`, past or future, according to the schedule.
+		fn block_reward(number: BlockNumber) -> Balance;
+	}
+}
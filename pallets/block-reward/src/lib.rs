@@ -0,0 +1,201 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! A pallet paying a reward to the author of every block.
+//!
+//! The reward starts at `Reward` and is halved every `HalvingInterval` blocks, or stays constant
+//! when the interval is zero. Wallets and explorers read the schedule through the
+//! `BlockRewardApi` runtime API.
+//!
+//! The block producer names the author through an inherent: it puts the author's account into
+//! the inherent data under `INHERENT_IDENTIFIER`, and the pallet turns it into an unsigned
+//! `claim_reward` call placed at the start of the block. The call can only be dispatched once
+//! per block and, not having an unsigned validator, is never accepted by the transaction pool.
+//!
+//! The configured halving schedule can be replaced on a live chain by another `EmissionCurve` at
+//! a runtime upgrade, with the `migrations::SwitchEmissionCurve` migration. The new curve counts
+//! from the first block of the upgraded runtime, the blocks before it keep their reward.
+//!
+//! A new chain can also start with its own curve, set from block 0 by the `schedule` field of the
+//! genesis configuration of the pallet. The migration doesn't replace a curve set at genesis.
+
+use frame_support::{
+	decl_error, decl_event, decl_module, decl_storage, ensure,
+	traits::{Currency, Get},
+	weights::{DispatchClass, Weight},
+};
+use frame_system::ensure_none;
+use parity_scale_codec::{Decode, Encode};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_inherents::{InherentData, InherentIdentifier, MakeFatalError, ProvideInherent};
+use sp_runtime::{
+	traits::{AtLeast32BitUnsigned, SaturatedConversion, Saturating, Zero},
+	RuntimeDebug,
+};
+use sp_std::prelude::*;
+
+pub mod migrations;
+
+#[cfg(test)]
+mod tests;
+
+/// Identifier of the inherent data holding the SCALE encoded account of the block author.
+pub const INHERENT_IDENTIFIER: InherentIdentifier = *b"blockrwd";
+
+pub type BalanceOf<T> =
+	<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+
+pub trait Config: frame_system::Config {
+	/// The overarching event type.
+	type Event: From<Event<Self>> + Into<<Self as frame_system::Config>::Event>;
+
+	/// The currency the reward is paid in.
+	type Currency: Currency<Self::AccountId>;
+
+	/// Amount minted for the author of each block, before any halving.
+	type Reward: Get<BalanceOf<Self>>;
+
+	/// Number of blocks between two halvings of the reward, zero to never halve it.
+	type HalvingInterval: Get<Self::BlockNumber>;
+}
+
+/// An emission curve: the reward of a block by the number of blocks since the curve took effect.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub enum EmissionCurve<Balance, BlockNumber> {
+	/// `reward`, halved every `interval` blocks, or constant when the interval is zero.
+	Halving { reward: Balance, interval: BlockNumber },
+	/// `reward`, lowered by `step` every `interval` blocks down to `floor`, or constant when the
+	/// interval is zero.
+	Linear { reward: Balance, step: Balance, interval: BlockNumber, floor: Balance },
+}
+
+impl<Balance, BlockNumber> EmissionCurve<Balance, BlockNumber>
+where
+	Balance: AtLeast32BitUnsigned + Copy,
+	BlockNumber: AtLeast32BitUnsigned + Copy,
+{
+	/// Reward of the block `elapsed` blocks after the curve took effect.
+	pub fn reward_at(&self, elapsed: BlockNumber) -> Balance {
+		match *self {
+			EmissionCurve::Halving { reward, interval } => {
+				if interval.is_zero() {
+					return reward;
+				}
+				// The reward reaches zero after as many halvings as it has bits.
+				let halvings = (elapsed / interval).saturated_into::<u32>();
+				let mut reward = reward;
+				for _ in 0..halvings {
+					if reward.is_zero() {
+						break;
+					}
+					reward = reward / 2u32.into();
+				}
+				reward
+			}
+			EmissionCurve::Linear { reward, step, interval, floor } => {
+				if interval.is_zero() {
+					return reward;
+				}
+				let steps = (elapsed / interval).saturated_into::<u32>();
+				reward.saturating_sub(step.saturating_mul(steps.into())).max(floor)
+			}
+		}
+	}
+}
+
+decl_storage! {
+	trait Store for Module<T: Config> as BlockReward {
+		/// Author rewarded in the current block, cleared when the block is finalized.
+		Author get(fn author): Option<T::AccountId>;
+		/// The curve that replaced the configured halving schedule, with the first block it
+		/// rewards. Set at genesis or by `migrations::SwitchEmissionCurve`.
+		Schedule get(fn schedule) config(): Option<(T::BlockNumber, EmissionCurve<BalanceOf<T>, T::BlockNumber>)>;
+	}
+}
+
+decl_event!(
+	pub enum Event<T>
+	where
+		Balance = BalanceOf<T>,
+		<T as frame_system::Config>::AccountId,
+	{
+		/// The author of the block has been rewarded
+		RewardClaimed(AccountId, Balance),
+	}
+);
+
+decl_error! {
+	pub enum Error for Module<T: Config> {
+		/// The reward of this block has already been claimed
+		AlreadyClaimed,
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Config> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+
+		const Reward: BalanceOf<T> = T::Reward::get();
+
+		const HalvingInterval: T::BlockNumber = T::HalvingInterval::get();
+
+		fn deposit_event() = default;
+
+		/// Reward `author` for producing the current block. Inherent only.
+		#[weight = (10_000, DispatchClass::Mandatory)]
+		fn claim_reward(origin, author: T::AccountId) {
+			ensure_none(origin)?;
+			ensure!(<Author<T>>::get().is_none(), Error::<T>::AlreadyClaimed);
+
+			let reward = Self::reward_at(<frame_system::Module<T>>::block_number());
+			let _ = T::Currency::deposit_creating(&author, reward);
+			<Author<T>>::put(&author);
+
+			Self::deposit_event(RawEvent::RewardClaimed(author, reward));
+		}
+
+		fn on_finalize() {
+			<Author<T>>::kill();
+		}
+
+		fn on_initialize() -> Weight {
+			// Accounts for killing `Author` in `on_finalize`.
+			T::DbWeight::get().writes(1)
+		}
+	}
+}
+
+impl<T: Config> Module<T> {
+	/// Reward of the author of block `number` according to the schedule.
+	pub fn reward_at(number: T::BlockNumber) -> BalanceOf<T> {
+		match Self::schedule() {
+			Some((since, curve)) if number >= since => curve.reward_at(number - since),
+			_ => Self::configured_curve().reward_at(number),
+		}
+	}
+
+	/// The halving schedule of the configuration, in force since genesis.
+	pub fn configured_curve() -> EmissionCurve<BalanceOf<T>, T::BlockNumber> {
+		EmissionCurve::Halving { reward: T::Reward::get(), interval: T::HalvingInterval::get() }
+	}
+}
+
+impl<T: Config> ProvideInherent for Module<T> {
+	type Call = Call<T>;
+	type Error = MakeFatalError<()>;
+	const INHERENT_IDENTIFIER: InherentIdentifier = INHERENT_IDENTIFIER;
+
+	fn create_inherent(data: &InherentData) -> Option<Self::Call> {
+		data.get_data::<T::AccountId>(&INHERENT_IDENTIFIER)
+			.ok()
+			.flatten()
+			.map(Call::claim_reward)
+	}
+
+	fn check_inherent(_call: &Self::Call, _data: &InherentData) -> Result<(), Self::Error> {
+		// Any author may be named, the call itself makes sure only one is rewarded per block.
+		Ok(())
+	}
+}
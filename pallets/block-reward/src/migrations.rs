@@ -0,0 +1,98 @@
+//! Runtime upgrade migrations of the block-reward pallet.
+//!
+//! Changing `Reward` or `HalvingInterval` in the configuration of a live chain would change the
+//! reward of every past block as well: `reward_at` would answer wrongly for them, and a chain
+//! half way through a halving period would jump to another one. `SwitchEmissionCurve` instead
+//! records the first block rewarded by the new curve, so the configured schedule keeps answering
+//! for the blocks before it. List it in the `OnRuntimeUpgrade` type parameter of the runtime's
+//! `Executive`, next to the upgrade setting the curve:
+//!
+//! ```ignore
+//! parameter_types! {
+//! 	pub const TailEmission: EmissionCurve<Balance, BlockNumber> = EmissionCurve::Linear {
+//! 		reward: 500_000_000_000,
+//! 		step: 10_000_000_000,
+//! 		interval: 100_000,
+//! 		floor: 100_000_000_000,
+//! 	};
+//! }
+//!
+//! pub type Executive = frame_executive::Executive<
+//! 	Runtime,
+//! 	Block,
+//! 	frame_system::ChainContext<Runtime>,
+//! 	Runtime,
+//! 	AllModules,
+//! 	block_reward::migrations::SwitchEmissionCurve<Runtime, TailEmission>,
+//! >;
+//! ```
+//!
+//! Check the switch against a copy of the live state with `try-runtime` before enacting it: the
+//! checks fail if the new curve would raise the reward at the switch, or if the blocks before it
+//! would be paid differently.
+
+use crate::{BalanceOf, Config, EmissionCurve, Schedule};
+use frame_support::{
+	traits::{Get, OnRuntimeUpgrade},
+	weights::Weight,
+};
+use sp_runtime::traits::One;
+#[cfg(feature = "try-runtime")]
+use {crate::Module, sp_runtime::traits::Zero};
+use sp_std::marker::PhantomData;
+
+/// Replace the configured halving schedule by `Curve` from the first block of the upgraded
+/// runtime on. Does nothing once a curve has replaced the schedule.
+pub struct SwitchEmissionCurve<T, Curve>(PhantomData<(T, Curve)>);
+
+impl<T, Curve> SwitchEmissionCurve<T, Curve>
+where
+	T: Config,
+	Curve: Get<EmissionCurve<BalanceOf<T>, T::BlockNumber>>,
+{
+	/// First block rewarded by the new curve. The migration runs before the block is initialized,
+	/// when the block number in the state still is the one of its parent.
+	fn first_block() -> T::BlockNumber {
+		<frame_system::Module<T>>::block_number() + One::one()
+	}
+}
+
+impl<T, Curve> OnRuntimeUpgrade for SwitchEmissionCurve<T, Curve>
+where
+	T: Config,
+	Curve: Get<EmissionCurve<BalanceOf<T>, T::BlockNumber>>,
+{
+	fn on_runtime_upgrade() -> Weight {
+		if <Schedule<T>>::exists() {
+			return T::DbWeight::get().reads(1);
+		}
+		<Schedule<T>>::put((Self::first_block(), Curve::get()));
+		T::DbWeight::get().reads_writes(2, 1)
+	}
+
+	#[cfg(feature = "try-runtime")]
+	fn pre_upgrade() -> Result<(), &'static str> {
+		if <Schedule<T>>::exists() {
+			return Ok(());
+		}
+		// Miners planned with the current reward, the switch may only lower it.
+		let first_block = Self::first_block();
+		if Curve::get().reward_at(Zero::zero()) > <Module<T>>::reward_at(first_block) {
+			return Err("the new emission curve raises the reward at the switch");
+		}
+		Ok(())
+	}
+
+	#[cfg(feature = "try-runtime")]
+	fn post_upgrade() -> Result<(), &'static str> {
+		let (since, _) = <Schedule<T>>::get().ok_or("no emission curve was set")?;
+		if since.is_zero() {
+			return Ok(());
+		}
+		let last_block = since - One::one();
+		if <Module<T>>::reward_at(last_block) != <Module<T>>::configured_curve().reward_at(last_block) {
+			return Err("the blocks before the switch are no longer paid the configured reward");
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,235 @@
+use crate::{
+	self as block_reward, migrations::SwitchEmissionCurve, Config, EmissionCurve, Error, RawEvent,
+	INHERENT_IDENTIFIER,
+};
+use frame_support::{
+	assert_noop, assert_ok, construct_runtime, parameter_types,
+	traits::{OnFinalize, OnRuntimeUpgrade},
+};
+use frame_system::{EventRecord, Phase, RawOrigin};
+use sp_core::H256;
+use sp_inherents::{InherentData, ProvideInherent};
+use sp_io::TestExternalities;
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup},
+	DispatchError,
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<TestRuntime>;
+type Block = frame_system::mocking::MockBlock<TestRuntime>;
+
+construct_runtime!(
+	pub enum TestRuntime where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Module, Call, Config, Storage, Event<T>},
+		Balances: pallet_balances::{Module, Call, Storage, Config<T>, Event<T>},
+		BlockReward: block_reward::{Module, Call, Storage, Event<T>, Inherent},
+	}
+);
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+}
+impl frame_system::Config for TestRuntime {
+	type BaseCallFilter = ();
+	type BlockWeights = ();
+	type BlockLength = ();
+	type Origin = Origin;
+	type Index = u64;
+	type Call = Call;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type DbWeight = ();
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u64>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: u64 = 1;
+}
+impl pallet_balances::Config for TestRuntime {
+	type MaxLocks = ();
+	type Balance = u64;
+	type Event = Event;
+	type DustRemoval = ();
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = System;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const Reward: u64 = 50;
+	pub const HalvingInterval: u64 = 10;
+}
+impl Config for TestRuntime {
+	type Event = Event;
+	type Currency = Balances;
+	type Reward = Reward;
+	type HalvingInterval = HalvingInterval;
+}
+
+struct ExternalityBuilder;
+
+impl ExternalityBuilder {
+	pub fn build() -> TestExternalities {
+		let storage = frame_system::GenesisConfig::default()
+			.build_storage::<TestRuntime>()
+			.unwrap();
+		let mut ext = TestExternalities::from(storage);
+		ext.execute_with(|| System::set_block_number(1));
+		ext
+	}
+}
+
+#[test]
+fn claim_rewards_author() {
+	ExternalityBuilder::build().execute_with(|| {
+		assert_ok!(BlockReward::claim_reward(RawOrigin::None.into(), 7));
+
+		assert_eq!(Balances::free_balance(7), 50);
+		assert_eq!(BlockReward::author(), Some(7));
+		assert!(System::events().contains(&EventRecord {
+			phase: Phase::Initialization,
+			event: Event::block_reward(RawEvent::RewardClaimed(7, 50)),
+			topics: vec![],
+		}));
+	})
+}
+
+#[test]
+fn reward_claimed_once_per_block() {
+	ExternalityBuilder::build().execute_with(|| {
+		assert_ok!(BlockReward::claim_reward(RawOrigin::None.into(), 7));
+		assert_noop!(
+			BlockReward::claim_reward(RawOrigin::None.into(), 8),
+			Error::<TestRuntime>::AlreadyClaimed
+		);
+
+		BlockReward::on_finalize(1);
+		System::set_block_number(2);
+
+		assert_ok!(BlockReward::claim_reward(RawOrigin::None.into(), 8));
+		assert_eq!(Balances::free_balance(8), 50);
+	})
+}
+
+#[test]
+fn reward_halves_every_interval() {
+	ExternalityBuilder::build().execute_with(|| {
+		assert_eq!(BlockReward::reward_at(0), 50);
+		assert_eq!(BlockReward::reward_at(9), 50);
+		assert_eq!(BlockReward::reward_at(10), 25);
+		assert_eq!(BlockReward::reward_at(25), 12);
+		assert_eq!(BlockReward::reward_at(1_000), 0);
+		assert_eq!(BlockReward::reward_at(u64::max_value()), 0);
+	})
+}
+
+#[test]
+fn claim_pays_scheduled_reward() {
+	ExternalityBuilder::build().execute_with(|| {
+		System::set_block_number(20);
+		assert_ok!(BlockReward::claim_reward(RawOrigin::None.into(), 7));
+		assert_eq!(Balances::free_balance(7), 12);
+	})
+}
+
+#[test]
+fn signed_claim_rejected() {
+	ExternalityBuilder::build().execute_with(|| {
+		assert_noop!(
+			BlockReward::claim_reward(RawOrigin::Signed(7).into(), 7),
+			DispatchError::BadOrigin
+		);
+	})
+}
+
+#[test]
+fn inherent_built_from_author() {
+	let mut data = InherentData::new();
+	assert!(BlockReward::create_inherent(&data).is_none());
+
+	data.put_data(INHERENT_IDENTIFIER, &7u64).unwrap();
+	assert_eq!(
+		BlockReward::create_inherent(&data),
+		Some(block_reward::Call::claim_reward(7))
+	);
+}
+
+parameter_types! {
+	pub const TailEmission: EmissionCurve<u64, u64> =
+		EmissionCurve::Linear { reward: 12, step: 2, interval: 5, floor: 4 };
+}
+
+#[test]
+fn linear_curve_steps_down_to_floor() {
+	let curve = TailEmission::get();
+	assert_eq!(curve.reward_at(0), 12);
+	assert_eq!(curve.reward_at(4), 12);
+	assert_eq!(curve.reward_at(5), 10);
+	assert_eq!(curve.reward_at(20), 4);
+	assert_eq!(curve.reward_at(u64::max_value()), 4);
+}
+
+#[test]
+fn migration_switches_curve_after_upgrade_block() {
+	ExternalityBuilder::build().execute_with(|| {
+		// The migration runs before block 21 is initialized.
+		System::set_block_number(20);
+		SwitchEmissionCurve::<TestRuntime, TailEmission>::on_runtime_upgrade();
+
+		assert_eq!(BlockReward::schedule(), Some((21, TailEmission::get())));
+		assert_eq!(BlockReward::reward_at(9), 50);
+		assert_eq!(BlockReward::reward_at(20), 12);
+		assert_eq!(BlockReward::reward_at(21), 12);
+		assert_eq!(BlockReward::reward_at(26), 10);
+		assert_eq!(BlockReward::reward_at(1_000), 4);
+	})
+}
+
+#[test]
+fn migration_runs_once() {
+	ExternalityBuilder::build().execute_with(|| {
+		System::set_block_number(20);
+		SwitchEmissionCurve::<TestRuntime, TailEmission>::on_runtime_upgrade();
+		System::set_block_number(40);
+		SwitchEmissionCurve::<TestRuntime, TailEmission>::on_runtime_upgrade();
+
+		assert_eq!(BlockReward::schedule(), Some((21, TailEmission::get())));
+	})
+}
+
+#[test]
+fn genesis_curve_rewards_from_first_block() {
+	let mut storage = frame_system::GenesisConfig::default()
+		.build_storage::<TestRuntime>()
+		.unwrap();
+	block_reward::GenesisConfig::<TestRuntime> { schedule: Some((0, TailEmission::get())) }
+		.assimilate_storage(&mut storage)
+		.unwrap();
+
+	TestExternalities::from(storage).execute_with(|| {
+		assert_eq!(BlockReward::reward_at(1), 12);
+		assert_eq!(BlockReward::reward_at(5), 10);
+
+		// The migration leaves the curve of the genesis in place.
+		System::set_block_number(20);
+		SwitchEmissionCurve::<TestRuntime, TailEmission>::on_runtime_upgrade();
+		assert_eq!(BlockReward::schedule(), Some((0, TailEmission::get())));
+	})
+}
@@ -0,0 +1,85 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! A pallet measuring the actual cadence of the chain.
+//!
+//! It keeps an exponential moving average of the time between blocks, fed by the timestamp
+//! pallet: the runtime sets it as `pallet_timestamp::Config::OnTimestampSet`. Every new
+//! interval moves the average by the `Smoothing` share of its difference with the average, so a
+//! higher smoothing follows changes faster but is more sensitive to lucky blocks.
+//!
+//! Other pallets, like difficulty adjustment or fee logic, read the average through the
+//! `BlockTimeProvider` trait, and dapps through the `BlockTimeOracleApi` runtime API.
+
+use frame_support::{decl_module, decl_storage, traits::Get};
+use sp_runtime::{
+	traits::{SaturatedConversion, Saturating, Zero},
+	Perbill,
+};
+
+#[cfg(test)]
+mod tests;
+
+pub trait Config: pallet_timestamp::Config {
+	/// Weight of the newest interval in the moving average.
+	type Smoothing: Get<Perbill>;
+}
+
+decl_storage! {
+	trait Store for Module<T: Config> as BlockTimeOracle {
+		/// Timestamp of the last block.
+		LastTimestamp get(fn last_timestamp): Option<T::Moment>;
+		/// Exponential moving average of the time between blocks.
+		AverageBlockTime get(fn average_block_time): Option<T::Moment>;
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Config> for enum Call where origin: T::Origin {
+		const Smoothing: Perbill = T::Smoothing::get();
+	}
+}
+
+impl<T: Config> Module<T> {
+	/// Fold the time between the last block and `now` into the average.
+	fn note_timestamp(now: T::Moment) {
+		if let Some(last) = <LastTimestamp<T>>::get() {
+			let interval = now.saturating_sub(last).saturated_into::<u64>();
+			let average = match <AverageBlockTime<T>>::get() {
+				Some(average) => {
+					let average = average.saturated_into::<u64>();
+					let smoothing = T::Smoothing::get();
+					if interval >= average {
+						average.saturating_add(smoothing * (interval - average))
+					} else {
+						average - smoothing * (average - interval)
+					}
+				}
+				None => interval,
+			};
+			<AverageBlockTime<T>>::put(average.saturated_into::<T::Moment>());
+		}
+		<LastTimestamp<T>>::put(now);
+	}
+}
+
+impl<T: Config> pallet_timestamp::OnTimestampSet<T::Moment> for Module<T> {
+	fn on_timestamp_set(now: T::Moment) {
+		// The genesis block has no timestamp to measure from.
+		if !now.is_zero() {
+			Self::note_timestamp(now);
+		}
+	}
+}
+
+/// Access to the average time between blocks, for pallets depending on the chain cadence.
+pub trait BlockTimeProvider<Moment> {
+	/// Exponential moving average of the time between blocks, `None` until two blocks have been
+	/// timestamped.
+	fn average_block_time() -> Option<Moment>;
+}
+
+impl<T: Config> BlockTimeProvider<T::Moment> for Module<T> {
+	fn average_block_time() -> Option<T::Moment> {
+		<AverageBlockTime<T>>::get()
+	}
+}
@@ -0,0 +1,136 @@
+use crate::{self as block_time_oracle, BlockTimeProvider, Config};
+use frame_support::{assert_ok, construct_runtime, parameter_types, traits::OnFinalize};
+use sp_core::H256;
+use sp_io::TestExternalities;
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup},
+	Perbill,
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<TestRuntime>;
+type Block = frame_system::mocking::MockBlock<TestRuntime>;
+
+construct_runtime!(
+	pub enum TestRuntime where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Module, Call, Config, Storage, Event<T>},
+		Timestamp: pallet_timestamp::{Module, Call, Storage, Inherent},
+		BlockTimeOracle: block_time_oracle::{Module, Storage},
+	}
+);
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+}
+impl frame_system::Config for TestRuntime {
+	type BaseCallFilter = ();
+	type BlockWeights = ();
+	type BlockLength = ();
+	type Origin = Origin;
+	type Index = u64;
+	type Call = Call;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type DbWeight = ();
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+}
+
+parameter_types! {
+	pub const MinimumPeriod: u64 = 1;
+}
+impl pallet_timestamp::Config for TestRuntime {
+	type Moment = u64;
+	type OnTimestampSet = BlockTimeOracle;
+	type MinimumPeriod = MinimumPeriod;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const Smoothing: Perbill = Perbill::from_percent(10);
+}
+impl Config for TestRuntime {
+	type Smoothing = Smoothing;
+}
+
+struct ExternalityBuilder;
+
+impl ExternalityBuilder {
+	pub fn build() -> TestExternalities {
+		let storage = frame_system::GenesisConfig::default()
+			.build_storage::<TestRuntime>()
+			.unwrap();
+		TestExternalities::from(storage)
+	}
+}
+
+/// Timestamp a block through the inherent, as block production would.
+fn timestamp_block(now: u64) {
+	assert_ok!(Timestamp::set(Origin::none(), now));
+	Timestamp::on_finalize(System::block_number());
+}
+
+#[test]
+fn no_average_before_second_block() {
+	ExternalityBuilder::build().execute_with(|| {
+		assert_eq!(BlockTimeOracle::average_block_time(), None);
+
+		timestamp_block(1_000);
+		assert_eq!(BlockTimeOracle::last_timestamp(), Some(1_000));
+		assert_eq!(BlockTimeOracle::average_block_time(), None);
+	})
+}
+
+#[test]
+fn first_interval_seeds_average() {
+	ExternalityBuilder::build().execute_with(|| {
+		timestamp_block(1_000);
+		timestamp_block(7_000);
+
+		assert_eq!(BlockTimeOracle::average_block_time(), Some(6_000));
+	})
+}
+
+#[test]
+fn average_moves_by_smoothing_share() {
+	ExternalityBuilder::build().execute_with(|| {
+		timestamp_block(1_000);
+		timestamp_block(7_000);
+
+		// 10% of the way from 6s to 16s.
+		timestamp_block(23_000);
+		assert_eq!(BlockTimeOracle::average_block_time(), Some(7_000));
+
+		// 10% of the way from 7s to 2s.
+		timestamp_block(25_000);
+		assert_eq!(BlockTimeOracle::average_block_time(), Some(6_500));
+	})
+}
+
+#[test]
+fn provider_reads_average() {
+	ExternalityBuilder::build().execute_with(|| {
+		timestamp_block(1_000);
+		timestamp_block(4_000);
+
+		assert_eq!(
+			<BlockTimeOracle as BlockTimeProvider<u64>>::average_block_time(),
+			Some(3_000)
+		);
+	})
+}
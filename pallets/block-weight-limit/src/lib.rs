@@ -0,0 +1,101 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! A pallet keeping the maximum block weight in storage, where root can change it.
+//!
+//! The runtime sets `frame_system::Config::BlockWeights` to `DynamicBlockWeights`, which builds
+//! the block weight limits from the stored maximum. The limits are read by `frame_system` when
+//! checking the weight of every extrinsic, so block producers applying extrinsics through the
+//! runtime, like the basic authorship proposer, honor a new limit from the block following the
+//! change, without a client release.
+
+use frame_support::{
+	decl_error, decl_event, decl_module, decl_storage, ensure,
+	traits::Get,
+	weights::{DispatchClass, Weight},
+};
+use frame_system::{ensure_root, limits::BlockWeights};
+use sp_runtime::Perbill;
+use sp_std::marker::PhantomData;
+
+#[cfg(test)]
+mod tests;
+
+pub trait Config: frame_system::Config {
+	/// The overarching event type.
+	type Event: From<Event> + Into<<Self as frame_system::Config>::Event>;
+
+	/// Maximum block weight until root sets one.
+	type DefaultMaxBlockWeight: Get<Weight>;
+
+	/// Lowest maximum block weight root may set, so mandatory extrinsics always fit.
+	type MinBlockWeight: Get<Weight>;
+
+	/// Highest maximum block weight root may set, so blocks can still be imported in time.
+	type MaxBlockWeight: Get<Weight>;
+
+	/// Share of the block weight available to normal extrinsics.
+	type NormalDispatchRatio: Get<Perbill>;
+}
+
+decl_storage! {
+	trait Store for Module<T: Config> as BlockWeightLimit {
+		/// Maximum weight of a block.
+		MaxBlockWeight get(fn max_block_weight): Weight = T::DefaultMaxBlockWeight::get();
+	}
+}
+
+decl_event!(
+	pub enum Event {
+		/// The maximum block weight has been changed
+		MaxBlockWeightSet(Weight),
+	}
+);
+
+decl_error! {
+	pub enum Error for Module<T: Config> {
+		/// The maximum block weight is below the allowed range
+		WeightTooLow,
+		/// The maximum block weight is above the allowed range
+		WeightTooHigh,
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Config> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+
+		const MinBlockWeight: Weight = T::MinBlockWeight::get();
+
+		const MaxBlockWeight: Weight = T::MaxBlockWeight::get();
+
+		fn deposit_event() = default;
+
+		/// Set the maximum weight of the blocks following this one. Root only.
+		#[weight = (T::DbWeight::get().writes(1), DispatchClass::Operational)]
+		fn set_max_block_weight(origin, max_block_weight: Weight) {
+			ensure_root(origin)?;
+			ensure!(max_block_weight >= T::MinBlockWeight::get(), Error::<T>::WeightTooLow);
+			ensure!(max_block_weight <= T::MaxBlockWeight::get(), Error::<T>::WeightTooHigh);
+
+			MaxBlockWeight::put(max_block_weight);
+
+			Self::deposit_event(Event::MaxBlockWeightSet(max_block_weight));
+		}
+	}
+}
+
+/// Block weight limits built from the stored maximum block weight, to be used as
+/// `frame_system::Config::BlockWeights`.
+///
+/// The limits are read from storage every time, so they can only be used where storage is
+/// available, which is the case for everything `frame_system` does with them.
+pub struct DynamicBlockWeights<T>(PhantomData<T>);
+
+impl<T: Config> Get<BlockWeights> for DynamicBlockWeights<T> {
+	fn get() -> BlockWeights {
+		BlockWeights::with_sensible_defaults(
+			<Module<T>>::max_block_weight(),
+			T::NormalDispatchRatio::get(),
+		)
+	}
+}
@@ -0,0 +1,136 @@
+use crate::{self as block_weight_limit, Config, DynamicBlockWeights, Error, Event as WeightEvent};
+use frame_support::{
+	assert_noop, assert_ok, construct_runtime, parameter_types, traits::Get,
+	weights::{constants::WEIGHT_PER_SECOND, DispatchClass, Weight},
+};
+use frame_system::{EventRecord, Phase, RawOrigin};
+use sp_core::H256;
+use sp_io::TestExternalities;
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup},
+	DispatchError, Perbill,
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<TestRuntime>;
+type Block = frame_system::mocking::MockBlock<TestRuntime>;
+
+construct_runtime!(
+	pub enum TestRuntime where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Module, Call, Config, Storage, Event<T>},
+		BlockWeightLimit: block_weight_limit::{Module, Call, Storage, Event},
+	}
+);
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+}
+impl frame_system::Config for TestRuntime {
+	type BaseCallFilter = ();
+	type BlockWeights = DynamicBlockWeights<TestRuntime>;
+	type BlockLength = ();
+	type Origin = Origin;
+	type Index = u64;
+	type Call = Call;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type DbWeight = ();
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+}
+
+parameter_types! {
+	pub const DefaultMaxBlockWeight: Weight = 2 * WEIGHT_PER_SECOND;
+	pub const MinBlockWeight: Weight = WEIGHT_PER_SECOND / 2;
+	pub const MaxBlockWeight: Weight = 4 * WEIGHT_PER_SECOND;
+	pub const NormalDispatchRatio: Perbill = Perbill::from_percent(75);
+}
+impl Config for TestRuntime {
+	type Event = Event;
+	type DefaultMaxBlockWeight = DefaultMaxBlockWeight;
+	type MinBlockWeight = MinBlockWeight;
+	type MaxBlockWeight = MaxBlockWeight;
+	type NormalDispatchRatio = NormalDispatchRatio;
+}
+
+struct ExternalityBuilder;
+
+impl ExternalityBuilder {
+	pub fn build() -> TestExternalities {
+		let storage = frame_system::GenesisConfig::default()
+			.build_storage::<TestRuntime>()
+			.unwrap();
+		let mut ext = TestExternalities::from(storage);
+		ext.execute_with(|| System::set_block_number(1));
+		ext
+	}
+}
+
+#[test]
+fn default_limit_used_until_set() {
+	ExternalityBuilder::build().execute_with(|| {
+		let weights = DynamicBlockWeights::<TestRuntime>::get();
+		assert_eq!(weights.max_block, 2 * WEIGHT_PER_SECOND);
+	})
+}
+
+#[test]
+fn root_raises_limit() {
+	ExternalityBuilder::build().execute_with(|| {
+		assert_ok!(BlockWeightLimit::set_max_block_weight(
+			RawOrigin::Root.into(),
+			3 * WEIGHT_PER_SECOND
+		));
+
+		let weights = DynamicBlockWeights::<TestRuntime>::get();
+		assert_eq!(weights.max_block, 3 * WEIGHT_PER_SECOND);
+		assert_eq!(
+			weights.get(DispatchClass::Normal).max_total,
+			Some(Perbill::from_percent(75) * 3 * WEIGHT_PER_SECOND)
+		);
+		assert!(System::events().contains(&EventRecord {
+			phase: Phase::Initialization,
+			event: Event::block_weight_limit(WeightEvent::MaxBlockWeightSet(3 * WEIGHT_PER_SECOND)),
+			topics: vec![],
+		}));
+	})
+}
+
+#[test]
+fn signed_origin_rejected() {
+	ExternalityBuilder::build().execute_with(|| {
+		assert_noop!(
+			BlockWeightLimit::set_max_block_weight(RawOrigin::Signed(1).into(), WEIGHT_PER_SECOND),
+			DispatchError::BadOrigin
+		);
+	})
+}
+
+#[test]
+fn limit_kept_in_range() {
+	ExternalityBuilder::build().execute_with(|| {
+		assert_noop!(
+			BlockWeightLimit::set_max_block_weight(RawOrigin::Root.into(), WEIGHT_PER_SECOND / 4),
+			Error::<TestRuntime>::WeightTooLow
+		);
+		assert_noop!(
+			BlockWeightLimit::set_max_block_weight(RawOrigin::Root.into(), 5 * WEIGHT_PER_SECOND),
+			Error::<TestRuntime>::WeightTooHigh
+		);
+	})
+}
@@ -0,0 +1,14 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![allow(clippy::too_many_arguments)]
+#![allow(clippy::unnecessary_mut_passed)]
+
+pub use ethpow_primitives::PowParams;
+
+// Here we declare the runtime API. It is implemented it the `impl` block in
+// runtime amalgamator file (the `runtime/src/lib.rs`)
+sp_api::decl_runtime_apis! {
+	pub trait PowParamsApi {
+		/// The seal requirements of the blocks built on the state queried.
+		fn pow_params() -> PowParams;
+	}
+}
@@ -0,0 +1,116 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! A pallet keeping the proof of work difficulty in the chain state.
+//!
+//! The difficulty required for the next block is retargeted every block from the times between
+//! the timestamps of the latest blocks: the runtime sets the pallet as (part of)
+//! `pallet_timestamp::Config::OnTimestampSet`.
+//!
+//! The retarget rule is a `DifficultyStrategy`, picked by the chain spec from the
+//! `RetargetStrategy` names: `"parent-delta"`, the Homestead formula and the default, `"eip100"`,
+//! `"lwma"` or `"fixed"`. Whatever the rule, the difficulty never drops below the minimum
+//! difficulty set at genesis. The consensus engine reads the result through
+//! `sp_consensus_pow::DifficultyApi`, so the difficulty of every block follows from the state of
+//! its parent instead of the seals of the chain.
+//!
+//! The seal requirements are `PowParams` the node reads through
+//! `difficulty_runtime_api::PowParamsApi`, also at the state of the parent of every block: a
+//! runtime upgrade changing `MinSealVersion`, or a migration changing the minimum difficulty,
+//! applies to every node from the same block.
+
+use ethpow_primitives::PowParams;
+use frame_support::{decl_module, decl_storage, traits::Get};
+use sp_core::U256;
+use sp_runtime::traits::{SaturatedConversion, Saturating, Zero};
+use sp_std::{cmp, prelude::*};
+
+pub mod strategy;
+
+pub use strategy::{BlockSample, DifficultyStrategy, RetargetParams, RetargetStrategy};
+
+#[cfg(test)]
+mod tests;
+
+pub trait Config: pallet_timestamp::Config {
+	/// A block moves the difficulty by multiples of `difficulty / BoundDivisor`.
+	type BoundDivisor: Get<U256>;
+	/// Width of the block time buckets of the adjustment.
+	type IncrementDivisor: Get<Self::Moment>;
+	/// A single block lowers the difficulty by at most this many multiples.
+	type MaxDecreaseSteps: Get<u64>;
+	/// Oldest seal layout accepted by the nodes, see `ethpow_primitives::VersionedWorkSeal`.
+	type MinSealVersion: Get<u8>;
+}
+
+decl_storage! {
+	trait Store for Module<T: Config> as Difficulty {
+		/// Difficulty required for the next block.
+		pub Difficulty get(fn difficulty) config(): U256;
+		/// The difficulty never drops below this value.
+		pub MinimumDifficulty get(fn minimum_difficulty) config(): U256;
+		/// The retarget rule.
+		pub Strategy get(fn strategy) config(): RetargetStrategy;
+		/// The latest blocks, oldest first, kept for the rules retargeting from more than one.
+		RecentBlocks get(fn recent_blocks): Vec<BlockSample>;
+		/// Timestamp of the last block.
+		LastTimestamp get(fn last_timestamp): Option<T::Moment>;
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Config> for enum Call where origin: T::Origin {
+		const BoundDivisor: U256 = T::BoundDivisor::get();
+		const IncrementDivisor: T::Moment = T::IncrementDivisor::get();
+		const MaxDecreaseSteps: u64 = T::MaxDecreaseSteps::get();
+		const MinSealVersion: u8 = T::MinSealVersion::get();
+	}
+}
+
+impl<T: Config> Module<T> {
+	/// The retarget parameters of the runtime.
+	pub fn retarget_params() -> RetargetParams {
+		RetargetParams {
+			bound_divisor: T::BoundDivisor::get(),
+			increment_divisor: T::IncrementDivisor::get().saturated_into(),
+			max_decrease_steps: T::MaxDecreaseSteps::get(),
+		}
+	}
+
+	/// The seal requirements of the blocks built on the current state.
+	pub fn pow_params() -> PowParams {
+		PowParams {
+			minimum_difficulty: Self::minimum_difficulty(),
+			min_seal_version: T::MinSealVersion::get(),
+		}
+	}
+
+	/// Difficulty of the block following one mined at the current difficulty in `block_time`,
+	/// recording the block for the rules retargeting from several.
+	fn retarget(block_time: T::Moment) -> U256 {
+		let rule = Self::strategy().rule();
+		let mut recent = if rule.window() > 1 { <RecentBlocks>::get() } else { Vec::new() };
+		recent.push(BlockSample { difficulty: Self::difficulty(), block_time: block_time.saturated_into() });
+		let excess = recent.len().saturating_sub(rule.window());
+		recent.drain(..excess);
+
+		let difficulty = rule.next_difficulty(&Self::retarget_params(), &recent);
+		if rule.window() > 1 {
+			<RecentBlocks>::put(recent);
+		}
+		cmp::max(Self::minimum_difficulty(), difficulty)
+	}
+}
+
+impl<T: Config> pallet_timestamp::OnTimestampSet<T::Moment> for Module<T> {
+	fn on_timestamp_set(now: T::Moment) {
+		// The genesis block has no timestamp to measure from.
+		if now.is_zero() {
+			return;
+		}
+		if let Some(last) = <LastTimestamp<T>>::get() {
+			let difficulty = Self::retarget(now.saturating_sub(last));
+			Difficulty::put(difficulty);
+		}
+		<LastTimestamp<T>>::put(now);
+	}
+}
@@ -0,0 +1,161 @@
+//! The retarget rules a chain picks from by name, with the `strategy` field of the genesis
+//! configuration of the pallet in its chain spec.
+
+use parity_scale_codec::{Decode, Encode};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_core::U256;
+use sp_runtime::RuntimeDebug;
+use sp_std::cmp;
+
+/// Number of blocks the LWMA averages over.
+pub const LWMA_WINDOW: usize = 60;
+
+/// A block the difficulty is retargeted from.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct BlockSample {
+	/// Difficulty the block was mined at.
+	pub difficulty: U256,
+	/// Time between the timestamps of the block and of its parent.
+	pub block_time: u64,
+}
+
+/// Parameters of the retarget set by the runtime, shared by the strategies.
+#[derive(Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct RetargetParams {
+	/// A block moves the difficulty by multiples of `difficulty / bound_divisor`.
+	pub bound_divisor: U256,
+	/// Width of the block time buckets of the adjustment.
+	pub increment_divisor: u64,
+	/// A single block lowers the difficulty by at most this many multiples.
+	pub max_decrease_steps: u64,
+}
+
+impl RetargetParams {
+	/// Block time the difficulty settles around, halfway between the times raising and lowering it.
+	pub fn target_block_time(&self) -> u64 {
+		self.increment_divisor + self.increment_divisor / 2
+	}
+}
+
+/// A retarget rule: the difficulty of the next block from the latest blocks of the chain.
+pub trait DifficultyStrategy {
+	/// Number of latest blocks the rule retargets from.
+	fn window(&self) -> usize {
+		1
+	}
+
+	/// Difficulty of the block following the `recent` ones, oldest first. There is at least one
+	/// block, and at most `window`. The pallet raises the result to the minimum difficulty.
+	fn next_difficulty(&self, params: &RetargetParams, recent: &[BlockSample]) -> U256;
+}
+
+/// The retarget rules, by the names they are chosen with.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "kebab-case"))]
+pub enum RetargetStrategy {
+	/// `"parent-delta"`, see `ParentDelta`.
+	ParentDelta,
+	/// `"eip100"`, see `Eip100`.
+	Eip100,
+	/// `"lwma"`, see `Lwma`.
+	Lwma,
+	/// `"fixed"`, see `Fixed`.
+	Fixed,
+}
+
+impl Default for RetargetStrategy {
+	fn default() -> Self {
+		RetargetStrategy::ParentDelta
+	}
+}
+
+impl RetargetStrategy {
+	/// The rule named by this strategy.
+	pub fn rule(self) -> &'static dyn DifficultyStrategy {
+		match self {
+			RetargetStrategy::ParentDelta => &ParentDelta,
+			RetargetStrategy::Eip100 => &Eip100,
+			RetargetStrategy::Lwma => &Lwma,
+			RetargetStrategy::Fixed => &Fixed,
+		}
+	}
+}
+
+/// Move `difficulty` by `difficulty / bound_divisor` times
+/// `max(1 - block_time / increment, -max_decrease_steps)`.
+fn bucket_adjustment(params: &RetargetParams, difficulty: U256, block_time: u64, increment: u64) -> U256 {
+	let bound = difficulty / params.bound_divisor;
+	let buckets = block_time / increment.max(1);
+	if buckets == 0 {
+		difficulty.saturating_add(bound)
+	} else {
+		let steps = cmp::min(buckets - 1, params.max_decrease_steps);
+		difficulty.saturating_sub(bound.saturating_mul(U256::from(steps)))
+	}
+}
+
+/// The Homestead formula, without the difficulty bomb: every block moves the difficulty of its
+/// parent by `difficulty / BoundDivisor` times `max(1 - block_time / IncrementDivisor,
+/// -MaxDecreaseSteps)`.
+pub struct ParentDelta;
+
+impl DifficultyStrategy for ParentDelta {
+	fn next_difficulty(&self, params: &RetargetParams, recent: &[BlockSample]) -> U256 {
+		let parent = &recent[recent.len() - 1];
+		bucket_adjustment(params, parent.difficulty, parent.block_time, params.increment_divisor)
+	}
+}
+
+/// The Byzantium formula of EIP-100. Without uncles, which this chain doesn't have, it is the
+/// Homestead formula with buckets of 9 rather than 10 seconds: the buckets are 9/10 of
+/// `IncrementDivisor` wide, settling on a slightly faster block time than `ParentDelta`.
+pub struct Eip100;
+
+impl DifficultyStrategy for Eip100 {
+	fn next_difficulty(&self, params: &RetargetParams, recent: &[BlockSample]) -> U256 {
+		let parent = &recent[recent.len() - 1];
+		let increment = params.increment_divisor.saturating_mul(9) / 10;
+		bucket_adjustment(params, parent.difficulty, parent.block_time, increment)
+	}
+}
+
+/// Linearly weighted moving average over the last `LWMA_WINDOW` blocks: the average difficulty
+/// scaled by the target block time over the average block time, weighting the latest blocks
+/// most. It reacts to hashrate changes within a few blocks without oscillating. Block times are
+/// clamped to six target block times, so that a single late timestamp can't crash the difficulty.
+pub struct Lwma;
+
+impl DifficultyStrategy for Lwma {
+	fn window(&self) -> usize {
+		LWMA_WINDOW
+	}
+
+	fn next_difficulty(&self, params: &RetargetParams, recent: &[BlockSample]) -> U256 {
+		let target = params.target_block_time().max(1);
+		let mut total_difficulty = U256::zero();
+		let mut weighted_times = U256::zero();
+		for (index, block) in recent.iter().enumerate() {
+			let block_time = cmp::min(cmp::max(block.block_time, 1), target.saturating_mul(6));
+			total_difficulty = total_difficulty.saturating_add(block.difficulty);
+			weighted_times = weighted_times.saturating_add(U256::from(index as u64 + 1) * U256::from(block_time));
+		}
+
+		let count = recent.len() as u64;
+		let weights = U256::from(count * (count + 1) / 2);
+		(total_difficulty / U256::from(count))
+			.saturating_mul(U256::from(target))
+			.saturating_mul(weights)
+			/ weighted_times
+	}
+}
+
+/// The difficulty set at genesis, forever.
+pub struct Fixed;
+
+impl DifficultyStrategy for Fixed {
+	fn next_difficulty(&self, _: &RetargetParams, recent: &[BlockSample]) -> U256 {
+		recent[recent.len() - 1].difficulty
+	}
+}
@@ -0,0 +1,242 @@
+use crate::{self as difficulty, strategy::LWMA_WINDOW, Config, MinimumDifficulty, RetargetStrategy};
+use ethpow_primitives::PowParams;
+use frame_support::{
+	assert_ok, construct_runtime, parameter_types,
+	traits::{Get, OnFinalize},
+	StorageValue,
+};
+use sp_core::{H256, U256};
+use sp_io::TestExternalities;
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup},
+	BuildStorage,
+};
+use std::cell::Cell;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<TestRuntime>;
+type Block = frame_system::mocking::MockBlock<TestRuntime>;
+
+construct_runtime!(
+	pub enum TestRuntime where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Module, Call, Config, Storage, Event<T>},
+		Timestamp: pallet_timestamp::{Module, Call, Storage, Inherent},
+		Difficulty: difficulty::{Module, Storage, Config},
+	}
+);
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+}
+impl frame_system::Config for TestRuntime {
+	type BaseCallFilter = ();
+	type BlockWeights = ();
+	type BlockLength = ();
+	type Origin = Origin;
+	type Index = u64;
+	type Call = Call;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type DbWeight = ();
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+}
+
+parameter_types! {
+	pub const MinimumPeriod: u64 = 1;
+}
+impl pallet_timestamp::Config for TestRuntime {
+	type Moment = u64;
+	type OnTimestampSet = Difficulty;
+	type MinimumPeriod = MinimumPeriod;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub BoundDivisor: U256 = U256::from(2048);
+	pub const IncrementDivisor: u64 = 10_000;
+	pub const MaxDecreaseSteps: u64 = 99;
+}
+
+thread_local! {
+	static MIN_SEAL_VERSION: Cell<u8> = Cell::new(1);
+}
+
+/// Oldest seal layout accepted, changed by the tests upgrading the runtime.
+pub struct MinSealVersion;
+
+impl Get<u8> for MinSealVersion {
+	fn get() -> u8 {
+		MIN_SEAL_VERSION.with(Cell::get)
+	}
+}
+
+impl Config for TestRuntime {
+	type BoundDivisor = BoundDivisor;
+	type IncrementDivisor = IncrementDivisor;
+	type MaxDecreaseSteps = MaxDecreaseSteps;
+	type MinSealVersion = MinSealVersion;
+}
+
+struct ExternalityBuilder;
+
+impl ExternalityBuilder {
+	pub fn build() -> TestExternalities {
+		Self::build_with(Default::default())
+	}
+
+	pub fn build_with(strategy: RetargetStrategy) -> TestExternalities {
+		let storage = GenesisConfig {
+			frame_system: Some(Default::default()),
+			difficulty: Some(difficulty::GenesisConfig {
+				difficulty: U256::from(2_048_000),
+				minimum_difficulty: U256::from(2_000_000),
+				strategy,
+			}),
+		}
+		.build_storage()
+		.unwrap();
+		TestExternalities::from(storage)
+	}
+}
+
+/// Timestamp a block through the inherent, as block production would.
+fn timestamp_block(now: u64) {
+	assert_ok!(Timestamp::set(Origin::none(), now));
+	Timestamp::on_finalize(System::block_number());
+}
+
+#[test]
+fn genesis_difficulty_until_second_block() {
+	ExternalityBuilder::build().execute_with(|| {
+		assert_eq!(Difficulty::difficulty(), U256::from(2_048_000));
+
+		timestamp_block(1_000);
+		assert_eq!(Difficulty::last_timestamp(), Some(1_000));
+		assert_eq!(Difficulty::difficulty(), U256::from(2_048_000));
+	})
+}
+
+#[test]
+fn fast_block_raises_difficulty() {
+	ExternalityBuilder::build().execute_with(|| {
+		timestamp_block(1_000);
+		timestamp_block(6_000);
+
+		assert_eq!(Difficulty::difficulty(), U256::from(2_049_000));
+	})
+}
+
+#[test]
+fn block_in_second_bucket_keeps_difficulty() {
+	ExternalityBuilder::build().execute_with(|| {
+		timestamp_block(1_000);
+		timestamp_block(16_000);
+
+		assert_eq!(Difficulty::difficulty(), U256::from(2_048_000));
+	})
+}
+
+#[test]
+fn slow_block_lowers_difficulty_down_to_minimum() {
+	ExternalityBuilder::build().execute_with(|| {
+		timestamp_block(1_000);
+		// Three buckets late: two steps of 1_000 down.
+		timestamp_block(31_000);
+		assert_eq!(Difficulty::difficulty(), U256::from(2_046_000));
+
+		timestamp_block(1_000_000);
+		assert_eq!(Difficulty::difficulty(), U256::from(2_000_000));
+	})
+}
+
+#[test]
+fn fixed_strategy_keeps_genesis_difficulty() {
+	ExternalityBuilder::build_with(RetargetStrategy::Fixed).execute_with(|| {
+		timestamp_block(1_000);
+		timestamp_block(2_000);
+		timestamp_block(1_000_000);
+
+		assert_eq!(Difficulty::difficulty(), U256::from(2_048_000));
+	})
+}
+
+#[test]
+fn eip100_strategy_has_narrower_buckets() {
+	ExternalityBuilder::build_with(RetargetStrategy::Eip100).execute_with(|| {
+		timestamp_block(1_000);
+		// In the first bucket of parent-delta, the second of EIP-100.
+		timestamp_block(10_500);
+
+		assert_eq!(Difficulty::difficulty(), U256::from(2_048_000));
+	})
+}
+
+#[test]
+fn lwma_strategy_settles_on_target_block_time() {
+	ExternalityBuilder::build_with(RetargetStrategy::Lwma).execute_with(|| {
+		timestamp_block(1_000);
+		for block in 1..=10 {
+			timestamp_block(1_000 + block * 15_000);
+		}
+
+		assert_eq!(Difficulty::difficulty(), U256::from(2_048_000));
+	})
+}
+
+#[test]
+fn lwma_strategy_follows_hashrate() {
+	ExternalityBuilder::build_with(RetargetStrategy::Lwma).execute_with(|| {
+		timestamp_block(1_000);
+		// Three times faster than the target.
+		timestamp_block(6_000);
+		assert_eq!(Difficulty::difficulty(), U256::from(6_144_000));
+
+		for block in 2..=100 {
+			timestamp_block(1_000 + block * 5_000);
+		}
+		assert_eq!(Difficulty::recent_blocks().len(), LWMA_WINDOW);
+		assert!(Difficulty::difficulty() > U256::from(6_144_000));
+	})
+}
+
+#[test]
+fn runtime_upgrade_changes_pow_params_at_once() {
+	ExternalityBuilder::build().execute_with(|| {
+		timestamp_block(1_000);
+		assert_eq!(
+			Difficulty::pow_params(),
+			PowParams { minimum_difficulty: U256::from(2_000_000), min_seal_version: 1 }
+		);
+
+		// The upgrade replaces the code and migrates the minimum difficulty: the nodes read the
+		// new parameters at the state it leaves, before any block is built on it.
+		MIN_SEAL_VERSION.with(|version| version.set(2));
+		MinimumDifficulty::put(U256::from(3_000_000));
+		assert_eq!(
+			Difficulty::pow_params(),
+			PowParams { minimum_difficulty: U256::from(3_000_000), min_seal_version: 2 }
+		);
+
+		// The difficulty the runtime retargets never falls short of the minimum the nodes check.
+		timestamp_block(6_000);
+		assert_eq!(Difficulty::difficulty(), U256::from(3_000_000));
+		timestamp_block(1_000_000);
+		assert_eq!(Difficulty::difficulty(), Difficulty::pow_params().minimum_difficulty);
+	})
+}
@@ -0,0 +1,16 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![allow(clippy::too_many_arguments)]
+#![allow(clippy::unnecessary_mut_passed)]
+
+pub use ethpow_primitives::{CacheProof, EpochCache, SealFailure, WorkSeal};
+
+// Here we declare the runtime API. It is implemented it the `impl` block in
+// runtime amalgamator file (the `runtime/src/lib.rs`)
+sp_api::decl_runtime_apis! {
+	pub trait EthashSealApi {
+		/// The commitment to the light cache of `epoch`, if the runtime holds one.
+		fn epoch_cache(epoch: u64) -> Option<EpochCache>;
+		/// Verify the proof of work of `seal` with the light cache nodes of `proof`.
+		fn verify_seal(seal: WorkSeal, proof: CacheProof) -> Result<(), SealFailure>;
+	}
+}
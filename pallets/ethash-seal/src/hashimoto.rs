@@ -0,0 +1,98 @@
+//! The ethash proof of work of a seal, computed from the light cache nodes it reads.
+//!
+//! Mirrors the light computation of the node: every dataset item accessed is derived from the
+//! cache nodes on the fly. Words are read and written little endian.
+
+use sp_core::{H256, H512};
+use sp_io::hashing::{keccak_256, keccak_512};
+use sp_std::{collections::btree_map::BTreeMap, prelude::*};
+
+const NODE_WORDS: usize = 16;
+const MIX_WORDS: usize = 32;
+/// Bytes of the dataset read by an access, two dataset items.
+const PAGE_BYTES: u64 = 128;
+const ACCESSES: u32 = 64;
+const DATASET_PARENTS: u32 = 256;
+const FNV_PRIME: u32 = 0x0100_0193;
+
+type Node = [u32; NODE_WORDS];
+
+fn fnv(x: u32, y: u32) -> u32 {
+	x.wrapping_mul(FNV_PRIME) ^ y
+}
+
+fn to_words(bytes: &[u8]) -> Node {
+	let mut words = [0u32; NODE_WORDS];
+	for (word, chunk) in words.iter_mut().zip(bytes.chunks_exact(4)) {
+		*word = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+	}
+	words
+}
+
+fn to_bytes(words: &[u32]) -> Vec<u8> {
+	words.iter().flat_map(|word| word.to_le_bytes().to_vec()).collect()
+}
+
+/// Some nodes of a light cache of `len` nodes.
+pub struct SparseCache<'a> {
+	nodes: BTreeMap<u32, &'a H512>,
+	len: u32,
+}
+
+impl<'a> SparseCache<'a> {
+	/// The cache of `len` nodes of which `nodes` are known, by index.
+	pub fn new(nodes: impl IntoIterator<Item = &'a (u32, H512)>, len: u32) -> Self {
+		Self { nodes: nodes.into_iter().map(|(index, node)| (*index, node)).collect(), len }
+	}
+
+	fn node(&self, index: u32) -> Result<Node, u32> {
+		self.nodes.get(&index).map(|node| to_words(node.as_bytes())).ok_or(index)
+	}
+
+	/// Dataset item `index`, derived from the cache nodes.
+	fn dataset_item(&self, index: u32) -> Result<Node, u32> {
+		let mut item = self.node(index % self.len)?;
+		item[0] ^= index;
+		item = to_words(&keccak_512(&to_bytes(&item)));
+		for i in 0..DATASET_PARENTS {
+			let parent = self.node(fnv(index ^ i, item[i as usize % NODE_WORDS]) % self.len)?;
+			for (word, parent) in item.iter_mut().zip(parent.iter()) {
+				*word = fnv(*word, *parent);
+			}
+		}
+		Ok(to_words(&keccak_512(&to_bytes(&item))))
+	}
+}
+
+/// The mix digest and the value, compared to the boundary of the difficulty, of `nonce` for
+/// `header_hash`, over a dataset of `dataset_size` bytes derived from `cache`. Fails with the
+/// index of the first cache node read which `cache` lacks.
+pub fn hashimoto(
+	cache: &SparseCache,
+	dataset_size: u64,
+	header_hash: &H256,
+	nonce: u64,
+) -> Result<(H256, H256), u32> {
+	let seed = keccak_512(&[header_hash.as_bytes(), &nonce.to_le_bytes()[..]].concat());
+	let seed_words = to_words(&seed);
+	let mut mix = [0u32; MIX_WORDS];
+	mix[..NODE_WORDS].copy_from_slice(&seed_words);
+	mix[NODE_WORDS..].copy_from_slice(&seed_words);
+
+	let pages = (dataset_size / PAGE_BYTES) as u32;
+	for i in 0..ACCESSES {
+		let page = fnv(seed_words[0] ^ i, mix[i as usize % MIX_WORDS]) % pages;
+		for (n, half) in mix.chunks_exact_mut(NODE_WORDS).enumerate() {
+			let item = cache.dataset_item(page * 2 + n as u32)?;
+			for (word, item) in half.iter_mut().zip(item.iter()) {
+				*word = fnv(*word, *item);
+			}
+		}
+	}
+
+	let compressed: Vec<u32> =
+		mix.chunks_exact(4).map(|words| fnv(fnv(fnv(words[0], words[1]), words[2]), words[3])).collect();
+	let mix_hash = to_bytes(&compressed);
+	let value = keccak_256(&[&seed[..], &mix_hash[..]].concat());
+	Ok((H256::from_slice(&mix_hash), H256(value)))
+}
@@ -0,0 +1,113 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! A pallet verifying ethash seals in the runtime, against commitments to the light caches.
+//!
+//! Computing a proof of work needs the light cache of its epoch, tens of megabytes the runtime
+//! can't generate. The pallet instead stores an `EpochCache` for the epochs of the chain: the
+//! Merkle root of the light cache, set at genesis or by root with `set_epoch_cache`, as printed
+//! by the `epoch-cache` subcommand of the node. The node proves the cache nodes a seal reads
+//! with a `CacheProof`, and the runtime computes the proof of work from them through
+//! `ethash_seal_runtime_api::EthashSealApi`.
+//!
+//! The verification then runs the code of the runtime rather than the one of the node, so nodes
+//! whose native ethash code differs agree on the seals as long as they run the same runtime.
+
+use ethpow_primitives::{CacheProof, EpochCache, SealFailure, WorkSeal};
+use frame_support::{decl_error, decl_event, decl_module, decl_storage, ensure, traits::Get};
+use frame_system::ensure_root;
+use sp_core::{H256, U256, U512};
+use sp_io::hashing::keccak_256;
+use sp_std::convert::TryFrom;
+
+mod hashimoto;
+
+pub use hashimoto::{hashimoto, SparseCache};
+
+#[cfg(test)]
+mod tests;
+
+pub trait Config: frame_system::Config {
+	/// The overarching event type.
+	type Event: From<Event> + Into<<Self as frame_system::Config>::Event>;
+	/// Number of blocks sharing a light cache.
+	type EpochLength: Get<u64>;
+}
+
+decl_storage! {
+	trait Store for Module<T: Config> as EthashSeal {
+		/// Commitments to the light caches, by epoch.
+		pub EpochCaches get(fn epoch_cache) config(): map hasher(twox_64_concat) u64 => Option<EpochCache>;
+	}
+}
+
+decl_event!(
+	pub enum Event {
+		/// The commitment to the light cache of the epoch was set.
+		EpochCacheSet(u64),
+	}
+);
+
+decl_error! {
+	pub enum Error for Module<T: Config> {
+		/// The commitment describes an empty cache or dataset.
+		MalformedEpochCache,
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Config> for enum Call where origin: T::Origin {
+		const EpochLength: u64 = T::EpochLength::get();
+
+		type Error = Error<T>;
+
+		fn deposit_event() = default;
+
+		/// Set the commitment to the light cache of `epoch`.
+		#[weight = T::DbWeight::get().writes(1)]
+		pub fn set_epoch_cache(origin, epoch: u64, cache: EpochCache) {
+			ensure_root(origin)?;
+			ensure!(is_well_formed(&cache), Error::<T>::MalformedEpochCache);
+			EpochCaches::insert(epoch, cache);
+			Self::deposit_event(Event::EpochCacheSet(epoch));
+		}
+	}
+}
+
+impl<T: Config> Module<T> {
+	/// Verify the proof of work of `seal` with the light cache nodes of `proof`, against the
+	/// commitment to the cache of its epoch.
+	pub fn verify_seal(seal: &WorkSeal, proof: &CacheProof) -> Result<(), SealFailure> {
+		let epoch = seal.header_nr / T::EpochLength::get();
+		let cache = Self::epoch_cache(epoch)
+			.filter(is_well_formed)
+			.ok_or(SealFailure::UnknownEpoch(epoch))?;
+		if proof.root(cache.depth(), keccak_256) != Some(cache.root) {
+			return Err(SealFailure::CacheMismatch);
+		}
+
+		let nodes = SparseCache::new(&proof.nodes, cache.cache_nodes);
+		let (mix, value) = hashimoto(&nodes, cache.dataset_size, &seal.pow_hash, seal.nonce)
+			.map_err(SealFailure::MissingNode)?;
+		if mix != seal.mix_digest {
+			return Err(SealFailure::MixMismatch { expected: mix, found: seal.mix_digest });
+		}
+		if boundary_to_difficulty(&value) < seal.difficulty {
+			return Err(SealFailure::UnderTarget);
+		}
+		Ok(())
+	}
+}
+
+/// Whether `cache` describes a cache and a dataset the proof of work can be computed over.
+fn is_well_formed(cache: &EpochCache) -> bool {
+	cache.cache_nodes > 0 && cache.dataset_size >= 128
+}
+
+/// The difficulty met by a proof of work of `value`, `2^256 / value`.
+fn boundary_to_difficulty(value: &H256) -> U256 {
+	let value = U256::from_big_endian(value.as_bytes());
+	if value <= U256::one() {
+		return U256::max_value();
+	}
+	U256::try_from((U512::one() << 256) / U512::from(value)).unwrap_or_else(|_| U256::max_value())
+}
@@ -0,0 +1,187 @@
+use crate::{self as ethash_seal, hashimoto, Config, Error, SparseCache};
+use ethpow_primitives::{tree_depth, CacheProof, EpochCache, SealFailure, WorkSeal};
+use frame_support::{assert_noop, assert_ok, construct_runtime, parameter_types};
+use sp_core::{H256, H512, U256};
+use sp_io::{hashing::keccak_256, TestExternalities};
+use sp_runtime::{
+	testing::Header,
+	traits::{BadOrigin, BlakeTwo256, IdentityLookup},
+	BuildStorage,
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<TestRuntime>;
+type Block = frame_system::mocking::MockBlock<TestRuntime>;
+
+construct_runtime!(
+	pub enum TestRuntime where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Module, Call, Config, Storage, Event<T>},
+		EthashSeal: ethash_seal::{Module, Call, Storage, Event, Config},
+	}
+);
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+}
+impl frame_system::Config for TestRuntime {
+	type BaseCallFilter = ();
+	type BlockWeights = ();
+	type BlockLength = ();
+	type Origin = Origin;
+	type Index = u64;
+	type Call = Call;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type DbWeight = ();
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+}
+
+parameter_types! {
+	pub const EpochLength: u64 = 100;
+}
+impl Config for TestRuntime {
+	type Event = Event;
+	type EpochLength = EpochLength;
+}
+
+/// A toy light cache, small enough to read entirely.
+fn cache_nodes() -> Vec<(u32, H512)> {
+	(0..5u8).map(|index| (u32::from(index), H512::repeat_byte(index + 1))).collect()
+}
+
+/// The proof of every node of `nodes`: the only siblings missing are the padding leaves.
+fn full_proof(nodes: Vec<(u32, H512)>) -> (H256, CacheProof) {
+	let mut level: Vec<H256> = nodes.iter().map(|(_, node)| H256(keccak_256(node.as_bytes()))).collect();
+	let mut padding = H256::zero();
+	let mut siblings = Vec::new();
+	for _ in 0..tree_depth(nodes.len() as u32) {
+		if level.len() % 2 == 1 {
+			level.push(padding);
+			siblings.push(padding);
+		}
+		level = level.chunks(2).map(|pair| H256(keccak_256(&[pair[0].0, pair[1].0].concat()))).collect();
+		padding = H256(keccak_256(&[padding.0, padding.0].concat()));
+	}
+	(level[0], CacheProof { nodes, siblings })
+}
+
+fn epoch_cache(root: H256) -> EpochCache {
+	EpochCache { root, cache_nodes: 5, dataset_size: 4 * 128 }
+}
+
+/// A seal of block 42, in epoch 0, with the mix digest of its nonce.
+fn seal(proof: &CacheProof) -> WorkSeal {
+	let cache = SparseCache::new(&proof.nodes, 5);
+	let pow_hash = H256::repeat_byte(0x11);
+	let (mix_digest, _) = hashimoto(&cache, 4 * 128, &pow_hash, 7).unwrap();
+	WorkSeal { nonce: 7, pow_hash, mix_digest, difficulty: U256::one(), header_nr: 42, timestamp: 0 }
+}
+
+fn build(root: H256) -> TestExternalities {
+	let storage = GenesisConfig {
+		frame_system: Some(Default::default()),
+		ethash_seal: Some(ethash_seal::GenesisConfig { epoch_caches: vec![(0, epoch_cache(root))] }),
+	}
+	.build_storage()
+	.unwrap();
+	TestExternalities::from(storage)
+}
+
+#[test]
+fn proof_recomputes_root() {
+	let (root, proof) = full_proof(cache_nodes());
+	assert_eq!(proof.root(3, keccak_256), Some(root));
+	assert_eq!(proof.root(4, keccak_256), None);
+
+	let mut unordered = proof.clone();
+	unordered.nodes.swap(0, 1);
+	assert_eq!(unordered.root(3, keccak_256), None);
+}
+
+#[test]
+fn verifies_seal_against_committed_cache() {
+	let (root, proof) = full_proof(cache_nodes());
+	build(root).execute_with(|| {
+		assert_ok!(EthashSeal::verify_seal(&seal(&proof), &proof));
+	})
+}
+
+#[test]
+fn rejects_wrong_mix_digest() {
+	let (root, proof) = full_proof(cache_nodes());
+	build(root).execute_with(|| {
+		let mut seal = seal(&proof);
+		let expected = seal.mix_digest;
+		seal.mix_digest = H256::repeat_byte(0x22);
+		assert_eq!(
+			EthashSeal::verify_seal(&seal, &proof),
+			Err(SealFailure::MixMismatch { expected, found: H256::repeat_byte(0x22) })
+		);
+	})
+}
+
+#[test]
+fn rejects_seal_under_its_difficulty() {
+	let (root, proof) = full_proof(cache_nodes());
+	build(root).execute_with(|| {
+		let mut seal = seal(&proof);
+		seal.difficulty = U256::max_value();
+		assert_eq!(EthashSeal::verify_seal(&seal, &proof), Err(SealFailure::UnderTarget));
+	})
+}
+
+#[test]
+fn rejects_nodes_of_another_cache() {
+	let (root, proof) = full_proof(cache_nodes());
+	build(root).execute_with(|| {
+		let mut altered = proof.clone();
+		altered.nodes[3].1 = H512::repeat_byte(0xff);
+		assert_eq!(EthashSeal::verify_seal(&seal(&altered), &altered), Err(SealFailure::CacheMismatch));
+	})
+}
+
+#[test]
+fn rejects_seal_of_uncommitted_epoch() {
+	let (root, proof) = full_proof(cache_nodes());
+	build(root).execute_with(|| {
+		let mut seal = seal(&proof);
+		seal.header_nr = 142;
+		assert_eq!(EthashSeal::verify_seal(&seal, &proof), Err(SealFailure::UnknownEpoch(1)));
+	})
+}
+
+#[test]
+fn root_sets_epoch_caches() {
+	let (root, proof) = full_proof(cache_nodes());
+	build(H256::zero()).execute_with(|| {
+		System::set_block_number(1);
+		assert_noop!(EthashSeal::set_epoch_cache(Origin::signed(1), 0, epoch_cache(root)), BadOrigin);
+		assert_noop!(
+			EthashSeal::set_epoch_cache(Origin::root(), 0, EpochCache { cache_nodes: 0, ..epoch_cache(root) }),
+			Error::<TestRuntime>::MalformedEpochCache
+		);
+		assert_eq!(EthashSeal::verify_seal(&seal(&proof), &proof), Err(SealFailure::CacheMismatch));
+
+		assert_ok!(EthashSeal::set_epoch_cache(Origin::root(), 0, epoch_cache(root)));
+		assert_ok!(EthashSeal::verify_seal(&seal(&proof), &proof));
+		assert_eq!(
+			System::events().last().map(|record| record.event.clone()),
+			Some(Event::ethash_seal(ethash_seal::Event::EpochCacheSet(0)))
+		);
+	})
+}
@@ -0,0 +1,86 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! A pallet depositing a `BlockMined` event for every block: its author, the difficulty it was
+//! mined at and the reward of its author.
+//!
+//! Dapps and indexers follow the mining activity by subscribing to the events, instead of
+//! decoding the PoW digests of every header themselves. The author is found through the
+//! `FindAuthor` configuration trait, `author_history::PowDigestAuthor` for the PoW pre-runtime
+//! digest; blocks without one deposit no event.
+//!
+//! The difficulty is read in `on_initialize`, before the timestamp inherent retargets it for the
+//! next block, and the event is deposited in `on_finalize`.
+
+use frame_support::{
+	decl_event, decl_module, decl_storage,
+	traits::{FindAuthor, Get},
+	weights::Weight,
+	Parameter,
+};
+use sp_core::U256;
+use sp_runtime::traits::{Convert, Member};
+
+#[cfg(test)]
+mod tests;
+
+pub trait Config: frame_system::Config {
+	/// The overarching event type.
+	type Event: From<Event<Self>> + Into<<Self as frame_system::Config>::Event>;
+
+	/// Finds the author of the current block from its pre-runtime digests.
+	type FindAuthor: FindAuthor<Self::AccountId>;
+
+	/// Difficulty required for the current block, as set by its parent.
+	type Difficulty: Get<U256>;
+
+	/// The balance the reward is counted in.
+	type Balance: Parameter + Member + Copy + Default;
+
+	/// Reward of the author of a block, by block number.
+	type BlockReward: Convert<Self::BlockNumber, Self::Balance>;
+}
+
+decl_storage! {
+	trait Store for Module<T: Config> as MiningEvents {
+		/// Author and difficulty of the current block, cleared when the block is finalized.
+		Mined get(fn mined): Option<(T::AccountId, U256)>;
+	}
+}
+
+decl_event!(
+	pub enum Event<T>
+	where
+		<T as frame_system::Config>::AccountId,
+		Balance = <T as Config>::Balance,
+	{
+		/// A block was mined by the author, at the difficulty, for the reward.
+		BlockMined(AccountId, U256, Balance),
+	}
+);
+
+decl_module! {
+	pub struct Module<T: Config> for enum Call where origin: T::Origin {
+		fn deposit_event() = default;
+
+		fn on_initialize(_n: T::BlockNumber) -> Weight {
+			let digest = <frame_system::Module<T>>::digest();
+			let pre_runtime_digests = digest.logs.iter().filter_map(|d| d.as_pre_runtime());
+
+			match T::FindAuthor::find_author(pre_runtime_digests) {
+				Some(author) => {
+					<Mined<T>>::put((author, T::Difficulty::get()));
+					// Accounts for taking `Mined` in `on_finalize`.
+					T::DbWeight::get().reads_writes(3, 2)
+				}
+				None => T::DbWeight::get().reads(2),
+			}
+		}
+
+		fn on_finalize(n: T::BlockNumber) {
+			if let Some((author, difficulty)) = <Mined<T>>::take() {
+				let reward = T::BlockReward::convert(n);
+				Self::deposit_event(RawEvent::BlockMined(author, difficulty, reward));
+			}
+		}
+	}
+}
@@ -0,0 +1,141 @@
+use crate::{self as mining_events, Config, RawEvent};
+use frame_support::{
+	construct_runtime, parameter_types,
+	traits::{FindAuthor, OnFinalize, OnInitialize},
+};
+use frame_system::{EventRecord, InitKind, Phase};
+use parity_scale_codec::{Decode, Encode};
+use sp_core::{H256, U256};
+use sp_io::TestExternalities;
+use sp_runtime::{
+	testing::{Digest, DigestItem, Header},
+	traits::{BlakeTwo256, Convert, IdentityLookup},
+	ConsensusEngineId,
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<TestRuntime>;
+type Block = frame_system::mocking::MockBlock<TestRuntime>;
+
+construct_runtime!(
+	pub enum TestRuntime where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Module, Call, Config, Storage, Event<T>},
+		MiningEvents: mining_events::{Module, Storage, Event<T>},
+	}
+);
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+}
+impl frame_system::Config for TestRuntime {
+	type BaseCallFilter = ();
+	type BlockWeights = ();
+	type BlockLength = ();
+	type Origin = Origin;
+	type Index = u64;
+	type Call = Call;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type DbWeight = ();
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+}
+
+const ENGINE_ID: ConsensusEngineId = *b"test";
+
+/// Reads the author from the pre-runtime digest of `ENGINE_ID`.
+pub struct DigestAuthor;
+
+impl FindAuthor<u64> for DigestAuthor {
+	fn find_author<'a, I>(digests: I) -> Option<u64>
+	where
+		I: 'a + IntoIterator<Item = (ConsensusEngineId, &'a [u8])>,
+	{
+		digests
+			.into_iter()
+			.find(|(id, _)| *id == ENGINE_ID)
+			.and_then(|(_, mut data)| u64::decode(&mut data).ok())
+	}
+}
+
+parameter_types! {
+	pub BlockDifficulty: U256 = U256::from(1_000);
+}
+
+/// Ten units per block number, to tell the blocks apart.
+pub struct TenPerBlock;
+
+impl Convert<u64, u64> for TenPerBlock {
+	fn convert(n: u64) -> u64 {
+		n * 10
+	}
+}
+
+impl Config for TestRuntime {
+	type Event = Event;
+	type FindAuthor = DigestAuthor;
+	type Difficulty = BlockDifficulty;
+	type Balance = u64;
+	type BlockReward = TenPerBlock;
+}
+
+struct ExternalityBuilder;
+
+impl ExternalityBuilder {
+	pub fn build() -> TestExternalities {
+		let storage = frame_system::GenesisConfig::default()
+			.build_storage::<TestRuntime>()
+			.unwrap();
+		TestExternalities::from(storage)
+	}
+}
+
+/// Run block `n`, with a pre-runtime digest naming `author` if any.
+fn run_block(n: u64, author: Option<u64>) {
+	let digest = Digest {
+		logs: author
+			.map(|author| DigestItem::PreRuntime(ENGINE_ID, author.encode()))
+			.into_iter()
+			.collect(),
+	};
+	System::initialize(&n, &Default::default(), &digest, InitKind::Full);
+	MiningEvents::on_initialize(n);
+	MiningEvents::on_finalize(n);
+}
+
+#[test]
+fn deposits_event_for_mined_block() {
+	ExternalityBuilder::build().execute_with(|| {
+		run_block(3, Some(42));
+
+		let expected_event = Event::mining_events(RawEvent::BlockMined(42, U256::from(1_000), 30));
+		assert_eq!(
+			System::events(),
+			vec![EventRecord { phase: Phase::Initialization, event: expected_event, topics: vec![] }]
+		);
+		assert_eq!(MiningEvents::mined(), None);
+	})
+}
+
+#[test]
+fn blocks_without_author_deposit_nothing() {
+	ExternalityBuilder::build().execute_with(|| {
+		run_block(1, None);
+
+		assert!(System::events().is_empty());
+	})
+}
@@ -0,0 +1,136 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! A pallet raising the transaction pool priority of the payouts of mining pools.
+//!
+//! A pool pays its miners with plain transfers from its payout account, competing for block space
+//! with every other transaction. When blocks are full, the fee-based priority of a payout can
+//! leave it in the pool for long, and miners waiting for their earnings move to another pool.
+//!
+//! Root registers the payout accounts of the pools. The `BoostPayouts` signed extension adds
+//! `PayoutPriority` to the priority of the transactions signed by a registered account whose call
+//! passes the `PayoutCalls` filter, typically the balance transfers. The boost only orders the
+//! transaction pool: payouts still pay their fees and fit in the block weight like any other
+//! transaction.
+
+use frame_support::{
+	decl_event, decl_module, decl_storage,
+	traits::{Filter, Get},
+	weights::DispatchClass,
+};
+use frame_system::ensure_root;
+use parity_scale_codec::{Decode, Encode};
+use sp_runtime::{
+	traits::{DispatchInfoOf, SignedExtension},
+	transaction_validity::{
+		TransactionPriority, TransactionValidity, TransactionValidityError, ValidTransaction,
+	},
+};
+use sp_std::{fmt, marker::PhantomData};
+
+#[cfg(test)]
+mod tests;
+
+pub trait Config: frame_system::Config {
+	/// The overarching event type.
+	type Event: From<Event<Self>> + Into<<Self as frame_system::Config>::Event>;
+
+	/// The calls of a payout account that are payouts.
+	type PayoutCalls: Filter<<Self as frame_system::Config>::Call>;
+
+	/// Priority added to the payouts of the registered accounts.
+	type PayoutPriority: Get<TransactionPriority>;
+}
+
+decl_storage! {
+	trait Store for Module<T: Config> as PayoutPriority {
+		/// Accounts the pools pay their miners from.
+		PayoutAccounts get(fn is_payout_account):
+			map hasher(blake2_128_concat) T::AccountId => bool;
+	}
+}
+
+decl_event!(
+	pub enum Event<T>
+	where
+		<T as frame_system::Config>::AccountId,
+	{
+		/// An account has been registered as a payout account, or unregistered
+		PayoutAccountSet(AccountId, bool),
+	}
+);
+
+decl_module! {
+	pub struct Module<T: Config> for enum Call where origin: T::Origin {
+		const PayoutPriority: TransactionPriority = T::PayoutPriority::get();
+
+		fn deposit_event() = default;
+
+		/// Register `account` as the payout account of a pool, or unregister it. Root only.
+		#[weight = (T::DbWeight::get().writes(1), DispatchClass::Operational)]
+		fn set_payout_account(origin, account: T::AccountId, payout: bool) {
+			ensure_root(origin)?;
+
+			if payout {
+				<PayoutAccounts<T>>::insert(&account, true);
+			} else {
+				<PayoutAccounts<T>>::remove(&account);
+			}
+
+			Self::deposit_event(RawEvent::PayoutAccountSet(account, payout));
+		}
+	}
+}
+
+impl<T: Config> Module<T> {
+	/// Whether `who` submitting `call` is a payout.
+	pub fn is_payout(who: &T::AccountId, call: &<T as frame_system::Config>::Call) -> bool {
+		Self::is_payout_account(who) && T::PayoutCalls::filter(call)
+	}
+}
+
+/// Signed extension adding `Config::PayoutPriority` to the priority of the payouts.
+#[derive(Encode, Decode, Clone, Eq, PartialEq)]
+pub struct BoostPayouts<T: Config + Send + Sync>(PhantomData<T>);
+
+impl<T: Config + Send + Sync> BoostPayouts<T> {
+	/// Create the extension.
+	pub fn new() -> Self {
+		Self(PhantomData)
+	}
+}
+
+impl<T: Config + Send + Sync> Default for BoostPayouts<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T: Config + Send + Sync> fmt::Debug for BoostPayouts<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "BoostPayouts")
+	}
+}
+
+impl<T: Config + Send + Sync> SignedExtension for BoostPayouts<T> {
+	const IDENTIFIER: &'static str = "BoostPayouts";
+	type AccountId = T::AccountId;
+	type Call = <T as frame_system::Config>::Call;
+	type AdditionalSigned = ();
+	type Pre = ();
+
+	fn additional_signed(&self) -> Result<(), TransactionValidityError> {
+		Ok(())
+	}
+
+	fn validate(
+		&self,
+		who: &Self::AccountId,
+		call: &Self::Call,
+		_info: &DispatchInfoOf<Self::Call>,
+		_len: usize,
+	) -> TransactionValidity {
+		// The priorities of the signed extensions add up.
+		let priority = if <Module<T>>::is_payout(who, call) { T::PayoutPriority::get() } else { 0 };
+		Ok(ValidTransaction { priority, ..Default::default() })
+	}
+}
@@ -0,0 +1,146 @@
+use crate::{self as payout_priority, BoostPayouts, Config, RawEvent};
+use frame_support::{
+	assert_noop, assert_ok, construct_runtime, parameter_types,
+	traits::Filter,
+	weights::DispatchInfo,
+};
+use frame_system::{EventRecord, Phase, RawOrigin};
+use sp_core::H256;
+use sp_io::TestExternalities;
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup, SignedExtension},
+	transaction_validity::TransactionPriority,
+	DispatchError,
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<TestRuntime>;
+type Block = frame_system::mocking::MockBlock<TestRuntime>;
+
+construct_runtime!(
+	pub enum TestRuntime where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Module, Call, Config, Storage, Event<T>},
+		PayoutPriority: payout_priority::{Module, Call, Storage, Event<T>},
+	}
+);
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+}
+impl frame_system::Config for TestRuntime {
+	type BaseCallFilter = ();
+	type BlockWeights = ();
+	type BlockLength = ();
+	type Origin = Origin;
+	type Index = u64;
+	type Call = Call;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type DbWeight = ();
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+}
+
+/// Remarks stand in for the transfers of a real runtime.
+pub struct Remarks;
+impl Filter<Call> for Remarks {
+	fn filter(call: &Call) -> bool {
+		matches!(call, Call::System(frame_system::Call::remark(..)))
+	}
+}
+
+parameter_types! {
+	pub const PayoutPriorityBoost: TransactionPriority = 1_000;
+}
+impl Config for TestRuntime {
+	type Event = Event;
+	type PayoutCalls = Remarks;
+	type PayoutPriority = PayoutPriorityBoost;
+}
+
+struct ExternalityBuilder;
+
+impl ExternalityBuilder {
+	pub fn build() -> TestExternalities {
+		let storage = frame_system::GenesisConfig::default()
+			.build_storage::<TestRuntime>()
+			.unwrap();
+		let mut ext = TestExternalities::from(storage);
+		ext.execute_with(|| System::set_block_number(1));
+		ext
+	}
+}
+
+fn priority(who: u64, call: Call) -> TransactionPriority {
+	BoostPayouts::<TestRuntime>::new()
+		.validate(&who, &call, &DispatchInfo::default(), 0)
+		.unwrap()
+		.priority
+}
+
+fn remark() -> Call {
+	Call::System(frame_system::Call::remark(vec![]))
+}
+
+#[test]
+fn root_registers_payout_account() {
+	ExternalityBuilder::build().execute_with(|| {
+		assert_ok!(PayoutPriority::set_payout_account(RawOrigin::Root.into(), 7, true));
+
+		assert!(PayoutPriority::is_payout_account(7));
+		assert!(System::events().contains(&EventRecord {
+			phase: Phase::Initialization,
+			event: Event::payout_priority(RawEvent::PayoutAccountSet(7, true)),
+			topics: vec![],
+		}));
+
+		assert_ok!(PayoutPriority::set_payout_account(RawOrigin::Root.into(), 7, false));
+		assert!(!PayoutPriority::is_payout_account(7));
+	})
+}
+
+#[test]
+fn signed_origin_rejected() {
+	ExternalityBuilder::build().execute_with(|| {
+		assert_noop!(
+			PayoutPriority::set_payout_account(RawOrigin::Signed(7).into(), 7, true),
+			DispatchError::BadOrigin
+		);
+	})
+}
+
+#[test]
+fn payouts_boosted() {
+	ExternalityBuilder::build().execute_with(|| {
+		assert_eq!(priority(7, remark()), 0);
+
+		assert_ok!(PayoutPriority::set_payout_account(RawOrigin::Root.into(), 7, true));
+		assert_eq!(priority(7, remark()), 1_000);
+		assert_eq!(priority(8, remark()), 0);
+	})
+}
+
+#[test]
+fn other_calls_of_payout_account_not_boosted() {
+	ExternalityBuilder::build().execute_with(|| {
+		assert_ok!(PayoutPriority::set_payout_account(RawOrigin::Root.into(), 7, true));
+
+		let call = Call::System(frame_system::Call::set_heap_pages(1));
+		assert_eq!(priority(7, call), 0);
+	})
+}
@@ -1,7 +1,13 @@
 //! Helper module to build a genesis configuration for the api-runtime
 
-use super::{AccountId, BalancesConfig, GenesisConfig, Signature, SudoConfig, SystemConfig};
-use sp_core::{sr25519, Pair};
+use super::{
+	AccountId, Balance, BalancesConfig, BlockNumber, BlockRewardConfig, DifficultyConfig,
+	EthashSealConfig, GenesisConfig, GrandpaConfig, Signature, SudoConfig, SystemConfig,
+};
+pub use block_reward::EmissionCurve;
+pub use difficulty::RetargetStrategy;
+use sp_core::{sr25519, Pair, U256};
+use sp_finality_grandpa::AuthorityId as GrandpaId;
 use sp_runtime::traits::{IdentifyAccount, Verify};
 
 /// Helper function to generate a crypto pair from seed
@@ -21,9 +27,16 @@ where
 	AccountPublic::from(get_from_seed::<TPair>(seed)).into_account()
 }
 
+/// Helper function to generate the GRANDPA key of an authority from seed
+pub fn authority_keys_from_seed(seed: &str) -> GrandpaId {
+	get_from_seed::<GrandpaId>(seed)
+}
+
 pub fn dev_genesis(wasm_binary: &[u8]) -> GenesisConfig {
 	testnet_genesis(
 		wasm_binary,
+		// Initial Authorities
+		vec![authority_keys_from_seed("Alice")],
 		// Root Key
 		account_id_from_seed::<sr25519::Pair>("Alice"),
 		// Endowed Accounts
@@ -39,21 +52,78 @@ pub fn dev_genesis(wasm_binary: &[u8]) -> GenesisConfig {
 /// Helper function to build a genesis configuration
 pub fn testnet_genesis(
 	wasm_binary: &[u8],
+	initial_authorities: Vec<GrandpaId>,
 	root_key: AccountId,
 	endowed_accounts: Vec<AccountId>,
 ) -> GenesisConfig {
-	GenesisConfig {
-		frame_system: Some(SystemConfig {
-			code: wasm_binary.to_vec(),
-			changes_trie_config: Default::default(),
-		}),
-		pallet_balances: Some(BalancesConfig {
-			balances: endowed_accounts
-				.iter()
-				.cloned()
-				.map(|k| (k, 1 << 60))
-				.collect(),
-		}),
-		pallet_sudo: Some(SudoConfig { key: root_key }),
+	pow_genesis(wasm_binary, initial_authorities, root_key, endowed_accounts, 1_000_000.into(), 1_000_000.into())
+}
+
+/// Build a genesis configuration whose first block is mined at `difficulty`, the retargeted
+/// difficulty never dropping below `minimum_difficulty`, and whose blocks are finalized by the
+/// votes of `initial_authorities`.
+pub fn pow_genesis(
+	wasm_binary: &[u8],
+	initial_authorities: Vec<GrandpaId>,
+	root_key: AccountId,
+	endowed_accounts: Vec<AccountId>,
+	difficulty: U256,
+	minimum_difficulty: U256,
+) -> GenesisConfig {
+	PowGenesis {
+		root_key,
+		balances: endowed_accounts.into_iter().map(|k| (k, 1 << 60)).collect(),
+		difficulty,
+		minimum_difficulty,
+		strategy: Default::default(),
+		reward: None,
+		grandpa_authorities: initial_authorities,
+	}
+	.build(wasm_binary)
+}
+
+/// Genesis of a PoW chain of its own, as chosen by the `new-chain-spec` subcommand of the node.
+#[derive(Debug, Clone)]
+pub struct PowGenesis {
+	/// The sudo key.
+	pub root_key: AccountId,
+	/// Accounts endowed at genesis, with their balance.
+	pub balances: Vec<(AccountId, Balance)>,
+	/// Difficulty of the first block.
+	pub difficulty: U256,
+	/// The retargeted difficulty never drops below this value.
+	pub minimum_difficulty: U256,
+	/// The retarget rule.
+	pub strategy: RetargetStrategy,
+	/// The reward curve from block 0, instead of the halving schedule of the runtime.
+	pub reward: Option<EmissionCurve<Balance, BlockNumber>>,
+	/// The GRANDPA voters finalizing the mined blocks, none leaving the blocks unfinalized.
+	pub grandpa_authorities: Vec<GrandpaId>,
+}
+
+impl PowGenesis {
+	/// The genesis configuration running `wasm_binary`.
+	pub fn build(self, wasm_binary: &[u8]) -> GenesisConfig {
+		GenesisConfig {
+			frame_system: Some(SystemConfig {
+				code: wasm_binary.to_vec(),
+				changes_trie_config: Default::default(),
+			}),
+			pallet_balances: Some(BalancesConfig { balances: self.balances }),
+			pallet_sudo: Some(SudoConfig { key: self.root_key }),
+			difficulty: Some(DifficultyConfig {
+				difficulty: self.difficulty,
+				minimum_difficulty: self.minimum_difficulty,
+				strategy: self.strategy,
+			}),
+			block_reward: Some(BlockRewardConfig {
+				schedule: self.reward.map(|curve| (0, curve)),
+			}),
+			// The commitments to the light caches are set by root, from those computed by a node.
+			ethash_seal: Some(EthashSealConfig { epoch_caches: Vec::new() }),
+			pallet_grandpa: Some(GrandpaConfig {
+				authorities: self.grandpa_authorities.into_iter().map(|id| (id, 1)).collect(),
+			}),
+		}
 	}
 }
@@ -1,4 +1,6 @@
-//! A Runtime that demonstrates a custom runtime API.
+//! The runtime of the ethash proof-of-work node: difficulty retargeting, block rewards and seal
+//! verification parameters, with GRANDPA finality. It also demonstrates a custom runtime API,
+//! `SumStorageApi`, served over the RPCs of the node.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 // `construct_runtime!` does a lot of recursion and requires us to increase the limit to 256.
@@ -12,13 +14,16 @@ include!(concat!(env!("OUT_DIR"), "/wasm_binary.rs"));
 #[cfg(feature = "std")]
 pub mod genesis;
 
+use pallet_grandpa::{AuthorityId as GrandpaId, AuthorityList as GrandpaAuthorityList};
 use pallet_transaction_payment::CurrencyAdapter;
 use sp_api::impl_runtime_apis;
-use sp_core::{OpaqueMetadata, H256};
-use sp_runtime::traits::{BlakeTwo256, Block as BlockT, IdentifyAccount, IdentityLookup, Verify};
+use sp_core::{crypto::KeyTypeId, OpaqueMetadata, H256, U256};
+use sp_runtime::traits::{
+	BlakeTwo256, Block as BlockT, Convert, IdentifyAccount, IdentityLookup, NumberFor, Verify,
+};
 use sp_runtime::{
-	create_runtime_str, generic,
-	transaction_validity::{TransactionSource, TransactionValidity},
+	create_runtime_str, generic, impl_opaque_keys,
+	transaction_validity::{TransactionPriority, TransactionSource, TransactionValidity},
 	ApplyExtrinsicResult, MultiSignature,
 };
 use sp_std::prelude::*;
@@ -28,10 +33,10 @@ use sp_version::RuntimeVersion;
 
 use frame_support::{
 	construct_runtime, parameter_types,
-	traits::Randomness,
+	traits::{Filter, Get, KeyOwnerProofSystem, Randomness},
 	weights::{
 		constants::{RocksDbWeight, WEIGHT_PER_SECOND},
-		IdentityFee,
+		IdentityFee, Weight,
 	},
 };
 #[cfg(any(feature = "std", test))]
@@ -57,6 +62,9 @@ pub type Index = u32;
 /// A hash of some data used by the chain.
 pub type Hash = H256;
 
+/// A timestamp: milliseconds since the unix epoch.
+pub type Moment = u64;
+
 /// Digest item type.
 pub type DigestItem = generic::DigestItem<Hash>;
 
@@ -75,6 +83,12 @@ pub mod opaque {
 	pub type Block = generic::Block<Header, UncheckedExtrinsic>;
 	/// Opaque block identifier type.
 	pub type BlockId = generic::BlockId<Block>;
+
+	impl_opaque_keys! {
+		pub struct SessionKeys {
+			pub grandpa: Grandpa,
+		}
+	}
 }
 
 /// This runtime version.
@@ -102,9 +116,6 @@ const NORMAL_DISPATCH_RATIO: Perbill = Perbill::from_percent(75);
 parameter_types! {
 	pub const Version: RuntimeVersion = VERSION;
 	pub const BlockHashCount: BlockNumber = 2400;
-	/// We allow for 2 seconds of compute with a 6 second average block time.
-	pub BlockWeights: frame_system::limits::BlockWeights = frame_system::limits::BlockWeights
-		::with_sensible_defaults(2 * WEIGHT_PER_SECOND, NORMAL_DISPATCH_RATIO);
 	pub BlockLength: frame_system::limits::BlockLength = frame_system::limits::BlockLength
 		::max_with_normal_ratio(5 * 1024 * 1024, NORMAL_DISPATCH_RATIO);
 	pub const SS58Prefix: u8 = 42;
@@ -113,8 +124,8 @@ parameter_types! {
 impl frame_system::Config for Runtime {
 	/// The basic call filter to use in dispatchable.
 	type BaseCallFilter = ();
-	/// Block & extrinsics weights: base values and limits.
-	type BlockWeights = BlockWeights;
+	/// Block & extrinsics weights: base values and limits, read from the `BlockWeightLimit` pallet.
+	type BlockWeights = block_weight_limit::DynamicBlockWeights<Runtime>;
 	/// The maximum length of a block (in bytes).
 	type BlockLength = BlockLength;
 	/// The identifier used to distinguish between accounts.
@@ -165,8 +176,8 @@ parameter_types! {
 
 impl pallet_timestamp::Config for Runtime {
 	/// A timestamp: milliseconds since the unix epoch.
-	type Moment = u64;
-	type OnTimestampSet = ();
+	type Moment = Moment;
+	type OnTimestampSet = (BlockTimeOracle, Difficulty);
 	type MinimumPeriod = MinimumPeriod;
 	type WeightInfo = ();
 }
@@ -211,6 +222,153 @@ impl sum_storage::Config for Runtime {
 	type Event = Event;
 }
 
+parameter_types! {
+	pub const AuthorHistoryDepth: u32 = 1024;
+}
+
+impl author_history::Config for Runtime {
+	type FindAuthor = author_history::PowDigestAuthor<AccountId>;
+	type HistoryDepth = AuthorHistoryDepth;
+}
+
+parameter_types! {
+	pub const AuthorReward: Balance = 1_000_000_000_000;
+	/// Roughly four years of 15 second blocks.
+	pub const RewardHalvingInterval: BlockNumber = 8_400_000;
+}
+
+impl block_reward::Config for Runtime {
+	type Event = Event;
+	type Currency = Balances;
+	type Reward = AuthorReward;
+	type HalvingInterval = RewardHalvingInterval;
+}
+
+parameter_types! {
+	/// We allow for 2 seconds of compute per block, until root says otherwise: the difficulty
+	/// settles on 15 second blocks, leaving most of the time to propagate and import them.
+	pub const DefaultMaxBlockWeight: Weight = 2 * WEIGHT_PER_SECOND;
+	pub const MinBlockWeight: Weight = WEIGHT_PER_SECOND / 2;
+	pub const MaxBlockWeight: Weight = 4 * WEIGHT_PER_SECOND;
+	pub const NormalDispatchRatio: Perbill = NORMAL_DISPATCH_RATIO;
+}
+
+impl block_weight_limit::Config for Runtime {
+	type Event = Event;
+	type DefaultMaxBlockWeight = DefaultMaxBlockWeight;
+	type MinBlockWeight = MinBlockWeight;
+	type MaxBlockWeight = MaxBlockWeight;
+	type NormalDispatchRatio = NormalDispatchRatio;
+}
+
+parameter_types! {
+	pub const BlockTimeSmoothing: Perbill = Perbill::from_percent(10);
+}
+
+impl block_time_oracle::Config for Runtime {
+	type Smoothing = BlockTimeSmoothing;
+}
+
+parameter_types! {
+	pub DifficultyBoundDivisor: U256 = U256::from(2048);
+	/// Blocks faster than 10 seconds raise the difficulty, blocks slower than 20 seconds lower it.
+	pub const DifficultyIncrementDivisor: Moment = 10_000;
+	pub const DifficultyMaxDecreaseSteps: u64 = 99;
+	/// Blocks sealed in the legacy layout are still accepted. An upgrade raising it to 2 requires
+	/// the seals committing to the block timestamp from the first block built on its state.
+	pub const DifficultyMinSealVersion: u8 = 1;
+}
+
+impl difficulty::Config for Runtime {
+	type BoundDivisor = DifficultyBoundDivisor;
+	type IncrementDivisor = DifficultyIncrementDivisor;
+	type MaxDecreaseSteps = DifficultyMaxDecreaseSteps;
+	type MinSealVersion = DifficultyMinSealVersion;
+}
+
+#[cfg(not(feature = "short-epochs"))]
+parameter_types! {
+	/// Blocks sharing a light cache, as in the ethash computation of the node.
+	pub const EthashEpochLength: u64 = 30_000;
+}
+
+#[cfg(feature = "short-epochs")]
+parameter_types! {
+	/// The epochs of the node built with `short-epochs`, for the epoch transition test.
+	pub const EthashEpochLength: u64 = 32;
+}
+
+impl ethash_seal::Config for Runtime {
+	type Event = Event;
+	type EpochLength = EthashEpochLength;
+}
+
+/// The difficulty the current block is mined at, until the timestamp retargets it.
+pub struct CurrentDifficulty;
+
+impl Get<U256> for CurrentDifficulty {
+	fn get() -> U256 {
+		Difficulty::difficulty()
+	}
+}
+
+/// The reward of the author of a block.
+pub struct AuthorRewardAt;
+
+impl Convert<BlockNumber, Balance> for AuthorRewardAt {
+	fn convert(number: BlockNumber) -> Balance {
+		BlockReward::reward_at(number)
+	}
+}
+
+impl mining_events::Config for Runtime {
+	type Event = Event;
+	type FindAuthor = author_history::PowDigestAuthor<AccountId>;
+	type Difficulty = CurrentDifficulty;
+	type Balance = Balance;
+	type BlockReward = AuthorRewardAt;
+}
+
+/// The transfers of the payout accounts are their payouts.
+pub struct PayoutTransfers;
+
+impl Filter<Call> for PayoutTransfers {
+	fn filter(call: &Call) -> bool {
+		matches!(
+			call,
+			Call::Balances(pallet_balances::Call::transfer(..))
+				| Call::Balances(pallet_balances::Call::transfer_keep_alive(..))
+		)
+	}
+}
+
+parameter_types! {
+	/// Payouts go ahead of any transaction whose fee doesn't make up for half the priority range.
+	pub const PayoutPriority: TransactionPriority = TransactionPriority::max_value() / 2;
+}
+
+/// Finality of the mined blocks, voted by the authorities of the genesis: the set only changes
+/// with a runtime upgrade, as there is no session pallet rotating it.
+impl pallet_grandpa::Config for Runtime {
+	type Event = Event;
+	type Call = Call;
+	type KeyOwnerProofSystem = ();
+	type KeyOwnerProof =
+		<Self::KeyOwnerProofSystem as KeyOwnerProofSystem<(KeyTypeId, GrandpaId)>>::Proof;
+	type KeyOwnerIdentification = <Self::KeyOwnerProofSystem as KeyOwnerProofSystem<(
+		KeyTypeId,
+		GrandpaId,
+	)>>::IdentificationTuple;
+	type HandleEquivocation = ();
+	type WeightInfo = ();
+}
+
+impl payout_priority::Config for Runtime {
+	type Event = Event;
+	type PayoutCalls = PayoutTransfers;
+	type PayoutPriority = PayoutPriority;
+}
+
 construct_runtime!(
 	pub enum Runtime where
 		Block = Block,
@@ -224,6 +382,15 @@ construct_runtime!(
 		Sudo: pallet_sudo::{Module, Call, Config<T>, Storage, Event<T>},
 		TransactionPayment: pallet_transaction_payment::{Module, Storage},
 		SumStorage: sum_storage::{Module, Call, Storage, Event},
+		AuthorHistory: author_history::{Module, Storage},
+		BlockReward: block_reward::{Module, Call, Storage, Config<T>, Event<T>, Inherent},
+		BlockWeightLimit: block_weight_limit::{Module, Call, Storage, Event},
+		BlockTimeOracle: block_time_oracle::{Module, Storage},
+		Difficulty: difficulty::{Module, Storage, Config},
+		EthashSeal: ethash_seal::{Module, Call, Storage, Event, Config},
+		MiningEvents: mining_events::{Module, Storage, Event<T>},
+		PayoutPriority: payout_priority::{Module, Call, Storage, Event<T>},
+		Grandpa: pallet_grandpa::{Module, Call, Storage, Config, Event},
 	}
 );
 
@@ -246,6 +413,7 @@ pub type SignedExtra = (
 	frame_system::CheckNonce<Runtime>,
 	frame_system::CheckWeight<Runtime>,
 	pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
+	payout_priority::BoostPayouts<Runtime>,
 );
 /// Unchecked extrinsic type as expected by this runtime.
 pub type UncheckedExtrinsic = generic::UncheckedExtrinsic<Address, Call, Signature, SignedExtra>;
@@ -332,15 +500,95 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl author_history_runtime_api::AuthorHistoryApi<Block, BlockNumber, AccountId> for Runtime {
+		fn recent_authors() -> Vec<(BlockNumber, AccountId)> {
+			AuthorHistory::recent_authors()
+		}
+	}
+
+	impl block_reward_runtime_api::BlockRewardApi<Block, BlockNumber, Balance> for Runtime {
+		fn block_reward(number: BlockNumber) -> Balance {
+			BlockReward::reward_at(number)
+		}
+	}
+
+	impl pallet_transaction_payment_rpc_runtime_api::TransactionPaymentApi<Block, Balance> for Runtime {
+		fn query_info(
+			uxt: <Block as BlockT>::Extrinsic,
+			len: u32,
+		) -> pallet_transaction_payment_rpc_runtime_api::RuntimeDispatchInfo<Balance> {
+			TransactionPayment::query_info(uxt, len)
+		}
+	}
+
+	impl block_time_oracle_runtime_api::BlockTimeOracleApi<Block, Moment> for Runtime {
+		fn average_block_time() -> Option<Moment> {
+			BlockTimeOracle::average_block_time()
+		}
+	}
+
+	impl sp_consensus_pow::DifficultyApi<Block, U256> for Runtime {
+		fn difficulty() -> U256 {
+			Difficulty::difficulty()
+		}
+	}
+
+	impl difficulty_runtime_api::PowParamsApi<Block> for Runtime {
+		fn pow_params() -> difficulty_runtime_api::PowParams {
+			Difficulty::pow_params()
+		}
+	}
+
+	impl ethash_seal_runtime_api::EthashSealApi<Block> for Runtime {
+		fn epoch_cache(epoch: u64) -> Option<ethash_seal_runtime_api::EpochCache> {
+			EthashSeal::epoch_cache(epoch)
+		}
+
+		fn verify_seal(
+			seal: ethash_seal_runtime_api::WorkSeal,
+			proof: ethash_seal_runtime_api::CacheProof,
+		) -> Result<(), ethash_seal_runtime_api::SealFailure> {
+			EthashSeal::verify_seal(&seal, &proof)
+		}
+	}
+
+	impl sp_finality_grandpa::GrandpaApi<Block> for Runtime {
+		fn grandpa_authorities() -> GrandpaAuthorityList {
+			Grandpa::grandpa_authorities()
+		}
+
+		fn submit_report_equivocation_unsigned_extrinsic(
+			equivocation_proof: sp_finality_grandpa::EquivocationProof<
+				<Block as BlockT>::Hash,
+				NumberFor<Block>,
+			>,
+			key_owner_proof: sp_finality_grandpa::OpaqueKeyOwnershipProof,
+		) -> Option<()> {
+			let key_owner_proof = key_owner_proof.decode()?;
+
+			Grandpa::submit_unsigned_equivocation_report(
+				equivocation_proof,
+				key_owner_proof,
+			)
+		}
+
+		fn generate_key_ownership_proof(
+			_set_id: sp_finality_grandpa::SetId,
+			_authority_id: GrandpaId,
+		) -> Option<sp_finality_grandpa::OpaqueKeyOwnershipProof> {
+			None
+		}
+	}
+
 	impl sp_session::SessionKeys<Block> for Runtime {
-		fn generate_session_keys(_seed: Option<Vec<u8>>) -> Vec<u8> {
-			Vec::new()
+		fn generate_session_keys(seed: Option<Vec<u8>>) -> Vec<u8> {
+			opaque::SessionKeys::generate(seed)
 		}
 
 		fn decode_session_keys(
-			_encoded: Vec<u8>,
-		) -> Option<Vec<(Vec<u8>, sp_core::crypto::KeyTypeId)>> {
-			None
+			encoded: Vec<u8>,
+		) -> Option<Vec<(Vec<u8>, KeyTypeId)>> {
+			opaque::SessionKeys::decode_into_raw_public_keys(&encoded)
 		}
 	}
 }